@@ -0,0 +1,670 @@
+//! Async callback handlers (非同步回調)
+//!
+//! The traits in [`crate::callbacks`] run on the SDK's callback thread while
+//! the GIL is held, so a handler that waits on I/O stalls market-data
+//! delivery for everyone. The async variants here let a handler `.await`
+//! database writes or order placement instead.
+//!
+//! Each registered handler gets its own ordered queue and a tokio task that
+//! drains it. The bridge only enqueues the event and returns; the task calls
+//! the handler one event at a time, so a handler sees its events in arrival
+//! order and never runs concurrently with itself. Handlers registered
+//! separately run independently of each other.
+//!
+//! By default the queue is unbounded: a handler slower than the feed
+//! accumulates a backlog rather than losing events or blocking the bridge.
+//! [`AsyncQueue::Bounded`] caps the backlog instead; events arriving while it
+//! is full are discarded. Events that cannot be queued — the queue is full or
+//! the handler's task has stopped — are counted in the stream's
+//! [`CallbackStats::dropped`](crate::CallbackStats). A panic inside
+//! a handler is reported through the client's [`CallbackErrorPolicy`] and
+//! the next event is delivered as usual; once the policy's failure limit is
+//! reached in a row the handler's task stops.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use rshioaji::{AsyncTickCallback, Exchange, Shioaji, TickFOPv1, TickSTKv1};
+//! struct Recorder;
+//!
+//! #[async_trait::async_trait]
+//! impl AsyncTickCallback for Recorder {
+//!     async fn on_tick_stk_v1(&self, _exchange: Exchange, tick: TickSTKv1) {
+//!         // e.g. write to a database without holding up other callbacks
+//!         tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+//!         println!("{} {}", tick.code, tick.close);
+//!     }
+//!
+//!     async fn on_tick_fop_v1(&self, _exchange: Exchange, _tick: TickFOPv1) {}
+//! }
+//!
+//! # async fn run(client: &Shioaji) -> rshioaji::Result<()> {
+//! let handler: Arc<dyn AsyncTickCallback> = Arc::new(Recorder);
+//! let handle = client.register_async_handler(handler)?;
+//! # drop(handle);
+//! # Ok(())
+//! # }
+//! ```
+
+use async_trait::async_trait;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+
+use crate::callbacks::{
+    panic_message, CallbackErrorPolicy, CallbackHandle, CallbackRegistry, EventHandlers,
+};
+use crate::error::{Error, Result};
+use crate::types::orders::{OrderState, OrderStateTracker};
+use crate::types::{BidAskFOPv1, BidAskSTKv1, Exchange, QuoteSTKv1, TickFOPv1, TickSTKv1};
+
+/// Async counterpart of [`crate::callbacks::TickCallback`]
+#[async_trait]
+pub trait AsyncTickCallback: Send + Sync {
+    /// Called for each stock tick
+    async fn on_tick_stk_v1(&self, exchange: Exchange, tick: TickSTKv1);
+
+    /// Called for each futures/options tick
+    async fn on_tick_fop_v1(&self, exchange: Exchange, tick: TickFOPv1);
+}
+
+/// Async counterpart of [`crate::callbacks::BidAskCallback`]
+#[async_trait]
+pub trait AsyncBidAskCallback: Send + Sync {
+    /// Called for each stock bid/ask update
+    async fn on_bidask_stk_v1(&self, exchange: Exchange, bidask: BidAskSTKv1);
+
+    /// Called for each futures/options bid/ask update
+    async fn on_bidask_fop_v1(&self, exchange: Exchange, bidask: BidAskFOPv1);
+}
+
+/// Async counterpart of [`crate::callbacks::QuoteCallback`]
+#[async_trait]
+pub trait AsyncQuoteCallback: Send + Sync {
+    /// Called for each stock quote
+    async fn on_quote_stk_v1(&self, exchange: Exchange, quote: QuoteSTKv1);
+
+    /// Called for each general quote
+    async fn on_quote(&self, topic: String, data: serde_json::Value);
+}
+
+/// Async counterpart of [`crate::callbacks::OrderCallback`]
+#[async_trait]
+pub trait AsyncOrderCallback: Send + Sync {
+    /// Called when an order status changes
+    async fn on_order(&self, order_state: OrderState, data: serde_json::Value);
+}
+
+/// Async counterpart of [`crate::callbacks::SystemCallback`]
+#[async_trait]
+pub trait AsyncSystemCallback: Send + Sync {
+    /// Called when system events occur
+    async fn on_event(&self, event_type: i32, code: i32, message: String, details: String);
+
+    /// Called when the session is disconnected
+    async fn on_session_down(&self);
+}
+
+/// Size of an async handler's queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AsyncQueue {
+    /// Never discards events; a slow handler builds up a backlog
+    #[default]
+    Unbounded,
+    /// Holds at most this many events (at least 1); events arriving while it
+    /// is full are dropped
+    Bounded(usize),
+}
+
+/// An async handler that can be attached to [`EventHandlers`]
+///
+/// Implemented for `Arc<dyn AsyncTickCallback>` and the other async traits.
+pub trait AsyncHandler {
+    /// Start the handler's queue on `runtime` and register its feeders
+    fn register_on(
+        self,
+        handlers: &EventHandlers,
+        runtime: &Handle,
+        queue: AsyncQueue,
+    ) -> CallbackHandle;
+}
+
+type Delivery = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+enum TickEvent {
+    Stk(Exchange, TickSTKv1),
+    Fop(Exchange, TickFOPv1),
+}
+
+enum BidAskEvent {
+    Stk(Exchange, BidAskSTKv1),
+    Fop(Exchange, BidAskFOPv1),
+}
+
+enum QuoteEvent {
+    Stk(Exchange, Box<QuoteSTKv1>),
+    Topic(String, serde_json::Value),
+}
+
+enum SystemEvent {
+    Event(i32, i32, String, String),
+    SessionDown,
+}
+
+/// Sending half of a handler's queue, used from the bridge thread
+enum Feeder<E> {
+    Unbounded(mpsc::UnboundedSender<E>),
+    Bounded(mpsc::Sender<E>),
+}
+
+impl<E> Clone for Feeder<E> {
+    fn clone(&self) -> Self {
+        match self {
+            Feeder::Unbounded(sender) => Feeder::Unbounded(sender.clone()),
+            Feeder::Bounded(sender) => Feeder::Bounded(sender.clone()),
+        }
+    }
+}
+
+impl<E> Feeder<E> {
+    /// Enqueue without blocking; an event the queue does not accept (full,
+    /// or the handler's task has stopped) is counted as dropped on `registry`
+    ///
+    /// The registry is held weakly: the feeder is itself one of its entries.
+    fn feed<T: Send + Sync + 'static>(&self, event: E, registry: &Weak<CallbackRegistry<T>>) {
+        let queued = match self {
+            Feeder::Unbounded(sender) => sender.send(event).is_ok(),
+            Feeder::Bounded(sender) => sender.try_send(event).is_ok(),
+        };
+        if !queued {
+            if let Some(registry) = registry.upgrade() {
+                registry.record_dropped();
+            }
+        }
+    }
+}
+
+enum Receiver<E> {
+    Unbounded(mpsc::UnboundedReceiver<E>),
+    Bounded(mpsc::Receiver<E>),
+}
+
+impl<E> Receiver<E> {
+    async fn recv(&mut self) -> Option<E> {
+        match self {
+            Receiver::Unbounded(receiver) => receiver.recv().await,
+            Receiver::Bounded(receiver) => receiver.recv().await,
+        }
+    }
+}
+
+/// Spawn the task draining one handler's queue
+///
+/// `deliver` turns a queued event into the handler call; events are awaited
+/// one after another so the handler sees them in order.
+fn spawn_queue<E, H, F>(
+    runtime: &Handle,
+    stream: &'static str,
+    policy: Arc<CallbackErrorPolicy>,
+    queue: AsyncQueue,
+    handler: H,
+    deliver: F,
+) -> Feeder<E>
+where
+    E: Send + 'static,
+    H: Clone + Send + 'static,
+    F: Fn(H, E) -> Delivery + Send + 'static,
+{
+    let (sender, mut receiver) = match queue {
+        AsyncQueue::Unbounded => {
+            let (sender, receiver) = mpsc::unbounded_channel::<E>();
+            (Feeder::Unbounded(sender), Receiver::Unbounded(receiver))
+        }
+        AsyncQueue::Bounded(capacity) => {
+            let (sender, receiver) = mpsc::channel::<E>(capacity.max(1));
+            (Feeder::Bounded(sender), Receiver::Bounded(receiver))
+        }
+    };
+    runtime.spawn(async move {
+        let mut failures = 0u32;
+        while let Some(event) = receiver.recv().await {
+            let Err(message) = CatchUnwind(deliver(handler.clone(), event)).await else {
+                failures = 0;
+                continue;
+            };
+            failures += 1;
+            let disable = policy
+                .failure_limit()
+                .is_some_and(|limit| failures >= limit);
+            policy.report(Error::Callback(format!(
+                "{} async handler panicked ({} in a row{}): {}",
+                stream,
+                failures,
+                if disable { ", disabled" } else { "" },
+                message
+            )));
+            if disable {
+                break;
+            }
+        }
+        log::debug!("🔌 {} async handler queue closed", stream);
+    });
+    sender
+}
+
+/// Resolve a handler future, turning a panic into its message
+struct CatchUnwind(Delivery);
+
+impl Future for CatchUnwind {
+    type Output = std::result::Result<(), String>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let delivery = &mut self.0;
+        match panic::catch_unwind(AssertUnwindSafe(|| delivery.as_mut().poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(())) => Poll::Ready(Ok(())),
+            Err(payload) => Poll::Ready(Err(panic_message(payload.as_ref()))),
+        }
+    }
+}
+
+impl AsyncHandler for Arc<dyn AsyncTickCallback> {
+    fn register_on(
+        self,
+        handlers: &EventHandlers,
+        runtime: &Handle,
+        queue: AsyncQueue,
+    ) -> CallbackHandle {
+        let queue = spawn_queue(
+            runtime,
+            "tick",
+            handlers.policy().clone(),
+            queue,
+            self,
+            |handler, event| {
+                Box::pin(async move {
+                    match event {
+                        TickEvent::Stk(exchange, tick) => {
+                            handler.on_tick_stk_v1(exchange, tick).await
+                        }
+                        TickEvent::Fop(exchange, tick) => {
+                            handler.on_tick_fop_v1(exchange, tick).await
+                        }
+                    }
+                })
+            },
+        );
+        let fop = queue.clone();
+        let (stk_registry, fop_registry) = (
+            Arc::downgrade(&handlers.tick_stk),
+            Arc::downgrade(&handlers.tick_fop),
+        );
+        handlers
+            .tick_stk
+            .register(Arc::new(move |exchange, tick| {
+                queue.feed(TickEvent::Stk(exchange, tick), &stk_registry);
+            }))
+            .join(handlers.tick_fop.register(Arc::new(move |exchange, tick| {
+                fop.feed(TickEvent::Fop(exchange, tick), &fop_registry);
+            })))
+    }
+}
+
+impl AsyncHandler for Arc<dyn AsyncBidAskCallback> {
+    fn register_on(
+        self,
+        handlers: &EventHandlers,
+        runtime: &Handle,
+        queue: AsyncQueue,
+    ) -> CallbackHandle {
+        let queue = spawn_queue(
+            runtime,
+            "bidask",
+            handlers.policy().clone(),
+            queue,
+            self,
+            |handler, event| {
+                Box::pin(async move {
+                    match event {
+                        BidAskEvent::Stk(exchange, bidask) => {
+                            handler.on_bidask_stk_v1(exchange, bidask).await
+                        }
+                        BidAskEvent::Fop(exchange, bidask) => {
+                            handler.on_bidask_fop_v1(exchange, bidask).await
+                        }
+                    }
+                })
+            },
+        );
+        let fop = queue.clone();
+        let (stk_registry, fop_registry) = (
+            Arc::downgrade(&handlers.bidask_stk),
+            Arc::downgrade(&handlers.bidask_fop),
+        );
+        handlers
+            .bidask_stk
+            .register(Arc::new(move |exchange, bidask| {
+                queue.feed(BidAskEvent::Stk(exchange, bidask), &stk_registry);
+            }))
+            .join(
+                handlers
+                    .bidask_fop
+                    .register(Arc::new(move |exchange, bidask| {
+                        fop.feed(BidAskEvent::Fop(exchange, bidask), &fop_registry);
+                    })),
+            )
+    }
+}
+
+impl AsyncHandler for Arc<dyn AsyncQuoteCallback> {
+    fn register_on(
+        self,
+        handlers: &EventHandlers,
+        runtime: &Handle,
+        queue: AsyncQueue,
+    ) -> CallbackHandle {
+        let queue = spawn_queue(
+            runtime,
+            "quote",
+            handlers.policy().clone(),
+            queue,
+            self,
+            |handler, event| {
+                Box::pin(async move {
+                    match event {
+                        QuoteEvent::Stk(exchange, quote) => {
+                            handler.on_quote_stk_v1(exchange, *quote).await
+                        }
+                        QuoteEvent::Topic(topic, data) => handler.on_quote(topic, data).await,
+                    }
+                })
+            },
+        );
+        let topic = queue.clone();
+        let (stk_registry, topic_registry) = (
+            Arc::downgrade(&handlers.quote_stk),
+            Arc::downgrade(&handlers.quote),
+        );
+        handlers
+            .quote_stk
+            .register(Arc::new(move |exchange, quote| {
+                queue.feed(QuoteEvent::Stk(exchange, Box::new(quote)), &stk_registry);
+            }))
+            .join(handlers.quote.register(Arc::new(move |name, data| {
+                topic.feed(QuoteEvent::Topic(name, data), &topic_registry);
+            })))
+    }
+}
+
+impl AsyncHandler for Arc<dyn AsyncOrderCallback> {
+    fn register_on(
+        self,
+        handlers: &EventHandlers,
+        runtime: &Handle,
+        queue: AsyncQueue,
+    ) -> CallbackHandle {
+        let queue = spawn_queue(
+            runtime,
+            "order",
+            handlers.policy().clone(),
+            queue,
+            self,
+            |handler, (state, data)| Box::pin(async move { handler.on_order(state, data).await }),
+        );
+        let tracker = Mutex::new(OrderStateTracker::new());
+        let registry = Arc::downgrade(&handlers.order);
+        handlers.order.register(Arc::new(move |event_type, data| {
+            let state = tracker
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .state(&event_type, &data);
+            queue.feed((state, data), &registry);
+        }))
+    }
+}
+
+impl AsyncHandler for Arc<dyn AsyncSystemCallback> {
+    fn register_on(
+        self,
+        handlers: &EventHandlers,
+        runtime: &Handle,
+        queue: AsyncQueue,
+    ) -> CallbackHandle {
+        let queue = spawn_queue(
+            runtime,
+            "system",
+            handlers.policy().clone(),
+            queue,
+            self,
+            |handler, event| {
+                Box::pin(async move {
+                    match event {
+                        SystemEvent::Event(event_type, code, message, details) => {
+                            handler.on_event(event_type, code, message, details).await
+                        }
+                        SystemEvent::SessionDown => handler.on_session_down().await,
+                    }
+                })
+            },
+        );
+        let down = queue.clone();
+        let (event_registry, down_registry) = (
+            Arc::downgrade(&handlers.event),
+            Arc::downgrade(&handlers.session_down),
+        );
+        handlers
+            .event
+            .register(Arc::new(move |event_type, code, message, details| {
+                queue.feed(
+                    SystemEvent::Event(event_type, code, message, details),
+                    &event_registry,
+                );
+            }))
+            .join(handlers.session_down.register(Arc::new(move || {
+                down.feed(SystemEvent::SessionDown, &down_registry);
+            })))
+    }
+}
+
+impl EventHandlers {
+    /// Register an async handler on the current tokio runtime
+    ///
+    /// Fails with `Error::System` outside a runtime. Removing the handle
+    /// stops new events; events already queued are still delivered.
+    pub fn register_async<H: AsyncHandler>(&self, handler: H) -> Result<CallbackHandle> {
+        self.register_async_with(handler, AsyncQueue::Unbounded)
+    }
+
+    /// Register an async handler with a chosen queue size
+    pub fn register_async_with<H: AsyncHandler>(
+        &self,
+        handler: H,
+        queue: AsyncQueue,
+    ) -> Result<CallbackHandle> {
+        let runtime = Handle::try_current().map_err(|_| {
+            Error::System("Async handlers must be registered inside a tokio runtime".to_string())
+        })?;
+        Ok(handler.register_on(self, &runtime, queue))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OrderEventType;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    #[derive(Default)]
+    struct SlowRecorder {
+        seen: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl AsyncSystemCallback for SlowRecorder {
+        async fn on_event(&self, _: i32, code: i32, _: String, _: String) {
+            if code == 13 {
+                panic!("unlucky event");
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.seen.lock().unwrap().push(format!("event {}", code));
+        }
+
+        async fn on_session_down(&self) {
+            self.seen.lock().unwrap().push("down".to_string());
+        }
+    }
+
+    #[async_trait]
+    impl AsyncOrderCallback for SlowRecorder {
+        async fn on_order(&self, state: OrderState, _: serde_json::Value) {
+            self.seen.lock().unwrap().push(state.to_string());
+        }
+    }
+
+    async fn wait_for(recorder: &SlowRecorder, count: usize) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let seen = recorder.seen.lock().unwrap().clone();
+            if seen.len() >= count || Instant::now() > deadline {
+                return seen;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_handler_runs_in_order_without_blocking_dispatch() {
+        let handlers = EventHandlers::new();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let sink = errors.clone();
+        handlers.policy().set_hook(Arc::new(move |error| {
+            sink.lock().unwrap().push(error.to_string())
+        }));
+
+        let recorder = Arc::new(SlowRecorder::default());
+        let handle = handlers
+            .register_async(recorder.clone() as Arc<dyn AsyncSystemCallback>)
+            .unwrap();
+
+        // Dispatch happens from a plain thread like the SDK bridge and must
+        // return before the slow handler has processed anything
+        let bridge = {
+            let handlers = Arc::new(handlers);
+            let feeder = handlers.clone();
+            std::thread::spawn(move || {
+                for code in [1, 13, 2, 3] {
+                    feeder.trigger_event(0, code, String::new(), String::new());
+                }
+                feeder.trigger_session_down();
+            })
+            .join()
+            .unwrap();
+            handlers
+        };
+        assert!(recorder.seen.lock().unwrap().is_empty());
+
+        let seen = wait_for(&recorder, 4).await;
+        assert_eq!(seen, vec!["event 1", "event 2", "event 3", "down"]);
+        assert_eq!(errors.lock().unwrap().len(), 1);
+        assert!(errors.lock().unwrap()[0].contains("system async handler panicked"));
+
+        assert!(handle.remove());
+        bridge.trigger_session_down();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(recorder.seen.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_async_order_handler_receives_state() {
+        let handlers = EventHandlers::new();
+        let recorder = Arc::new(SlowRecorder::default());
+        handlers
+            .register_async(recorder.clone() as Arc<dyn AsyncOrderCallback>)
+            .unwrap();
+
+        let order = serde_json::json!({
+            "operation": {"op_type": "New", "op_code": "00"},
+            "order": {"id": "f1", "quantity": 1},
+        });
+        handlers.trigger_order(OrderEventType::FuturesOrder, order);
+        let deal = serde_json::json!({"trade_id": "f1", "quantity": 1});
+        handlers.trigger_order(OrderEventType::FuturesDeal, deal);
+        assert_eq!(
+            wait_for(&recorder, 2).await,
+            vec![
+                OrderState::Submitted.to_string(),
+                OrderState::Filled.to_string()
+            ]
+        );
+    }
+
+    fn dropped(handlers: &EventHandlers, stream: &str) -> u64 {
+        handlers
+            .stats()
+            .into_iter()
+            .find(|stats| stats.stream == stream)
+            .unwrap()
+            .dropped
+    }
+
+    #[tokio::test]
+    async fn test_bounded_queue_drops_overflow() {
+        let handlers = Arc::new(EventHandlers::new());
+        let recorder = Arc::new(SlowRecorder::default());
+        handlers
+            .register_async_with(
+                recorder.clone() as Arc<dyn AsyncSystemCallback>,
+                AsyncQueue::Bounded(1),
+            )
+            .unwrap();
+
+        // The handler takes 20ms per event; a burst overflows the one slot
+        let feeder = handlers.clone();
+        std::thread::spawn(move || {
+            for code in 1..=5 {
+                feeder.trigger_event(0, code, String::new(), String::new());
+            }
+        })
+        .join()
+        .unwrap();
+
+        let dropped = dropped(&handlers, "event");
+        assert!(dropped >= 3, "dropped {}", dropped);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let seen = recorder.seen.lock().unwrap().len() as u64;
+        assert_eq!(seen + dropped, 5);
+    }
+
+    #[tokio::test]
+    async fn test_stopped_handler_counts_drops() {
+        let handlers = EventHandlers::new();
+        handlers.policy().set_hook(Arc::new(|_| {}));
+        handlers.policy().set_failure_limit(Some(1));
+        let recorder = Arc::new(SlowRecorder::default());
+        handlers
+            .register_async(recorder.clone() as Arc<dyn AsyncSystemCallback>)
+            .unwrap();
+
+        // Code 13 panics and the limit of one stops the handler's task
+        handlers.trigger_event(0, 13, String::new(), String::new());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        handlers.trigger_event(0, 1, String::new(), String::new());
+        handlers.trigger_session_down();
+
+        assert_eq!(dropped(&handlers, "event"), 1);
+        assert_eq!(dropped(&handlers, "session_down"), 1);
+        assert!(recorder.seen.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_register_async_requires_runtime() {
+        let handlers = EventHandlers::new();
+        let recorder = Arc::new(SlowRecorder::default());
+        let result = handlers.register_async(recorder as Arc<dyn AsyncOrderCallback>);
+        assert!(matches!(result, Err(Error::System(_))));
+    }
+}
@@ -0,0 +1,534 @@
+//! Trading backend abstraction (交易後端抽象)
+//!
+//! [`Backend`] covers login, contracts, orders, queries (trades, positions,
+//! margin) and quote subscription. `Shioaji` implements it over the system
+//! shioaji package (PyO3) and [`PaperBroker`] implements it locally, so
+//! strategy code written against `Arc<dyn Backend>` runs unchanged in unit
+//! tests, paper trading and production:
+//!
+//! ```no_run
+//! use rshioaji::{Action, Backend, Exchange, Order, OrderType, Stock, StockPriceType};
+//! use std::sync::Arc;
+//!
+//! async fn buy_one(backend: Arc<dyn Backend>) -> rshioaji::Result<()> {
+//!     let order = Order::new(Action::Buy, 580.0, 1, OrderType::ROD, StockPriceType::LMT);
+//!     let trade = backend
+//!         .place_order(Stock::new("2330", Exchange::TSE).contract, order)
+//!         .await?;
+//!     println!("{} placed on {}", trade.order_id, backend.name());
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`Shioaji::with_backend`] goes the other way: the client hands its calls to
+//! another backend, so code written against `Shioaji` itself also runs on a
+//! `PaperBroker` without Python.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::client::{OrderEventCallback, Shioaji, TickFOPCallback, TickSTKCallback};
+use crate::error::{Error, Result};
+use crate::paper::PaperBroker;
+use crate::types::{
+    Account, Contract, Contracts, FuturesOrder, FuturesTrade, Margin, Order, Position, QuoteType,
+    Trade, Unit,
+};
+
+/// Boxed future returned by [`Backend`] methods
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Operations a trading backend provides
+pub trait Backend: Send + Sync {
+    /// Short backend name for logs ("shioaji", "paper", ...)
+    fn name(&self) -> &'static str;
+
+    fn login<'a>(
+        &'a self,
+        api_key: &'a str,
+        secret_key: &'a str,
+    ) -> BackendFuture<'a, Vec<Account>>;
+
+    fn logout(&self) -> BackendFuture<'_, bool>;
+
+    /// Contracts currently loaded, if any
+    fn contracts(&self) -> BackendFuture<'_, Option<Contracts>>;
+
+    fn list_accounts(&self) -> BackendFuture<'_, Vec<Account>>;
+
+    fn place_order(&self, contract: Contract, order: Order) -> BackendFuture<'_, Trade>;
+
+    fn place_futures_order(
+        &self,
+        contract: Contract,
+        order: FuturesOrder,
+    ) -> BackendFuture<'_, FuturesTrade>;
+
+    fn update_order(
+        &self,
+        trade: Trade,
+        price: Option<f64>,
+        qty: Option<i32>,
+    ) -> BackendFuture<'_, Trade>;
+
+    fn cancel_order(&self, trade: Trade) -> BackendFuture<'_, Trade>;
+
+    fn list_trades(&self) -> BackendFuture<'_, Vec<Trade>>;
+
+    /// Positions of `account`; stock quantities in lots unless `unit` is
+    /// `Unit::Share`
+    fn list_positions(
+        &self,
+        account: Option<Account>,
+        unit: Option<Unit>,
+    ) -> BackendFuture<'_, Vec<Position>>;
+
+    /// Futures/options account margin (`api.margin`)
+    fn margin(&self, account: Option<Account>) -> BackendFuture<'_, Margin>;
+
+    fn subscribe(&self, contract: Contract, quote_type: QuoteType) -> BackendFuture<'_, ()>;
+
+    /// Register an order/deal callback (`set_order_callback` messages)
+    fn on_order(&self, callback: OrderEventCallback) -> BackendFuture<'_, ()>;
+
+    fn on_tick_stk_v1(&self, callback: TickSTKCallback) -> BackendFuture<'_, ()>;
+
+    fn on_tick_fop_v1(&self, callback: TickFOPCallback) -> BackendFuture<'_, ()>;
+}
+
+fn quote_type_str(quote_type: &QuoteType) -> &'static str {
+    match quote_type {
+        QuoteType::Tick => "tick",
+        QuoteType::BidAsk => "bidask",
+        QuoteType::Quote => "quote",
+    }
+}
+
+/// Parse the `quote_type` argument of `Shioaji::subscribe`
+pub(crate) fn quote_type_from_str(quote_type: &str) -> Result<QuoteType> {
+    match quote_type.to_ascii_lowercase().as_str() {
+        "tick" => Ok(QuoteType::Tick),
+        "bidask" => Ok(QuoteType::BidAsk),
+        "quote" => Ok(QuoteType::Quote),
+        _ => Err(Error::InvalidInput(format!(
+            "Unknown quote type {}",
+            quote_type
+        ))),
+    }
+}
+
+impl Backend for Shioaji {
+    fn name(&self) -> &'static str {
+        "shioaji"
+    }
+
+    fn login<'a>(
+        &'a self,
+        api_key: &'a str,
+        secret_key: &'a str,
+    ) -> BackendFuture<'a, Vec<Account>> {
+        Box::pin(Shioaji::login_simple(self, api_key, secret_key, true))
+    }
+
+    fn logout(&self) -> BackendFuture<'_, bool> {
+        Box::pin(Shioaji::logout(self))
+    }
+
+    fn contracts(&self) -> BackendFuture<'_, Option<Contracts>> {
+        Box::pin(async move { Ok(self.get_contracts().await) })
+    }
+
+    fn list_accounts(&self) -> BackendFuture<'_, Vec<Account>> {
+        Box::pin(Shioaji::list_accounts(self))
+    }
+
+    fn place_order(&self, contract: Contract, order: Order) -> BackendFuture<'_, Trade> {
+        Box::pin(Shioaji::place_order(self, contract, order))
+    }
+
+    fn place_futures_order(
+        &self,
+        contract: Contract,
+        order: FuturesOrder,
+    ) -> BackendFuture<'_, FuturesTrade> {
+        Box::pin(Shioaji::place_futures_order(self, contract, order))
+    }
+
+    fn update_order(
+        &self,
+        trade: Trade,
+        price: Option<f64>,
+        qty: Option<i32>,
+    ) -> BackendFuture<'_, Trade> {
+        Box::pin(Shioaji::update_order(self, trade, price, qty, None))
+    }
+
+    fn cancel_order(&self, trade: Trade) -> BackendFuture<'_, Trade> {
+        Box::pin(Shioaji::cancel_order(self, trade, None))
+    }
+
+    fn list_trades(&self) -> BackendFuture<'_, Vec<Trade>> {
+        Box::pin(Shioaji::list_trades(self))
+    }
+
+    fn list_positions(
+        &self,
+        account: Option<Account>,
+        unit: Option<Unit>,
+    ) -> BackendFuture<'_, Vec<Position>> {
+        Box::pin(Shioaji::list_positions(self, account, unit, None))
+    }
+
+    fn margin(&self, account: Option<Account>) -> BackendFuture<'_, Margin> {
+        Box::pin(Shioaji::margin(self, account, None))
+    }
+
+    fn subscribe(&self, contract: Contract, quote_type: QuoteType) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            Shioaji::subscribe(self, contract, quote_type_str(&quote_type)).await?;
+            Ok(())
+        })
+    }
+
+    fn on_order(&self, callback: OrderEventCallback) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            Shioaji::on_order(self, move |event_type, msg| callback(event_type, msg)).await?;
+            Ok(())
+        })
+    }
+
+    fn on_tick_stk_v1(&self, callback: TickSTKCallback) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            Shioaji::on_tick_stk_v1(self, move |exchange, tick| callback(exchange, tick), false)
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn on_tick_fop_v1(&self, callback: TickFOPCallback) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            Shioaji::on_tick_fop_v1(self, move |exchange, tick| callback(exchange, tick), false)
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+impl Backend for PaperBroker {
+    fn name(&self) -> &'static str {
+        "paper"
+    }
+
+    /// Paper accounts from `PaperConfig`; credentials are ignored
+    fn login<'a>(
+        &'a self,
+        _api_key: &'a str,
+        _secret_key: &'a str,
+    ) -> BackendFuture<'a, Vec<Account>> {
+        Backend::list_accounts(self)
+    }
+
+    fn logout(&self) -> BackendFuture<'_, bool> {
+        Box::pin(async { Ok(true) })
+    }
+
+    fn contracts(&self) -> BackendFuture<'_, Option<Contracts>> {
+        Box::pin(async move { Ok(PaperBroker::contracts(self).cloned()) })
+    }
+
+    fn list_accounts(&self) -> BackendFuture<'_, Vec<Account>> {
+        Box::pin(async move {
+            let config = self.config();
+            Ok(vec![
+                config.stock_account.clone(),
+                config.futopt_account.clone(),
+            ])
+        })
+    }
+
+    fn place_order(&self, contract: Contract, order: Order) -> BackendFuture<'_, Trade> {
+        Box::pin(PaperBroker::place_order(self, contract, order))
+    }
+
+    fn place_futures_order(
+        &self,
+        contract: Contract,
+        order: FuturesOrder,
+    ) -> BackendFuture<'_, FuturesTrade> {
+        Box::pin(PaperBroker::place_futures_order(self, contract, order))
+    }
+
+    fn update_order(
+        &self,
+        trade: Trade,
+        price: Option<f64>,
+        qty: Option<i32>,
+    ) -> BackendFuture<'_, Trade> {
+        Box::pin(PaperBroker::update_order(self, trade, price, qty, None))
+    }
+
+    fn cancel_order(&self, trade: Trade) -> BackendFuture<'_, Trade> {
+        Box::pin(PaperBroker::cancel_order(self, trade, None))
+    }
+
+    fn list_trades(&self) -> BackendFuture<'_, Vec<Trade>> {
+        Box::pin(PaperBroker::list_trades(self))
+    }
+
+    fn list_positions(
+        &self,
+        account: Option<Account>,
+        unit: Option<Unit>,
+    ) -> BackendFuture<'_, Vec<Position>> {
+        Box::pin(async move { PaperBroker::list_positions(self, account.as_ref(), unit).await })
+    }
+
+    fn margin(&self, account: Option<Account>) -> BackendFuture<'_, Margin> {
+        Box::pin(async move { PaperBroker::margin(self, account.as_ref()).await })
+    }
+
+    /// Paper market data is fed with `on_tick_*` / `attach`; nothing to subscribe
+    fn subscribe(&self, contract: Contract, _quote_type: QuoteType) -> BackendFuture<'_, ()> {
+        log::debug!("📊 Paper subscribe {} (no-op)", contract.base.code);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn on_order(&self, callback: OrderEventCallback) -> BackendFuture<'_, ()> {
+        Box::pin(PaperBroker::on_order(self, move |event_type, msg| {
+            callback(event_type, msg)
+        }))
+    }
+
+    fn on_tick_stk_v1(&self, callback: TickSTKCallback) -> BackendFuture<'_, ()> {
+        let result =
+            PaperBroker::on_tick_stk_v1(self, move |exchange, tick| callback(exchange, tick));
+        Box::pin(async move { result })
+    }
+
+    fn on_tick_fop_v1(&self, callback: TickFOPCallback) -> BackendFuture<'_, ()> {
+        let result =
+            PaperBroker::on_tick_fop_v1(self, move |exchange, tick| callback(exchange, tick));
+        Box::pin(async move { result })
+    }
+}
+
+/// Shared backends are backends too
+impl<B: Backend + ?Sized> Backend for Arc<B> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn login<'a>(
+        &'a self,
+        api_key: &'a str,
+        secret_key: &'a str,
+    ) -> BackendFuture<'a, Vec<Account>> {
+        (**self).login(api_key, secret_key)
+    }
+
+    fn logout(&self) -> BackendFuture<'_, bool> {
+        (**self).logout()
+    }
+
+    fn contracts(&self) -> BackendFuture<'_, Option<Contracts>> {
+        (**self).contracts()
+    }
+
+    fn list_accounts(&self) -> BackendFuture<'_, Vec<Account>> {
+        (**self).list_accounts()
+    }
+
+    fn place_order(&self, contract: Contract, order: Order) -> BackendFuture<'_, Trade> {
+        (**self).place_order(contract, order)
+    }
+
+    fn place_futures_order(
+        &self,
+        contract: Contract,
+        order: FuturesOrder,
+    ) -> BackendFuture<'_, FuturesTrade> {
+        (**self).place_futures_order(contract, order)
+    }
+
+    fn update_order(
+        &self,
+        trade: Trade,
+        price: Option<f64>,
+        qty: Option<i32>,
+    ) -> BackendFuture<'_, Trade> {
+        (**self).update_order(trade, price, qty)
+    }
+
+    fn cancel_order(&self, trade: Trade) -> BackendFuture<'_, Trade> {
+        (**self).cancel_order(trade)
+    }
+
+    fn list_trades(&self) -> BackendFuture<'_, Vec<Trade>> {
+        (**self).list_trades()
+    }
+
+    fn list_positions(
+        &self,
+        account: Option<Account>,
+        unit: Option<Unit>,
+    ) -> BackendFuture<'_, Vec<Position>> {
+        (**self).list_positions(account, unit)
+    }
+
+    fn margin(&self, account: Option<Account>) -> BackendFuture<'_, Margin> {
+        (**self).margin(account)
+    }
+
+    fn subscribe(&self, contract: Contract, quote_type: QuoteType) -> BackendFuture<'_, ()> {
+        (**self).subscribe(contract, quote_type)
+    }
+
+    fn on_order(&self, callback: OrderEventCallback) -> BackendFuture<'_, ()> {
+        (**self).on_order(callback)
+    }
+
+    fn on_tick_stk_v1(&self, callback: TickSTKCallback) -> BackendFuture<'_, ()> {
+        (**self).on_tick_stk_v1(callback)
+    }
+
+    fn on_tick_fop_v1(&self, callback: TickFOPCallback) -> BackendFuture<'_, ()> {
+        (**self).on_tick_fop_v1(callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paper::PaperConfig;
+    use crate::types::{Action, Exchange, OrderType, Stock, StockPriceType, TickSTKv1};
+    use chrono::{TimeZone, Utc};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn tick(price: f64, volume: i64, second: u32) -> TickSTKv1 {
+        TickSTKv1 {
+            code: "2330".to_string(),
+            datetime: Utc.with_ymd_and_hms(2024, 1, 2, 1, 30, second).unwrap(),
+            close: price,
+            volume,
+            ..Default::default()
+        }
+    }
+
+    /// Strategy written only against the trait
+    async fn buy_on_dip(backend: &dyn Backend, last: f64) -> Result<Option<Trade>> {
+        if last > 580.0 {
+            return Ok(None);
+        }
+        let contract = Stock::new("2330", Exchange::TSE).contract;
+        backend.subscribe(contract.clone(), QuoteType::Tick).await?;
+        let order = Order::new(Action::Buy, last, 2, OrderType::ROD, StockPriceType::LMT);
+        backend.place_order(contract, order).await.map(Some)
+    }
+
+    #[tokio::test]
+    async fn test_paper_backend_runs_strategy() {
+        let broker = Arc::new(PaperBroker::new(PaperConfig::default()));
+        let backend: Arc<dyn Backend> = broker.clone();
+        assert_eq!(backend.name(), "paper");
+        assert_eq!(backend.login("key", "secret").await.unwrap().len(), 2);
+
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let counter = ticks.clone();
+        backend
+            .on_tick_stk_v1(Arc::new(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }))
+            .await
+            .unwrap();
+
+        assert!(buy_on_dip(backend.as_ref(), 585.0).await.unwrap().is_none());
+        let trade = buy_on_dip(backend.as_ref(), 579.0).await.unwrap().unwrap();
+        broker.on_tick_stk(&tick(579.0, 1, 0));
+        broker.on_tick_stk(&tick(578.0, 5, 1));
+        broker.on_tick_stk(&tick(582.0, 1, 2));
+        assert_eq!(ticks.load(Ordering::SeqCst), 3);
+
+        let trades = backend.list_trades().await.unwrap();
+        assert_eq!(trades[0].order_id, trade.order_id);
+        let positions = backend.list_positions(None, None).await.unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].quantity, 2);
+        assert_eq!(positions[0].direction, Action::Buy);
+        assert!((positions[0].price - 579.0).abs() < 1e-9);
+        // (582 - 579) × 2 張 × 1000 股
+        assert!((positions[0].pnl - 6000.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_client_delegates_to_paper_backend() {
+        let broker = Arc::new(PaperBroker::new(PaperConfig::default()));
+        let client = Shioaji::new(true, Default::default())
+            .unwrap()
+            .with_backend(broker.clone());
+        let dir = tempfile::tempdir().unwrap();
+        client.set_journal(Arc::new(crate::Journal::open(dir.path()).unwrap()));
+
+        // No Python: init and login go to the paper broker
+        client.init().await.unwrap();
+        let accounts = client.login_simple("key", "secret", true).await.unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert!(client.get_default_stock_account().await.is_some());
+
+        let (deals, ticks) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let counter = deals.clone();
+        client
+            .on_order(move |event_type, _| {
+                if event_type.is_deal() {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .await
+            .unwrap();
+        let counter = ticks.clone();
+        client
+            .on_tick_stk_v1(
+                move |_, _| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                },
+                false,
+            )
+            .await
+            .unwrap();
+
+        let contract = Stock::new("2330", Exchange::TSE).contract;
+        client.subscribe(contract.clone(), "tick").await.unwrap();
+        let order = Order::new(Action::Buy, 579.0, 2, OrderType::ROD, StockPriceType::LMT);
+        let trade = client.place_order(contract, order).await.unwrap();
+        broker.on_tick_stk(&tick(579.0, 1, 0));
+        broker.on_tick_stk(&tick(578.0, 5, 1));
+
+        assert_eq!(ticks.load(Ordering::SeqCst), 2);
+        assert!(deals.load(Ordering::SeqCst) > 0);
+        assert_eq!(
+            client.list_trades().await.unwrap()[0].order_id,
+            trade.order_id
+        );
+        let positions = client.list_positions(None, None, None).await.unwrap();
+        assert_eq!(positions[0].quantity, 2);
+        let shares = client
+            .list_positions(None, Some(Unit::Share), None)
+            .await
+            .unwrap();
+        assert_eq!(shares[0].quantity, 2000);
+        // Margin queries go to the paper broker too
+        let margin = client.margin(None, None).await.unwrap();
+        assert_eq!(margin.available_margin, 0.0);
+
+        // The client's own journal saw the order and its fills
+        let state = crate::journal::replay(dir.path()).unwrap();
+        assert!(state.unresolved_intents.is_empty());
+        assert_eq!(
+            state.trades[&trade.order_id].status,
+            crate::types::Status::Filled
+        );
+
+        assert!(client.logout().await.unwrap());
+        assert!(!client.is_logged_in().await);
+    }
+}
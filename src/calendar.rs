@@ -0,0 +1,420 @@
+//! Trading calendar and sessions (交易日曆與交易時段)
+//!
+//! All session times are Taipei local time:
+//!
+//! | 市場 | 時段 | 委託 | 交易 |
+//! |------|------|------|------|
+//! | TWSE/TPEx | 一般 (Regular) | 08:30 | 09:00–13:30 |
+//! | TWSE/TPEx | 盤中零股 (OddLot) | 08:30 | 09:00–13:30 |
+//! | TWSE/TPEx | 盤後零股 (AfterHoursOddLot) | 13:40 | 13:40–14:30 |
+//! | TWSE/TPEx | 盤後定價 (AfterHoursFixed) | 14:00 | 14:00–14:30 |
+//! | TAIFEX | 日盤 (Day) | 08:30 | 08:45–13:45 |
+//! | TAIFEX | 夜盤 (Night) | 14:50 | 15:00–05:00 (+1) |
+//!
+//! A night session belongs to the trading day on which it opens. Holidays
+//! come from the bundled `data/taiwan_holidays.json` or a file in the same
+//! format loaded with [`TradingCalendar::load`].
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::futures::RollCalendar;
+use crate::types::{Exchange, StockOrderLot};
+
+const TAIPEI_OFFSET_SECS: i32 = 8 * 3600;
+const BUNDLED_HOLIDAYS: &str = include_str!("../data/taiwan_holidays.json");
+
+/// Trading session kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SessionKind {
+    /// 證券一般交易
+    Regular,
+    /// 盤中零股
+    OddLot,
+    /// 盤後零股
+    AfterHoursOddLot,
+    /// 盤後定價
+    AfterHoursFixed,
+    /// 期貨日盤
+    Day,
+    /// 期貨夜盤 (盤後交易)
+    Night,
+}
+
+impl SessionKind {
+    /// Session a stock order of `lot` trades in
+    pub fn for_order_lot(lot: Option<&StockOrderLot>) -> Self {
+        match lot {
+            Some(StockOrderLot::IntradayOdd) => SessionKind::OddLot,
+            Some(StockOrderLot::Odd) => SessionKind::AfterHoursOddLot,
+            Some(StockOrderLot::Fixing) => SessionKind::AfterHoursFixed,
+            Some(StockOrderLot::Common) | Some(StockOrderLot::BlockTrade) | None => {
+                SessionKind::Regular
+            }
+        }
+    }
+}
+
+/// Session hours in Taipei time; `close` before `open` means it ends the next day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub kind: SessionKind,
+    /// 開始接受委託
+    pub order_entry: NaiveTime,
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+}
+
+impl Session {
+    const fn new(
+        kind: SessionKind,
+        entry: (u32, u32),
+        open: (u32, u32),
+        close: (u32, u32),
+    ) -> Self {
+        Self {
+            kind,
+            order_entry: hm(entry),
+            open: hm(open),
+            close: hm(close),
+        }
+    }
+
+    pub fn is_overnight(&self) -> bool {
+        self.close <= self.open
+    }
+}
+
+const fn hm((hour, minute): (u32, u32)) -> NaiveTime {
+    match NaiveTime::from_hms_opt(hour, minute, 0) {
+        Some(time) => time,
+        None => panic!("invalid session time"),
+    }
+}
+
+const SECURITIES_SESSIONS: [Session; 4] = [
+    Session::new(SessionKind::Regular, (8, 30), (9, 0), (13, 30)),
+    Session::new(SessionKind::OddLot, (8, 30), (9, 0), (13, 30)),
+    Session::new(SessionKind::AfterHoursOddLot, (13, 40), (13, 40), (14, 30)),
+    Session::new(SessionKind::AfterHoursFixed, (14, 0), (14, 0), (14, 30)),
+];
+
+const FUTURES_SESSIONS: [Session; 2] = [
+    Session::new(SessionKind::Day, (8, 30), (8, 45), (13, 45)),
+    Session::new(SessionKind::Night, (14, 50), (15, 0), (5, 0)),
+];
+
+/// One concrete session on a trading day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionWindow {
+    pub kind: SessionKind,
+    pub trading_day: NaiveDate,
+    pub order_entry: DateTime<Utc>,
+    pub open: DateTime<Utc>,
+    pub close: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HolidayFile {
+    holidays: Vec<HolidayEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HolidayEntry {
+    date: NaiveDate,
+    #[serde(default)]
+    name: String,
+}
+
+/// Market holidays and session hours
+#[derive(Debug, Clone)]
+pub struct TradingCalendar {
+    holidays: BTreeMap<NaiveDate, String>,
+}
+
+impl Default for TradingCalendar {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+impl TradingCalendar {
+    /// Calendar with the bundled holiday list
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_HOLIDAYS).expect("bundled holiday file is valid")
+    }
+
+    /// Calendar with weekends as the only non-trading days
+    pub fn empty() -> Self {
+        Self {
+            holidays: BTreeMap::new(),
+        }
+    }
+
+    /// Parse a holiday file: `{"holidays": [{"date": "2025-01-01", "name": "元旦"}]}`
+    pub fn from_json(content: &str) -> Result<Self> {
+        let file: HolidayFile = serde_json::from_str(content)?;
+        Ok(Self {
+            holidays: file
+                .holidays
+                .into_iter()
+                .map(|entry| (entry.date, entry.name))
+                .collect(),
+        })
+    }
+
+    /// Load a holiday file, replacing the bundled list
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            Error::FileSystem(format!(
+                "Failed to read holiday file {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        let calendar = Self::from_json(&content)?;
+        log::info!("📅 Loaded {} market holidays", calendar.holidays.len());
+        Ok(calendar)
+    }
+
+    /// Add holidays, e.g. ad-hoc typhoon closures
+    pub fn with_holiday(mut self, date: NaiveDate, name: &str) -> Self {
+        self.holidays.insert(date, name.to_string());
+        self
+    }
+
+    /// Holiday name, if `date` is a listed holiday
+    pub fn holiday(&self, date: NaiveDate) -> Option<&str> {
+        self.holidays.get(&date).map(String::as_str)
+    }
+
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.holidays.contains_key(&date)
+    }
+
+    /// Trading days in `[start, end]`
+    pub fn trading_days(&self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .filter(|date| self.is_trading_day(*date))
+            .collect()
+    }
+
+    /// Roll calendar for futures settlement sharing these holidays
+    pub fn roll_calendar(&self) -> RollCalendar {
+        RollCalendar::new().with_holidays(self.holidays.keys().copied())
+    }
+
+    pub fn sessions(exchange: &Exchange) -> &'static [Session] {
+        match exchange {
+            Exchange::TAIFEX => &FUTURES_SESSIONS,
+            Exchange::TSE | Exchange::OTC | Exchange::OES => &SECURITIES_SESSIONS,
+        }
+    }
+
+    /// Main sessions used by [`is_open`](Self::is_open) and [`next_open`](Self::next_open)
+    fn primary(exchange: &Exchange) -> &'static [SessionKind] {
+        match exchange {
+            Exchange::TAIFEX => &[SessionKind::Day, SessionKind::Night],
+            Exchange::TSE | Exchange::OTC | Exchange::OES => &[SessionKind::Regular],
+        }
+    }
+
+    /// `kind` on `trading_day`, if the exchange trades it that day
+    pub fn window(
+        &self,
+        exchange: &Exchange,
+        kind: SessionKind,
+        trading_day: NaiveDate,
+    ) -> Option<SessionWindow> {
+        if !self.is_trading_day(trading_day) {
+            return None;
+        }
+        let session = Self::sessions(exchange).iter().find(|s| s.kind == kind)?;
+        let close_day = if session.is_overnight() {
+            trading_day + Duration::days(1)
+        } else {
+            trading_day
+        };
+        Some(SessionWindow {
+            kind,
+            trading_day,
+            order_entry: taipei(trading_day, session.order_entry),
+            open: taipei(trading_day, session.open),
+            close: taipei(close_day, session.close),
+        })
+    }
+
+    /// Session of `kind` trading at `ts`
+    pub fn session_at(
+        &self,
+        exchange: &Exchange,
+        kind: SessionKind,
+        ts: DateTime<Utc>,
+    ) -> Option<SessionWindow> {
+        let today = local_date(ts);
+        [today - Duration::days(1), today]
+            .into_iter()
+            .filter_map(|day| self.window(exchange, kind, day))
+            .find(|w| w.open <= ts && ts < w.close)
+    }
+
+    /// Whether the exchange's main session is trading at `ts`
+    /// (regular session for securities, day or night session for TAIFEX)
+    pub fn is_open(&self, exchange: &Exchange, ts: DateTime<Utc>) -> bool {
+        Self::primary(exchange)
+            .iter()
+            .any(|kind| self.session_at(exchange, *kind, ts).is_some())
+    }
+
+    /// Whether an order for `kind` is accepted at `ts` (pre-open entry included)
+    pub fn accepts_orders(
+        &self,
+        exchange: &Exchange,
+        kind: SessionKind,
+        ts: DateTime<Utc>,
+    ) -> bool {
+        let today = local_date(ts);
+        [today - Duration::days(1), today]
+            .into_iter()
+            .filter_map(|day| self.window(exchange, kind, day))
+            .any(|w| w.order_entry <= ts && ts < w.close)
+    }
+
+    /// Next opening of the exchange's main session at or after `ts`
+    pub fn next_open(&self, exchange: &Exchange, ts: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = local_date(ts);
+        // 長假最長不超過一個月
+        (0..=31)
+            .map(|offset| today + Duration::days(offset))
+            .flat_map(|day| {
+                Self::primary(exchange)
+                    .iter()
+                    .filter_map(move |kind| self.window(exchange, *kind, day))
+            })
+            .map(|w| w.open)
+            .filter(|open| *open >= ts)
+            .min()
+    }
+
+    /// `Err(MarketClosed)` unless orders for `kind` are accepted at `ts`
+    pub fn ensure_accepts_orders(
+        &self,
+        exchange: &Exchange,
+        kind: SessionKind,
+        ts: DateTime<Utc>,
+    ) -> Result<()> {
+        if self.accepts_orders(exchange, kind, ts) {
+            Ok(())
+        } else {
+            log::warn!("⚠️ {} {:?} session is closed at {}", exchange, kind, ts);
+            Err(Error::MarketClosed)
+        }
+    }
+}
+
+fn taipei_offset() -> FixedOffset {
+    FixedOffset::east_opt(TAIPEI_OFFSET_SECS).expect("valid offset")
+}
+
+pub(crate) fn taipei(date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    taipei_offset()
+        .from_local_datetime(&date.and_time(time))
+        .single()
+        .expect("fixed offset has no gaps")
+        .with_timezone(&Utc)
+}
+
+pub(crate) fn local_date(ts: DateTime<Utc>) -> NaiveDate {
+    ts.with_timezone(&taipei_offset()).date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        taipei(
+            NaiveDate::from_ymd_opt(y, m, d).unwrap(),
+            NaiveTime::from_hms_opt(h, min, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_bundled_holidays() {
+        let calendar = TradingCalendar::bundled();
+        let new_year = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        assert_eq!(calendar.holiday(new_year), Some("元旦"));
+        assert!(!calendar.is_trading_day(new_year));
+
+        // 2025/01/20 ~ 2025/02/07: 春節封關 1/23 起，2/3 開紅盤
+        let days = calendar.trading_days(
+            NaiveDate::from_ymd_opt(2025, 1, 20).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 2, 7).unwrap(),
+        );
+        assert_eq!(days.len(), 8);
+        assert_eq!(days[3], NaiveDate::from_ymd_opt(2025, 2, 3).unwrap());
+
+        let roll = calendar.roll_calendar();
+        assert!(!roll.is_business_day(new_year));
+    }
+
+    #[test]
+    fn test_stock_sessions() {
+        let calendar = TradingCalendar::empty();
+        let tse = Exchange::TSE;
+        assert!(!calendar.is_open(&tse, at(2024, 3, 4, 8, 59)));
+        assert!(calendar.is_open(&tse, at(2024, 3, 4, 9, 0)));
+        assert!(!calendar.is_open(&tse, at(2024, 3, 4, 13, 30)));
+        // 週六休市
+        assert!(!calendar.is_open(&tse, at(2024, 3, 9, 10, 0)));
+
+        assert!(calendar.accepts_orders(&tse, SessionKind::Regular, at(2024, 3, 4, 8, 30)));
+        assert!(calendar.accepts_orders(
+            &tse,
+            SessionKind::AfterHoursFixed,
+            at(2024, 3, 4, 14, 10)
+        ));
+        assert!(matches!(
+            calendar.ensure_accepts_orders(&tse, SessionKind::OddLot, at(2024, 3, 4, 13, 45)),
+            Err(Error::MarketClosed)
+        ));
+        assert_eq!(
+            SessionKind::for_order_lot(Some(&StockOrderLot::IntradayOdd)),
+            SessionKind::OddLot
+        );
+    }
+
+    #[test]
+    fn test_futures_night_session_and_next_open() {
+        let friday = NaiveDate::from_ymd_opt(2024, 3, 8).unwrap();
+        let calendar = TradingCalendar::empty().with_holiday(
+            NaiveDate::from_ymd_opt(2024, 3, 11).unwrap(),
+            "test holiday",
+        );
+        let taifex = Exchange::TAIFEX;
+
+        // 週五夜盤延續到週六 05:00
+        let night = calendar.session_at(&taifex, SessionKind::Night, at(2024, 3, 9, 4, 59));
+        assert_eq!(night.unwrap().trading_day, friday);
+        assert!(!calendar.is_open(&taifex, at(2024, 3, 9, 5, 0)));
+        assert!(!calendar.is_open(&taifex, at(2024, 3, 8, 14, 0)));
+        assert!(calendar.is_open(&taifex, at(2024, 3, 8, 8, 45)));
+
+        // 週末加週一假日，下次開盤為週二日盤
+        assert_eq!(
+            calendar.next_open(&taifex, at(2024, 3, 9, 5, 0)),
+            Some(at(2024, 3, 12, 8, 45))
+        );
+        assert_eq!(
+            calendar.next_open(&Exchange::TSE, at(2024, 3, 8, 9, 1)),
+            Some(at(2024, 3, 12, 9, 0))
+        );
+    }
+}
@@ -1,13 +1,33 @@
-use crate::types::orders::OrderState;
+use crate::error::Error;
+use crate::types::orders::{OrderState, OrderStateTracker};
+use crate::types::OrderEventType;
 use crate::types::{
     BidAskFOPv1, BidAskSTKv1, Exchange, QuoteSTKv1, SecurityType, TickFOPv1, TickSTKv1,
 };
-use std::sync::Arc;
-
-/// Type alias for event closure to reduce complexity
-type EventClosure = Arc<dyn Fn(i32, i32, String, String) + Send + Sync>;
-/// Type alias for session down closure
-type SessionDownClosure = Arc<dyn Fn() + Send + Sync>;
+use arc_swap::ArcSwap;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+/// Stock tick callback (`on_tick_stk_v1`)
+pub type TickSTKCallback = Arc<dyn Fn(Exchange, TickSTKv1) + Send + Sync>;
+/// Futures/options tick callback (`on_tick_fop_v1`)
+pub type TickFOPCallback = Arc<dyn Fn(Exchange, TickFOPv1) + Send + Sync>;
+/// Stock bid/ask callback
+pub type BidAskSTKCallback = Arc<dyn Fn(Exchange, BidAskSTKv1) + Send + Sync>;
+/// Futures/options bid/ask callback (`on_bidask_fop_v1`)
+pub type BidAskFOPCallback = Arc<dyn Fn(Exchange, BidAskFOPv1) + Send + Sync>;
+/// Stock quote callback
+pub type QuoteSTKCallback = Arc<dyn Fn(Exchange, QuoteSTKv1) + Send + Sync>;
+/// General quote callback: topic and payload
+pub type QuoteTopicCallback = Arc<dyn Fn(String, serde_json::Value) + Send + Sync>;
+/// System event callback (`on_event`)
+pub type EventCallback = Arc<dyn Fn(i32, i32, String, String) + Send + Sync>;
+/// Session down callback
+pub type SessionDownCallback = Arc<dyn Fn() + Send + Sync>;
+/// Order/deal callback (`set_order_callback`): event type and the raw message as JSON
+pub type OrderEventCallback = Arc<dyn Fn(OrderEventType, serde_json::Value) + Send + Sync>;
 
 /// Trait for handling market data tick events
 pub trait TickCallback: Send + Sync {
@@ -67,156 +87,264 @@ pub trait ContractCallback: Send + Sync {
 }
 
 /// Event handler registry that manages all callback types
+///
+/// The single dispatch path for a client: the Python bridge and
+/// `Shioaji::on_*` closures use the per-stream registries below, and trait
+/// handlers are adapted into closures on the same registries. Every
+/// `register_*` returns a [`CallbackHandle`] that removes the handler.
 pub struct EventHandlers {
-    tick_callbacks: Vec<Arc<dyn TickCallback>>,
-    bidask_callbacks: Vec<Arc<dyn BidAskCallback>>,
-    quote_callbacks: Vec<Arc<dyn QuoteCallback>>,
-    order_callbacks: Vec<Arc<dyn OrderCallback>>,
-    system_callbacks: Vec<Arc<dyn SystemCallback>>,
-    contract_callbacks: Vec<Arc<dyn ContractCallback>>,
-    // Direct function closures for flexibility
-    event_closures: Vec<EventClosure>,
-    session_down_closures: Vec<SessionDownClosure>,
+    pub(crate) tick_stk: Arc<CallbackRegistry<TickSTKCallback>>,
+    pub(crate) tick_fop: Arc<CallbackRegistry<TickFOPCallback>>,
+    pub(crate) bidask_stk: Arc<CallbackRegistry<BidAskSTKCallback>>,
+    pub(crate) bidask_fop: Arc<CallbackRegistry<BidAskFOPCallback>>,
+    pub(crate) quote_stk: Arc<CallbackRegistry<QuoteSTKCallback>>,
+    pub(crate) quote: Arc<CallbackRegistry<QuoteTopicCallback>>,
+    pub(crate) event: Arc<CallbackRegistry<EventCallback>>,
+    pub(crate) session_down: Arc<CallbackRegistry<SessionDownCallback>>,
+    pub(crate) order: Arc<CallbackRegistry<OrderEventCallback>>,
+    pub(crate) contract: Arc<CallbackRegistry<Arc<dyn ContractCallback>>>,
+    policy: Arc<CallbackErrorPolicy>,
 }
 
 impl EventHandlers {
     pub fn new() -> Self {
+        let policy = Arc::new(CallbackErrorPolicy::new());
+        fn registry<T: Send + Sync + 'static>(
+            stream: &'static str,
+            policy: &Arc<CallbackErrorPolicy>,
+        ) -> Arc<CallbackRegistry<T>> {
+            Arc::new(CallbackRegistry::new(stream).with_policy(policy.clone()))
+        }
         Self {
-            tick_callbacks: Vec::new(),
-            bidask_callbacks: Vec::new(),
-            quote_callbacks: Vec::new(),
-            order_callbacks: Vec::new(),
-            system_callbacks: Vec::new(),
-            contract_callbacks: Vec::new(),
-            event_closures: Vec::new(),
-            session_down_closures: Vec::new(),
+            tick_stk: registry("tick_stk", &policy),
+            tick_fop: registry("tick_fop", &policy),
+            bidask_stk: registry("bidask_stk", &policy),
+            bidask_fop: registry("bidask_fop", &policy),
+            quote_stk: registry("quote_stk", &policy),
+            quote: registry("quote", &policy),
+            event: registry("event", &policy),
+            session_down: registry("session_down", &policy),
+            order: registry("order", &policy),
+            contract: registry("contract", &policy),
+            policy,
         }
     }
 
+    /// Panic reporting shared by every stream
+    pub fn policy(&self) -> &Arc<CallbackErrorPolicy> {
+        &self.policy
+    }
+
+    /// Register any supported handler trait object
+    pub fn register<H: Handler>(&self, handler: H) -> CallbackHandle {
+        handler.register_with(self)
+    }
+
     /// Register a tick data callback handler
-    pub fn register_tick_callback(&mut self, callback: Arc<dyn TickCallback>) {
-        self.tick_callbacks.push(callback);
+    pub fn register_tick_callback(&self, callback: Arc<dyn TickCallback>) -> CallbackHandle {
+        let fop = callback.clone();
+        self.tick_stk
+            .register(Arc::new(move |exchange, tick| {
+                callback.on_tick_stk_v1(exchange, tick)
+            }))
+            .join(self.tick_fop.register(Arc::new(move |exchange, tick| {
+                fop.on_tick_fop_v1(exchange, tick)
+            })))
     }
 
     /// Register a bid/ask callback handler
-    pub fn register_bidask_callback(&mut self, callback: Arc<dyn BidAskCallback>) {
-        self.bidask_callbacks.push(callback);
+    pub fn register_bidask_callback(&self, callback: Arc<dyn BidAskCallback>) -> CallbackHandle {
+        let fop = callback.clone();
+        self.bidask_stk
+            .register(Arc::new(move |exchange, bidask| {
+                callback.on_bidask_stk_v1(exchange, bidask)
+            }))
+            .join(self.bidask_fop.register(Arc::new(move |exchange, bidask| {
+                fop.on_bidask_fop_v1(exchange, bidask)
+            })))
     }
 
     /// Register a quote callback handler
-    pub fn register_quote_callback(&mut self, callback: Arc<dyn QuoteCallback>) {
-        self.quote_callbacks.push(callback);
+    pub fn register_quote_callback(&self, callback: Arc<dyn QuoteCallback>) -> CallbackHandle {
+        let topic = callback.clone();
+        self.quote_stk
+            .register(Arc::new(move |exchange, quote| {
+                callback.on_quote_stk_v1(exchange, quote)
+            }))
+            .join(
+                self.quote
+                    .register(Arc::new(move |name, data| topic.on_quote(name, data))),
+            )
     }
 
     /// Register an order callback handler
-    pub fn register_order_callback(&mut self, callback: Arc<dyn OrderCallback>) {
-        self.order_callbacks.push(callback);
+    ///
+    /// Order/deal events are reported as [`OrderState`] via an
+    /// [`OrderStateTracker`], so deals are `PartFilled` until the order's
+    /// quantity is reached.
+    pub fn register_order_callback(&self, callback: Arc<dyn OrderCallback>) -> CallbackHandle {
+        let tracker = Mutex::new(OrderStateTracker::new());
+        self.order.register(Arc::new(move |event_type, data| {
+            let state = tracker
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .state(&event_type, &data);
+            callback.on_order(state, data)
+        }))
     }
 
     /// Register a system callback handler
-    pub fn register_system_callback(&mut self, callback: Arc<dyn SystemCallback>) {
-        self.system_callbacks.push(callback);
+    pub fn register_system_callback(&self, callback: Arc<dyn SystemCallback>) -> CallbackHandle {
+        let down = callback.clone();
+        self.event
+            .register(Arc::new(move |resp_code, event_code, info, event| {
+                callback.on_event(resp_code, event_code, info, event)
+            }))
+            .join(
+                self.session_down
+                    .register(Arc::new(move || down.on_session_down())),
+            )
     }
 
     /// Register a contract callback handler
-    pub fn register_contract_callback(&mut self, callback: Arc<dyn ContractCallback>) {
-        self.contract_callbacks.push(callback);
+    pub fn register_contract_callback(
+        &self,
+        callback: Arc<dyn ContractCallback>,
+    ) -> CallbackHandle {
+        self.contract.register(callback)
     }
 
     /// Register an event closure (for direct function callbacks)
-    pub fn register_event_closure(
-        &mut self,
-        callback: Arc<dyn Fn(i32, i32, String, String) + Send + Sync>,
-    ) {
-        self.event_closures.push(callback);
+    pub fn register_event_closure(&self, callback: EventCallback) -> CallbackHandle {
+        self.event.register(callback)
     }
 
     /// Register a session down closure (for direct function callbacks)
-    pub fn register_session_down_closure(&mut self, callback: Arc<dyn Fn() + Send + Sync>) {
-        self.session_down_closures.push(callback);
+    pub fn register_session_down_closure(&self, callback: SessionDownCallback) -> CallbackHandle {
+        self.session_down.register(callback)
     }
 
     /// Trigger stock tick callbacks
     pub fn trigger_tick_stk_v1(&self, exchange: Exchange, tick: TickSTKv1) {
-        for callback in &self.tick_callbacks {
-            callback.on_tick_stk_v1(exchange, tick.clone());
-        }
+        self.tick_stk
+            .dispatch(|callback| callback(exchange, tick.clone()));
     }
 
     /// Trigger futures/options tick callbacks
     pub fn trigger_tick_fop_v1(&self, exchange: Exchange, tick: TickFOPv1) {
-        for callback in &self.tick_callbacks {
-            callback.on_tick_fop_v1(exchange, tick.clone());
-        }
+        self.tick_fop
+            .dispatch(|callback| callback(exchange, tick.clone()));
     }
 
     /// Trigger stock bid/ask callbacks
     pub fn trigger_bidask_stk_v1(&self, exchange: Exchange, bidask: BidAskSTKv1) {
-        for callback in &self.bidask_callbacks {
-            callback.on_bidask_stk_v1(exchange, bidask.clone());
-        }
+        self.bidask_stk
+            .dispatch(|callback| callback(exchange, bidask.clone()));
     }
 
     /// Trigger futures/options bid/ask callbacks
     pub fn trigger_bidask_fop_v1(&self, exchange: Exchange, bidask: BidAskFOPv1) {
-        for callback in &self.bidask_callbacks {
-            callback.on_bidask_fop_v1(exchange, bidask.clone());
-        }
+        self.bidask_fop
+            .dispatch(|callback| callback(exchange, bidask.clone()));
     }
 
     /// Trigger stock quote callbacks
     pub fn trigger_quote_stk_v1(&self, exchange: Exchange, quote: QuoteSTKv1) {
-        for callback in &self.quote_callbacks {
-            callback.on_quote_stk_v1(exchange, quote.clone());
-        }
+        self.quote_stk
+            .dispatch(|callback| callback(exchange, quote.clone()));
     }
 
     /// Trigger general quote callbacks
     pub fn trigger_quote(&self, topic: String, data: serde_json::Value) {
-        for callback in &self.quote_callbacks {
-            callback.on_quote(topic.clone(), data.clone());
-        }
+        self.quote
+            .dispatch(|callback| callback(topic.clone(), data.clone()));
     }
 
     /// Trigger order callbacks
-    pub fn trigger_order(&self, order_state: OrderState, data: serde_json::Value) {
-        for callback in &self.order_callbacks {
-            callback.on_order(order_state.clone(), data.clone());
-        }
+    pub fn trigger_order(&self, event_type: OrderEventType, data: serde_json::Value) {
+        self.order
+            .dispatch(|callback| callback(event_type.clone(), data.clone()));
     }
 
     /// Trigger system event callbacks
     pub fn trigger_event(&self, event_type: i32, code: i32, message: String, details: String) {
-        for callback in &self.system_callbacks {
-            callback.on_event(event_type, code, message.clone(), details.clone());
-        }
-        for closure in &self.event_closures {
-            closure(event_type, code, message.clone(), details.clone());
-        }
+        self.event
+            .dispatch(|callback| callback(event_type, code, message.clone(), details.clone()));
     }
 
     /// Trigger session down callbacks
     pub fn trigger_session_down(&self) {
-        for callback in &self.system_callbacks {
-            callback.on_session_down();
-        }
-        for closure in &self.session_down_closures {
-            closure();
-        }
+        self.session_down.dispatch(|callback| callback());
     }
 
     /// Trigger contract fetched callbacks for specific security type
     pub fn trigger_contracts_fetched(&self, security_type: SecurityType) {
-        for callback in &self.contract_callbacks {
-            callback.on_contracts_fetched(security_type.clone());
-        }
+        self.contract
+            .dispatch(|callback| callback.on_contracts_fetched(security_type.clone()));
     }
 
     /// Trigger all contracts fetched callbacks
     pub fn trigger_all_contracts_fetched(&self) {
-        for callback in &self.contract_callbacks {
-            callback.on_all_contracts_fetched();
-        }
+        self.contract
+            .dispatch(|callback| callback.on_all_contracts_fetched());
+    }
+
+    /// Dispatched/dropped/panicked counters for every stream
+    pub fn stats(&self) -> Vec<CallbackStats> {
+        vec![
+            self.tick_stk.stats(),
+            self.tick_fop.stats(),
+            self.bidask_stk.stats(),
+            self.bidask_fop.stats(),
+            self.quote_stk.stats(),
+            self.quote.stats(),
+            self.event.stats(),
+            self.session_down.stats(),
+            self.order.stats(),
+            self.contract.stats(),
+        ]
+    }
+}
+
+/// Handler trait objects accepted by [`EventHandlers::register`] and
+/// `Shioaji::register_handler`
+pub trait Handler {
+    fn register_with(self, handlers: &EventHandlers) -> CallbackHandle;
+}
+
+impl Handler for Arc<dyn TickCallback> {
+    fn register_with(self, handlers: &EventHandlers) -> CallbackHandle {
+        handlers.register_tick_callback(self)
+    }
+}
+
+impl Handler for Arc<dyn BidAskCallback> {
+    fn register_with(self, handlers: &EventHandlers) -> CallbackHandle {
+        handlers.register_bidask_callback(self)
+    }
+}
+
+impl Handler for Arc<dyn QuoteCallback> {
+    fn register_with(self, handlers: &EventHandlers) -> CallbackHandle {
+        handlers.register_quote_callback(self)
+    }
+}
+
+impl Handler for Arc<dyn OrderCallback> {
+    fn register_with(self, handlers: &EventHandlers) -> CallbackHandle {
+        handlers.register_order_callback(self)
+    }
+}
+
+impl Handler for Arc<dyn SystemCallback> {
+    fn register_with(self, handlers: &EventHandlers) -> CallbackHandle {
+        handlers.register_system_callback(self)
+    }
+}
+
+impl Handler for Arc<dyn ContractCallback> {
+    fn register_with(self, handlers: &EventHandlers) -> CallbackHandle {
+        handlers.register_contract_callback(self)
     }
 }
 
@@ -226,6 +354,343 @@ impl Default for EventHandlers {
     }
 }
 
+/// Hook receiving `Error::Callback` for every panicking user callback
+pub type CallbackErrorHook = Arc<dyn Fn(Error) + Send + Sync>;
+
+/// Dispatch counters for one callback stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallbackStats {
+    /// Stream name, e.g. `"tick_stk"`
+    pub stream: &'static str,
+    /// Callbacks currently registered, including disabled ones
+    pub callbacks: usize,
+    /// Callbacks disabled after repeated panics
+    pub disabled: usize,
+    /// Events handed to the registered callbacks
+    pub dispatched: u64,
+    /// Events discarded: malformed SDK payloads, or events an async
+    /// handler's queue did not accept (full, or the handler has stopped)
+    pub dropped: u64,
+    /// Callback invocations that panicked
+    pub panicked: u64,
+}
+
+/// How panicking callbacks are reported and when they are disabled
+///
+/// One policy is usually shared by every registry of a client.
+#[derive(Default)]
+pub struct CallbackErrorPolicy {
+    hook: RwLock<Option<CallbackErrorHook>>,
+    /// Consecutive panics before a callback is disabled; 0 never disables
+    failure_limit: AtomicU32,
+}
+
+impl CallbackErrorPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deliver callback panics to `hook` instead of only logging them
+    pub fn set_hook(&self, hook: CallbackErrorHook) {
+        *self.hook.write().unwrap_or_else(|e| e.into_inner()) = Some(hook);
+    }
+
+    /// Disable a callback after `limit` consecutive panics (`None` never)
+    pub fn set_failure_limit(&self, limit: Option<u32>) {
+        self.failure_limit
+            .store(limit.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    pub fn failure_limit(&self) -> Option<u32> {
+        match self.failure_limit.load(Ordering::Relaxed) {
+            0 => None,
+            limit => Some(limit),
+        }
+    }
+
+    pub(crate) fn report(&self, error: Error) {
+        log::error!("❌ {}", error);
+        let hook = self.hook.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(hook) = hook {
+            // 錯誤掛鉤本身 panic 也不可回拋至 Python
+            if panic::catch_unwind(AssertUnwindSafe(|| hook(error))).is_err() {
+                log::error!("❌ Callback error hook panicked");
+            }
+        }
+    }
+}
+
+/// Text of a caught panic payload
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+/// A registered callback and its failure state
+struct Entry<T> {
+    id: u64,
+    callback: T,
+    consecutive_failures: AtomicU32,
+    disabled: AtomicBool,
+}
+
+/// Copy-on-write callback list for one stream (回調快照)
+///
+/// Registration copies the list of handles and atomically swaps the new list
+/// in; dispatch loads the current `Arc` snapshot without taking a lock, so an
+/// event never waits for a registration. An event arriving during
+/// registration sees either the old or the new list, and callbacks may
+/// register further callbacks while running.
+///
+/// Each callback runs under `catch_unwind`, so a panicking closure never
+/// unwinds into the PyO3 bridge; the panic is reported to the
+/// [`CallbackErrorPolicy`] hook as `Error::Callback`, and the callback is
+/// disabled once it panics the policy's failure limit times in a row.
+pub struct CallbackRegistry<T> {
+    stream: &'static str,
+    snapshot: ArcSwap<Vec<Arc<Entry<T>>>>,
+    /// Serializes registration and removal; dispatch never takes it
+    writer: Mutex<()>,
+    policy: Arc<CallbackErrorPolicy>,
+    next_id: AtomicU64,
+    dispatched: AtomicU64,
+    dropped: AtomicU64,
+    panicked: AtomicU64,
+}
+
+impl<T: Send + Sync + 'static> CallbackRegistry<T> {
+    pub fn new(stream: &'static str) -> Self {
+        Self {
+            stream,
+            snapshot: ArcSwap::from_pointee(Vec::new()),
+            writer: Mutex::new(()),
+            policy: Arc::new(CallbackErrorPolicy::new()),
+            next_id: AtomicU64::new(1),
+            dispatched: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            panicked: AtomicU64::new(0),
+        }
+    }
+
+    /// Share a panic policy with other registries
+    pub fn with_policy(mut self, policy: Arc<CallbackErrorPolicy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Append a callback; the handle removes it again
+    pub fn register(self: &Arc<Self>, callback: T) -> CallbackHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let entry = Arc::new(Entry {
+            id,
+            callback,
+            consecutive_failures: AtomicU32::new(0),
+            disabled: AtomicBool::new(false),
+        });
+        self.swap(|entries| entries.push(entry));
+        let registry: Weak<dyn Unregister> = Arc::downgrade(self) as Weak<dyn Unregister>;
+        CallbackHandle {
+            stream: self.stream,
+            id,
+            parts: vec![(registry, id)],
+        }
+    }
+
+    /// Replace the snapshot with an edited copy
+    fn swap(&self, edit: impl FnOnce(&mut Vec<Arc<Entry<T>>>)) {
+        let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let mut next = self.snapshot.load().as_ref().clone();
+        edit(&mut next);
+        self.snapshot.store(Arc::new(next));
+    }
+
+    fn entries(&self) -> Arc<Vec<Arc<Entry<T>>>> {
+        self.snapshot.load_full()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Invoke `call` for every enabled callback in the current snapshot
+    ///
+    /// Returns the number of callbacks that completed without panicking.
+    pub fn dispatch(&self, mut call: impl FnMut(&T)) -> usize {
+        let entries = self.entries();
+        let mut completed = 0;
+        for entry in entries.iter() {
+            if entry.disabled.load(Ordering::Relaxed) {
+                continue;
+            }
+            match panic::catch_unwind(AssertUnwindSafe(|| call(&entry.callback))) {
+                Ok(()) => {
+                    entry.consecutive_failures.store(0, Ordering::Relaxed);
+                    completed += 1;
+                }
+                Err(payload) => self.on_panic(entry, payload.as_ref()),
+            }
+        }
+        self.dispatched.fetch_add(1, Ordering::Relaxed);
+        completed
+    }
+
+    fn on_panic(&self, entry: &Entry<T>, payload: &(dyn Any + Send)) {
+        self.panicked.fetch_add(1, Ordering::Relaxed);
+        let failures = entry.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        let message = panic_message(payload);
+
+        let disable = self
+            .policy
+            .failure_limit()
+            .is_some_and(|limit| failures >= limit);
+        if disable {
+            entry.disabled.store(true, Ordering::Relaxed);
+        }
+        self.policy.report(Error::Callback(format!(
+            "{} callback #{} panicked ({} in a row{}): {}",
+            self.stream,
+            entry.id,
+            failures,
+            if disable { ", disabled" } else { "" },
+            message
+        )));
+    }
+
+    /// Count an event that could not be dispatched
+    pub fn record_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> CallbackStats {
+        let entries = self.entries();
+        CallbackStats {
+            stream: self.stream,
+            callbacks: entries.len(),
+            disabled: entries
+                .iter()
+                .filter(|entry| entry.disabled.load(Ordering::Relaxed))
+                .count(),
+            dispatched: self.dispatched.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            panicked: self.panicked.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Type-erased removal so handles need not know the callback type
+trait Unregister: Send + Sync {
+    fn unregister(&self, id: u64) -> bool;
+    fn contains(&self, id: u64) -> bool;
+}
+
+impl<T: Send + Sync + 'static> Unregister for CallbackRegistry<T> {
+    fn unregister(&self, id: u64) -> bool {
+        let mut removed = false;
+        self.swap(|entries| {
+            let before = entries.len();
+            entries.retain(|entry| entry.id != id);
+            removed = entries.len() != before;
+        });
+        removed
+    }
+
+    fn contains(&self, id: u64) -> bool {
+        self.entries().iter().any(|entry| entry.id == id)
+    }
+}
+
+/// Registration of one callback (回調註冊控制代碼)
+///
+/// Dropping the handle keeps the callback registered; call
+/// [`remove`](Self::remove) to unregister it, or convert it with
+/// [`into_guard`](Self::into_guard) to unregister on drop. Removal takes
+/// effect from the next event; one already being dispatched still reaches
+/// the callback.
+pub struct CallbackHandle {
+    stream: &'static str,
+    id: u64,
+    /// Every registration covered; a trait handler spans several streams
+    parts: Vec<(Weak<dyn Unregister>, u64)>,
+}
+
+impl CallbackHandle {
+    /// Stream the callback is registered on, e.g. `"tick_stk"`; the first
+    /// one for handlers spanning several streams
+    pub fn stream(&self) -> &'static str {
+        self.stream
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Cover `other`'s registrations too, so one handle removes both
+    pub(crate) fn join(mut self, other: CallbackHandle) -> Self {
+        self.parts.extend(other.parts);
+        self
+    }
+
+    /// Whether the callback is still registered
+    pub fn is_registered(&self) -> bool {
+        self.parts.iter().any(|(registry, id)| {
+            registry
+                .upgrade()
+                .is_some_and(|registry| registry.contains(*id))
+        })
+    }
+
+    /// Unregister the callback; `false` if it was already removed
+    pub fn remove(self) -> bool {
+        let mut removed = false;
+        for (registry, id) in &self.parts {
+            if let Some(registry) = registry.upgrade() {
+                removed |= registry.unregister(*id);
+            }
+        }
+        removed
+    }
+
+    /// Unregister the callback when the returned guard is dropped
+    pub fn into_guard(self) -> CallbackGuard {
+        CallbackGuard(Some(self))
+    }
+}
+
+impl std::fmt::Debug for CallbackHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackHandle")
+            .field("stream", &self.stream)
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/// Unregisters its callback on drop
+#[derive(Debug)]
+pub struct CallbackGuard(Option<CallbackHandle>);
+
+impl CallbackGuard {
+    /// Keep the callback registered and get the handle back
+    pub fn into_handle(mut self) -> CallbackHandle {
+        self.0.take().expect("guard holds a handle until dropped")
+    }
+}
+
+impl Drop for CallbackGuard {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0.take() {
+            handle.remove();
+        }
+    }
+}
+
 /// Convenience macro for implementing multiple callback traits on a single struct
 #[macro_export]
 macro_rules! impl_callbacks {
@@ -235,3 +700,246 @@ macro_rules! impl_callbacks {
         )+
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    type Listener = Arc<dyn Fn(i32) + Send + Sync>;
+
+    #[test]
+    fn test_registry_dispatches_snapshot_and_counts() {
+        let registry: Arc<CallbackRegistry<Listener>> = Arc::new(CallbackRegistry::new("test"));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let sink = seen.clone();
+        let inner = registry.clone();
+        registry.register(Arc::new(move |value| {
+            sink.lock().unwrap().push(value);
+            // Registering from inside a callback must neither deadlock nor
+            // change the snapshot being dispatched
+            if value == 1 {
+                let sink = sink.clone();
+                inner.register(Arc::new(move |value| sink.lock().unwrap().push(value * 10)));
+            }
+        }));
+
+        assert_eq!(registry.dispatch(|callback| callback(1)), 1);
+        assert_eq!(registry.dispatch(|callback| callback(2)), 2);
+        registry.record_dropped();
+
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 20]);
+        assert_eq!(
+            registry.stats(),
+            CallbackStats {
+                stream: "test",
+                callbacks: 2,
+                disabled: 0,
+                dispatched: 2,
+                dropped: 1,
+                panicked: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_registry_never_drops_while_registering() {
+        let registry: Arc<CallbackRegistry<Listener>> = Arc::new(CallbackRegistry::new("test"));
+        let count = Arc::new(AtomicU64::new(0));
+        let sink = count.clone();
+        registry.register(Arc::new(move |_| {
+            sink.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        let writer = {
+            let registry = registry.clone();
+            std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    registry.register(Arc::new(|_| {}));
+                }
+            })
+        };
+        for value in 0..1000 {
+            registry.dispatch(|callback| callback(value));
+        }
+        writer.join().unwrap();
+
+        // The first callback sees every event regardless of registrations
+        assert_eq!(count.load(Ordering::Relaxed), 1000);
+        assert_eq!(registry.stats().dispatched, 1000);
+        assert_eq!(registry.len(), 1001);
+    }
+
+    #[test]
+    fn test_dispatch_does_not_wait_for_writers() {
+        let registry: Arc<CallbackRegistry<Listener>> = Arc::new(CallbackRegistry::new("test"));
+        let count = Arc::new(AtomicU64::new(0));
+        let sink = count.clone();
+        registry.register(Arc::new(move |_| {
+            sink.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        // A registration in progress holds the writer lock
+        let _writer = registry.writer.lock().unwrap();
+        let dispatcher = {
+            let registry = registry.clone();
+            std::thread::spawn(move || registry.dispatch(|callback| callback(1)))
+        };
+        assert_eq!(dispatcher.join().unwrap(), 1);
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_panicking_callback_is_isolated_reported_and_disabled() {
+        let policy = Arc::new(CallbackErrorPolicy::new());
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let sink = errors.clone();
+        policy.set_hook(Arc::new(move |error| {
+            sink.lock().unwrap().push(error.to_string())
+        }));
+        policy.set_failure_limit(Some(2));
+
+        let registry: Arc<CallbackRegistry<Listener>> =
+            Arc::new(CallbackRegistry::new("tick_stk").with_policy(policy));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        registry.register(Arc::new(|value| {
+            if value != 2 {
+                panic!("bad tick {}", value);
+            }
+        }));
+        let sink = seen.clone();
+        registry.register(Arc::new(move |value| sink.lock().unwrap().push(value)));
+
+        // A success in between resets the streak; two in a row disable it
+        for value in [1, 2, 3, 4, 5] {
+            registry.dispatch(|callback| callback(value));
+        }
+
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3, 4, 5]);
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].contains("tick_stk callback #1 panicked (1 in a row): bad tick 1"));
+        assert!(errors[2].contains("(2 in a row, disabled): bad tick 4"));
+        let stats = registry.stats();
+        assert_eq!(stats.panicked, 3);
+        assert_eq!(stats.disabled, 1);
+        assert_eq!(stats.dispatched, 5);
+    }
+
+    #[test]
+    fn test_handles_remove_callbacks() {
+        let registry: Arc<CallbackRegistry<Listener>> = Arc::new(CallbackRegistry::new("test"));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let register = |tag: i32| {
+            let sink = seen.clone();
+            registry.register(Arc::new(move |value| {
+                sink.lock().unwrap().push(tag * value)
+            }))
+        };
+
+        let first = register(1);
+        let second = register(10);
+        let third = register(100).into_guard();
+        assert_eq!((first.stream(), first.id(), second.id()), ("test", 1, 2));
+        registry.dispatch(|callback| callback(1));
+
+        assert!(second.is_registered());
+        assert!(second.remove());
+        drop(third);
+        registry.dispatch(|callback| callback(2));
+        assert_eq!(*seen.lock().unwrap(), vec![1, 10, 100, 2]);
+        assert_eq!(registry.len(), 1);
+
+        // Dropping a plain handle keeps the callback; ids are never reused
+        let kept = first.id();
+        drop(first);
+        assert_eq!(registry.len(), 1);
+        let again = register(1000);
+        assert_ne!(again.id(), kept);
+        assert!(again.remove());
+
+        // A guard turned back into a handle no longer unregisters on drop
+        let handle = register(1).into_guard().into_handle();
+        assert!(handle.is_registered());
+        drop(handle);
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    fn test_event_handlers_register_returns_handle() {
+        struct Counter(Mutex<u32>);
+        impl SystemCallback for Counter {
+            fn on_event(&self, _: i32, _: i32, _: String, _: String) {
+                *self.0.lock().unwrap() += 1;
+            }
+            fn on_session_down(&self) {}
+        }
+
+        let handlers = EventHandlers::new();
+        let counter = Arc::new(Counter(Mutex::new(0)));
+        let handle = handlers.register_system_callback(counter.clone());
+        handlers.trigger_event(0, 16, String::new(), String::new());
+        assert!(handle.remove());
+        handlers.trigger_event(0, 16, String::new(), String::new());
+        assert_eq!(*counter.0.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_trait_handlers_share_closure_registries() {
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+        impl SystemCallback for Recorder {
+            fn on_event(&self, _: i32, code: i32, _: String, _: String) {
+                self.0.lock().unwrap().push(format!("event {}", code));
+            }
+            fn on_session_down(&self) {
+                self.0.lock().unwrap().push("down".to_string());
+            }
+        }
+        impl OrderCallback for Recorder {
+            fn on_order(&self, state: OrderState, _: serde_json::Value) {
+                self.0.lock().unwrap().push(state.to_string());
+            }
+        }
+
+        let handlers = EventHandlers::new();
+        let recorder = Arc::new(Recorder::default());
+        let system = handlers.register(recorder.clone() as Arc<dyn SystemCallback>);
+        handlers.register(recorder.clone() as Arc<dyn OrderCallback>);
+        handlers.register_event_closure(Arc::new(|_, _, _, _| {}));
+
+        handlers.trigger_event(0, 16, String::new(), String::new());
+        handlers.trigger_session_down();
+        let cancel = serde_json::json!({"operation": {"op_type": "Cancel", "op_code": "00"}});
+        handlers.trigger_order(OrderEventType::StockOrder, cancel);
+        let order = serde_json::json!({
+            "operation": {"op_type": "New", "op_code": "00"},
+            "order": {"id": "o1", "quantity": 2},
+        });
+        handlers.trigger_order(OrderEventType::StockOrder, order);
+        let deal = serde_json::json!({"trade_id": "o1", "quantity": 1});
+        handlers.trigger_order(OrderEventType::StockDeal, deal.clone());
+        handlers.trigger_order(OrderEventType::StockDeal, deal);
+
+        // One handle covers both streams the system handler was adapted into
+        assert!(system.remove());
+        handlers.trigger_event(0, 16, String::new(), String::new());
+        handlers.trigger_session_down();
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                "event 16".to_string(),
+                "down".to_string(),
+                OrderState::Cancelled.to_string(),
+                OrderState::Submitted.to_string(),
+                OrderState::PartFilled.to_string(),
+                OrderState::Filled.to_string(),
+            ]
+        );
+        let stats = handlers.stats();
+        let event = stats.iter().find(|s| s.stream == "event").unwrap();
+        assert_eq!((event.callbacks, event.dispatched), (1, 2));
+    }
+}
@@ -1,42 +1,39 @@
 use pyo3::prelude::*;
+use pyo3::types::IntoPyDict;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use crate::callbacks::{ContractCallback, EventHandlers};
+use crate::async_callbacks::{AsyncHandler, AsyncQueue};
+use crate::backend::Backend;
+use crate::calendar::{SessionKind, TradingCalendar};
+use crate::callbacks::{CallbackHandle, CallbackStats, ContractCallback, EventHandlers, Handler};
+pub use crate::callbacks::{OrderEventCallback, TickFOPCallback, TickSTKCallback};
 use crate::error::{Error, Result};
+use crate::journal::{Journal, JournalRecord};
+use crate::python_worker::{PythonWorker, DEFAULT_REQUEST_TIMEOUT};
 use crate::types::*;
 use crate::utils::{
     check_contract_cache, clear_outdated_contract_cache_default, get_contracts_filename,
     new_contracts,
 };
 
-// Type aliases for complex callback types
-type TickSTKCallback = Arc<dyn Fn(Exchange, TickSTKv1) + Send + Sync>;
-type TickFOPCallback = Arc<dyn Fn(Exchange, TickFOPv1) + Send + Sync>;
-type BidAskSTKCallback = Arc<dyn Fn(Exchange, BidAskSTKv1) + Send + Sync>;
-type BidAskFOPCallback = Arc<dyn Fn(Exchange, BidAskFOPv1) + Send + Sync>;
-type QuoteSTKCallback = Arc<dyn Fn(Exchange, QuoteSTKv1) + Send + Sync>;
-type QuoteCallback = Arc<dyn Fn(String, serde_json::Value) + Send + Sync>;
-type EventCallback = Arc<dyn Fn(i32, i32, String, String) + Send + Sync>;
-type SessionDownCallback = Arc<dyn Fn() + Send + Sync>;
-
 /// High-level Rust wrapper around system shioaji client
 ///
 /// **NEW ARCHITECTURE**: Pure system shioaji + PyO3 hybrid
 /// - 移除所有二進制檔案相依性
 /// - 直接使用系統安裝的 shioaji 套件
-/// - 確保單一實例連線限制
+/// - 確保單一實例連線限制 (每個 client 一個連線；多組 API key 請用
+///   [`SessionManager`](crate::SessionManager))
 /// - 參考原始 shioaji.py 實作模式
 pub struct Shioaji {
     instance: Arc<Mutex<Option<PyObject>>>,
     simulation: bool,
-    #[allow(dead_code)]
-    proxies: HashMap<String, String>, // Reserved for future proxy support
+    /// 代理伺服器設定 (傳入 Python Shioaji(proxies=...))
+    proxies: HashMap<String, String>,
     vpn: bool,
     stock_account: Arc<Mutex<Option<StockAccount>>>,
     future_account: Arc<Mutex<Option<FutureAccount>>>,
-    _event_handlers: Arc<Mutex<EventHandlers>>,
     // Business state
     logged_in: Arc<Mutex<bool>>,
     /// 合約資料 (對應原始 Python 的 self.Contracts 和 self._solace.Contracts)
@@ -61,22 +58,20 @@ pub struct Shioaji {
     error_tracking_enabled: Arc<Mutex<bool>>,
 
     // === 全域 Callback 儲存機制 ===
-    /// 股票 Tick 回調函數
-    tick_stk_callbacks: Arc<Mutex<Vec<TickSTKCallback>>>,
-    /// 期貨/選擇權 Tick 回調函數
-    tick_fop_callbacks: Arc<Mutex<Vec<TickFOPCallback>>>,
-    /// 股票 BidAsk 回調函數
-    bidask_stk_callbacks: Arc<Mutex<Vec<BidAskSTKCallback>>>,
-    /// 期貨/選擇權 BidAsk 回調函數
-    bidask_fop_callbacks: Arc<Mutex<Vec<BidAskFOPCallback>>>,
-    /// 股票 Quote 回調函數
-    quote_stk_callbacks: Arc<Mutex<Vec<QuoteSTKCallback>>>,
-    /// 通用 Quote 回調函數
-    quote_callbacks: Arc<Mutex<Vec<QuoteCallback>>>,
-    /// 系統事件回調函數
-    event_callbacks: Arc<Mutex<Vec<EventCallback>>>,
-    /// 連線中斷回調函數
-    session_down_callbacks: Arc<Mutex<Vec<SessionDownCallback>>>,
+    /// 所有回調 (閉包與 trait 處理器) 的註冊表與共用 panic 設定
+    handlers: Arc<EventHandlers>,
+    /// 委託/成交日誌 (callback 執行緒中同步寫入，故使用 std RwLock)
+    journal: Arc<std::sync::RwLock<Option<Arc<Journal>>>>,
+    /// 交易日曆；設定後下單前檢查是否在交易時段
+    calendar: Arc<std::sync::RwLock<Option<Arc<TradingCalendar>>>>,
+    /// 執行所有 SDK 呼叫的 Python 執行緒
+    python: Arc<PythonWorker>,
+    /// 未指定 timeout 的 SDK 呼叫期限
+    request_timeout: Arc<std::sync::RwLock<std::time::Duration>>,
+    /// 設定後交易與查詢改由此後端處理，不經 Python SDK
+    backend: Option<Arc<dyn Backend>>,
+    /// 後端的委託與行情回調是否已接上
+    backend_bridged: Arc<Mutex<bool>>,
 }
 
 /// Contracts cache for business logic
@@ -91,17 +86,41 @@ pub struct ContractsCache {
 }
 
 impl Shioaji {
-    /// Create a new Shioaji client using pure system shioaji
+    /// Create a new Shioaji client using pure system shioaji (VPN off)
     pub fn new(simulation: bool, proxies: HashMap<String, String>) -> Result<Self> {
         Self::new_with_options(simulation, proxies, false)
     }
 
+    /// Create a client configured from the environment
+    ///
+    /// Entries from `SHIOAJI_PROXY_*` fill in proxy keys not given in
+    /// `proxies` and VPN mode follows `SHIOAJI_VPN`; see [`crate::proxy`].
+    pub fn from_env(simulation: bool, proxies: HashMap<String, String>) -> Result<Self> {
+        Self::new_with_options(
+            simulation,
+            crate::proxy::merge_env_proxies(proxies),
+            crate::proxy::vpn_from_env(),
+        )
+    }
+
     /// Create a new Shioaji client with VPN option
+    ///
+    /// `proxies` maps a scheme (`"http"`, `"https"`) to a proxy URL and is
+    /// used as given. Invalid URLs fail with `Error::InvalidInput`.
     pub fn new_with_options(
         simulation: bool,
         proxies: HashMap<String, String>,
         vpn: bool,
     ) -> Result<Self> {
+        crate::proxy::validate_proxies(&proxies)?;
+        for (scheme, url) in &proxies {
+            log::info!(
+                "🌐 Using {} proxy {}",
+                scheme,
+                crate::proxy::redact_proxy_url(url)
+            );
+        }
+
         // 檢查是否為 simulation-to-staging 模式
         let simu_to_stag = simulation
             && std::env::var("SHIOAJI_SIMU_TO_STAG")
@@ -116,7 +135,6 @@ impl Shioaji {
             vpn,
             stock_account: Arc::new(Mutex::new(None)),
             future_account: Arc::new(Mutex::new(None)),
-            _event_handlers: Arc::new(Mutex::new(EventHandlers::new())),
             logged_in: Arc::new(Mutex::new(false)),
             contracts: Arc::new(Mutex::new(None)),
             contracts_cache: Arc::new(Mutex::new(None)),
@@ -131,17 +149,41 @@ impl Shioaji {
             error_tracking_enabled: Arc::new(Mutex::new(false)),
 
             // 初始化全域 Callback 儲存
-            tick_stk_callbacks: Arc::new(Mutex::new(Vec::new())),
-            tick_fop_callbacks: Arc::new(Mutex::new(Vec::new())),
-            bidask_stk_callbacks: Arc::new(Mutex::new(Vec::new())),
-            bidask_fop_callbacks: Arc::new(Mutex::new(Vec::new())),
-            quote_stk_callbacks: Arc::new(Mutex::new(Vec::new())),
-            quote_callbacks: Arc::new(Mutex::new(Vec::new())),
-            event_callbacks: Arc::new(Mutex::new(Vec::new())),
-            session_down_callbacks: Arc::new(Mutex::new(Vec::new())),
+            handlers: Arc::new(EventHandlers::new()),
+            journal: Arc::new(std::sync::RwLock::new(None)),
+            calendar: Arc::new(std::sync::RwLock::new(None)),
+            python: PythonWorker::shared(),
+            request_timeout: Arc::new(std::sync::RwLock::new(DEFAULT_REQUEST_TIMEOUT)),
+            backend: None,
+            backend_bridged: Arc::new(Mutex::new(false)),
         })
     }
 
+    /// Delegate login, orders, queries and subscriptions to `backend`
+    /// instead of the Python SDK (委派後端)
+    ///
+    /// The client keeps its own checks, journal and callbacks: the backend's
+    /// order events and ticks reach handlers registered on this client. With
+    /// a [`PaperBroker`](crate::PaperBroker) the client runs without Python:
+    ///
+    /// ```no_run
+    /// # async fn run() -> rshioaji::Result<()> {
+    /// use rshioaji::{PaperBroker, PaperConfig, Shioaji};
+    /// use std::sync::Arc;
+    ///
+    /// let broker = Arc::new(PaperBroker::new(PaperConfig::default()));
+    /// let client = Shioaji::new(true, Default::default())?.with_backend(broker.clone());
+    /// client.init().await?;
+    /// client.login_simple("key", "secret", false).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_backend(mut self, backend: Arc<dyn Backend>) -> Self {
+        log::info!("🔌 Delegating to {} backend", backend.name());
+        self.backend = Some(backend);
+        self
+    }
+
     /// Initialize the client - pure system shioaji approach
     pub async fn init(&self) -> Result<()> {
         log::info!("🚀 Initializing Shioaji client with system shioaji...");
@@ -157,20 +199,112 @@ impl Shioaji {
             *instance_lock = true;
         }
 
+        if let Some(backend) = &self.backend {
+            log::info!(
+                "✅ Shioaji client 使用 {} 後端，無需 Python",
+                backend.name()
+            );
+            return Ok(());
+        }
+
         // 第一步：取得 shioaji 套件版本 (對應原始 Python 初始化邏輯)
         log::info!("📦 檢測系統 shioaji 套件版本...");
         let shioaji_version = crate::utils::get_system_shioaji_version()
             .map_err(|e| Error::Initialization(format!("無法取得 shioaji 版本: {}", e)))?;
         log::info!("✅ 系統 shioaji 版本: {}", shioaji_version);
 
-        // 簡化初始化 - 不需要預先創建 shioaji 實例
-        // 實際的 shioaji 實例會在 login() 時創建
-        log::info!("✅ Shioaji client 初始化完成，準備進行登入");
-        log::info!("💡 shioaji 實例將在 login() 時創建以確保最佳相容性");
+        // 第二步：建立 shioaji 實例 (對應原始 Python: Shioaji(simulation, proxies, vpn=vpn))
+        let (simulation, proxies, vpn) = (self.simulation, self.proxies.clone(), self.vpn);
+        let instance = self
+            .run_python("init", self.request_timeout(), move |py| {
+                Self::new_system_instance(py, simulation, &proxies, vpn)
+            })
+            .await;
+        let instance = match instance {
+            Ok(instance) => instance,
+            Err(e) => {
+                *self._instance_lock.lock().await = false;
+                return Err(e);
+            }
+        };
+        *self.instance.lock().await = Some(instance);
 
+        log::info!("✅ Shioaji client 初始化完成，準備進行登入");
         Ok(())
     }
 
+    /// Construct the SDK's `Shioaji` object with this client's settings
+    ///
+    /// Arguments are passed by keyword: the SDK's second positional
+    /// parameter is `proxies`, not `vpn`.
+    fn new_system_instance(
+        py: Python,
+        simulation: bool,
+        proxies: &HashMap<String, String>,
+        vpn: bool,
+    ) -> Result<PyObject> {
+        let shioaji_module = py
+            .import("shioaji")
+            .map_err(|e| Error::System(format!("Failed to import shioaji: {}", e)))?;
+        let kwargs = pyo3::types::PyDict::new(py);
+        kwargs.set_item("simulation", simulation)?;
+        kwargs.set_item("proxies", proxies.clone().into_py_dict(py))?;
+        kwargs.set_item("vpn", vpn)?;
+        log::info!(
+            "🏗️ Creating Shioaji instance (simulation: {}, proxies: {}, vpn: {})",
+            simulation,
+            proxies.len(),
+            vpn
+        );
+        let api = shioaji_module
+            .getattr("Shioaji")?
+            .call((), Some(kwargs))
+            .map_err(|e| {
+                Error::Initialization(format!("Failed to create Shioaji instance: {}", e))
+            })?;
+        Ok(api.to_object(py))
+    }
+
+    /// Proxies passed to the SDK
+    pub fn proxies(&self) -> &HashMap<String, String> {
+        &self.proxies
+    }
+
+    /// Whether the SDK connects in VPN mode
+    pub fn vpn(&self) -> bool {
+        self.vpn
+    }
+
+    /// A handle sharing this client's state, for jobs moved onto the
+    /// Python worker thread
+    fn worker_handle(&self) -> Self {
+        Self {
+            instance: self.instance.clone(),
+            simulation: self.simulation,
+            proxies: self.proxies.clone(),
+            vpn: self.vpn,
+            stock_account: self.stock_account.clone(),
+            future_account: self.future_account.clone(),
+            logged_in: self.logged_in.clone(),
+            contracts: self.contracts.clone(),
+            contracts_cache: self.contracts_cache.clone(),
+            _instance_lock: self._instance_lock.clone(),
+            person_id: self.person_id.clone(),
+            _simu_to_stag: self._simu_to_stag,
+            session_token: self.session_token.clone(),
+            default_stock_account: self.default_stock_account.clone(),
+            default_futopt_account: self.default_futopt_account.clone(),
+            error_tracking_enabled: self.error_tracking_enabled.clone(),
+            handlers: self.handlers.clone(),
+            journal: self.journal.clone(),
+            calendar: self.calendar.clone(),
+            python: self.python.clone(),
+            request_timeout: self.request_timeout.clone(),
+            backend: self.backend.clone(),
+            backend_bridged: self.backend_bridged.clone(),
+        }
+    }
+
     /// Login using system shioaji API (完整實現原始 shioaji.py 登入功能)
     ///
     /// 對應原始 Python 函數：
@@ -233,93 +367,98 @@ impl Shioaji {
             }
         }
 
-        // 直接使用真實 shioaji 進行登入 (移除不必要的包裝層)
-        let (accounts, contract_download, person_id, api_instance) =
-            Python::with_gil(|py| -> Result<(Vec<Account>, bool, String, PyObject)> {
-                log::info!("🌟 Creating system shioaji instance for real login...");
-
-                // 導入 shioaji 模組
-                let shioaji_module = py
-                    .import("shioaji")
-                    .map_err(|e| Error::System(format!("Failed to import shioaji: {}", e)))?;
-
-                // 創建 shioaji 實例，根據模式設定參數
-                let api = if self.vpn {
-                    log::info!("🌐 Creating Shioaji instance in VPN mode...");
-                    shioaji_module.call_method1("Shioaji", (self.simulation, self.vpn))
-                } else {
-                    log::info!(
-                        "🏢 Creating Shioaji instance in {} mode...",
-                        if self._simu_to_stag {
-                            "simulation-to-staging"
-                        } else {
-                            "standard"
-                        }
-                    );
-                    shioaji_module.call_method1("Shioaji", (self.simulation,))
-                }
-                .map_err(|e| Error::System(format!("Failed to create Shioaji instance: {}", e)))?;
+        if let Some(backend) = self.backend.clone() {
+            return self
+                .login_backend(backend.as_ref(), api_key, secret_key, fetch_contract)
+                .await;
+        }
 
-                log::info!("🔐 Calling api.login() with real credentials...");
-                // 直接調用 api.login() - 這會自動下載合約
-                let kwargs = pyo3::types::PyDict::new(py);
-                kwargs.set_item("fetch_contract", fetch_contract)?;
-                kwargs.set_item("contracts_timeout", contracts_timeout)?;
-                kwargs.set_item("subscribe_trade", subscribe_trade)?;
-                kwargs.set_item("receive_window", receive_window)?;
+        // init() 已建立的實例；未呼叫 init() 時在此建立
+        let existing = self.instance.lock().await.clone();
 
-                let login_result = api
-                    .call_method("login", (api_key, secret_key), Some(kwargs))
-                    .map_err(|e| Error::System(format!("Login failed: {}", e)))?;
+        // 直接使用真實 shioaji 進行登入 (移除不必要的包裝層)
+        // 登入含合約下載，是最久的 SDK 呼叫，須在 Python 執行緒執行
+        let this = self.worker_handle();
+        let (api_key, secret_key) = (api_key.to_string(), secret_key.to_string());
+        let deadline = self.deadline_for(Some(contracts_timeout as i32));
+        let (accounts, contract_download, person_id, api_instance) = self
+            .run_python(
+                "login",
+                deadline,
+                move |py| -> Result<(Vec<Account>, bool, String, PyObject)> {
+                    let api = match existing {
+                        Some(instance) => instance,
+                        None => {
+                            log::info!("🌟 Creating system shioaji instance for real login...");
+                            Self::new_system_instance(py, this.simulation, &this.proxies, this.vpn)?
+                        }
+                    };
+                    let api = api.as_ref(py);
+
+                    log::info!("🔐 Calling api.login() with real credentials...");
+                    // 直接調用 api.login() - 這會自動下載合約
+                    let kwargs = pyo3::types::PyDict::new(py);
+                    kwargs.set_item("fetch_contract", fetch_contract)?;
+                    kwargs.set_item("contracts_timeout", contracts_timeout)?;
+                    kwargs.set_item("subscribe_trade", subscribe_trade)?;
+                    kwargs.set_item("receive_window", receive_window)?;
+
+                    let login_result = api
+                        .call_method("login", (&api_key, &secret_key), Some(kwargs))
+                        .map_err(|e| {
+                            Error::from_sdk(e, |e| Error::System(format!("Login failed: {}", e)))
+                        })?;
 
-                // 解析登入結果 - api.login() 回傳 List[Account]
-                let mut accounts = Vec::new();
+                    // 解析登入結果 - api.login() 回傳 List[Account]
+                    let mut accounts = Vec::new();
 
-                if let Ok(accounts_list) = login_result.downcast::<pyo3::types::PyList>() {
-                    log::info!(
-                        "📊 Processing {} accounts from login result...",
-                        accounts_list.len()
-                    );
-                    for account_py in accounts_list.iter() {
-                        if let Ok(account) = self.parse_account_from_python(py, account_py) {
-                            log::info!(
-                                "   ✅ Account: {} - {} ({:?})",
-                                account.broker_id,
-                                account.account_id,
-                                account.account_type
-                            );
-                            accounts.push(account);
+                    if let Ok(accounts_list) = login_result.downcast::<pyo3::types::PyList>() {
+                        log::info!(
+                            "📊 Processing {} accounts from login result...",
+                            accounts_list.len()
+                        );
+                        for account_py in accounts_list.iter() {
+                            if let Ok(account) = this.parse_account_from_python(py, account_py) {
+                                log::info!(
+                                    "   ✅ Account: {} - {} ({:?})",
+                                    account.broker_id,
+                                    account.account_id,
+                                    account.account_type
+                                );
+                                accounts.push(account);
+                            }
                         }
+                    } else {
+                        return Err(Error::Authentication(
+                            "Unexpected login result format - expected List[Account]".to_string(),
+                        ));
                     }
-                } else {
-                    return Err(Error::Authentication(
-                        "Unexpected login result format - expected List[Account]".to_string(),
-                    ));
-                }
 
-                // 從 API 實例取得其他資訊
-                let person_id = if let Ok(person_id_py) = api.getattr("person_id") {
-                    person_id_py
-                        .extract::<String>()
-                        .unwrap_or_else(|_| format!("user_{}", chrono::Utc::now().timestamp()))
-                } else {
-                    format!("user_{}", chrono::Utc::now().timestamp())
-                };
+                    // 從 API 實例取得其他資訊
+                    let person_id = if let Ok(person_id_py) = api.getattr("person_id") {
+                        person_id_py
+                            .extract::<String>()
+                            .unwrap_or_else(|_| format!("user_{}", chrono::Utc::now().timestamp()))
+                    } else {
+                        format!("user_{}", chrono::Utc::now().timestamp())
+                    };
 
-                log::info!(
-                    "✅ Real system shioaji login completed with {} accounts",
-                    accounts.len()
-                );
-                log::info!("   👤 Person ID: {}", person_id);
+                    log::info!(
+                        "✅ Real system shioaji login completed with {} accounts",
+                        accounts.len()
+                    );
+                    log::info!("   👤 Person ID: {}", person_id);
 
-                if accounts.is_empty() {
-                    return Err(Error::Authentication(
-                        "No accounts returned from system shioaji login".to_string(),
-                    ));
-                }
+                    if accounts.is_empty() {
+                        return Err(Error::Authentication(
+                            "No accounts returned from system shioaji login".to_string(),
+                        ));
+                    }
 
-                Ok((accounts, true, person_id, api.to_object(py)))
-            })?;
+                    Ok((accounts, true, person_id, api.to_object(py)))
+                },
+            )
+            .await?;
 
         // 更新實例引用為真實的 shioaji 實例
         {
@@ -394,141 +533,250 @@ impl Shioaji {
         Ok(accounts)
     }
 
-    /// Load contracts from Python shioaji instance into Rust structures
-    async fn load_contracts_from_instance(&self, instance: &PyObject) -> Result<()> {
-        Python::with_gil(|py| -> Result<()> {
-            log::info!("📋 Loading contracts from Python shioaji instance...");
+    /// Log in through a delegated backend and route its events to this
+    /// client's handlers
+    async fn login_backend(
+        &self,
+        backend: &dyn Backend,
+        api_key: &str,
+        secret_key: &str,
+        fetch_contract: bool,
+    ) -> Result<Vec<Account>> {
+        let accounts = backend.login(api_key, secret_key).await?;
+        *self.logged_in.lock().await = true;
+        self.store_default_accounts(&accounts).await?;
 
-            // 從 shioaji 實例取得 Contracts 物件
-            let contracts_obj = instance.getattr(py, "Contracts").map_err(|e| {
-                Error::System(format!(
-                    "Failed to get Contracts from shioaji instance: {}",
-                    e
-                ))
+        if fetch_contract {
+            if let Some(contracts) = backend.contracts().await? {
+                *self.contracts.lock().await = Some(contracts);
+            }
+        }
+
+        // 後端回調只接一次；重新登入沿用
+        let mut bridged = self.backend_bridged.lock().await;
+        if !*bridged {
+            let (handlers, journal) = (self.handlers.clone(), self.journal.clone());
+            backend
+                .on_order(Arc::new(move |event_type, msg| {
+                    Self::append_journal(
+                        &journal,
+                        JournalRecord::OrderEvent {
+                            event_type: event_type.clone(),
+                            message: msg.clone(),
+                        },
+                    );
+                    handlers.trigger_order(event_type, msg);
+                }))
+                .await?;
+            let handlers = self.handlers.clone();
+            backend
+                .on_tick_stk_v1(Arc::new(move |exchange, tick| {
+                    handlers.trigger_tick_stk_v1(exchange, tick)
+                }))
+                .await?;
+            let handlers = self.handlers.clone();
+            backend
+                .on_tick_fop_v1(Arc::new(move |exchange, tick| {
+                    handlers.trigger_tick_fop_v1(exchange, tick)
+                }))
+                .await?;
+            *bridged = true;
+        }
+
+        log::info!(
+            "✅ Login completed with {} accounts using {} backend",
+            accounts.len(),
+            backend.name()
+        );
+        Ok(accounts)
+    }
+
+    /// Share contract data with other clients (共用合約資料)
+    ///
+    /// Every client built with the same `contracts` sees contracts loaded by
+    /// any of them; see [`adopt_contracts`](Self::adopt_contracts) for the
+    /// SDK side.
+    pub fn with_contracts(mut self, contracts: Arc<Mutex<Option<Contracts>>>) -> Self {
+        self.contracts = contracts;
+        self
+    }
+
+    /// Reuse `source`'s downloaded SDK contracts instead of fetching them again
+    ///
+    /// Both clients must be logged in. If the SDK does not allow replacing
+    /// `Contracts`, this session fetches its own copy instead.
+    pub async fn adopt_contracts(&self, source: &Shioaji) -> Result<()> {
+        let instance = self
+            .instance
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| Error::NotInitialized("Client not initialized".to_string()))?;
+        let source_instance =
+            source.instance.lock().await.clone().ok_or_else(|| {
+                Error::NotInitialized("Source client not initialized".to_string())
+            })?;
+        let deadline = self.request_timeout();
+        self.run_python("adopt_contracts", deadline, move |py| {
+            let contracts = source_instance.getattr(py, "Contracts")?;
+            if instance.setattr(py, "Contracts", contracts).is_ok() {
+                log::info!("📊 Reusing contracts downloaded by another session");
+                return Ok(());
+            }
+            // Rust 端合約已共用，只需補齊 SDK 端
+            log::warn!("⚠️ SDK Contracts cannot be shared; fetching for this session");
+            instance.call_method0(py, "fetch_contracts").map_err(|e| {
+                Error::from_sdk(e, |e| {
+                    Error::ContractFetch(format!("fetch_contracts failed: {}", e))
+                })
             })?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Load contracts from Python shioaji instance into Rust structures
+    async fn load_contracts_from_instance(&self, instance: &PyObject) -> Result<()> {
+        let this = self.worker_handle();
+        let instance = instance.clone();
+        self.run_python(
+            "load_contracts",
+            self.request_timeout(),
+            move |py| -> Result<()> {
+                log::info!("📋 Loading contracts from Python shioaji instance...");
+
+                // 從 shioaji 實例取得 Contracts 物件
+                let contracts_obj = instance.getattr(py, "Contracts").map_err(|e| {
+                    Error::System(format!(
+                        "Failed to get Contracts from shioaji instance: {}",
+                        e
+                    ))
+                })?;
 
-            // 取得各類型合約
-            let stocks = contracts_obj
-                .getattr(py, "Stocks")
-                .map_err(|e| Error::System(format!("Failed to get Stocks: {}", e)))?;
-            let futures = contracts_obj
-                .getattr(py, "Futures")
-                .map_err(|e| Error::System(format!("Failed to get Futures: {}", e)))?;
-            let options = contracts_obj
-                .getattr(py, "Options")
-                .map_err(|e| Error::System(format!("Failed to get Options: {}", e)))?;
-            let indices = contracts_obj
-                .getattr(py, "Indexs") // 注意：原始 API 是 "Indexs" 不是 "Indices"
-                .map_err(|e| Error::System(format!("Failed to get Indexs: {}", e)))?;
-
-            // 計算股票合約數量 (遍歷群組：OES, OTC, TSE) 並記錄細分數量
-            let (stocks_len, stocks_tse, stocks_otc, stocks_oes) =
-                if let Ok(keys_generator) = stocks.call_method0(py, "keys") {
-                    // 將 generator 轉換為 list
-                    if let Ok(keys_list) = py
-                        .import("builtins")?
-                        .call_method1("list", (keys_generator,))
-                    {
-                        if let Ok(keys) = keys_list.extract::<Vec<String>>() {
-                            log::info!("🔍 Stock groups: {:?}", keys);
-                            let mut total = 0;
-                            let mut tse_count = 0;
-                            let mut otc_count = 0;
-                            let mut oes_count = 0;
-
-                            for key in keys {
-                                if let Ok(group) = stocks.getattr(py, key.as_str()) {
-                                    // 嘗試用 len() 或 iteration 計算群組內合約數量
-                                    if let Ok(py_list) =
-                                        py.import("builtins")?.call_method1("list", (group,))
-                                    {
-                                        if let Ok(contracts_list) =
-                                            py_list.downcast::<pyo3::types::PyList>()
+                // 取得各類型合約
+                let stocks = contracts_obj
+                    .getattr(py, "Stocks")
+                    .map_err(|e| Error::System(format!("Failed to get Stocks: {}", e)))?;
+                let futures = contracts_obj
+                    .getattr(py, "Futures")
+                    .map_err(|e| Error::System(format!("Failed to get Futures: {}", e)))?;
+                let options = contracts_obj
+                    .getattr(py, "Options")
+                    .map_err(|e| Error::System(format!("Failed to get Options: {}", e)))?;
+                let indices = contracts_obj
+                    .getattr(py, "Indexs") // 注意：原始 API 是 "Indexs" 不是 "Indices"
+                    .map_err(|e| Error::System(format!("Failed to get Indexs: {}", e)))?;
+
+                // 計算股票合約數量 (遍歷群組：OES, OTC, TSE) 並記錄細分數量
+                let (stocks_len, stocks_tse, stocks_otc, stocks_oes) =
+                    if let Ok(keys_generator) = stocks.call_method0(py, "keys") {
+                        // 將 generator 轉換為 list
+                        if let Ok(keys_list) = py
+                            .import("builtins")?
+                            .call_method1("list", (keys_generator,))
+                        {
+                            if let Ok(keys) = keys_list.extract::<Vec<String>>() {
+                                log::info!("🔍 Stock groups: {:?}", keys);
+                                let mut total = 0;
+                                let mut tse_count = 0;
+                                let mut otc_count = 0;
+                                let mut oes_count = 0;
+
+                                for key in keys {
+                                    if let Ok(group) = stocks.getattr(py, key.as_str()) {
+                                        // 嘗試用 len() 或 iteration 計算群組內合約數量
+                                        if let Ok(py_list) =
+                                            py.import("builtins")?.call_method1("list", (group,))
                                         {
-                                            let count = contracts_list.len();
-                                            log::info!("   📈 群組 {}: {} 檔", key, count);
-                                            total += count;
-
-                                            // 記錄到對應的交易所計數
-                                            match key.as_str() {
-                                                "TSE" => tse_count = count,
-                                                "OTC" => otc_count = count,
-                                                "OES" => oes_count = count,
-                                                _ => {}
+                                            if let Ok(contracts_list) =
+                                                py_list.downcast::<pyo3::types::PyList>()
+                                            {
+                                                let count = contracts_list.len();
+                                                log::info!("   📈 群組 {}: {} 檔", key, count);
+                                                total += count;
+
+                                                // 記錄到對應的交易所計數
+                                                match key.as_str() {
+                                                    "TSE" => tse_count = count,
+                                                    "OTC" => otc_count = count,
+                                                    "OES" => oes_count = count,
+                                                    _ => {}
+                                                }
                                             }
                                         }
                                     }
                                 }
+                                (total, tse_count as i32, otc_count as i32, oes_count as i32)
+                            } else {
+                                log::warn!("⚠️ Cannot extract keys as Vec<String>");
+                                (0, 0, 0, 0)
                             }
-                            (total, tse_count as i32, otc_count as i32, oes_count as i32)
                         } else {
-                            log::warn!("⚠️ Cannot extract keys as Vec<String>");
+                            log::warn!("⚠️ Cannot convert generator to list");
                             (0, 0, 0, 0)
                         }
                     } else {
-                        log::warn!("⚠️ Cannot convert generator to list");
+                        log::warn!("⚠️ Cannot call stocks.keys()");
                         (0, 0, 0, 0)
-                    }
-                } else {
-                    log::warn!("⚠️ Cannot call stocks.keys()");
-                    (0, 0, 0, 0)
+                    };
+
+                // 為其他合約類型使用簡化計算 (待完整實作)
+                let futures_len = this.count_stream_contracts(py, &futures, "Futures")?;
+                let options_len = this.count_stream_contracts(py, &options, "Options")?;
+                let indices_len = this.count_stream_contracts(py, &indices, "Indices")?;
+
+                log::info!("📊 Contract counts from Python shioaji:");
+                log::info!("   📈 Stocks: {} 檔", stocks_len);
+                log::info!("   📊 Futures: {} 檔", futures_len);
+                log::info!("   📋 Options: {} 檔", options_len);
+                log::info!("   📉 Indices: {} 檔", indices_len);
+
+                let total = stocks_len + futures_len + options_len + indices_len;
+                log::info!("   🎯 Total: {} 檔", total);
+
+                // 創建 Rust Contracts 結構並儲存
+                let contracts = crate::types::Contracts {
+                    status: crate::types::FetchStatus::Fetched,
+                    stocks: std::collections::HashMap::new(), // TODO: 實際轉換 Python 合約
+                    futures: std::collections::HashMap::new(),
+                    options: std::collections::HashMap::new(),
+                    indices: std::collections::HashMap::new(),
+                    last_updated: chrono::Utc::now(),
+                    counts: crate::types::ContractCounts {
+                        stocks: stocks_len as i32,
+                        stocks_tse,
+                        stocks_otc,
+                        stocks_oes,
+                        futures: futures_len as i32,
+                        futures_groups: 0, // TODO: 計算期貨群組數
+                        options: options_len as i32,
+                        options_groups: 0, // TODO: 計算選擇權群組數
+                        indices: indices_len as i32,
+                        indices_otc: 0, // TODO: 細分指數交易所
+                        indices_taifex: 0,
+                        indices_tse: 0,
+                    },
                 };
 
-            // 為其他合約類型使用簡化計算 (待完整實作)
-            let futures_len = self.count_stream_contracts(py, &futures, "Futures")?;
-            let options_len = self.count_stream_contracts(py, &options, "Options")?;
-            let indices_len = self.count_stream_contracts(py, &indices, "Indices")?;
-
-            log::info!("📊 Contract counts from Python shioaji:");
-            log::info!("   📈 Stocks: {} 檔", stocks_len);
-            log::info!("   📊 Futures: {} 檔", futures_len);
-            log::info!("   📋 Options: {} 檔", options_len);
-            log::info!("   📉 Indices: {} 檔", indices_len);
-
-            let total = stocks_len + futures_len + options_len + indices_len;
-            log::info!("   🎯 Total: {} 檔", total);
-
-            // 創建 Rust Contracts 結構並儲存
-            let contracts = crate::types::Contracts {
-                status: crate::types::FetchStatus::Fetched,
-                stocks: std::collections::HashMap::new(), // TODO: 實際轉換 Python 合約
-                futures: std::collections::HashMap::new(),
-                options: std::collections::HashMap::new(),
-                indices: std::collections::HashMap::new(),
-                last_updated: chrono::Utc::now(),
-                counts: crate::types::ContractCounts {
-                    stocks: stocks_len as i32,
-                    stocks_tse,
-                    stocks_otc,
-                    stocks_oes,
-                    futures: futures_len as i32,
-                    futures_groups: 0, // TODO: 計算期貨群組數
-                    options: options_len as i32,
-                    options_groups: 0, // TODO: 計算選擇權群組數
-                    indices: indices_len as i32,
-                    indices_otc: 0, // TODO: 細分指數交易所
-                    indices_taifex: 0,
-                    indices_tse: 0,
-                },
-            };
-
-            // 儲存到 self.contracts (使用 try_lock 以符合同步函式)
-            if let Ok(mut contracts_guard) = self.contracts.try_lock() {
-                *contracts_guard = Some(contracts);
-            } else {
-                log::warn!("⚠️ 無法取得 contracts lock，跳過儲存");
-            }
+                // 儲存到 this.contracts (使用 try_lock 以符合同步函式)
+                if let Ok(mut contracts_guard) = this.contracts.try_lock() {
+                    *contracts_guard = Some(contracts);
+                } else {
+                    log::warn!("⚠️ 無法取得 contracts lock，跳過儲存");
+                }
 
-            if total > 10000 {
-                log::info!("✅ 成功載入真實合約資料！({} 檔)", total);
-            } else if total > 100 {
-                log::info!("✅ 部分合約資料載入 ({} 檔)", total);
-            } else {
-                log::warn!("⚠️ 合約數量較少，可能載入不完整 ({} 檔)", total);
-            }
+                if total > 10000 {
+                    log::info!("✅ 成功載入真實合約資料！({} 檔)", total);
+                } else if total > 100 {
+                    log::info!("✅ 部分合約資料載入 ({} 檔)", total);
+                } else {
+                    log::warn!("⚠️ 合約數量較少，可能載入不完整 ({} 檔)", total);
+                }
 
-            Ok(())
-        })
+                Ok(())
+            },
+        )
+        .await
     }
 
     /// Count contracts in a Stream contract object (helper function)
@@ -694,40 +942,43 @@ impl Shioaji {
     /// 從 Solace API 獲取錯誤追蹤狀態
     /// 對應原始 Python: self._solace.error_tracking(person_id)
     async fn get_solace_error_tracking(&self, person_id: &str) -> Result<bool> {
-        Python::with_gil(|py| -> Result<bool> {
-            log::info!(
-                "🔍 Getting error tracking status from Solace for person_id: {}",
-                person_id
-            );
+        let this = self.worker_handle();
+        let person_id = person_id.to_string();
+        self.run_python(
+            "error_tracking",
+            self.request_timeout(),
+            move |py| -> Result<bool> {
+                log::info!(
+                    "🔍 Getting error tracking status from Solace for person_id: {}",
+                    person_id
+                );
 
-            // 嘗試使用真實的 shioaji 實例
-            match self.try_real_solace_error_tracking(py, person_id) {
-                Ok(status) => {
-                    log::info!("✅ Real Solace error tracking status: {}", status);
-                    return Ok(status);
-                }
-                Err(e) => {
-                    log::warn!("⚠️ Real Solace error tracking failed: {}, using default", e);
+                // 嘗試使用真實的 shioaji 實例
+                match this.try_real_solace_error_tracking(py, &person_id) {
+                    Ok(status) => {
+                        log::info!("✅ Real Solace error tracking status: {}", status);
+                        return Ok(status);
+                    }
+                    Err(e) => {
+                        log::warn!("⚠️ Real Solace error tracking failed: {}, using default", e);
+                    }
                 }
-            }
 
-            // 回退到預設值
-            log::info!("🔧 Using default error tracking status: true");
-            Ok(true)
-        })
+                // 回退到預設值
+                log::info!("🔧 Using default error tracking status: true");
+                Ok(true)
+            },
+        )
+        .await
     }
 
     /// 嘗試從真實 Solace API 獲取錯誤追蹤狀態
     fn try_real_solace_error_tracking(&self, py: Python, person_id: &str) -> Result<bool> {
         log::info!("🌟 Attempting real Solace error_tracking call...");
 
-        // 導入系統 shioaji
-        let shioaji_module = py
-            .import("shioaji")
-            .map_err(|e| Error::System(format!("Failed to import system shioaji: {}", e)))?;
-
         // 創建 Shioaji 實例
-        let sj_instance = shioaji_module.call_method1("Shioaji", (self.simulation,))?;
+        let sj_instance =
+            Self::new_system_instance(py, self.simulation, &self.proxies, self.vpn)?.into_ref(py);
 
         // 獲取 _solace 對象
         let solace = sj_instance
@@ -764,44 +1015,51 @@ impl Shioaji {
 
     /// 設定預設帳戶引用 (對應原始 Python: self.stock_account = self._solace.default_stock_account)
     async fn setup_default_account_references(&self, instance: &PyObject) -> Result<()> {
-        Python::with_gil(|py| -> Result<()> {
-            log::info!("🔗 Setting up default account references");
-
-            // 嘗試從真實 shioaji 實例設定預設帳戶
-            match self.try_setup_real_default_accounts(py, instance) {
-                Ok(_) => {
-                    log::info!("✅ Real default account references setup successful");
-                    return Ok(());
-                }
-                Err(e) => {
-                    log::warn!(
-                        "⚠️ Real default account setup failed: {}, using fallback",
-                        e
-                    );
+        let this = self.worker_handle();
+        let instance = instance.clone();
+        self.run_python(
+            "setup_default_accounts",
+            self.request_timeout(),
+            move |py| -> Result<()> {
+                log::info!("🔗 Setting up default account references");
+
+                // 嘗試從真實 shioaji 實例設定預設帳戶
+                match this.try_setup_real_default_accounts(py, &instance) {
+                    Ok(_) => {
+                        log::info!("✅ Real default account references setup successful");
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "⚠️ Real default account setup failed: {}, using fallback",
+                            e
+                        );
+                    }
                 }
-            }
 
-            // 檢查是否為字典模式（代理模式）
-            if let Ok(instance_dict) = instance.downcast::<pyo3::types::PyDict>(py) {
-                if let Some(instance_type) = instance_dict.get_item("type")? {
-                    if instance_type.to_string() == "SystemShioajiProxy" {
-                        log::info!("🔧 Setting default accounts for proxy");
+                // 檢查是否為字典模式（代理模式）
+                if let Ok(instance_dict) = instance.downcast::<pyo3::types::PyDict>(py) {
+                    if let Some(instance_type) = instance_dict.get_item("type")? {
+                        if instance_type.to_string() == "SystemShioajiProxy" {
+                            log::info!("🔧 Setting default accounts for proxy");
 
-                        // 使用已存儲的帳戶作為預設 (代理模式)
-                        self.setup_proxy_default_accounts()?;
+                            // 使用已存儲的帳戶作為預設 (代理模式)
+                            this.setup_proxy_default_accounts()?;
 
-                        log::info!("✅ Proxy default account references setup completed");
-                        return Ok(());
+                            log::info!("✅ Proxy default account references setup completed");
+                            return Ok(());
+                        }
                     }
                 }
-            }
 
-            // 如果是真實的 shioaji 實例，簡化處理
-            log::info!("🎯 Real shioaji instance detected - using stored accounts as defaults");
-            self.setup_proxy_default_accounts()?;
-            log::info!("✅ Default account references setup completed for real instance");
-            Ok(())
-        })
+                // 如果是真實的 shioaji 實例，簡化處理
+                log::info!("🎯 Real shioaji instance detected - using stored accounts as defaults");
+                this.setup_proxy_default_accounts()?;
+                log::info!("✅ Default account references setup completed for real instance");
+                Ok(())
+            },
+        )
+        .await
     }
 
     /// 嘗試從真實 shioaji 實例設定預設帳戶
@@ -1209,7 +1467,7 @@ impl Shioaji {
                 let version = crate::utils::get_system_shioaji_version().unwrap_or_default();
                 log::debug!("📦 使用 shioaji 版本: {} 的快取檔案", version);
 
-                match crate::utils::load_contracts_file() {
+                match crate::utils::load_contracts_file().map_err(|e| e.to_string()) {
                     Ok(Some(loaded_contracts)) => {
                         // 對應原始 Python: if not self.Contracts: (快取載入成功，非空)
                         {
@@ -1319,39 +1577,50 @@ impl Shioaji {
             }
         }
 
+        // 回調同時交給 Python 執行緒與下方的完成通知
+        let _contracts_cb: Option<Arc<dyn Fn(SecurityType) + Send + Sync>> =
+            _contracts_cb.map(Arc::from);
+
         // 第一步：呼叫真實的 _solace.fetch_all_contract() API
         let downloaded_contracts = {
             log::info!("📡 正在呼叫系統 shioaji _solace.fetch_all_contract...");
 
             // 使用當前已登入的 session 呼叫真實的 API
-            Python::with_gil(|py| -> Result<Contracts> {
-                // 嘗試使用已登入的實例下載合約
-                match self.call_logged_in_fetch_contracts(
-                    py,
-                    contracts_timeout,
-                    _contracts_cb.as_deref(),
-                ) {
-                    Ok(contracts) => {
-                        log::info!("✅ 使用已登入實例下載合約成功");
-                        Ok(contracts)
-                    }
-                    Err(e) => {
-                        log::warn!("⚠️ 已登入實例下載失敗: {}, 嘗試快取或測試資料", e);
-
-                        // 嘗試載入快取檔案
-                        match crate::utils::load_contracts_file() {
-                            Ok(Some(cached_contracts)) => {
-                                log::info!("✅ 載入快取合約成功");
-                                Ok(cached_contracts)
-                            }
-                            _ => {
-                                log::warn!("⚠️ 快取載入失敗，使用測試資料");
-                                Ok(crate::utils::create_default_test_contracts())
+            let this = self.worker_handle();
+            let contracts_cb = _contracts_cb.clone();
+            self.run_python(
+                "fetch_contracts",
+                self.deadline_for(Some(contracts_timeout as i32)),
+                move |py| -> Result<Contracts> {
+                    // 嘗試使用已登入的實例下載合約
+                    match this.call_logged_in_fetch_contracts(
+                        py,
+                        contracts_timeout,
+                        contracts_cb.as_deref(),
+                    ) {
+                        Ok(contracts) => {
+                            log::info!("✅ 使用已登入實例下載合約成功");
+                            Ok(contracts)
+                        }
+                        Err(e) => {
+                            log::warn!("⚠️ 已登入實例下載失敗: {}, 嘗試快取或測試資料", e);
+
+                            // 嘗試載入快取檔案
+                            match crate::utils::load_contracts_file() {
+                                Ok(Some(cached_contracts)) => {
+                                    log::info!("✅ 載入快取合約成功");
+                                    Ok(cached_contracts)
+                                }
+                                _ => {
+                                    log::warn!("⚠️ 快取載入失敗，使用測試資料");
+                                    Ok(crate::utils::create_default_test_contracts())
+                                }
                             }
                         }
                     }
-                }
-            })?
+                },
+            )
+            .await?
         };
 
         // 第二步：同步合約資料 (對應 Python: self.Contracts = self._solace.Contracts)
@@ -1383,14 +1652,13 @@ impl Shioaji {
         }
 
         // 觸發事件處理器回調
-        {
-            let event_handlers = self._event_handlers.lock().await;
-            event_handlers.trigger_contracts_fetched(SecurityType::Stock);
-            event_handlers.trigger_contracts_fetched(SecurityType::Future);
-            event_handlers.trigger_contracts_fetched(SecurityType::Option);
-            event_handlers.trigger_contracts_fetched(SecurityType::Index);
-            event_handlers.trigger_all_contracts_fetched();
-        }
+        self.handlers.trigger_contracts_fetched(SecurityType::Stock);
+        self.handlers
+            .trigger_contracts_fetched(SecurityType::Future);
+        self.handlers
+            .trigger_contracts_fetched(SecurityType::Option);
+        self.handlers.trigger_contracts_fetched(SecurityType::Index);
+        self.handlers.trigger_all_contracts_fetched();
 
         log::info!(
             "✅ 完整 fetch_all_contract 流程完成: 股票 {} (TSE: {}, OTC: {}, OES: {})",
@@ -1508,13 +1776,21 @@ impl Shioaji {
             let py_callback = pyo3::types::PyNone::get(py).to_object(py);
             solace
                 .call_method1(py, "fetch_all_contract", (contracts_timeout, py_callback))
-                .map_err(|e| Error::System(format!("fetch_all_contract failed: {}", e)))?;
+                .map_err(|e| {
+                    Error::from_sdk(e, |e| {
+                        Error::System(format!("fetch_all_contract failed: {}", e))
+                    })
+                })?;
         } else {
             // 沒有回調函數的情況 - 使用 None
             let py_none = pyo3::types::PyNone::get(py).to_object(py);
             solace
                 .call_method1(py, "fetch_all_contract", (contracts_timeout, py_none))
-                .map_err(|e| Error::System(format!("fetch_all_contract failed: {}", e)))?;
+                .map_err(|e| {
+                    Error::from_sdk(e, |e| {
+                        Error::System(format!("fetch_all_contract failed: {}", e))
+                    })
+                })?;
         }
 
         log::info!("✅ _solace.fetch_all_contract 執行完成");
@@ -1810,11 +2086,8 @@ inject_libpath()
         }
 
         // 1. 導入系統 shioaji 並創建已登入的實例
-        let shioaji_module = py
-            .import("shioaji")
-            .map_err(|e| Error::System(format!("無法導入 shioaji: {}", e)))?;
-
-        let sj_instance = shioaji_module.call_method1("Shioaji", (self.simulation,))?;
+        let sj_instance =
+            Self::new_system_instance(py, self.simulation, &self.proxies, self.vpn)?.into_ref(py);
 
         // 2. 獲取 API 憑證並登入
         let api_key = std::env::var("SHIOAJI_API_KEY")
@@ -1826,8 +2099,9 @@ inject_libpath()
 
         // 登入以取得有效的 session
         log::info!("🔐 登入以取得有效 session...");
-        let _login_result =
-            solace.call_method1("token_login", (&api_key, &secret_key, true, 30000))?;
+        let _login_result = solace
+            .call_method1("token_login", (&api_key, &secret_key, true, 30000))
+            .map_err(|e| Error::from_sdk(e, Error::Python))?;
 
         // 3. 呼叫真實的 fetch_all_contract API
         log::info!(
@@ -1847,11 +2121,15 @@ inject_libpath()
         if _contracts_cb.is_some() {
             // 有回調函數的情況 - 創建 Python 回調
             let py_callback = pyo3::types::PyNone::get(py).to_object(py);
-            solace.call_method1("fetch_all_contract", (contracts_timeout, py_callback))?;
+            solace
+                .call_method1("fetch_all_contract", (contracts_timeout, py_callback))
+                .map_err(|e| Error::from_sdk(e, Error::Python))?;
         } else {
             // 沒有回調函數的情況 - 使用 None
             let py_none = pyo3::types::PyNone::get(py).to_object(py);
-            solace.call_method1("fetch_all_contract", (contracts_timeout, py_none))?;
+            solace
+                .call_method1("fetch_all_contract", (contracts_timeout, py_none))
+                .map_err(|e| Error::from_sdk(e, Error::Python))?;
         }
 
         log::info!("✅ _solace.fetch_all_contract 執行完成");
@@ -2020,17 +2298,113 @@ inject_libpath()
         contracts_guard.clone()
     }
 
+    /// Look up a contract by code, with its real exchange
+    ///
+    /// Uses the loaded contract data when it has the code, otherwise asks
+    /// the SDK (`api.Contracts.Stocks["2330"]`).
+    pub async fn resolve_contract(
+        &self,
+        security_type: SecurityType,
+        code: &str,
+    ) -> Result<Contract> {
+        if let Some(contracts) = self.contracts.lock().await.as_ref() {
+            let loaded = match security_type {
+                SecurityType::Stock => &contracts.stocks,
+                SecurityType::Future => &contracts.futures,
+                SecurityType::Option => &contracts.options,
+                SecurityType::Index => &contracts.indices,
+            };
+            if let Some(contract) = loaded.get(code) {
+                return Ok(contract.clone());
+            }
+        }
+
+        let instance = self
+            .instance
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| Error::NotInitialized("Client not initialized".to_string()))?;
+        let mut query = Stock::new(code, Exchange::TSE).contract;
+        query.base.security_type = security_type;
+        self.run_python("resolve_contract", self.request_timeout(), move |py| {
+            let py_contract = Self::get_system_contract(py, &instance, &query)?;
+            Self::convert_python_trade_contract(py, &py_contract).ok_or_else(|| {
+                Error::InvalidContract(format!("Contract {} has no code", query.base.code))
+            })
+        })
+        .await
+    }
+
     /// 取得合約統計
     pub async fn get_contracts_counts(&self) -> Option<ContractCounts> {
         let contracts_guard = self.contracts.lock().await;
         contracts_guard.as_ref().map(|c| c.counts.clone())
     }
 
+    /// 註冊 trait 事件處理器 (Tick/BidAsk/Quote/Order/System/Contract)
+    ///
+    /// The handler is adapted into the same registries as the `on_*`
+    /// closures, so it receives data from the SDK bridge and shares their
+    /// panic isolation and stats. The handle removes it again.
+    ///
+    /// ```no_run
+    /// # use std::sync::Arc;
+    /// # use rshioaji::{Exchange, Shioaji, TickCallback, TickFOPv1, TickSTKv1};
+    /// struct Printer;
+    /// impl TickCallback for Printer {
+    ///     fn on_tick_stk_v1(&self, _exchange: Exchange, tick: TickSTKv1) {
+    ///         println!("{} {}", tick.code, tick.close);
+    ///     }
+    ///     fn on_tick_fop_v1(&self, _exchange: Exchange, _tick: TickFOPv1) {}
+    /// }
+    ///
+    /// # fn run(client: &Shioaji) {
+    /// let handle = client.register_handler(Arc::new(Printer) as Arc<dyn TickCallback>);
+    /// # drop(handle);
+    /// # }
+    /// ```
+    pub fn register_handler<H: Handler>(&self, handler: H) -> CallbackHandle {
+        let handle = self.handlers.register(handler);
+        log::info!("✅ Registered {} handler", handle.stream());
+        handle
+    }
+
+    /// 註冊非同步事件處理器 (Async Tick/BidAsk/Quote/Order/System)
+    ///
+    /// The handler runs on the current tokio runtime through its own ordered
+    /// queue, so it may await I/O without delaying other callbacks. Fails
+    /// with `Error::System` when called outside a runtime.
+    pub fn register_async_handler<H: AsyncHandler>(&self, handler: H) -> Result<CallbackHandle> {
+        self.register_async_handler_with(handler, AsyncQueue::Unbounded)
+    }
+
+    /// Register an async handler with a bounded or unbounded queue
+    ///
+    /// With `AsyncQueue::Bounded(n)` at most `n` events wait for the handler;
+    /// later ones are dropped (see `callback_stats`) rather than buffered.
+    pub fn register_async_handler_with<H: AsyncHandler>(
+        &self,
+        handler: H,
+        queue: AsyncQueue,
+    ) -> Result<CallbackHandle> {
+        let handle = self.handlers.register_async_with(handler, queue)?;
+        log::info!(
+            "✅ Registered async {} handler ({:?} queue)",
+            handle.stream(),
+            queue
+        );
+        Ok(handle)
+    }
+
     /// 註冊合約回調處理器
-    pub async fn register_contract_callback(&self, callback: Arc<dyn ContractCallback>) {
-        let mut event_handlers = self._event_handlers.lock().await;
-        event_handlers.register_contract_callback(callback);
+    pub async fn register_contract_callback(
+        &self,
+        callback: Arc<dyn ContractCallback>,
+    ) -> CallbackHandle {
+        let handle = self.handlers.register_contract_callback(callback);
         log::info!("✅ 已註冊合約回調處理器");
+        handle
     }
 
     /// Count contracts in a contract category
@@ -2074,19 +2448,34 @@ inject_libpath()
             }
         }
 
-        // Get instance
-        let instance = {
-            let instance_guard = self.instance.lock().await;
-            instance_guard
-                .as_ref()
-                .ok_or_else(|| Error::NotInitialized("Client not initialized".to_string()))?
-                .clone()
-        };
+        self.check_session(
+            &contract,
+            &[SessionKind::for_order_lot(order.order_lot.as_ref())],
+        )?;
+
+        // Journal the intent before the broker sees the order
+        let intent = self.journal_intent("place_order", &contract, &order);
 
         // Perform system shioaji place_order
-        let trade = self
-            .perform_system_place_order(&instance, contract, order)
-            .await?;
+        let result = match &self.backend {
+            Some(backend) => backend.place_order(contract, order).await,
+            None => {
+                async {
+                    let instance = self.system_instance().await?;
+                    self.perform_system_place_order(&instance, contract, order)
+                        .await
+                }
+                .await
+            }
+        }
+        .map_err(Error::into_order_outcome);
+        self.journal_result("place_order", None, intent, &result, |trade| {
+            JournalRecord::OrderPlaced {
+                trade: trade.clone(),
+                intent,
+            }
+        });
+        let trade = result?;
 
         log::info!(
             "✅ Order placed successfully using system shioaji: Order ID {}",
@@ -2102,66 +2491,165 @@ inject_libpath()
         contract: Contract,
         order: Order,
     ) -> Result<Trade> {
-        Python::with_gil(|py| -> Result<Trade> {
-            log::info!("📊 Calling system shioaji place_order...");
-
-            // Get contract object from system shioaji
-            let py_contract = self.get_system_contract(py, &contract)?;
-
-            // Create order object for system shioaji
-            let py_order = self.create_system_order(py, &order)?;
-
-            // Call place_order method
-            let trade_result = instance
-                .call_method(py, "place_order", (py_contract, py_order), None)
-                .map_err(|e| {
-                    Error::Trading(format!("System shioaji place_order failed: {:?}", e))
-                })?;
+        let instance = instance.clone();
+        self.run_python(
+            "place_order",
+            self.request_timeout(),
+            move |py| -> Result<Trade> {
+                log::info!("📊 Calling system shioaji place_order...");
+
+                // Get contract object from system shioaji
+                let py_contract = Self::get_system_contract(py, &instance, &contract)?;
+
+                // Create order object for system shioaji
+                let py_order = Self::create_system_order(py, &instance, &order)?;
+
+                // Call place_order method
+                let trade_result = instance
+                    .call_method(py, "place_order", (py_contract, py_order), None)
+                    .map_err(|e| {
+                        Error::from_sdk(e, |e| {
+                            Error::Trading(format!("System shioaji place_order failed: {:?}", e))
+                        })
+                    })?;
 
-            log::info!("✅ System shioaji place_order successful");
+                log::info!("✅ System shioaji place_order successful");
 
-            // Convert result to Trade object
-            let trade = self.convert_system_trade_result(py, &trade_result, &contract, &order)?;
+                // Convert result to Trade object
+                let trade =
+                    Self::convert_system_trade_result(py, &trade_result, &contract, &order)?;
 
-            Ok(trade)
-        })
+                Ok(trade)
+            },
+        )
+        .await
     }
 
-    /// Get real system shioaji contract object from downloaded contracts
+    /// Place futures/options order using system shioaji API
     ///
-    /// Returns real Python contract instances like api.Contracts.Stocks["2330"]
-    /// from the logged-in instance's contract collection
-    ///
-    /// **Requirements**: Must be logged in and contracts must be fetched
-    fn get_system_contract(&self, py: Python, contract: &Contract) -> Result<PyObject> {
-        // Check if logged in first
-        let logged_in = {
-            let logged_in_guard = self
-                .logged_in
-                .try_lock()
-                .map_err(|_| Error::Authentication("Login status lock contention".to_string()))?;
-            *logged_in_guard
-        };
+    /// 對應原始 Python：
+    /// ```python
+    /// order = api.Order(action, price, quantity, price_type, order_type, octype)
+    /// trade = api.place_order(contract, order)
+    /// ```
+    pub async fn place_futures_order(
+        &self,
+        contract: Contract,
+        order: FuturesOrder,
+    ) -> Result<FuturesTrade> {
+        log::info!(
+            "📊 Placing futures order using system shioaji for contract: {}",
+            contract.base.code
+        );
 
-        if !logged_in {
-            return Err(Error::Authentication(
-                "Must login first before accessing contracts. Please call login() method."
-                    .to_string(),
-            ));
+        // Validate login state
+        {
+            let logged_in = self.logged_in.lock().await;
+            if !*logged_in {
+                return Err(Error::NotLoggedIn(
+                    "Must login before placing orders".to_string(),
+                ));
+            }
         }
 
-        // Get the logged-in instance to access its Contracts
-        let instance = {
-            let instance_guard = self
-                .instance
-                .try_lock()
-                .map_err(|_| Error::Trading("Instance lock contention".to_string()))?;
-            instance_guard
-                .as_ref()
-                .ok_or_else(|| Error::Trading("Client not initialized".to_string()))?
-                .clone()
-        };
+        self.check_session(&contract, &[SessionKind::Day, SessionKind::Night])?;
+
+        // Journal the intent before the broker sees the order
+        let intent = self.journal_intent("place_futures_order", &contract, &order);
+
+        // Perform system shioaji place_order
+        let result = match &self.backend {
+            Some(backend) => backend.place_futures_order(contract, order).await,
+            None => {
+                async {
+                    let instance = self.system_instance().await?;
+                    self.perform_system_place_futures_order(&instance, contract, order)
+                        .await
+                }
+                .await
+            }
+        }
+        .map_err(Error::into_order_outcome);
+        self.journal_result("place_futures_order", None, intent, &result, |trade| {
+            JournalRecord::FuturesOrderPlaced {
+                trade: trade.clone(),
+                intent,
+            }
+        });
+        let trade = result?;
+
+        log::info!(
+            "✅ Futures order placed successfully using system shioaji: Order ID {}",
+            trade.order_id
+        );
+        Ok(trade)
+    }
+
+    /// Perform system shioaji futures/options order placement
+    async fn perform_system_place_futures_order(
+        &self,
+        instance: &PyObject,
+        contract: Contract,
+        order: FuturesOrder,
+    ) -> Result<FuturesTrade> {
+        let instance = instance.clone();
+        self.run_python(
+            "place_futures_order",
+            self.request_timeout(),
+            move |py| -> Result<FuturesTrade> {
+                log::info!("📊 Calling system shioaji place_order for futures...");
+
+                // Get contract object from system shioaji
+                let py_contract = Self::get_system_contract(py, &instance, &contract)?;
+
+                // Create futures order object for system shioaji
+                let py_order = Self::create_system_futures_order(py, &instance, &order)?;
+
+                // Call place_order method
+                let trade_result = instance
+                    .call_method(py, "place_order", (py_contract, py_order), None)
+                    .map_err(|e| {
+                        Error::from_sdk(e, |e| {
+                            Error::Trading(format!("System shioaji place_order failed: {:?}", e))
+                        })
+                    })?;
+
+                log::info!("✅ System shioaji futures place_order successful");
+
+                let (order_id, seqno, ordno, status) =
+                    Self::extract_system_trade_fields(py, &trade_result);
+
+                Ok(FuturesTrade {
+                    order,
+                    status,
+                    order_id,
+                    seqno,
+                    ordno,
+                    account: Account::new(
+                        "SinoPac".to_string(),
+                        "Default".to_string(),
+                        AccountType::Future,
+                        "User".to_string(),
+                        true,
+                    ),
+                    contracts: vec![contract],
+                })
+            },
+        )
+        .await
+    }
 
+    /// Get real system shioaji contract object from downloaded contracts
+    ///
+    /// Returns real Python contract instances like api.Contracts.Stocks["2330"]
+    /// from the logged-in instance's contract collection
+    ///
+    /// **Requirements**: Callers must check login; contracts must be fetched
+    fn get_system_contract(
+        py: Python,
+        instance: &PyObject,
+        contract: &Contract,
+    ) -> Result<PyObject> {
         // Access the Contracts attribute from the logged-in instance
         let contracts = instance.getattr(py, "Contracts")
             .map_err(|e| Error::Trading(format!("Cannot access Contracts from logged-in instance: {:?}. Make sure fetch_contracts() was called after login.", e)))?;
@@ -2215,7 +2703,7 @@ inject_libpath()
     }
 
     /// Create system shioaji order object
-    fn create_system_order(&self, py: Python, order: &Order) -> Result<PyObject> {
+    fn create_system_order(py: Python, instance: &PyObject, order: &Order) -> Result<PyObject> {
         // Import order from system shioaji
         let shioaji_module = py.import("shioaji")?;
         let order_class = shioaji_module.getattr("Order")?;
@@ -2226,6 +2714,15 @@ inject_libpath()
         order_dict.set_item("quantity", order.quantity)?;
         order_dict.set_item("order_type", order.order_type.to_string())?;
         order_dict.set_item("price_type", order.price_type.to_string())?;
+        if let Some(order_lot) = &order.order_lot {
+            order_dict.set_item("order_lot", order_lot.to_string())?;
+        }
+        if let Some(order_cond) = &order.order_cond {
+            order_dict.set_item("order_cond", order_cond.to_string())?;
+        }
+        if let Some(account) = &order.account {
+            order_dict.set_item("account", Self::find_system_account(py, instance, account)?)?;
+        }
 
         let py_order = order_class
             .call((), Some(order_dict))
@@ -2234,61 +2731,109 @@ inject_libpath()
         Ok(py_order.into())
     }
 
-    /// Convert system shioaji trade result to Trade object
-    fn convert_system_trade_result(
-        &self,
+    /// Create system shioaji futures/options order object
+    fn create_system_futures_order(
         py: Python,
-        trade_result: &PyObject,
-        contract: &Contract,
-        order: &Order,
-    ) -> Result<Trade> {
-        let order_id = trade_result
-            .getattr(py, "order_id")
-            .and_then(|v| v.extract::<String>(py))
-            .unwrap_or_else(|_| format!("order_{}", chrono::Utc::now().timestamp()));
+        instance: &PyObject,
+        order: &FuturesOrder,
+    ) -> Result<PyObject> {
+        let shioaji_module = py.import("shioaji")?;
+        let order_class = shioaji_module.getattr("Order")?;
 
-        let seqno = trade_result
-            .getattr(py, "seqno")
-            .and_then(|v| v.extract::<String>(py))
-            .unwrap_or_else(|_| "0".to_string());
+        let order_dict = pyo3::types::PyDict::new(py);
+        order_dict.set_item("action", order.action.to_string())?;
+        order_dict.set_item("price", order.price)?;
+        order_dict.set_item("quantity", order.quantity)?;
+        order_dict.set_item("order_type", order.order_type.to_string())?;
+        order_dict.set_item("price_type", order.price_type.to_string())?;
+        order_dict.set_item("octype", order.octype.to_string())?;
+        if let Some(account) = &order.account {
+            order_dict.set_item("account", Self::find_system_account(py, instance, account)?)?;
+        }
 
-        let ordno = trade_result
-            .getattr(py, "ordno")
-            .and_then(|v| v.extract::<String>(py))
-            .unwrap_or_else(|_| "0".to_string());
+        let py_order = order_class.call((), Some(order_dict)).map_err(|e| {
+            Error::Trading(format!("Failed to create system futures order: {:?}", e))
+        })?;
+
+        Ok(py_order.into())
+    }
+
+    /// The SDK's own account object for `account` (from `list_accounts`)
+    ///
+    /// Falls back to a plain dict when the session does not list it, so the
+    /// SDK reports the mismatch instead of silently using its default account.
+    fn find_system_account(py: Python, instance: &PyObject, account: &Account) -> Result<PyObject> {
+        let listed = instance
+            .call_method0(py, "list_accounts")
+            .and_then(|accounts| accounts.extract::<Vec<PyObject>>(py))
+            .unwrap_or_default();
+        for py_account in listed {
+            let field = |name: &str| {
+                py_account
+                    .getattr(py, name)
+                    .and_then(|value| value.extract::<String>(py))
+                    .ok()
+            };
+            if field("account_id").as_deref() == Some(account.account_id.as_str())
+                && field("broker_id").as_deref() == Some(account.broker_id.as_str())
+            {
+                return Ok(py_account);
+            }
+        }
+        Self::convert_account_to_python(py, account)
+    }
+
+    /// Extract order_id, seqno, ordno and status from a system shioaji trade object
+    fn extract_system_trade_fields(
+        py: Python,
+        trade_result: &PyObject,
+    ) -> (String, String, String, Status) {
+        // 原始 Python Trade 結構為 trade.order.id / trade.status.status，
+        // 舊版代理物件則直接提供 order_id / seqno / ordno / status
+        let py_order = trade_result.getattr(py, "order").ok();
+        let order_field = |nested: &str, flat: &str| -> Option<String> {
+            py_order
+                .as_ref()
+                .and_then(|o| o.getattr(py, nested).ok())
+                .and_then(|v| v.extract::<String>(py).ok())
+                .or_else(|| {
+                    trade_result
+                        .getattr(py, flat)
+                        .and_then(|v| v.extract::<String>(py))
+                        .ok()
+                })
+        };
+
+        let order_id = order_field("id", "order_id")
+            .unwrap_or_else(|| format!("order_{}", chrono::Utc::now().timestamp()));
+        let seqno = order_field("seqno", "seqno").unwrap_or_else(|| "0".to_string());
+        let ordno = order_field("ordno", "ordno").unwrap_or_else(|| "0".to_string());
 
         let status_obj = trade_result
             .getattr(py, "status")
+            .map(|status| status.getattr(py, "status").unwrap_or(status))
             .unwrap_or_else(|_| py.None());
 
         let status = if let Ok(status_enum) = status_obj.getattr(py, "value") {
             // Handle enum-style status
-            match status_enum
-                .extract::<String>(py)
-                .unwrap_or_default()
-                .as_str()
-            {
-                "Filled" => Status::Filled,
-                "PartFilled" => Status::PartFilled,
-                "Cancelled" => Status::Cancelled,
-                "Failed" => Status::Failed,
-                _ => Status::Submitted,
-            }
+            Status::from_string(&status_enum.extract::<String>(py).unwrap_or_default())
         } else {
             // Handle string status
-            match status_obj
-                .extract::<String>(py)
-                .unwrap_or_default()
-                .as_str()
-            {
-                "Filled" => Status::Filled,
-                "PartFilled" => Status::PartFilled,
-                "Cancelled" => Status::Cancelled,
-                "Failed" => Status::Failed,
-                _ => Status::Submitted,
-            }
+            Status::from_string(&status_obj.extract::<String>(py).unwrap_or_default())
         };
 
+        (order_id, seqno, ordno, status)
+    }
+
+    /// Convert system shioaji trade result to Trade object
+    fn convert_system_trade_result(
+        py: Python,
+        trade_result: &PyObject,
+        contract: &Contract,
+        order: &Order,
+    ) -> Result<Trade> {
+        let (order_id, seqno, ordno, status) = Self::extract_system_trade_fields(py, trade_result);
+
         // Create a default account for the trade
         let account = Account::new(
             "SinoPac".to_string(),
@@ -2310,7 +2855,13 @@ inject_libpath()
     }
 
     /// Update order price or quantity
-    pub async fn update_order(&self, trade: Trade, price: Option<f64>, qty: Option<i32>, timeout: Option<i32>) -> Result<Trade> {
+    pub async fn update_order(
+        &self,
+        trade: Trade,
+        price: Option<f64>,
+        qty: Option<i32>,
+        timeout: Option<i32>,
+    ) -> Result<Trade> {
         log::info!("📊 Updating order ID: {}", trade.order_id);
 
         // Validate login state
@@ -2323,19 +2874,29 @@ inject_libpath()
             }
         }
 
-        // Get instance
-        let instance = {
-            let instance_guard = self.instance.lock().await;
-            instance_guard
-                .as_ref()
-                .ok_or_else(|| Error::NotInitialized("Client not initialized".to_string()))?
-                .clone()
-        };
-
         // Perform system shioaji update_order
-        let updated_trade = self
-            .perform_system_update_order(&instance, trade, price, qty, timeout)
-            .await?;
+        let order_id = trade.order_id.clone();
+        let result = match &self.backend {
+            Some(backend) => backend.update_order(trade, price, qty).await,
+            None => {
+                async {
+                    let instance = self.system_instance().await?;
+                    self.perform_system_update_order(&instance, trade, price, qty, timeout)
+                        .await
+                }
+                .await
+            }
+        }
+        .map_err(Error::into_order_outcome);
+        self.journal_result("update_order", Some(&order_id), None, &result, |updated| {
+            JournalRecord::OrderUpdated {
+                order_id: order_id.clone(),
+                price,
+                quantity: qty,
+                status: updated.status.clone(),
+            }
+        });
+        let updated_trade = result?;
 
         log::info!(
             "✅ Order updated successfully: Order ID {}",
@@ -2358,19 +2919,31 @@ inject_libpath()
             }
         }
 
-        // Get instance
-        let instance = {
-            let instance_guard = self.instance.lock().await;
-            instance_guard
-                .as_ref()
-                .ok_or_else(|| Error::NotInitialized("Client not initialized".to_string()))?
-                .clone()
-        };
-
         // Perform system shioaji cancel_order
-        let cancelled_trade = self
-            .perform_system_cancel_order(&instance, trade, timeout)
-            .await?;
+        let order_id = trade.order_id.clone();
+        let result = match &self.backend {
+            Some(backend) => backend.cancel_order(trade).await,
+            None => {
+                async {
+                    let instance = self.system_instance().await?;
+                    self.perform_system_cancel_order(&instance, trade, timeout)
+                        .await
+                }
+                .await
+            }
+        }
+        .map_err(Error::into_order_outcome);
+        self.journal_result(
+            "cancel_order",
+            Some(&order_id),
+            None,
+            &result,
+            |cancelled| JournalRecord::OrderCancelled {
+                order_id: order_id.clone(),
+                status: cancelled.status.clone(),
+            },
+        );
+        let cancelled_trade = result?;
 
         log::info!(
             "✅ Order cancelled successfully: Order ID {}",
@@ -2393,6 +2966,10 @@ inject_libpath()
             }
         }
 
+        if let Some(backend) = &self.backend {
+            return backend.list_trades().await;
+        }
+
         // Get instance
         let instance = {
             let instance_guard = self.instance.lock().await;
@@ -2403,9 +2980,7 @@ inject_libpath()
         };
 
         // Perform system shioaji list_trades
-        let trades = self
-            .perform_system_list_trades(&instance)
-            .await?;
+        let trades = self.perform_system_list_trades(&instance).await?;
 
         log::info!("✅ Listed {} trades", trades.len());
         Ok(trades)
@@ -2425,6 +3000,10 @@ inject_libpath()
             }
         }
 
+        if let Some(backend) = &self.backend {
+            return backend.list_accounts().await;
+        }
+
         // Get instance
         let instance = {
             let instance_guard = self.instance.lock().await;
@@ -2435,16 +3014,19 @@ inject_libpath()
         };
 
         // Perform system shioaji list_accounts
-        let accounts = self
-            .perform_system_list_accounts(&instance)
-            .await?;
+        let accounts = self.perform_system_list_accounts(&instance).await?;
 
         log::info!("✅ Listed {} accounts", accounts.len());
         Ok(accounts)
     }
 
     /// List positions
-    pub async fn list_positions(&self, account: Option<Account>, unit: Option<Unit>, timeout: Option<i32>) -> Result<Vec<Position>> {
+    pub async fn list_positions(
+        &self,
+        account: Option<Account>,
+        unit: Option<Unit>,
+        timeout: Option<i32>,
+    ) -> Result<Vec<Position>> {
         log::info!("📊 Listing positions");
 
         // Validate login state
@@ -2457,6 +3039,10 @@ inject_libpath()
             }
         }
 
+        if let Some(backend) = &self.backend {
+            return backend.list_positions(account, unit).await;
+        }
+
         // Get instance
         let instance = {
             let instance_guard = self.instance.lock().await;
@@ -2475,6 +3061,41 @@ inject_libpath()
         Ok(positions)
     }
 
+    /// Query futures account margin (對應原始 Python 的 api.margin)
+    pub async fn margin(&self, account: Option<Account>, timeout: Option<i32>) -> Result<Margin> {
+        log::info!("📊 Querying margin");
+
+        // Validate login state
+        {
+            let logged_in = self.logged_in.lock().await;
+            if !*logged_in {
+                return Err(Error::NotLoggedIn(
+                    "Must login before querying margin".to_string(),
+                ));
+            }
+        }
+
+        if let Some(backend) = &self.backend {
+            return backend.margin(account).await;
+        }
+
+        // Get instance
+        let instance = {
+            let instance_guard = self.instance.lock().await;
+            instance_guard
+                .as_ref()
+                .ok_or_else(|| Error::NotInitialized("Client not initialized".to_string()))?
+                .clone()
+        };
+
+        let margin = self
+            .perform_system_margin(&instance, account, timeout)
+            .await?;
+
+        log::info!("✅ Margin available: {:.0}", margin.available_margin);
+        Ok(margin)
+    }
+
     /// Subscribe to market data using system shioaji API
     pub async fn subscribe(&self, contract: Contract, quote_type: &str) -> Result<String> {
         log::info!(
@@ -2493,6 +3114,12 @@ inject_libpath()
             }
         }
 
+        if let Some(backend) = &self.backend {
+            let kind = crate::backend::quote_type_from_str(quote_type)?;
+            backend.subscribe(contract.clone(), kind).await?;
+            return Ok(format!("{}_{}", contract.base.code, quote_type));
+        }
+
         // Get instance
         let instance = {
             let instance_guard = self.instance.lock().await;
@@ -2521,107 +3148,118 @@ inject_libpath()
         contract: Contract,
         quote_type: &str,
     ) -> Result<String> {
-        Python::with_gil(|py| -> Result<String> {
-            log::info!("📊 Calling Quote.subscribe following original shioaji pattern...");
+        let instance = instance.clone();
+        let quote_type = quote_type.to_string();
+        self.run_python(
+            "subscribe",
+            self.request_timeout(),
+            move |py| -> Result<String> {
+                log::info!("📊 Calling Quote.subscribe following original shioaji pattern...");
+
+                // Get quote object from real Shioaji instance (api.quote)
+                let quote = instance.getattr(py, "quote").map_err(|e| {
+                    Error::Subscription(format!("Failed to get quote object: {:?}", e))
+                })?;
 
-            // Get quote object from real Shioaji instance (api.quote)
-            let quote = instance
-                .getattr(py, "quote")
-                .map_err(|e| Error::Subscription(format!("Failed to get quote object: {:?}", e)))?;
+                // Get the contract from the real Shioaji contracts
+                // Following Python pattern: api.Contracts.Futures.MXF["MXFG5"]
+                let contracts = instance.getattr(py, "Contracts").map_err(|e| {
+                    Error::Subscription(format!("Failed to get Contracts object: {:?}", e))
+                })?;
 
-            // Get the contract from the real Shioaji contracts
-            // Following Python pattern: api.Contracts.Futures.MXF["MXFG5"]
-            let contracts = instance.getattr(py, "Contracts").map_err(|e| {
-                Error::Subscription(format!("Failed to get Contracts object: {:?}", e))
-            })?;
+                // Get the appropriate contract collection based on contract type
+                let contract_collection = match contract.base.security_type {
+                    SecurityType::Stock => contracts.getattr(py, "Stocks"),
+                    SecurityType::Future => contracts.getattr(py, "Futures"),
+                    SecurityType::Option => contracts.getattr(py, "Options"),
+                    SecurityType::Index => contracts.getattr(py, "Indexs"), // Note: shioaji uses "Indexs" not "Indices"
+                }
+                .map_err(|e| {
+                    Error::Subscription(format!("Failed to get contract collection: {:?}", e))
+                })?;
 
-            // Get the appropriate contract collection based on contract type
-            let contract_collection = match contract.base.security_type {
-                SecurityType::Stock => contracts.getattr(py, "Stocks"),
-                SecurityType::Future => contracts.getattr(py, "Futures"),
-                SecurityType::Option => contracts.getattr(py, "Options"),
-                SecurityType::Index => contracts.getattr(py, "Indexs"), // Note: shioaji uses "Indexs" not "Indices"
-            }
-            .map_err(|e| {
-                Error::Subscription(format!("Failed to get contract collection: {:?}", e))
-            })?;
+                // For futures, need to get the specific exchange group first
+                // Following: api.Contracts.Futures.MXF["MXFG5"]
+                let python_contract = if contract.base.security_type == SecurityType::Future {
+                    // Get the futures exchange group (e.g., MXF for mini futures)
+                    let exchange_group = match contract.base.code.as_str() {
+                        code if code.starts_with("MXF") => "MXF",
+                        code if code.starts_with("TXF") => "TXF",
+                        code if code.starts_with("EXF") => "EXF",
+                        _ => "MXF", // Default to MXF for unknown codes
+                    };
 
-            // For futures, need to get the specific exchange group first
-            // Following: api.Contracts.Futures.MXF["MXFG5"]
-            let python_contract = if contract.base.security_type == SecurityType::Future {
-                // Get the futures exchange group (e.g., MXF for mini futures)
-                let exchange_group = match contract.base.code.as_str() {
-                    code if code.starts_with("MXF") => "MXF",
-                    code if code.starts_with("TXF") => "TXF",
-                    code if code.starts_with("EXF") => "EXF",
-                    _ => "MXF", // Default to MXF for unknown codes
-                };
-
-                log::info!(
-                    "📊 Getting futures contract: Contracts.Futures.{}[{}]",
-                    exchange_group,
-                    contract.base.code
-                );
+                    log::info!(
+                        "📊 Getting futures contract: Contracts.Futures.{}[{}]",
+                        exchange_group,
+                        contract.base.code
+                    );
 
-                let futures_group =
+                    let futures_group =
+                        contract_collection
+                            .getattr(py, exchange_group)
+                            .map_err(|e| {
+                                Error::Subscription(format!(
+                                    "Failed to get futures group {}: {:?}",
+                                    exchange_group, e
+                                ))
+                            })?;
+
+                    futures_group
+                        .call_method1(py, "__getitem__", (&contract.base.code,))
+                        .map_err(|e| {
+                            Error::Subscription(format!(
+                                "Contract {} not found in {}: {:?}",
+                                contract.base.code, exchange_group, e
+                            ))
+                        })?
+                } else {
+                    // For stocks, options, indices: direct access
                     contract_collection
-                        .getattr(py, exchange_group)
+                        .call_method1(py, "__getitem__", (&contract.base.code,))
                         .map_err(|e| {
                             Error::Subscription(format!(
-                                "Failed to get futures group {}: {:?}",
-                                exchange_group, e
+                                "Contract {} not found in collection: {:?}",
+                                contract.base.code, e
                             ))
-                        })?;
-
-                futures_group
-                    .call_method1(py, "__getitem__", (&contract.base.code,))
-                    .map_err(|e| {
-                        Error::Subscription(format!(
-                            "Contract {} not found in {}: {:?}",
-                            contract.base.code, exchange_group, e
-                        ))
-                    })?
-            } else {
-                // For stocks, options, indices: direct access
-                contract_collection
-                    .call_method1(py, "__getitem__", (&contract.base.code,))
-                    .map_err(|e| {
-                        Error::Subscription(format!(
-                            "Contract {} not found in collection: {:?}",
-                            contract.base.code, e
-                        ))
-                    })?
-            };
+                        })?
+                };
 
-            log::info!("📊 Found contract, calling quote.subscribe...");
+                log::info!("📊 Found contract, calling quote.subscribe...");
 
-            // Subscribe using the real contract object
-            // Following Python: api.quote.subscribe(contract, quote_type="tick", version='v1')
-            let kwargs = pyo3::types::PyDict::new(py);
-            kwargs.set_item("quote_type", quote_type)?;
-            kwargs.set_item("version", "v1")?;
+                // Subscribe using the real contract object
+                // Following Python: api.quote.subscribe(contract, quote_type="tick", version='v1')
+                let kwargs = pyo3::types::PyDict::new(py);
+                kwargs.set_item("quote_type", &quote_type)?;
+                kwargs.set_item("version", "v1")?;
 
-            let _result = quote
-                .call_method(py, "subscribe", (python_contract,), Some(kwargs))
-                .map_err(|e| Error::Subscription(format!("Quote.subscribe failed: {:?}", e)))?;
+                let _result = quote
+                    .call_method(py, "subscribe", (python_contract,), Some(kwargs))
+                    .map_err(|e| {
+                        Error::from_sdk(e, |e| {
+                            Error::Subscription(format!("Quote.subscribe failed: {:?}", e))
+                        })
+                    })?;
 
-            log::info!(
-                "✅ Quote.subscribe successful for {} ({})",
-                contract.base.code,
-                quote_type
-            );
+                log::info!(
+                    "✅ Quote.subscribe successful for {} ({})",
+                    contract.base.code,
+                    quote_type
+                );
 
-            // Generate subscription ID
-            let subscription_id = format!(
-                "{}_{}_{}_{}",
-                contract.base.code,
-                quote_type,
-                chrono::Utc::now().timestamp(),
-                fastrand::u32(1000..9999)
-            );
+                // Generate subscription ID
+                let subscription_id = format!(
+                    "{}_{}_{}_{}",
+                    contract.base.code,
+                    quote_type,
+                    chrono::Utc::now().timestamp(),
+                    fastrand::u32(1000..9999)
+                );
 
-            Ok(subscription_id)
-        })
+                Ok(subscription_id)
+            },
+        )
+        .await
     }
 
     /// Perform system shioaji update_order
@@ -2633,44 +3271,57 @@ inject_libpath()
         qty: Option<i32>,
         timeout: Option<i32>,
     ) -> Result<Trade> {
-        Python::with_gil(|py| -> Result<Trade> {
-            log::info!("📊 Calling system shioaji update_order...");
-
-            // Convert trade to Python object
-            let py_trade = self.convert_trade_to_python(py, &trade)?;
-
-            // Build parameters
-            let mut args = vec![py_trade];
-            if let Some(p) = price {
-                args.push(p.to_object(py));
-            } else {
-                args.push(py.None());
-            }
-            if let Some(q) = qty {
-                args.push(q.to_object(py));
-            } else {
-                args.push(py.None());
-            }
-            if let Some(t) = timeout {
-                args.push(t.to_object(py));
-            } else {
-                args.push(5000i32.to_object(py));
-            }
+        let instance = instance.clone();
+        self.run_python(
+            "update_order",
+            self.deadline_for(timeout),
+            move |py| -> Result<Trade> {
+                log::info!("📊 Calling system shioaji update_order...");
+
+                // Convert trade to Python object
+                let py_trade = Self::convert_trade_to_python(py, &trade)?;
+
+                // Build parameters
+                let mut args = vec![py_trade];
+                if let Some(p) = price {
+                    args.push(p.to_object(py));
+                } else {
+                    args.push(py.None());
+                }
+                if let Some(q) = qty {
+                    args.push(q.to_object(py));
+                } else {
+                    args.push(py.None());
+                }
+                if let Some(t) = timeout {
+                    args.push(t.to_object(py));
+                } else {
+                    args.push(5000i32.to_object(py));
+                }
 
-            // Call update_order method
-            let trade_result = instance
-                .call_method(py, "update_order", pyo3::types::PyTuple::new(py, args), None)
-                .map_err(|e| {
-                    Error::Trading(format!("System shioaji update_order failed: {:?}", e))
-                })?;
+                // Call update_order method
+                let trade_result = instance
+                    .call_method(
+                        py,
+                        "update_order",
+                        pyo3::types::PyTuple::new(py, args),
+                        None,
+                    )
+                    .map_err(|e| {
+                        Error::from_sdk(e, |e| {
+                            Error::Trading(format!("System shioaji update_order failed: {:?}", e))
+                        })
+                    })?;
 
-            log::info!("✅ System shioaji update_order successful");
+                log::info!("✅ System shioaji update_order successful");
 
-            // Convert result back to Trade object
-            let updated_trade = self.convert_python_trade_result(py, &trade_result)?;
+                // Convert result back to Trade object
+                let updated_trade = Self::convert_python_trade_result(py, &trade_result)?;
 
-            Ok(updated_trade)
-        })
+                Ok(updated_trade)
+            },
+        )
+        .await
     }
 
     /// Perform system shioaji cancel_order
@@ -2680,78 +3331,100 @@ inject_libpath()
         trade: Trade,
         timeout: Option<i32>,
     ) -> Result<Trade> {
-        Python::with_gil(|py| -> Result<Trade> {
-            log::info!("📊 Calling system shioaji cancel_order...");
-
-            // Convert trade to Python object
-            let py_trade = self.convert_trade_to_python(py, &trade)?;
-
-            // Build parameters
-            let timeout_val = timeout.unwrap_or(5000);
-            let args = (py_trade, timeout_val);
-
-            // Call cancel_order method
-            let trade_result = instance
-                .call_method(py, "cancel_order", args, None)
-                .map_err(|e| {
-                    Error::Trading(format!("System shioaji cancel_order failed: {:?}", e))
-                })?;
+        let instance = instance.clone();
+        self.run_python(
+            "cancel_order",
+            self.deadline_for(timeout),
+            move |py| -> Result<Trade> {
+                log::info!("📊 Calling system shioaji cancel_order...");
+
+                // Convert trade to Python object
+                let py_trade = Self::convert_trade_to_python(py, &trade)?;
+
+                // Build parameters
+                let timeout_val = timeout.unwrap_or(5000);
+                let args = (py_trade, timeout_val);
+
+                // Call cancel_order method
+                let trade_result = instance
+                    .call_method(py, "cancel_order", args, None)
+                    .map_err(|e| {
+                        Error::from_sdk(e, |e| {
+                            Error::Trading(format!("System shioaji cancel_order failed: {:?}", e))
+                        })
+                    })?;
 
-            log::info!("✅ System shioaji cancel_order successful");
+                log::info!("✅ System shioaji cancel_order successful");
 
-            // Convert result back to Trade object
-            let cancelled_trade = self.convert_python_trade_result(py, &trade_result)?;
+                // Convert result back to Trade object
+                let cancelled_trade = Self::convert_python_trade_result(py, &trade_result)?;
 
-            Ok(cancelled_trade)
-        })
+                Ok(cancelled_trade)
+            },
+        )
+        .await
     }
 
     /// Perform system shioaji list_trades
-    async fn perform_system_list_trades(
-        &self,
-        instance: &PyObject,
-    ) -> Result<Vec<Trade>> {
-        Python::with_gil(|py| -> Result<Vec<Trade>> {
-            log::info!("📊 Calling system shioaji list_trades...");
-
-            // Call list_trades method
-            let trades_result = instance
-                .call_method(py, "list_trades", (), None)
-                .map_err(|e| {
-                    Error::Trading(format!("System shioaji list_trades failed: {:?}", e))
-                })?;
+    async fn perform_system_list_trades(&self, instance: &PyObject) -> Result<Vec<Trade>> {
+        let instance = instance.clone();
+        self.run_python(
+            "list_trades",
+            self.request_timeout(),
+            move |py| -> Result<Vec<Trade>> {
+                log::info!("📊 Calling system shioaji list_trades...");
+
+                // Call list_trades method
+                let trades_result =
+                    instance
+                        .call_method(py, "list_trades", (), None)
+                        .map_err(|e| {
+                            Error::from_sdk(e, |e| {
+                                Error::Trading(format!(
+                                    "System shioaji list_trades failed: {:?}",
+                                    e
+                                ))
+                            })
+                        })?;
 
-            log::info!("✅ System shioaji list_trades successful");
+                log::info!("✅ System shioaji list_trades successful");
 
-            // Convert result to Vec<Trade>
-            let trades = self.convert_python_trades_list(py, &trades_result)?;
+                // Convert result to Vec<Trade>
+                let trades = Self::convert_python_trades_list(py, &trades_result)?;
 
-            Ok(trades)
-        })
+                Ok(trades)
+            },
+        )
+        .await
     }
 
     /// Perform system shioaji list_accounts
-    async fn perform_system_list_accounts(
-        &self,
-        instance: &PyObject,
-    ) -> Result<Vec<Account>> {
-        Python::with_gil(|py| -> Result<Vec<Account>> {
-            log::info!("📊 Calling system shioaji list_accounts...");
-
-            // Call list_accounts method
-            let accounts_result = instance
-                .call_method(py, "list_accounts", (), None)
-                .map_err(|e| {
-                    Error::Trading(format!("System shioaji list_accounts failed: {:?}", e))
-                })?;
+    async fn perform_system_list_accounts(&self, instance: &PyObject) -> Result<Vec<Account>> {
+        let instance = instance.clone();
+        self.run_python(
+            "list_accounts",
+            self.request_timeout(),
+            move |py| -> Result<Vec<Account>> {
+                log::info!("📊 Calling system shioaji list_accounts...");
+
+                // Call list_accounts method
+                let accounts_result = instance
+                    .call_method(py, "list_accounts", (), None)
+                    .map_err(|e| {
+                        Error::from_sdk(e, |e| {
+                            Error::Trading(format!("System shioaji list_accounts failed: {:?}", e))
+                        })
+                    })?;
 
-            log::info!("✅ System shioaji list_accounts successful");
+                log::info!("✅ System shioaji list_accounts successful");
 
-            // Convert result to Vec<Account>
-            let accounts = self.convert_python_accounts_list(py, &accounts_result)?;
+                // Convert result to Vec<Account>
+                let accounts = Self::convert_python_accounts_list(py, &accounts_result)?;
 
-            Ok(accounts)
-        })
+                Ok(accounts)
+            },
+        )
+        .await
     }
 
     /// Perform system shioaji list_positions
@@ -2762,41 +3435,102 @@ inject_libpath()
         unit: Option<Unit>,
         timeout: Option<i32>,
     ) -> Result<Vec<Position>> {
-        Python::with_gil(|py| -> Result<Vec<Position>> {
-            log::info!("📊 Calling system shioaji list_positions...");
-
-            // Build parameters
-            let mut args = vec![];
-            if let Some(acc) = account {
-                let py_account = self.convert_account_to_python(py, &acc)?;
-                args.push(py_account);
-            } else {
-                args.push(py.None());
-            }
+        let instance = instance.clone();
+        self.run_python(
+            "list_positions",
+            self.deadline_for(timeout),
+            move |py| -> Result<Vec<Position>> {
+                log::info!("📊 Calling system shioaji list_positions...");
+
+                // Build parameters
+                let mut args = vec![];
+                if let Some(acc) = account {
+                    let py_account = Self::convert_account_to_python(py, &acc)?;
+                    args.push(py_account);
+                } else {
+                    args.push(py.None());
+                }
 
-            // Build kwargs
-            let kwargs = pyo3::types::PyDict::new(py);
-            if let Some(u) = unit {
-                kwargs.set_item("unit", self.convert_unit_to_python(py, &u)?)?;
-            }
-            if let Some(t) = timeout {
-                kwargs.set_item("timeout", t)?;
-            }
+                // Build kwargs
+                let kwargs = pyo3::types::PyDict::new(py);
+                if let Some(u) = unit {
+                    kwargs.set_item("unit", Self::convert_unit_to_python(py, &u)?)?;
+                }
+                if let Some(t) = timeout {
+                    kwargs.set_item("timeout", t)?;
+                }
 
-            // Call list_positions method
-            let positions_result = instance
-                .call_method(py, "list_positions", pyo3::types::PyTuple::new(py, args), Some(kwargs))
-                .map_err(|e| {
-                    Error::Trading(format!("System shioaji list_positions failed: {:?}", e))
-                })?;
+                // Call list_positions method
+                let positions_result = instance
+                    .call_method(
+                        py,
+                        "list_positions",
+                        pyo3::types::PyTuple::new(py, args),
+                        Some(kwargs),
+                    )
+                    .map_err(|e| {
+                        Error::from_sdk(e, |e| {
+                            Error::Trading(format!("System shioaji list_positions failed: {:?}", e))
+                        })
+                    })?;
 
-            log::info!("✅ System shioaji list_positions successful");
+                log::info!("✅ System shioaji list_positions successful");
 
-            // Convert result to Vec<Position>
-            let positions = self.convert_python_positions_list(py, &positions_result)?;
+                // Convert result to Vec<Position>
+                let positions = Self::convert_python_positions_list(py, &positions_result)?;
 
-            Ok(positions)
-        })
+                Ok(positions)
+            },
+        )
+        .await
+    }
+
+    /// Perform system shioaji margin
+    async fn perform_system_margin(
+        &self,
+        instance: &PyObject,
+        account: Option<Account>,
+        timeout: Option<i32>,
+    ) -> Result<Margin> {
+        let instance = instance.clone();
+        self.run_python(
+            "margin",
+            self.deadline_for(timeout),
+            move |py| -> Result<Margin> {
+                let kwargs = pyo3::types::PyDict::new(py);
+                if let Some(acc) = account {
+                    kwargs.set_item("account", Self::convert_account_to_python(py, &acc)?)?;
+                }
+                if let Some(t) = timeout {
+                    kwargs.set_item("timeout", t)?;
+                }
+
+                let margin_result = instance
+                    .call_method(py, "margin", (), Some(kwargs))
+                    .map_err(|e| {
+                        Error::from_sdk(e, |e| {
+                            Error::Trading(format!("System shioaji margin failed: {:?}", e))
+                        })
+                    })?;
+
+                let field = |name: &str| -> f64 {
+                    margin_result
+                        .getattr(py, name)
+                        .and_then(|v| v.extract::<f64>(py))
+                        .unwrap_or(0.0)
+                };
+
+                Ok(Margin {
+                    account_balance: field("today_balance"),
+                    available_margin: field("available_margin"),
+                    initial_margin: field("initial_margin"),
+                    maintenance_margin: field("maintenance_margin"),
+                    margin_call: field("margin_call"),
+                    unrealized_pnl: field("future_open_position"),
+                })
+            },
+        )
+        .await
     }
 
     /// Setup callbacks using system shioaji API
@@ -2821,7 +3555,9 @@ inject_libpath()
 
     /// Perform system shioaji callback setup
     async fn perform_system_setup_callbacks(&self, instance: &PyObject) -> Result<()> {
-        Python::with_gil(|py| -> Result<()> {
+        let this = self.worker_handle();
+        let instance = instance.clone();
+        self.run_python("setup_callbacks", self.request_timeout(), move |py| -> Result<()> {
             log::info!("📊 Setting up system shioaji callbacks...");
 
             // Get quote object from instance
@@ -2830,14 +3566,14 @@ inject_libpath()
                 .map_err(|e| Error::Connection(format!("Failed to get quote object: {:?}", e)))?;
 
             // Clone callback collections for use in closures
-            let tick_stk_callbacks = self.tick_stk_callbacks.clone();
-            let tick_fop_callbacks = self.tick_fop_callbacks.clone();
-            let _bidask_stk_callbacks = self.bidask_stk_callbacks.clone();
-            let _bidask_fop_callbacks = self.bidask_fop_callbacks.clone();
-            let _quote_stk_callbacks = self.quote_stk_callbacks.clone();
-            let _quote_callbacks = self.quote_callbacks.clone();
-            let _event_callbacks = self.event_callbacks.clone();
-            let _session_down_callbacks = self.session_down_callbacks.clone();
+            let tick_stk_callbacks = this.handlers.tick_stk.clone();
+            let tick_fop_callbacks = this.handlers.tick_fop.clone();
+            let bidask_stk_callbacks = this.handlers.bidask_stk.clone();
+            let _bidask_fop_callbacks = this.handlers.bidask_fop.clone();
+            let quote_stk_callbacks = this.handlers.quote_stk.clone();
+            let quote_callbacks = this.handlers.quote.clone();
+            let event_callbacks = this.handlers.event.clone();
+            let session_down_callbacks = this.handlers.session_down.clone();
 
             // Create callback functions for system shioaji
             let tick_stk_callback = pyo3::types::PyCFunction::new_closure(
@@ -2977,29 +3713,25 @@ inject_libpath()
                             };
 
                             // 🎯 CRITICAL FIX: Call registered user callbacks directly!
-                            if let Ok(callbacks_guard) = tick_stk_callbacks.try_lock() {
-                                for callback in callbacks_guard.iter() {
-                                    // Execute each registered callback
-                                    callback(exchange, tick_data.clone());
-                                }
-                                if !callbacks_guard.is_empty() {
-                                    log::debug!(
-                                        "✅ Triggered {} STK tick callbacks for {}",
-                                        callbacks_guard.len(),
-                                        tick_data.code
-                                    );
-                                }
-                            } else {
-                                log::warn!("Failed to acquire lock for STK tick callbacks");
+                            let triggered = tick_stk_callbacks
+                                .dispatch(|callback| callback(exchange, tick_data.clone()));
+                            if triggered > 0 {
+                                log::debug!(
+                                    "✅ Triggered {} STK tick callbacks for {}",
+                                    triggered,
+                                    tick_data.code
+                                );
                             }
                         } else {
                             log::warn!(
                                 "Failed to extract tick object from STK tick callback args: {:?}",
                                 args
                             );
+                            tick_stk_callbacks.record_dropped();
                         }
                     } else {
                         log::warn!("Insufficient parameters for STK tick callback: {:?}", args);
+                        tick_stk_callbacks.record_dropped();
                     }
                     Python::with_gil(|py| Ok(py.None()))
                 },
@@ -3132,29 +3864,25 @@ inject_libpath()
                             };
 
                             // 🎯 CRITICAL FIX: Call registered user callbacks directly!
-                            if let Ok(callbacks_guard) = tick_fop_callbacks.try_lock() {
-                                for callback in callbacks_guard.iter() {
-                                    // Execute each registered callback
-                                    callback(exchange, tick_data.clone());
-                                }
-                                if !callbacks_guard.is_empty() {
-                                    log::debug!(
-                                        "✅ Triggered {} FOP tick callbacks for {}",
-                                        callbacks_guard.len(),
-                                        tick_data.code
-                                    );
-                                }
-                            } else {
-                                log::warn!("Failed to acquire lock for FOP tick callbacks");
+                            let triggered = tick_fop_callbacks
+                                .dispatch(|callback| callback(exchange, tick_data.clone()));
+                            if triggered > 0 {
+                                log::debug!(
+                                    "✅ Triggered {} FOP tick callbacks for {}",
+                                    triggered,
+                                    tick_data.code
+                                );
                             }
                         } else {
                             log::warn!(
                                 "Failed to extract tick object from FOP tick callback args: {:?}",
                                 args
                             );
+                            tick_fop_callbacks.record_dropped();
                         }
                     } else {
                         log::warn!("Insufficient parameters for FOP tick callback: {:?}", args);
+                        tick_fop_callbacks.record_dropped();
                     }
                     Python::with_gil(|py| Ok(py.None()))
                 },
@@ -3164,18 +3892,119 @@ inject_libpath()
                 py,
                 None,
                 None,
-                |args, _kwargs| -> PyResult<PyObject> {
-                    log::debug!("STK bidask callback triggered with args: {:?}", args);
-                    Python::with_gil(|py| Ok(py.None()))
+                move |args, _kwargs| -> PyResult<PyObject> {
+                    // Parse the args from Python callback: (exchange_enum, bidask_object)
+                    let py = args.py();
+                    let Ok(bidask_obj) = args.get_item(1) else {
+                        log::warn!("Insufficient parameters for STK bidask callback: {:?}", args);
+                        bidask_stk_callbacks.record_dropped();
+                        return Ok(py.None());
+                    };
+                    let exchange = Self::callback_exchange(args.get_item(0), Exchange::TSE);
+                    let bool_field = |name: &str| {
+                        bidask_obj
+                            .getattr(name)
+                            .and_then(|v| v.is_true())
+                            .unwrap_or(false)
+                    };
+                    let f64_list = |name: &str| {
+                        bidask_obj
+                            .getattr(name)
+                            .and_then(|v| v.extract::<Vec<f64>>())
+                            .unwrap_or_default()
+                    };
+                    let i64_list = |name: &str| {
+                        bidask_obj
+                            .getattr(name)
+                            .and_then(|v| v.extract::<Vec<i64>>())
+                            .unwrap_or_default()
+                    };
+
+                    let bidask_data = crate::types::BidAskSTKv1 {
+                        code: bidask_obj
+                            .getattr("code")
+                            .and_then(|v| v.extract::<String>())
+                            .unwrap_or_default(),
+                        datetime: chrono::Utc::now(), // Use current time as default
+                        bid_price: f64_list("bid_price"),
+                        bid_volume: i64_list("bid_volume"),
+                        diff_bid_vol: i64_list("diff_bid_vol"),
+                        ask_price: f64_list("ask_price"),
+                        ask_volume: i64_list("ask_volume"),
+                        diff_ask_vol: i64_list("diff_ask_vol"),
+                        suspend: bool_field("suspend"),
+                        simtrade: bool_field("simtrade"),
+                        intraday_odd: bool_field("intraday_odd"),
+                    };
+
+                    bidask_stk_callbacks.dispatch(|callback| callback(exchange, bidask_data.clone()));
+                    Ok(py.None())
                 },
             )?;
 
+            let bidask_fop_callbacks = _bidask_fop_callbacks;
             let bidask_fop_callback = pyo3::types::PyCFunction::new_closure(
                 py,
                 None,
                 None,
-                |args, _kwargs| -> PyResult<PyObject> {
+                move |args, _kwargs| -> PyResult<PyObject> {
                     log::debug!("FOP bidask callback triggered with args: {:?}", args);
+                    // Parse the args from Python callback: (exchange_enum, bidask_object)
+                    if let Ok(bidask_obj) = args.get_item(1) {
+                        let f64_field = |name: &str| {
+                            bidask_obj
+                                .getattr(name)
+                                .and_then(|v| v.extract::<f64>())
+                                .unwrap_or(0.0)
+                        };
+                        let i64_field = |name: &str| {
+                            bidask_obj
+                                .getattr(name)
+                                .and_then(|v| v.extract::<i64>())
+                                .unwrap_or(0)
+                        };
+                        let f64_list = |name: &str| {
+                            bidask_obj
+                                .getattr(name)
+                                .and_then(|v| v.extract::<Vec<f64>>())
+                                .unwrap_or_default()
+                        };
+                        let i64_list = |name: &str| {
+                            bidask_obj
+                                .getattr(name)
+                                .and_then(|v| v.extract::<Vec<i64>>())
+                                .unwrap_or_default()
+                        };
+
+                        let bidask_data = crate::types::BidAskFOPv1 {
+                            code: bidask_obj
+                                .getattr("code")
+                                .and_then(|v| v.extract::<String>())
+                                .unwrap_or_default(),
+                            datetime: chrono::Utc::now(), // Use current time as default
+                            bid_total_vol: i64_field("bid_total_vol"),
+                            ask_total_vol: i64_field("ask_total_vol"),
+                            bid_price: f64_list("bid_price"),
+                            bid_volume: i64_list("bid_volume"),
+                            diff_bid_vol: i64_list("diff_bid_vol"),
+                            ask_price: f64_list("ask_price"),
+                            ask_volume: i64_list("ask_volume"),
+                            diff_ask_vol: i64_list("diff_ask_vol"),
+                            first_derived_bid_price: f64_field("first_derived_bid_price"),
+                            first_derived_ask_price: f64_field("first_derived_ask_price"),
+                            first_derived_bid_vol: i64_field("first_derived_bid_vol"),
+                            first_derived_ask_vol: i64_field("first_derived_ask_vol"),
+                            underlying_price: f64_field("underlying_price"),
+                            simtrade: i64_field("simtrade") != 0,
+                        };
+
+                        bidask_fop_callbacks.dispatch(|callback| {
+                            callback(crate::types::Exchange::TAIFEX, bidask_data.clone())
+                        });
+                    } else {
+                        log::warn!("Insufficient parameters for FOP bidask callback: {:?}", args);
+                        bidask_fop_callbacks.record_dropped();
+                    }
                     Python::with_gil(|py| Ok(py.None()))
                 },
             )?;
@@ -3184,14 +4013,126 @@ inject_libpath()
                 py,
                 None,
                 None,
-                |args, _kwargs| -> PyResult<PyObject> {
-                    log::debug!("STK quote callback triggered with args: {:?}", args);
-                    Python::with_gil(|py| Ok(py.None()))
+                move |args, _kwargs| -> PyResult<PyObject> {
+                    // Parse the args from Python callback: (exchange_enum, quote_object)
+                    let py = args.py();
+                    let Ok(quote_obj) = args.get_item(1) else {
+                        log::warn!("Insufficient parameters for STK quote callback: {:?}", args);
+                        quote_stk_callbacks.record_dropped();
+                        return Ok(py.None());
+                    };
+                    let exchange = Self::callback_exchange(args.get_item(0), Exchange::TSE);
+                    let f64_field = |name: &str| {
+                        quote_obj
+                            .getattr(name)
+                            .and_then(|v| v.extract::<f64>())
+                            .unwrap_or(0.0)
+                    };
+                    let i64_field = |name: &str| {
+                        quote_obj
+                            .getattr(name)
+                            .and_then(|v| v.extract::<i64>())
+                            .unwrap_or(0)
+                    };
+                    let bool_field = |name: &str| {
+                        quote_obj
+                            .getattr(name)
+                            .and_then(|v| v.is_true())
+                            .unwrap_or(false)
+                    };
+                    let f64_list = |name: &str| {
+                        quote_obj
+                            .getattr(name)
+                            .and_then(|v| v.extract::<Vec<f64>>())
+                            .unwrap_or_default()
+                    };
+                    let i64_list = |name: &str| {
+                        quote_obj
+                            .getattr(name)
+                            .and_then(|v| v.extract::<Vec<i64>>())
+                            .unwrap_or_default()
+                    };
+
+                    let quote_data = crate::types::QuoteSTKv1 {
+                        code: quote_obj
+                            .getattr("code")
+                            .and_then(|v| v.extract::<String>())
+                            .unwrap_or_default(),
+                        datetime: chrono::Utc::now(), // Use current time as default
+                        open: f64_field("open"),
+                        avg_price: f64_field("avg_price"),
+                        close: f64_field("close"),
+                        high: f64_field("high"),
+                        low: f64_field("low"),
+                        amount: f64_field("amount"),
+                        total_amount: f64_field("total_amount"),
+                        volume: i64_field("volume"),
+                        total_volume: i64_field("total_volume"),
+                        tick_type: (i64_field("tick_type") as i32).into(),
+                        chg_type: (i64_field("chg_type") as i32).into(),
+                        price_chg: f64_field("price_chg"),
+                        pct_chg: f64_field("pct_chg"),
+                        bid_side_total_vol: i64_field("bid_side_total_vol"),
+                        ask_side_total_vol: i64_field("ask_side_total_vol"),
+                        bid_side_total_cnt: i64_field("bid_side_total_cnt"),
+                        ask_side_total_cnt: i64_field("ask_side_total_cnt"),
+                        closing_oddlot_shares: i64_field("closing_oddlot_shares"),
+                        closing_oddlot_close: f64_field("closing_oddlot_close"),
+                        closing_oddlot_amount: f64_field("closing_oddlot_amount"),
+                        closing_oddlot_bid_price: f64_field("closing_oddlot_bid_price"),
+                        closing_oddlot_ask_price: f64_field("closing_oddlot_ask_price"),
+                        fixed_trade_vol: i64_field("fixed_trade_vol"),
+                        fixed_trade_amount: f64_field("fixed_trade_amount"),
+                        bid_price: f64_list("bid_price"),
+                        bid_volume: i64_list("bid_volume"),
+                        diff_bid_vol: i64_list("diff_bid_vol"),
+                        ask_price: f64_list("ask_price"),
+                        ask_volume: i64_list("ask_volume"),
+                        diff_ask_vol: i64_list("diff_ask_vol"),
+                        avail_borrowing: i64_field("avail_borrowing"),
+                        suspend: bool_field("suspend"),
+                        simtrade: bool_field("simtrade"),
+                    };
+
+                    quote_stk_callbacks.dispatch(|callback| callback(exchange, quote_data.clone()));
+                    Ok(py.None())
                 },
             )?;
 
-            // Get the stored Rust event handlers to forward events
-            let event_handlers = self._event_handlers.clone();
+            // v0 quotes: (topic, dict)
+            let quote_callback = pyo3::types::PyCFunction::new_closure(
+                py,
+                None,
+                None,
+                move |args, _kwargs| -> PyResult<PyObject> {
+                    let py = args.py();
+                    let topic: String = args
+                        .get_item(0)
+                        .and_then(|item| item.extract())
+                        .unwrap_or_default();
+                    let data = match args.get_item(1) {
+                        Ok(data) => Self::python_to_json(py, data),
+                        Err(_) => {
+                            log::warn!("Insufficient parameters for quote callback: {:?}", args);
+                            quote_callbacks.record_dropped();
+                            return Ok(py.None());
+                        }
+                    };
+                    quote_callbacks.dispatch(|callback| callback(topic.clone(), data.clone()));
+                    Ok(py.None())
+                },
+            )?;
+
+            let session_down_callback = pyo3::types::PyCFunction::new_closure(
+                py,
+                None,
+                None,
+                move |args, _kwargs| -> PyResult<PyObject> {
+                    log::warn!("🔌 Shioaji session down");
+                    session_down_callbacks.dispatch(|callback| callback());
+                    Ok(args.py().None())
+                },
+            )?;
 
             let event_callback = pyo3::types::PyCFunction::new_closure(
                 py,
@@ -3216,15 +4157,62 @@ inject_libpath()
                         .and_then(|item| item.extract())
                         .unwrap_or_else(|_| "".to_string());
 
-                    // Forward to all registered Rust event callbacks
-                    if let Ok(handlers) = event_handlers.try_lock() {
-                        handlers.trigger_event(resp_code, event_code, info.clone(), event.clone());
-                    }
+                    // Forward to on_event closures and SystemCallback
+                    // handlers (both live in the event registry)
+                    event_callbacks.dispatch(|callback| {
+                        callback(resp_code, event_code, info.clone(), event.clone())
+                    });
+
+                    Python::with_gil(|py| Ok(py.None()))
+                },
+            )?;
+
+            let order_callbacks = this.handlers.order.clone();
+            let order_journal = this.journal.clone();
+
+            let order_callback = pyo3::types::PyCFunction::new_closure(
+                py,
+                None,
+                None,
+                move |args, _kwargs| -> PyResult<PyObject> {
+                    let py = args.py();
+
+                    // stat 為 OrderState enum，msg 為 dict
+                    let stat = args
+                        .get_item(0)
+                        .and_then(|item| item.str())
+                        .map(|s| s.to_string())
+                        .unwrap_or_default();
+                    let Some(event_type) = OrderEventType::from_string(&stat) else {
+                        log::warn!("Unknown order callback state: {}", stat);
+                        order_callbacks.record_dropped();
+                        return Ok(py.None());
+                    };
+
+                    let msg = match args.get_item(1) {
+                        Ok(msg) => Self::python_to_json(py, msg),
+                        Err(_) => serde_json::Value::Null,
+                    };
+
+                    Self::append_journal(
+                        &order_journal,
+                        JournalRecord::OrderEvent {
+                            event_type: event_type.clone(),
+                            message: msg.clone(),
+                        },
+                    );
 
-                    Python::with_gil(|py| Ok(py.None()))
+                    order_callbacks.dispatch(|callback| callback(event_type.clone(), msg.clone()));
+
+                    Ok(py.None())
                 },
             )?;
 
+            match instance.call_method1(py, "set_order_callback", (order_callback,)) {
+                Ok(_) => log::debug!("✅ set_order_callback registered successfully"),
+                Err(e) => log::warn!("❌ Failed to register set_order_callback: {}", e),
+            }
+
             // Set callbacks using correct quote object methods with error checking
             match quote.call_method1(py, "set_on_tick_stk_v1_callback", (tick_stk_callback,)) {
                 Ok(_) => log::debug!("✅ set_on_tick_stk_v1_callback registered successfully"),
@@ -3246,6 +4234,20 @@ inject_libpath()
                 Ok(_) => log::debug!("✅ set_on_quote_stk_v1_callback registered successfully"),
                 Err(e) => log::warn!("❌ Failed to register set_on_quote_stk_v1_callback: {}", e),
             }
+            match quote.call_method1(py, "set_quote_callback", (quote_callback,)) {
+                Ok(_) => log::debug!("✅ set_quote_callback registered successfully"),
+                Err(e) => log::warn!("❌ Failed to register set_quote_callback: {}", e),
+            }
+            // api.set_session_down_callback; older SDKs only expose it on api.quote
+            let session_down_result = instance
+                .call_method1(py, "set_session_down_callback", (session_down_callback,))
+                .or_else(|_| {
+                    quote.call_method1(py, "set_session_down_callback", (session_down_callback,))
+                });
+            match session_down_result {
+                Ok(_) => log::debug!("✅ set_session_down_callback registered successfully"),
+                Err(e) => log::warn!("❌ Failed to register set_session_down_callback: {}", e),
+            }
             match quote.call_method1(py, "set_event_callback", (event_callback,)) {
                 Ok(_) => log::info!("✅ set_event_callback registered successfully - events should now forward to Rust"),
                 Err(e) => log::error!("❌ Failed to register set_event_callback: {}", e),
@@ -3255,6 +4257,7 @@ inject_libpath()
 
             Ok(())
         })
+        .await
     }
 
     /// Create stock contract (convenience method)
@@ -3340,41 +4343,53 @@ inject_libpath()
         start: &str,
         end: &str,
     ) -> Result<Vec<Kbar>> {
-        Python::with_gil(|py| -> Result<Vec<Kbar>> {
-            log::info!("📊 Calling system shioaji kbars...");
-
-            // Get contract object from system shioaji
-            let py_contract = self.get_system_contract(py, &contract)?;
-
-            // Call kbars method
-            let kwargs = pyo3::types::PyDict::new(py);
-            kwargs.set_item("start", start)?;
-            kwargs.set_item("end", end)?;
+        let instance = instance.clone();
+        let start = start.to_string();
+        let end = end.to_string();
+        self.run_python(
+            "kbars",
+            self.request_timeout(),
+            move |py| -> Result<Vec<Kbar>> {
+                log::info!("📊 Calling system shioaji kbars...");
+
+                // Get contract object from system shioaji
+                let py_contract = Self::get_system_contract(py, &instance, &contract)?;
+
+                // Call kbars method
+                let kwargs = pyo3::types::PyDict::new(py);
+                kwargs.set_item("start", start)?;
+                kwargs.set_item("end", end)?;
 
-            let kbars_result = instance
-                .call_method(py, "kbars", (py_contract,), Some(kwargs))
-                .map_err(|e| Error::DataFetch(format!("System shioaji kbars failed: {:?}", e)))?;
+                let kbars_result = instance
+                    .call_method(py, "kbars", (py_contract,), Some(kwargs))
+                    .map_err(|e| {
+                        Error::from_sdk(e, |e| {
+                            Error::DataFetch(format!("System shioaji kbars failed: {:?}", e))
+                        })
+                    })?;
 
-            log::info!("✅ System shioaji kbars successful");
+                log::info!("✅ System shioaji kbars successful");
 
-            // Convert result to Vec<Kbar>
-            let mut kbars = Vec::new();
+                // Convert result to Vec<Kbar>
+                let mut kbars = Vec::new();
 
-            // Try to extract as list/array
-            if let Ok(list) = kbars_result.downcast::<pyo3::types::PyList>(py) {
-                for item in list.iter() {
-                    if let Ok(kbar) = self.extract_single_kbar(py, &item.into()) {
-                        kbars.push(kbar);
+                // Try to extract as list/array
+                if let Ok(list) = kbars_result.downcast::<pyo3::types::PyList>(py) {
+                    for item in list.iter() {
+                        if let Ok(kbar) = Self::extract_single_kbar(py, &item.into()) {
+                            kbars.push(kbar);
+                        }
                     }
                 }
-            }
 
-            Ok(kbars)
-        })
+                Ok(kbars)
+            },
+        )
+        .await
     }
 
     /// Extract single K-bar from system shioaji result
-    fn extract_single_kbar(&self, py: Python, kbar_obj: &PyObject) -> Result<Kbar> {
+    fn extract_single_kbar(py: Python, kbar_obj: &PyObject) -> Result<Kbar> {
         let ts_str = kbar_obj
             .getattr(py, "ts")
             .and_then(|v| v.extract::<String>(py))
@@ -3429,6 +4444,16 @@ inject_libpath()
     pub async fn logout(&self) -> Result<bool> {
         log::info!("🚪 Logging out using system shioaji...");
 
+        if let Some(backend) = &self.backend {
+            if let Err(e) = backend.logout().await {
+                log::warn!(
+                    "⚠️ {} backend logout did not complete: {}",
+                    backend.name(),
+                    e
+                );
+            }
+        }
+
         // Get instance
         let instance_opt = {
             let instance_guard = self.instance.lock().await;
@@ -3436,11 +4461,16 @@ inject_libpath()
         };
 
         if let Some(instance) = instance_opt {
-            // Call system shioaji logout
-            Python::with_gil(|py| -> Result<()> {
-                let _ = instance.call_method(py, "logout", (), None);
-                Ok(())
-            })?;
+            // Call system shioaji logout; local state is cleared even if it stalls
+            let result = self
+                .run_python("logout", self.request_timeout(), move |py| -> Result<()> {
+                    let _ = instance.call_method(py, "logout", (), None);
+                    Ok(())
+                })
+                .await;
+            if let Err(e) = result {
+                log::warn!("⚠️ System shioaji logout did not complete: {}", e);
+            }
         }
 
         // Update state
@@ -3460,24 +4490,24 @@ inject_libpath()
     }
 
     // === Callback Registration Methods (原始 shioaji API 相容) ===
+    //
+    // Each returns a `CallbackHandle`; `handle.remove()` unregisters the
+    // callback, dropping the handle keeps it registered.
 
     /// Register tick callback for stocks (原始 on_tick_stk_v1)
-    pub async fn on_tick_stk_v1<F>(&self, callback: F, bind: bool) -> Result<()>
+    pub async fn on_tick_stk_v1<F>(&self, callback: F, bind: bool) -> Result<CallbackHandle>
     where
         F: Fn(Exchange, crate::types::TickSTKv1) + Send + Sync + 'static,
     {
         let callback_arc = Arc::new(callback);
 
         // Store callback in global storage
-        {
-            let mut callbacks = self.tick_stk_callbacks.lock().await;
-            callbacks.push(callback_arc.clone());
-            log::info!(
-                "📊 Registered tick STK callback #{} (bind: {})",
-                callbacks.len(),
-                bind
-            );
-        }
+        let handle = self.handlers.tick_stk.register(callback_arc);
+        log::info!(
+            "📊 Registered tick STK callback #{} (bind: {})",
+            handle.id(),
+            bind
+        );
 
         // Register to Python instance if bind is true
         if bind {
@@ -3487,21 +4517,333 @@ inject_libpath()
         }
 
         log::info!("✅ Tick STK callback registration completed");
+        Ok(handle)
+    }
+
+    /// Register tick callback for futures/options (原始 on_tick_fop_v1)
+    pub async fn on_tick_fop_v1<F>(&self, callback: F, bind: bool) -> Result<CallbackHandle>
+    where
+        F: Fn(Exchange, crate::types::TickFOPv1) + Send + Sync + 'static,
+    {
+        let callback_arc = Arc::new(callback);
+
+        // Store callback in global storage
+        let handle = self.handlers.tick_fop.register(callback_arc);
+        log::info!(
+            "📊 Registered tick FOP callback #{} (bind: {})",
+            handle.id(),
+            bind
+        );
+
+        if bind {
+            log::info!("📊 Tick FOP callback bind mode enabled");
+        }
+
+        log::info!("✅ Tick FOP callback registration completed");
+        Ok(handle)
+    }
+
+    /// Register FOP bid/ask callback (原始 set_on_bidask_fop_v1_callback)
+    pub async fn on_bidask_fop_v1<F>(&self, callback: F, bind: bool) -> Result<CallbackHandle>
+    where
+        F: Fn(Exchange, crate::types::BidAskFOPv1) + Send + Sync + 'static,
+    {
+        let handle = self.handlers.bidask_fop.register(Arc::new(callback));
+        log::info!(
+            "📊 Registered bidask FOP callback #{} (bind: {})",
+            handle.id(),
+            bind
+        );
+        Ok(handle)
+    }
+
+    /// Register stock bid/ask callback (原始 set_on_bidask_stk_v1_callback)
+    pub async fn on_bidask_stk_v1<F>(&self, callback: F, bind: bool) -> Result<CallbackHandle>
+    where
+        F: Fn(Exchange, crate::types::BidAskSTKv1) + Send + Sync + 'static,
+    {
+        let handle = self.handlers.bidask_stk.register(Arc::new(callback));
+        log::info!(
+            "📊 Registered bidask STK callback #{} (bind: {})",
+            handle.id(),
+            bind
+        );
+        Ok(handle)
+    }
+
+    /// Register stock quote callback (原始 set_on_quote_stk_v1_callback)
+    pub async fn on_quote_stk_v1<F>(&self, callback: F, bind: bool) -> Result<CallbackHandle>
+    where
+        F: Fn(Exchange, crate::types::QuoteSTKv1) + Send + Sync + 'static,
+    {
+        let handle = self.handlers.quote_stk.register(Arc::new(callback));
+        log::info!(
+            "📊 Registered quote STK callback #{} (bind: {})",
+            handle.id(),
+            bind
+        );
+        Ok(handle)
+    }
+
+    /// Register order/deal event callback (原始 set_order_callback)
+    ///
+    /// 對應原始 Python：
+    /// ```python
+    /// def order_cb(stat: OrderState, msg: dict):
+    ///     print(stat, msg)
+    /// api.set_order_callback(order_cb)
+    /// ```
+    pub async fn on_order<F>(&self, callback: F) -> Result<CallbackHandle>
+    where
+        F: Fn(OrderEventType, serde_json::Value) + Send + Sync + 'static,
+    {
+        let handle = self.handlers.order.register(Arc::new(callback));
+        log::info!("📊 Registered order callback #{}", handle.id());
+        Ok(handle)
+    }
+
+    /// Register system event callback (原始 on_event)
+    ///
+    /// 對應原始 Python：
+    /// ```python
+    /// @api.quote.on_event
+    /// def event_callback(resp_code: int, event_code: int, info: str, event: str):
+    ///     print(f'Event code: {event_code} | Event: {event}')
+    /// ```
+    pub async fn on_event<F>(&self, callback: F) -> Result<CallbackHandle>
+    where
+        F: Fn(i32, i32, String, String) + Send + Sync + 'static,
+    {
+        let handle = self.handlers.event.register(Arc::new(callback));
+        log::info!("📊 Registered event callback #{}", handle.id());
+        Ok(handle)
+    }
+
+    /// Deliver a system event to `on_event` callbacks and registered
+    /// `SystemCallback` handlers
+    pub async fn emit_event(&self, resp_code: i32, event_code: i32, info: String, event: String) {
+        self.handlers
+            .trigger_event(resp_code, event_code, info, event);
+    }
+
+    /// Register a hook for panics in user callbacks (回調錯誤處理)
+    ///
+    /// Every panic inside a registered callback is caught before it reaches
+    /// the Python bridge and delivered here as `Error::Callback`.
+    pub async fn on_callback_error<F>(&self, hook: F) -> Result<()>
+    where
+        F: Fn(Error) + Send + Sync + 'static,
+    {
+        self.handlers.policy().set_hook(Arc::new(hook));
+        log::info!("📊 Registered callback error hook");
         Ok(())
     }
 
+    /// Disable a callback after `limit` consecutive panics (`None` keeps
+    /// calling it, the default)
+    pub fn set_callback_failure_limit(&self, limit: Option<u32>) {
+        self.handlers.policy().set_failure_limit(limit);
+    }
+
+    /// Dispatched/dropped/panicked counters for every callback stream
+    pub fn callback_stats(&self) -> Vec<CallbackStats> {
+        self.handlers.stats()
+    }
+
+    /// Journal every order call and order/deal event from now on
+    pub fn set_journal(&self, journal: Arc<Journal>) {
+        if let Ok(mut guard) = self.journal.write() {
+            log::info!("📒 Order journal enabled at {}", journal.dir().display());
+            *guard = Some(journal);
+        }
+    }
+
+    /// Currently attached journal, if any
+    pub fn journal(&self) -> Option<Arc<Journal>> {
+        self.journal.read().ok().and_then(|guard| guard.clone())
+    }
+
+    /// Reject orders outside trading sessions with `Error::MarketClosed`
+    pub fn set_calendar(&self, calendar: Arc<TradingCalendar>) {
+        if let Ok(mut guard) = self.calendar.write() {
+            log::info!("📅 Trading calendar enabled for order checks");
+            *guard = Some(calendar);
+        }
+    }
+
+    /// Currently attached trading calendar, if any
+    pub fn calendar(&self) -> Option<Arc<TradingCalendar>> {
+        self.calendar.read().ok().and_then(|guard| guard.clone())
+    }
+
+    /// Deadline for SDK calls that do not pass their own timeout
+    /// (default 30s); exceeding it returns `Error::Timeout`
+    pub fn set_request_timeout(&self, timeout: std::time::Duration) {
+        if let Ok(mut guard) = self.request_timeout.write() {
+            *guard = timeout;
+        }
+    }
+
+    /// Current SDK call deadline
+    pub fn request_timeout(&self) -> std::time::Duration {
+        self.request_timeout
+            .read()
+            .map(|guard| *guard)
+            .unwrap_or(DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// Deadline for a call that forwards `timeout_ms` to the SDK: never
+    /// shorter than the SDK's own timeout
+    fn deadline_for(&self, timeout_ms: Option<i32>) -> std::time::Duration {
+        let sdk = timeout_ms
+            .map(|ms| std::time::Duration::from_millis(ms.max(0) as u64))
+            .unwrap_or_default();
+        self.request_timeout().max(sdk)
+    }
+
+    /// Run a Python call on the worker thread instead of the tokio worker
+    async fn run_python<T, F>(&self, name: &str, deadline: std::time::Duration, job: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(Python<'_>) -> Result<T> + Send + 'static,
+    {
+        self.python.run(name, deadline, job).await
+    }
+
+    /// `Err(MarketClosed)` when a calendar is attached and none of `kinds`
+    /// accepts orders on the contract's exchange right now
+    fn check_session(&self, contract: &Contract, kinds: &[SessionKind]) -> Result<()> {
+        let Some(calendar) = self.calendar() else {
+            return Ok(());
+        };
+        let now = chrono::Utc::now();
+        let exchange = &contract.base.exchange;
+        if kinds
+            .iter()
+            .any(|kind| calendar.accepts_orders(exchange, *kind, now))
+        {
+            return Ok(());
+        }
+        log::warn!("⚠️ {} is closed for {:?} orders", exchange, kinds);
+        Err(Error::MarketClosed)
+    }
+
+    /// The SDK instance created by `init`/`login`
+    async fn system_instance(&self) -> Result<PyObject> {
+        self.instance
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| Error::NotInitialized("Client not initialized".to_string()))
+    }
+
+    /// Append to the journal if one is attached; journal failures are logged
+    /// and never fail the order call itself. Returns the entry's sequence number
+    fn append_journal(
+        journal: &std::sync::RwLock<Option<Arc<Journal>>>,
+        record: JournalRecord,
+    ) -> Option<u64> {
+        let journal = journal.read().ok().and_then(|guard| guard.clone())?;
+        match journal.append(record) {
+            Ok(seq) => Some(seq),
+            Err(e) => {
+                log::error!("❌ Failed to write order journal: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Journal a new order before it is sent; returns the intent's sequence
+    /// number for the result record to reference
+    fn journal_intent<O: serde::Serialize>(
+        &self,
+        operation: &str,
+        contract: &Contract,
+        order: &O,
+    ) -> Option<u64> {
+        let order = match serde_json::to_value(order) {
+            Ok(order) => order,
+            Err(e) => {
+                log::error!("❌ Failed to serialize order for journal: {}", e);
+                return None;
+            }
+        };
+        Self::append_journal(
+            &self.journal,
+            JournalRecord::OrderIntent {
+                operation: operation.to_string(),
+                contract: contract.clone(),
+                order,
+            },
+        )
+    }
+
+    /// Journal the outcome of an order call
+    fn journal_result<T>(
+        &self,
+        operation: &str,
+        order_id: Option<&str>,
+        intent: Option<u64>,
+        result: &Result<T>,
+        record: impl FnOnce(&T) -> JournalRecord,
+    ) {
+        let record = match result {
+            Ok(value) => record(value),
+            Err(e) => JournalRecord::CallFailed {
+                operation: operation.to_string(),
+                order_id: order_id.map(str::to_string),
+                error: e.to_string(),
+                intent,
+            },
+        };
+        Self::append_journal(&self.journal, record);
+    }
+
+    /// Exchange passed to a quote callback as an `Exchange` enum or a string
+    fn callback_exchange(arg: PyResult<&PyAny>, default: Exchange) -> Exchange {
+        let Ok(arg) = arg else {
+            return default;
+        };
+        let value = arg.getattr("value").unwrap_or(arg);
+        match value.extract::<String>().as_deref() {
+            Ok("TSE") => Exchange::TSE,
+            Ok("OTC") => Exchange::OTC,
+            Ok("OES") => Exchange::OES,
+            Ok("TXE") | Ok("TAIFEX") => Exchange::TAIFEX,
+            _ => default,
+        }
+    }
+
+    /// Convert a Python object (dict of callback data) to JSON via `json.dumps`
+    fn python_to_json(py: Python, obj: &PyAny) -> serde_json::Value {
+        let dumped = py.import("json").and_then(|json| {
+            let kwargs = pyo3::types::PyDict::new(py);
+            kwargs.set_item("default", py.import("builtins")?.getattr("str")?)?;
+            json.call_method("dumps", (obj,), Some(kwargs))?
+                .extract::<String>()
+        });
+
+        match dumped {
+            Ok(text) => serde_json::from_str(&text).unwrap_or(serde_json::Value::Null),
+            Err(e) => {
+                log::warn!("Failed to convert Python object to JSON: {}", e);
+                serde_json::Value::Null
+            }
+        }
+    }
+
     /// Convert Trade to Python object
-    fn convert_trade_to_python(&self, py: Python, trade: &Trade) -> Result<PyObject> {
+    fn convert_trade_to_python(py: Python, trade: &Trade) -> Result<PyObject> {
         // Create a Python Trade object equivalent
         // For now, create a dictionary with essential fields
         let trade_dict = pyo3::types::PyDict::new(py);
-        
+
         // Set essential trade fields
         trade_dict.set_item("order_id", &trade.order_id)?;
         trade_dict.set_item("seqno", &trade.seqno)?;
         trade_dict.set_item("ordno", &trade.ordno)?;
         trade_dict.set_item("status", trade.status.to_string())?;
-        
+
         // Convert order to Python equivalent
         let order_dict = pyo3::types::PyDict::new(py);
         order_dict.set_item("action", trade.order.action.to_string())?;
@@ -3509,48 +4851,34 @@ inject_libpath()
         order_dict.set_item("quantity", trade.order.quantity)?;
         order_dict.set_item("order_type", trade.order.order_type.to_string())?;
         order_dict.set_item("price_type", trade.order.price_type.to_string())?;
-        
+
         trade_dict.set_item("order", order_dict)?;
-        
+
         Ok(trade_dict.to_object(py))
     }
 
     /// Convert Python Trade result to Rust Trade
-    fn convert_python_trade_result(&self, py: Python, trade_result: &PyObject) -> Result<Trade> {
-        // Extract fields from Python trade object
-        let order_id = trade_result.getattr(py, "order_id")?
-            .extract::<String>(py)
-            .unwrap_or_default();
-        
-        let seqno = trade_result.getattr(py, "seqno")?
-            .extract::<String>(py)
-            .unwrap_or_default();
-        
-        let ordno = trade_result.getattr(py, "ordno")?
-            .extract::<String>(py)
-            .unwrap_or_default();
-        
-        let status_str = trade_result.getattr(py, "status")?
-            .extract::<String>(py)
-            .unwrap_or_default();
-        
-        let status = crate::types::Status::from_string(&status_str);
-        
+    fn convert_python_trade_result(py: Python, trade_result: &PyObject) -> Result<Trade> {
+        let (order_id, seqno, ordno, status) = Self::extract_system_trade_fields(py, trade_result);
+
         // Extract order information
         let py_order = trade_result.getattr(py, "order")?;
-        let action_str = py_order.getattr(py, "action")?
+        let action_str = py_order
+            .getattr(py, "action")?
             .extract::<String>(py)
             .unwrap_or_default();
         let action = crate::types::Action::from_string(&action_str);
-        
-        let price = py_order.getattr(py, "price")?
+
+        let price = py_order
+            .getattr(py, "price")?
             .extract::<f64>(py)
             .unwrap_or(0.0);
-        
-        let quantity = py_order.getattr(py, "quantity")?
+
+        let quantity = py_order
+            .getattr(py, "quantity")?
             .extract::<i32>(py)
             .unwrap_or(0);
-        
+
         let order = crate::types::Order {
             action,
             price,
@@ -3564,17 +4892,30 @@ inject_libpath()
             ca: Some(String::new()),
             seqno: Some(seqno.clone()),
         };
-        
-        // Create default account (will be populated if available)
-        let account = crate::types::Account {
-            account_type: crate::types::AccountType::Stock,
-            person_id: Some(String::new()),
-            broker_id: String::new(),
-            account_id: String::new(),
-            signed: false,
-            username: String::new(),
-        };
-        
+
+        // 原始 Python 的委託帳戶位於 trade.order.account
+        let account = py_order
+            .getattr(py, "account")
+            .ok()
+            .filter(|acc| !acc.is_none(py))
+            .and_then(|acc| Self::convert_python_account(py, &acc).ok())
+            .unwrap_or_else(|| crate::types::Account {
+                account_type: crate::types::AccountType::Stock,
+                person_id: Some(String::new()),
+                broker_id: String::new(),
+                account_id: String::new(),
+                signed: false,
+                username: String::new(),
+            });
+
+        // 原始 Python 的合約位於 trade.contract
+        let contracts = trade_result
+            .getattr(py, "contract")
+            .ok()
+            .and_then(|contract| Self::convert_python_trade_contract(py, &contract))
+            .into_iter()
+            .collect();
+
         Ok(Trade {
             order,
             status,
@@ -3582,70 +4923,106 @@ inject_libpath()
             seqno,
             ordno,
             account,
-            contracts: vec![], // Will be populated if available
+            contracts,
         })
     }
 
+    /// Convert the contract attached to a Python trade (code, security type, exchange)
+    fn convert_python_trade_contract(py: Python, py_contract: &PyObject) -> Option<Contract> {
+        let text = |name: &str| -> String {
+            py_contract
+                .getattr(py, name)
+                .and_then(|v| v.extract::<String>(py))
+                .unwrap_or_default()
+        };
+
+        let code = text("code");
+        if code.is_empty() {
+            return None;
+        }
+        let exchange = text("exchange")
+            .parse::<Exchange>()
+            .unwrap_or(Exchange::TSE);
+
+        let mut contract = match SecurityType::from_string(&text("security_type")) {
+            SecurityType::Stock => Stock::new(&code, exchange).contract,
+            SecurityType::Future => Future::new(&code).contract,
+            SecurityType::Option => OptionContract::new(&code, OptionRight::No, 0.0).contract,
+            SecurityType::Index => Index::new(&code, exchange).contract,
+        };
+        contract.base.exchange = exchange;
+        Some(contract)
+    }
+
     /// Convert Python trades list to Rust Vec<Trade>
-    fn convert_python_trades_list(&self, py: Python, trades_result: &PyObject) -> Result<Vec<Trade>> {
+    fn convert_python_trades_list(py: Python, trades_result: &PyObject) -> Result<Vec<Trade>> {
         let mut trades = Vec::new();
-        
+
         // Check if it's a list/sequence
         if let Ok(py_list) = trades_result.extract::<Vec<PyObject>>(py) {
             for py_trade in py_list {
-                if let Ok(trade) = self.convert_python_trade_result(py, &py_trade) {
+                if let Ok(trade) = Self::convert_python_trade_result(py, &py_trade) {
                     trades.push(trade);
                 }
             }
         }
-        
+
         Ok(trades)
     }
 
     /// Convert Python accounts list to Rust Vec<Account>
-    fn convert_python_accounts_list(&self, py: Python, accounts_result: &PyObject) -> Result<Vec<Account>> {
+    fn convert_python_accounts_list(
+        py: Python,
+        accounts_result: &PyObject,
+    ) -> Result<Vec<Account>> {
         let mut accounts = Vec::new();
-        
+
         // Check if it's a list/sequence
         if let Ok(py_list) = accounts_result.extract::<Vec<PyObject>>(py) {
             for py_account in py_list {
-                if let Ok(account) = self.convert_python_account(py, &py_account) {
+                if let Ok(account) = Self::convert_python_account(py, &py_account) {
                     accounts.push(account);
                 }
             }
         }
-        
+
         Ok(accounts)
     }
 
     /// Convert Python account to Rust Account
-    fn convert_python_account(&self, py: Python, py_account: &PyObject) -> Result<Account> {
-        let account_type_str = py_account.getattr(py, "account_type")?
+    fn convert_python_account(py: Python, py_account: &PyObject) -> Result<Account> {
+        let account_type_str = py_account
+            .getattr(py, "account_type")?
             .extract::<String>(py)
             .unwrap_or_default();
-        
+
         let account_type = crate::types::AccountType::from_string(&account_type_str);
-        
-        let person_id = py_account.getattr(py, "person_id")?
+
+        let person_id = py_account
+            .getattr(py, "person_id")?
             .extract::<String>(py)
             .unwrap_or_default();
-        
-        let broker_id = py_account.getattr(py, "broker_id")?
+
+        let broker_id = py_account
+            .getattr(py, "broker_id")?
             .extract::<String>(py)
             .unwrap_or_default();
-        
-        let account_id = py_account.getattr(py, "account_id")?
+
+        let account_id = py_account
+            .getattr(py, "account_id")?
             .extract::<String>(py)
             .unwrap_or_default();
-        
-        let signed = py_account.getattr(py, "signed")?
+
+        let signed = py_account
+            .getattr(py, "signed")?
             .extract::<bool>(py)
             .unwrap_or(false);
-        
-        let username = py_account.getattr(py, "username")?
+
+        let username = py_account
+            .getattr(py, "username")?
             .extract::<String>(py)
             .unwrap_or_default();
-        
+
         Ok(Account {
             account_type,
             person_id: Some(person_id),
@@ -3657,67 +5034,92 @@ inject_libpath()
     }
 
     /// Convert Account to Python object
-    fn convert_account_to_python(&self, py: Python, account: &Account) -> Result<PyObject> {
+    fn convert_account_to_python(py: Python, account: &Account) -> Result<PyObject> {
         let account_dict = pyo3::types::PyDict::new(py);
-        
+
         account_dict.set_item("account_type", account.account_type.to_string())?;
-        account_dict.set_item("person_id", account.person_id.as_ref().unwrap_or(&String::new()))?;
+        account_dict.set_item(
+            "person_id",
+            account.person_id.as_ref().unwrap_or(&String::new()),
+        )?;
         account_dict.set_item("broker_id", &account.broker_id)?;
         account_dict.set_item("account_id", &account.account_id)?;
         account_dict.set_item("signed", account.signed)?;
         account_dict.set_item("username", &account.username)?;
-        
+
         Ok(account_dict.to_object(py))
     }
 
     /// Convert Unit to Python object
-    fn convert_unit_to_python(&self, py: Python, unit: &Unit) -> Result<PyObject> {
+    fn convert_unit_to_python(py: Python, unit: &Unit) -> Result<PyObject> {
         Ok(unit.to_string().to_object(py))
     }
 
     /// Convert Python positions list to Rust Vec<Position>
-    fn convert_python_positions_list(&self, py: Python, positions_result: &PyObject) -> Result<Vec<Position>> {
+    fn convert_python_positions_list(
+        py: Python,
+        positions_result: &PyObject,
+    ) -> Result<Vec<Position>> {
         let mut positions = Vec::new();
-        
+
         // Check if it's a list/sequence
         if let Ok(py_list) = positions_result.extract::<Vec<PyObject>>(py) {
             for py_position in py_list {
-                if let Ok(position) = self.convert_python_position(py, &py_position) {
+                if let Ok(position) = Self::convert_python_position(py, &py_position) {
                     positions.push(position);
                 }
             }
         }
-        
+
         Ok(positions)
     }
 
     /// Convert Python position to Rust Position
-    fn convert_python_position(&self, py: Python, py_position: &PyObject) -> Result<Position> {
+    fn convert_python_position(py: Python, py_position: &PyObject) -> Result<Position> {
         // Extract basic position fields
-        let code = py_position.getattr(py, "code")?
+        let code = py_position
+            .getattr(py, "code")?
             .extract::<String>(py)
             .unwrap_or_default();
-        
-        let quantity = py_position.getattr(py, "quantity")?
+
+        let quantity = py_position
+            .getattr(py, "quantity")?
             .extract::<i64>(py)
             .unwrap_or(0);
-        
-        let price = py_position.getattr(py, "price")?
+
+        let price = py_position
+            .getattr(py, "price")?
             .extract::<f64>(py)
             .unwrap_or(0.0);
-        
-        let last_price = py_position.getattr(py, "last_price")?
+
+        let last_price = py_position
+            .getattr(py, "last_price")?
             .extract::<f64>(py)
             .unwrap_or(0.0);
-        
-        let pnl = py_position.getattr(py, "pnl")?
+
+        let pnl = py_position
+            .getattr(py, "pnl")?
             .extract::<f64>(py)
             .unwrap_or(0.0);
-        
-        let yd_quantity = py_position.getattr(py, "yd_quantity")?
+
+        let yd_quantity = py_position
+            .getattr(py, "yd_quantity")?
             .extract::<i64>(py)
             .unwrap_or(0);
-        
+
+        let direction = py_position
+            .getattr(py, "direction")
+            .and_then(|d| d.extract::<String>(py))
+            .map(|d| crate::types::Action::from_string(&d))
+            .unwrap_or(crate::types::Action::Buy);
+
+        // 股票部位才有 cond (Cash / MarginTrading / ShortSelling)
+        let cond = py_position
+            .getattr(py, "cond")
+            .and_then(|c| c.extract::<String>(py))
+            .map(|c| StockOrderCond::from_string(&c))
+            .unwrap_or_default();
+
         // Create default account
         let account = crate::types::Account {
             account_type: crate::types::AccountType::Stock,
@@ -3727,7 +5129,7 @@ inject_libpath()
             signed: false,
             username: String::new(),
         };
-        
+
         Ok(Position {
             account,
             code,
@@ -3736,6 +5138,8 @@ inject_libpath()
             last_price,
             pnl,
             yd_quantity,
+            direction,
+            cond,
         })
     }
 }
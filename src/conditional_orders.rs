@@ -0,0 +1,1159 @@
+//! Client-side conditional orders (停損 / 停損限價 / 移動停損 / 停利)
+//!
+//! TWSE and TAIFEX have no native stop orders, so triggers are held locally and
+//! evaluated against live `TickSTKv1` / `TickFOPv1` prices. When a trigger fires
+//! the order is submitted through `Shioaji::place_order` or
+//! `Shioaji::place_futures_order`.
+//!
+//! With [`ConditionalOrderBook::with_persistence`] the book is saved as JSON.
+//! Changes made from the tick path (trailing extremes, fired triggers) are
+//! written by a background thread at most every [`PERSIST_DEBOUNCE`], so price
+//! callbacks never wait on the disk; `add` / `cancel` save synchronously.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc as std_mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::client::Shioaji;
+use crate::error::{Error, Result};
+pub use crate::types::OrderTemplate;
+use crate::types::{
+    Action, Contract, FuturesOrder, FuturesPriceType, Order, OrderType, StockPriceType,
+};
+use crate::utils::new_id;
+
+/// Longest delay between a tick-path change and its write to disk
+pub const PERSIST_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Trailing distance for a trailing stop
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TrailAmount {
+    /// Fixed number of price points (ticks are not rounded)
+    Points(f64),
+    /// Percentage of the best price seen, e.g. `1.5` means 1.5%
+    Percent(f64),
+}
+
+impl TrailAmount {
+    fn distance(&self, reference: f64) -> f64 {
+        match self {
+            TrailAmount::Points(points) => *points,
+            TrailAmount::Percent(pct) => reference * pct / 100.0,
+        }
+    }
+}
+
+/// Trigger condition of a conditional order
+///
+/// Direction follows the order action: a `Sell` stop fires when the price falls
+/// to the stop price, a `Buy` stop fires when the price rises to it. Take-profit
+/// works the other way round.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Trigger {
+    /// Send a market order once the stop price is touched
+    Stop { stop_price: f64 },
+    /// Send a limit order at `limit_price` once the stop price is touched
+    StopLimit { stop_price: f64, limit_price: f64 },
+    /// Stop that follows the best price seen since activation
+    TrailingStop {
+        trail: TrailAmount,
+        /// Highest (for `Sell`) or lowest (for `Buy`) price seen so far
+        extreme_price: Option<f64>,
+    },
+    /// Send a limit order (or market order when `limit_price` is `None`)
+    /// once the target price is reached
+    TakeProfit {
+        target_price: f64,
+        limit_price: Option<f64>,
+    },
+}
+
+impl Trigger {
+    /// Create a trailing stop trigger
+    pub fn trailing(trail: TrailAmount) -> Self {
+        Trigger::TrailingStop {
+            trail,
+            extreme_price: None,
+        }
+    }
+
+    /// Current stop level of a trailing stop, if one has been established
+    pub fn trailing_stop_price(&self, action: &Action) -> Option<f64> {
+        match self {
+            Trigger::TrailingStop {
+                trail,
+                extreme_price: Some(extreme),
+            } => {
+                let distance = trail.distance(*extreme);
+                Some(match action {
+                    Action::Sell => extreme - distance,
+                    Action::Buy => extreme + distance,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Update trigger state with a new price and report whether it fires
+    fn update(&mut self, action: &Action, price: f64) -> bool {
+        match self {
+            Trigger::Stop { stop_price } | Trigger::StopLimit { stop_price, .. } => match action {
+                Action::Sell => price <= *stop_price,
+                Action::Buy => price >= *stop_price,
+            },
+            Trigger::TakeProfit { target_price, .. } => match action {
+                Action::Sell => price >= *target_price,
+                Action::Buy => price <= *target_price,
+            },
+            Trigger::TrailingStop {
+                trail,
+                extreme_price,
+            } => {
+                let extreme = match (action, *extreme_price) {
+                    (_, None) => price,
+                    (Action::Sell, Some(prev)) => prev.max(price),
+                    (Action::Buy, Some(prev)) => prev.min(price),
+                };
+                *extreme_price = Some(extreme);
+                let distance = trail.distance(extreme);
+                match action {
+                    Action::Sell => price <= extreme - distance,
+                    Action::Buy => price >= extreme + distance,
+                }
+            }
+        }
+    }
+}
+
+impl OrderTemplate {
+    /// Build the concrete order to send when `trigger` fires
+    fn materialize(&self, trigger: &Trigger) -> OrderTemplate {
+        let limit_price = match trigger {
+            Trigger::StopLimit { limit_price, .. } => Some(*limit_price),
+            Trigger::TakeProfit { limit_price, .. } => *limit_price,
+            Trigger::Stop { .. } | Trigger::TrailingStop { .. } => None,
+        };
+
+        match self {
+            OrderTemplate::Stock(order) => {
+                let mut order = order.clone();
+                match limit_price {
+                    Some(price) => {
+                        order.price = price;
+                        order.price_type = StockPriceType::LMT;
+                    }
+                    None => {
+                        // 證券市價單僅接受 IOC / FOK
+                        order.price = 0.0;
+                        order.price_type = StockPriceType::MKT;
+                        if order.order_type == OrderType::ROD {
+                            order.order_type = OrderType::IOC;
+                        }
+                    }
+                }
+                OrderTemplate::Stock(order)
+            }
+            OrderTemplate::Futures(order) => {
+                let mut order = order.clone();
+                match limit_price {
+                    Some(price) => {
+                        order.price = price;
+                        order.price_type = FuturesPriceType::LMT;
+                    }
+                    None => {
+                        order.price = 0.0;
+                        order.price_type = FuturesPriceType::MKT;
+                        if order.order_type == OrderType::ROD {
+                            order.order_type = OrderType::IOC;
+                        }
+                    }
+                }
+                OrderTemplate::Futures(order)
+            }
+        }
+    }
+}
+
+/// Lifecycle of a conditional order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConditionalStatus {
+    /// Watching prices
+    Pending,
+    /// Bracket child waiting for its parent's order to be submitted
+    Inactive,
+    /// Trigger fired, order is being submitted
+    Triggered,
+    /// Order accepted by the broker
+    Submitted { order_id: String },
+    /// Cancelled by the user or by an OCO sibling
+    Cancelled,
+    /// Order submission failed
+    Failed(String),
+}
+
+impl ConditionalStatus {
+    /// Whether the order is still waiting to fire
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            ConditionalStatus::Pending | ConditionalStatus::Inactive
+        )
+    }
+
+    /// Whether the order is saved by a persistent book: waiting to fire, or
+    /// fired without a known outcome (the order may have reached the broker)
+    fn is_persisted(&self) -> bool {
+        self.is_active() || *self == ConditionalStatus::Triggered
+    }
+}
+
+/// A stop / stop-limit / trailing-stop / take-profit order held client-side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionalOrder {
+    pub id: String,
+    pub contract: Contract,
+    pub order: OrderTemplate,
+    pub trigger: Trigger,
+    pub status: ConditionalStatus,
+    /// Orders sharing an OCO group cancel each other when one fires
+    pub oco_group: Option<String>,
+    /// Bracket parent; this order stays `Inactive` until the parent's order
+    /// is submitted, and is cancelled if that submission fails
+    pub parent_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub triggered_at: Option<DateTime<Utc>>,
+    /// Price that fired the trigger
+    pub trigger_price: Option<f64>,
+}
+
+impl ConditionalOrder {
+    pub fn new(contract: Contract, order: OrderTemplate, trigger: Trigger) -> Self {
+        Self {
+            id: new_id("cond"),
+            contract,
+            order,
+            trigger,
+            status: ConditionalStatus::Pending,
+            oco_group: None,
+            parent_id: None,
+            created_at: Utc::now(),
+            triggered_at: None,
+            trigger_price: None,
+        }
+    }
+
+    /// Stop order for stocks
+    pub fn stock_stop(contract: Contract, order: Order, stop_price: f64) -> Self {
+        Self::new(
+            contract,
+            OrderTemplate::Stock(order),
+            Trigger::Stop { stop_price },
+        )
+    }
+
+    /// Stop order for futures/options
+    pub fn futures_stop(contract: Contract, order: FuturesOrder, stop_price: f64) -> Self {
+        Self::new(
+            contract,
+            OrderTemplate::Futures(order),
+            Trigger::Stop { stop_price },
+        )
+    }
+
+    pub fn code(&self) -> &str {
+        &self.contract.base.code
+    }
+}
+
+/// Ids returned by [`ConditionalOrderBook::add_bracket`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BracketIds {
+    pub entry: String,
+    pub stop_loss: String,
+    pub take_profit: String,
+}
+
+/// A conditional order whose trigger fired and which must now be submitted
+#[derive(Debug, Clone)]
+pub struct FiredOrder {
+    pub id: String,
+    pub contract: Contract,
+    pub order: OrderTemplate,
+    pub trigger_price: f64,
+}
+
+type SharedOrders = Arc<Mutex<HashMap<String, ConditionalOrder>>>;
+
+/// Thread-safe store of conditional orders with optional on-disk persistence
+///
+/// Prices are fed from the tick callbacks (which run on the Python callback
+/// thread), so state is kept behind a `std::sync::Mutex` rather than a tokio one.
+pub struct ConditionalOrderBook {
+    orders: SharedOrders,
+    store: Option<Arc<OrderStore>>,
+    writer: Option<PersistWriter>,
+}
+
+/// JSON file holding the persisted orders
+struct OrderStore {
+    path: PathBuf,
+    /// Serializes writers so an older snapshot never replaces a newer one
+    write_lock: Mutex<()>,
+}
+
+impl OrderStore {
+    /// Snapshot persisted orders and write them to disk (temp file, fsync,
+    /// rename, so a crash never leaves a half-written file behind). The book
+    /// lock is only held while cloning.
+    fn save(&self, orders: &Mutex<HashMap<String, ConditionalOrder>>) -> Result<()> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let snapshot: Vec<ConditionalOrder> = orders
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|o| o.status.is_persisted())
+            .cloned()
+            .collect();
+        let content = serde_json::to_string_pretty(&snapshot)?;
+
+        let parent = self.path.parent().filter(|p| !p.as_os_str().is_empty());
+        if let Some(parent) = parent {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&tmp_path, &self.path)?;
+        #[cfg(unix)]
+        if let Ok(dir) = std::fs::File::open(parent.unwrap_or(Path::new("."))) {
+            let _ = dir.sync_all();
+        }
+        Ok(())
+    }
+}
+
+enum PersistSignal {
+    Dirty,
+    Shutdown,
+}
+
+/// Background thread that saves the book after tick-path changes
+struct PersistWriter {
+    signals: std_mpsc::Sender<PersistSignal>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PersistWriter {
+    fn spawn(store: Arc<OrderStore>, orders: SharedOrders) -> Result<Self> {
+        let (signals, rx) = std_mpsc::channel();
+        let handle = std::thread::Builder::new()
+            .name("conditional-orders-writer".to_string())
+            .spawn(move || {
+                let save = || {
+                    if let Err(e) = store.save(&orders) {
+                        log::warn!("⚠️ Failed to persist conditional orders: {}", e);
+                    }
+                };
+                let mut shutdown = false;
+                while !shutdown {
+                    match rx.recv() {
+                        Ok(PersistSignal::Dirty) => {}
+                        Ok(PersistSignal::Shutdown) | Err(_) => break,
+                    }
+                    // Coalesce the changes arriving within the debounce window
+                    let deadline = Instant::now() + PERSIST_DEBOUNCE;
+                    loop {
+                        let wait = deadline.saturating_duration_since(Instant::now());
+                        match rx.recv_timeout(wait) {
+                            Ok(PersistSignal::Dirty) => continue,
+                            Ok(PersistSignal::Shutdown)
+                            | Err(std_mpsc::RecvTimeoutError::Disconnected) => {
+                                shutdown = true;
+                                break;
+                            }
+                            Err(std_mpsc::RecvTimeoutError::Timeout) => break,
+                        }
+                    }
+                    save();
+                }
+            })?;
+        Ok(Self {
+            signals,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for ConditionalOrderBook {
+    /// Stop the writer after it has saved the latest state
+    fn drop(&mut self) {
+        if let Some(mut writer) = self.writer.take() {
+            let _ = writer.signals.send(PersistSignal::Dirty);
+            let _ = writer.signals.send(PersistSignal::Shutdown);
+            if let Some(handle) = writer.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+impl Default for ConditionalOrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConditionalOrderBook {
+    /// In-memory book (pending triggers are lost on restart)
+    pub fn new() -> Self {
+        Self {
+            orders: Arc::new(Mutex::new(HashMap::new())),
+            store: None,
+            writer: None,
+        }
+    }
+
+    /// Book persisted to `path`; active triggers saved there are reloaded
+    ///
+    /// Orders that had fired but whose submission outcome was not recorded
+    /// come back as `Triggered`: they never fire again, and should be checked
+    /// against the broker's trades.
+    pub fn with_persistence<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut orders = HashMap::new();
+
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            if !content.trim().is_empty() {
+                let saved: Vec<ConditionalOrder> = serde_json::from_str(&content)?;
+                for order in saved.into_iter().filter(|o| o.status.is_persisted()) {
+                    if order.status == ConditionalStatus::Triggered {
+                        log::warn!(
+                            "⚠️ Conditional order {} fired before restart without a recorded outcome; check broker trades",
+                            order.id
+                        );
+                    }
+                    orders.insert(order.id.clone(), order);
+                }
+            }
+            log::info!(
+                "📂 Restored {} pending conditional orders from {}",
+                orders.len(),
+                path.display()
+            );
+        }
+
+        let orders = Arc::new(Mutex::new(orders));
+        let store = Arc::new(OrderStore {
+            path,
+            write_lock: Mutex::new(()),
+        });
+        let writer = PersistWriter::spawn(store.clone(), orders.clone())?;
+        Ok(Self {
+            orders,
+            store: Some(store),
+            writer: Some(writer),
+        })
+    }
+
+    /// Add a single conditional order and return its id
+    pub fn add(&self, order: ConditionalOrder) -> Result<String> {
+        self.validate(&order)?;
+        let id = order.id.clone();
+        self.lock().insert(id.clone(), order);
+        self.flush()?;
+        log::info!("🎯 Conditional order {} added", id);
+        Ok(id)
+    }
+
+    /// Add two orders that cancel each other when either fires
+    pub fn add_oco(
+        &self,
+        mut first: ConditionalOrder,
+        mut second: ConditionalOrder,
+    ) -> Result<(String, String)> {
+        self.validate(&first)?;
+        self.validate(&second)?;
+
+        let group = new_id("oco");
+        first.oco_group = Some(group.clone());
+        second.oco_group = Some(group.clone());
+        let ids = (first.id.clone(), second.id.clone());
+
+        {
+            let mut orders = self.lock();
+            orders.insert(first.id.clone(), first);
+            orders.insert(second.id.clone(), second);
+        }
+        self.flush()?;
+        log::info!("🎯 OCO group {} added ({}, {})", group, ids.0, ids.1);
+        Ok(ids)
+    }
+
+    /// Add an entry trigger with a stop-loss / take-profit OCO pair that is
+    /// activated once the entry order is submitted
+    pub fn add_bracket(
+        &self,
+        entry: ConditionalOrder,
+        mut stop_loss: ConditionalOrder,
+        mut take_profit: ConditionalOrder,
+    ) -> Result<BracketIds> {
+        self.validate(&entry)?;
+        self.validate(&stop_loss)?;
+        self.validate(&take_profit)?;
+
+        let group = new_id("oco");
+        for child in [&mut stop_loss, &mut take_profit] {
+            child.parent_id = Some(entry.id.clone());
+            child.oco_group = Some(group.clone());
+            child.status = ConditionalStatus::Inactive;
+        }
+
+        let ids = BracketIds {
+            entry: entry.id.clone(),
+            stop_loss: stop_loss.id.clone(),
+            take_profit: take_profit.id.clone(),
+        };
+
+        {
+            let mut orders = self.lock();
+            orders.insert(entry.id.clone(), entry);
+            orders.insert(stop_loss.id.clone(), stop_loss);
+            orders.insert(take_profit.id.clone(), take_profit);
+        }
+        self.flush()?;
+        log::info!("🎯 Bracket {} added", ids.entry);
+        Ok(ids)
+    }
+
+    /// Cancel an active conditional order (and any bracket children)
+    pub fn cancel(&self, id: &str) -> Result<()> {
+        self.cancel_in_memory(id)?;
+        self.flush()?;
+        log::info!("🚫 Conditional order {} cancelled", id);
+        Ok(())
+    }
+
+    fn cancel_in_memory(&self, id: &str) -> Result<()> {
+        let mut orders = self.lock();
+        match orders.get_mut(id) {
+            Some(order) if order.status.is_active() => order.status = ConditionalStatus::Cancelled,
+            Some(_) => {
+                return Err(Error::InvalidOrder(format!(
+                    "Conditional order {} is no longer active",
+                    id
+                )))
+            }
+            None => {
+                return Err(Error::InvalidOrder(format!(
+                    "Conditional order {} not found",
+                    id
+                )))
+            }
+        }
+        for order in orders.values_mut() {
+            if order.parent_id.as_deref() == Some(id) && order.status.is_active() {
+                order.status = ConditionalStatus::Cancelled;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the book to disk now (no-op for an in-memory book)
+    pub fn flush(&self) -> Result<()> {
+        match &self.store {
+            Some(store) => store.save(&self.orders),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<ConditionalOrder> {
+        self.lock().get(id).cloned()
+    }
+
+    /// All orders still waiting to fire
+    pub fn active(&self) -> Vec<ConditionalOrder> {
+        self.lock()
+            .values()
+            .filter(|o| o.status.is_active())
+            .cloned()
+            .collect()
+    }
+
+    /// Feed a traded price for `code` and return the orders that fired
+    pub fn on_price(&self, code: &str, price: f64, ts: DateTime<Utc>) -> Vec<FiredOrder> {
+        if !price.is_finite() || price <= 0.0 {
+            return Vec::new();
+        }
+
+        let mut orders = self.lock();
+        let mut fired = Vec::new();
+        let mut trailing_changed = false;
+
+        for order in orders.values_mut() {
+            if order.status != ConditionalStatus::Pending || order.code() != code {
+                continue;
+            }
+            let action = order.order.action().clone();
+            let before = order.trigger.clone();
+            if order.trigger.update(&action, price) {
+                order.status = ConditionalStatus::Triggered;
+                order.triggered_at = Some(ts);
+                order.trigger_price = Some(price);
+                fired.push(FiredOrder {
+                    id: order.id.clone(),
+                    contract: order.contract.clone(),
+                    order: order.order.materialize(&order.trigger),
+                    trigger_price: price,
+                });
+            } else if before != order.trigger {
+                trailing_changed = true;
+            }
+        }
+
+        if fired.is_empty() {
+            if trailing_changed {
+                self.schedule_persist();
+            }
+            return fired;
+        }
+
+        // OCO 互斥取消；bracket 子單待進場單送出後才啟動
+        for fired_order in &fired {
+            let group = orders
+                .get(&fired_order.id)
+                .and_then(|o| o.oco_group.clone());
+            for order in orders.values_mut() {
+                if order.id == fired_order.id {
+                    continue;
+                }
+                if group.is_some() && order.oco_group == group && order.status.is_active() {
+                    order.status = ConditionalStatus::Cancelled;
+                    log::info!(
+                        "🚫 OCO sibling {} cancelled by {}",
+                        order.id,
+                        fired_order.id
+                    );
+                }
+            }
+        }
+
+        drop(orders);
+        self.schedule_persist();
+
+        for fired_order in &fired {
+            log::info!(
+                "⚡ Conditional order {} triggered at {} for {}",
+                fired_order.id,
+                fired_order.trigger_price,
+                code
+            );
+        }
+        fired
+    }
+
+    /// Record the broker order id of a fired order and activate its bracket
+    /// children
+    pub fn mark_submitted(&self, id: &str, order_id: String) {
+        self.settle(
+            id,
+            ConditionalStatus::Submitted { order_id },
+            ConditionalStatus::Pending,
+        );
+    }
+
+    /// Record a failed submission of a fired order; its bracket children are
+    /// cancelled, since there is no position for them to exit
+    pub fn mark_failed(&self, id: &str, reason: String) {
+        self.settle(
+            id,
+            ConditionalStatus::Failed(reason),
+            ConditionalStatus::Cancelled,
+        );
+    }
+
+    /// Register tick callbacks on `client` and submit fired orders on the
+    /// current tokio runtime
+    ///
+    /// Must be called from within a tokio runtime.
+    pub async fn attach(self: &Arc<Self>, client: Arc<Shioaji>) -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel::<FiredOrder>();
+
+        let book = self.clone();
+        let stk_tx = tx.clone();
+        client
+            .on_tick_stk_v1(
+                move |_exchange, tick| {
+                    if tick.simtrade {
+                        return;
+                    }
+                    for fired in book.on_price(&tick.code, tick.close, tick.datetime) {
+                        let _ = stk_tx.send(fired);
+                    }
+                },
+                false,
+            )
+            .await?;
+
+        let book = self.clone();
+        client
+            .on_tick_fop_v1(
+                move |_exchange, tick| {
+                    if tick.simtrade {
+                        return;
+                    }
+                    for fired in book.on_price(&tick.code, tick.close, tick.datetime) {
+                        let _ = tx.send(fired);
+                    }
+                },
+                false,
+            )
+            .await?;
+
+        let book = self.clone();
+        tokio::spawn(async move {
+            while let Some(fired) = rx.recv().await {
+                // Make `Triggered` durable before the order can reach the
+                // broker, so a crash cannot re-arm a trigger that already fired
+                let flush_book = book.clone();
+                if let Ok(Err(e)) = tokio::task::spawn_blocking(move || flush_book.flush()).await {
+                    log::warn!("⚠️ Failed to persist conditional orders: {}", e);
+                }
+                let result = match fired.order {
+                    OrderTemplate::Stock(order) => client
+                        .place_order(fired.contract, order)
+                        .await
+                        .map(|trade| trade.order_id),
+                    OrderTemplate::Futures(order) => client
+                        .place_futures_order(fired.contract, order)
+                        .await
+                        .map(|trade| trade.order_id),
+                };
+                match result {
+                    Ok(order_id) => {
+                        log::info!(
+                            "✅ Conditional order {} submitted as {}",
+                            fired.id,
+                            order_id
+                        );
+                        book.mark_submitted(&fired.id, order_id);
+                    }
+                    Err(e) => {
+                        log::error!("❌ Conditional order {} submission failed: {}", fired.id, e);
+                        book.mark_failed(&fired.id, e.to_string());
+                    }
+                }
+            }
+        });
+
+        log::info!("✅ Conditional order book attached to tick stream");
+        Ok(())
+    }
+
+    /// Set the outcome of a fired order and move its inactive bracket
+    /// children to `children`
+    fn settle(&self, id: &str, status: ConditionalStatus, children: ConditionalStatus) {
+        {
+            let mut orders = self.lock();
+            if let Some(order) = orders.get_mut(id) {
+                order.status = status;
+            }
+            for order in orders.values_mut() {
+                if order.parent_id.as_deref() == Some(id)
+                    && order.status == ConditionalStatus::Inactive
+                {
+                    order.status = children.clone();
+                    log::info!("🎯 Bracket child {} is now {:?}", order.id, children);
+                }
+            }
+        }
+        self.schedule_persist();
+    }
+
+    fn validate(&self, order: &ConditionalOrder) -> Result<()> {
+        let valid = match &order.trigger {
+            Trigger::Stop { stop_price } => *stop_price > 0.0,
+            Trigger::StopLimit {
+                stop_price,
+                limit_price,
+            } => *stop_price > 0.0 && *limit_price > 0.0,
+            Trigger::TrailingStop { trail, .. } => match trail {
+                TrailAmount::Points(points) => *points > 0.0,
+                TrailAmount::Percent(pct) => *pct > 0.0 && *pct < 100.0,
+            },
+            Trigger::TakeProfit {
+                target_price,
+                limit_price,
+            } => *target_price > 0.0 && limit_price.is_none_or(|p| p > 0.0),
+        };
+        if !valid {
+            return Err(Error::InvalidOrder(format!(
+                "Invalid trigger for conditional order {}: {:?}",
+                order.id, order.trigger
+            )));
+        }
+        if order.contract.base.code.is_empty() {
+            return Err(Error::InvalidContract(
+                "Conditional order contract code is empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ConditionalOrder>> {
+        self.orders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Ask the background writer to save; never blocks on the disk
+    fn schedule_persist(&self) {
+        if let Some(writer) = &self.writer {
+            let _ = writer.signals.send(PersistSignal::Dirty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Exchange, Future, FuturesOCType, Stock};
+
+    fn stock_sell(quantity: i32) -> OrderTemplate {
+        OrderTemplate::Stock(Order::new(
+            Action::Sell,
+            0.0,
+            quantity,
+            OrderType::ROD,
+            StockPriceType::LMT,
+        ))
+    }
+
+    fn stock_contract() -> Contract {
+        Stock::new("2330", Exchange::TSE).contract
+    }
+
+    #[test]
+    fn test_stop_triggers_market_order() {
+        let book = ConditionalOrderBook::new();
+        let id = book
+            .add(ConditionalOrder::new(
+                stock_contract(),
+                stock_sell(1),
+                Trigger::Stop { stop_price: 580.0 },
+            ))
+            .unwrap();
+
+        assert!(book.on_price("2330", 590.0, Utc::now()).is_empty());
+        assert!(book.on_price("2317", 100.0, Utc::now()).is_empty());
+
+        let fired = book.on_price("2330", 579.0, Utc::now());
+        assert_eq!(fired.len(), 1);
+        match &fired[0].order {
+            OrderTemplate::Stock(order) => {
+                assert_eq!(order.price_type, StockPriceType::MKT);
+                assert_eq!(order.order_type, OrderType::IOC);
+            }
+            _ => panic!("Expected stock order"),
+        }
+        assert_eq!(book.get(&id).unwrap().status, ConditionalStatus::Triggered);
+
+        // Fires only once
+        assert!(book.on_price("2330", 570.0, Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn test_stop_limit_for_futures() {
+        let book = ConditionalOrderBook::new();
+        let order = FuturesOrder::new(
+            Action::Buy,
+            0.0,
+            1,
+            OrderType::ROD,
+            FuturesPriceType::LMT,
+            FuturesOCType::Auto,
+        );
+        book.add(ConditionalOrder::new(
+            Future::new("TXFL6").contract,
+            OrderTemplate::Futures(order),
+            Trigger::StopLimit {
+                stop_price: 23000.0,
+                limit_price: 23010.0,
+            },
+        ))
+        .unwrap();
+
+        let fired = book.on_price("TXFL6", 23000.0, Utc::now());
+        assert_eq!(fired.len(), 1);
+        match &fired[0].order {
+            OrderTemplate::Futures(order) => {
+                assert_eq!(order.price, 23010.0);
+                assert_eq!(order.price_type, FuturesPriceType::LMT);
+                assert_eq!(order.order_type, OrderType::ROD);
+            }
+            _ => panic!("Expected futures order"),
+        }
+    }
+
+    #[test]
+    fn test_trailing_stop_follows_high() {
+        let book = ConditionalOrderBook::new();
+        let id = book
+            .add(ConditionalOrder::new(
+                stock_contract(),
+                stock_sell(1),
+                Trigger::trailing(TrailAmount::Points(10.0)),
+            ))
+            .unwrap();
+
+        assert!(book.on_price("2330", 600.0, Utc::now()).is_empty());
+        assert!(book.on_price("2330", 620.0, Utc::now()).is_empty());
+        assert!(book.on_price("2330", 611.0, Utc::now()).is_empty());
+
+        let order = book.get(&id).unwrap();
+        assert_eq!(
+            order.trigger.trailing_stop_price(&Action::Sell),
+            Some(610.0)
+        );
+
+        assert_eq!(book.on_price("2330", 610.0, Utc::now()).len(), 1);
+    }
+
+    #[test]
+    fn test_oco_cancels_sibling() {
+        let book = ConditionalOrderBook::new();
+        let (stop_id, tp_id) = book
+            .add_oco(
+                ConditionalOrder::new(
+                    stock_contract(),
+                    stock_sell(1),
+                    Trigger::Stop { stop_price: 580.0 },
+                ),
+                ConditionalOrder::new(
+                    stock_contract(),
+                    stock_sell(1),
+                    Trigger::TakeProfit {
+                        target_price: 650.0,
+                        limit_price: Some(650.0),
+                    },
+                ),
+            )
+            .unwrap();
+
+        let fired = book.on_price("2330", 655.0, Utc::now());
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].id, tp_id);
+        assert_eq!(
+            book.get(&stop_id).unwrap().status,
+            ConditionalStatus::Cancelled
+        );
+        assert!(book.on_price("2330", 500.0, Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn test_bracket_activates_children() {
+        let book = ConditionalOrderBook::new();
+        let entry = ConditionalOrder::new(
+            stock_contract(),
+            OrderTemplate::Stock(Order::new(
+                Action::Buy,
+                0.0,
+                1,
+                OrderType::ROD,
+                StockPriceType::LMT,
+            )),
+            Trigger::Stop { stop_price: 600.0 },
+        );
+        let stop_loss = ConditionalOrder::new(
+            stock_contract(),
+            stock_sell(1),
+            Trigger::Stop { stop_price: 590.0 },
+        );
+        let take_profit = ConditionalOrder::new(
+            stock_contract(),
+            stock_sell(1),
+            Trigger::TakeProfit {
+                target_price: 620.0,
+                limit_price: None,
+            },
+        );
+        let ids = book.add_bracket(entry, stop_loss, take_profit).unwrap();
+
+        // Children are inactive until the entry fires
+        assert!(book.on_price("2330", 585.0, Utc::now()).is_empty());
+        assert_eq!(
+            book.get(&ids.stop_loss).unwrap().status,
+            ConditionalStatus::Inactive
+        );
+
+        let fired = book.on_price("2330", 601.0, Utc::now());
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].id, ids.entry);
+        // ... and stay inactive until the entry order is submitted
+        assert_eq!(
+            book.get(&ids.take_profit).unwrap().status,
+            ConditionalStatus::Inactive
+        );
+        assert!(book.on_price("2330", 589.0, Utc::now()).is_empty());
+        book.mark_submitted(&ids.entry, "entry-order".to_string());
+        assert_eq!(
+            book.get(&ids.take_profit).unwrap().status,
+            ConditionalStatus::Pending
+        );
+
+        let fired = book.on_price("2330", 589.0, Utc::now());
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].id, ids.stop_loss);
+        assert_eq!(
+            book.get(&ids.take_profit).unwrap().status,
+            ConditionalStatus::Cancelled
+        );
+    }
+
+    #[test]
+    fn test_failed_entry_cancels_bracket_children() {
+        let book = ConditionalOrderBook::new();
+        let entry = ConditionalOrder::new(
+            stock_contract(),
+            OrderTemplate::Stock(Order::new(
+                Action::Buy,
+                0.0,
+                1,
+                OrderType::ROD,
+                StockPriceType::LMT,
+            )),
+            Trigger::Stop { stop_price: 600.0 },
+        );
+        let stop_loss = ConditionalOrder::new(
+            stock_contract(),
+            stock_sell(1),
+            Trigger::Stop { stop_price: 590.0 },
+        );
+        let take_profit = ConditionalOrder::new(
+            stock_contract(),
+            stock_sell(1),
+            Trigger::TakeProfit {
+                target_price: 620.0,
+                limit_price: None,
+            },
+        );
+        let ids = book.add_bracket(entry, stop_loss, take_profit).unwrap();
+
+        assert_eq!(book.on_price("2330", 601.0, Utc::now()).len(), 1);
+        book.mark_failed(&ids.entry, "帳戶未簽署".to_string());
+
+        for id in [&ids.stop_loss, &ids.take_profit] {
+            assert_eq!(book.get(id).unwrap().status, ConditionalStatus::Cancelled);
+        }
+        // No exit order for a position that was never opened
+        assert!(book.on_price("2330", 580.0, Utc::now()).is_empty());
+        assert!(book.on_price("2330", 630.0, Utc::now()).is_empty());
+        assert!(book.active().is_empty());
+    }
+
+    #[test]
+    fn test_persistence_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("conditional_orders.json");
+
+        let id = {
+            let book = ConditionalOrderBook::with_persistence(&path).unwrap();
+            let id = book
+                .add(ConditionalOrder::new(
+                    stock_contract(),
+                    stock_sell(2),
+                    Trigger::trailing(TrailAmount::Percent(1.0)),
+                ))
+                .unwrap();
+            book.on_price("2330", 600.0, Utc::now());
+            id
+        };
+
+        let restored = ConditionalOrderBook::with_persistence(&path).unwrap();
+        let order = restored.get(&id).unwrap();
+        assert_eq!(order.status, ConditionalStatus::Pending);
+        assert_eq!(
+            order.trigger.trailing_stop_price(&Action::Sell),
+            Some(594.0)
+        );
+    }
+
+    #[test]
+    fn test_triggered_orders_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("conditional_orders.json");
+
+        let (fired_id, submitted_id) = {
+            let book = ConditionalOrderBook::with_persistence(&path).unwrap();
+            let fired_id = book
+                .add(ConditionalOrder::stock_stop(
+                    stock_contract(),
+                    Order::new(Action::Sell, 0.0, 1, OrderType::IOC, StockPriceType::MKT),
+                    580.0,
+                ))
+                .unwrap();
+            let submitted_id = book
+                .add(ConditionalOrder::stock_stop(
+                    stock_contract(),
+                    Order::new(Action::Sell, 0.0, 1, OrderType::IOC, StockPriceType::MKT),
+                    590.0,
+                ))
+                .unwrap();
+            assert_eq!(book.on_price("2330", 575.0, Utc::now()).len(), 2);
+            book.mark_submitted(&submitted_id, "ord1".to_string());
+            (fired_id, submitted_id)
+        };
+
+        let restored = ConditionalOrderBook::with_persistence(&path).unwrap();
+        let order = restored.get(&fired_id).unwrap();
+        assert_eq!(order.status, ConditionalStatus::Triggered);
+        assert_eq!(order.trigger_price, Some(575.0));
+        assert!(restored.get(&submitted_id).is_none());
+        assert!(restored.active().is_empty());
+        // A fired trigger is never re-armed
+        assert!(restored.on_price("2330", 570.0, Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn test_tick_changes_are_written_in_background() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("conditional_orders.json");
+        let book = ConditionalOrderBook::with_persistence(&path).unwrap();
+        book.add(ConditionalOrder::new(
+            stock_contract(),
+            stock_sell(1),
+            Trigger::trailing(TrailAmount::Points(5.0)),
+        ))
+        .unwrap();
+
+        book.on_price("2330", 600.0, Utc::now());
+        let saved_extreme = || {
+            let content = std::fs::read_to_string(&path).unwrap();
+            let saved: Vec<ConditionalOrder> = serde_json::from_str(&content).unwrap();
+            saved[0].trigger.trailing_stop_price(&Action::Sell)
+        };
+        let deadline = Instant::now() + PERSIST_DEBOUNCE * 20;
+        while saved_extreme() != Some(595.0) {
+            assert!(Instant::now() < deadline, "trailing extreme never saved");
+            std::thread::sleep(PERSIST_DEBOUNCE / 5);
+        }
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn test_invalid_trigger_rejected() {
+        let book = ConditionalOrderBook::new();
+        let result = book.add(ConditionalOrder::new(
+            stock_contract(),
+            stock_sell(1),
+            Trigger::Stop { stop_price: 0.0 },
+        ));
+        assert!(result.is_err());
+    }
+}
@@ -1,4 +1,5 @@
-use pyo3::PyDowncastError;
+use pyo3::types::PyType;
+use pyo3::{PyAny, PyDowncastError, PyErr, Python};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -82,6 +83,185 @@ pub enum Error {
 
     #[error("System error: {0}")]
     System(String),
+
+    #[error("Token error{}: {message}", status_suffix(.status_code))]
+    Token {
+        status_code: Option<i64>,
+        message: String,
+    },
+
+    #[error("System maintenance{}: {message}", status_suffix(.status_code))]
+    SystemMaintenance {
+        status_code: Option<i64>,
+        message: String,
+    },
+
+    #[error("Account not signed: {0}")]
+    AccountNotSigned(String),
+
+    #[error("Account not provided: {0}")]
+    AccountNotProvided(String),
+
+    #[error("Order outcome unknown: {0}")]
+    OutcomeUnknown(String),
+
+    #[error("Shioaji {exception}{}: {message}", status_suffix(.status_code))]
+    Sdk {
+        exception: String,
+        status_code: Option<i64>,
+        message: String,
+    },
+}
+
+fn status_suffix(status_code: &Option<i64>) -> String {
+    status_code
+        .map(|code| format!(" [{}]", code))
+        .unwrap_or_default()
+}
+
+impl Error {
+    /// Map an exception raised by the shioaji SDK to its typed variant
+    ///
+    /// Exceptions from `shioaji.error` (and its subclasses) keep their status
+    /// code and broker message; anything else is handed to `fallback`, so call
+    /// sites keep their existing context for plain Python errors.
+    ///
+    /// | Python exception | Variant |
+    /// |------------------|---------|
+    /// | `TokenError` | [`Error::Token`] |
+    /// | `SystemMaintenance` | [`Error::SystemMaintenance`] |
+    /// | `TimeoutError` | [`Error::Timeout`] ([`Error::OutcomeUnknown`] for order calls) |
+    /// | `AccountNotSignError` | [`Error::AccountNotSigned`] |
+    /// | `AccountNotProvideError` | [`Error::AccountNotProvided`] |
+    /// | `TargetContractNotExistError` | [`Error::InvalidContract`] |
+    /// | other `shioaji` exceptions | [`Error::Sdk`] |
+    pub fn from_sdk(err: PyErr, fallback: impl FnOnce(PyErr) -> Error) -> Error {
+        match Python::with_gil(|py| SdkException::parse(py, &err)) {
+            Some(exception) => exception.into_error(),
+            None => fallback(err),
+        }
+    }
+
+    /// An order call's timeout: the order may still have reached the broker
+    ///
+    /// Applied to `place_order`, `update_order` and `cancel_order` so retry
+    /// logic never resends an order whose outcome is not known.
+    pub(crate) fn into_order_outcome(self) -> Self {
+        match self {
+            Error::Timeout(message) => Error::OutcomeUnknown(message),
+            other => other,
+        }
+    }
+
+    /// Whether the same call may succeed if simply retried later
+    ///
+    /// True for timeouts, network/connection failures and system maintenance.
+    /// Token errors need a fresh login first and rejections (invalid orders,
+    /// unsigned accounts, bad input) will fail again, so they are not retryable.
+    /// [`Error::OutcomeUnknown`] is not retryable either: reconcile with
+    /// `list_trades` first, or the order may be sent twice.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Error::Timeout(_)
+                | Error::Network(_)
+                | Error::Connection(_)
+                | Error::SystemMaintenance { .. }
+        )
+    }
+}
+
+/// Fields read from a `shioaji` exception instance
+struct SdkException {
+    class_chain: Vec<String>,
+    status_code: Option<i64>,
+    message: String,
+}
+
+impl SdkException {
+    const MODULE: &'static str = "shioaji";
+
+    fn parse(py: Python<'_>, err: &PyErr) -> Option<Self> {
+        let value = err.value(py);
+        // Walk the MRO so subclasses map like their documented parent
+        let mro: Vec<&PyType> = value.get_type().getattr("__mro__").ok()?.extract().ok()?;
+        let from_sdk = mro.iter().any(|class| {
+            class
+                .getattr("__module__")
+                .and_then(|module| module.extract::<&str>())
+                .is_ok_and(|module| module == Self::MODULE || module.starts_with("shioaji."))
+        });
+        if !from_sdk {
+            return None;
+        }
+        let class_chain = mro
+            .iter()
+            .filter_map(|class| class.name().ok())
+            .map(str::to_string)
+            .collect();
+
+        let args: Vec<&PyAny> = value
+            .getattr("args")
+            .and_then(|args| args.extract())
+            .unwrap_or_default();
+        let attr = |names: &[&str]| {
+            names
+                .iter()
+                .filter_map(|name| value.getattr(*name).ok())
+                .find(|attr| !attr.is_none())
+        };
+
+        let status_code = attr(&["status_code", "code"])
+            .or_else(|| args.first().copied())
+            .and_then(|code| code.extract::<i64>().ok());
+        let message = attr(&["message", "detail"])
+            .or_else(|| match status_code {
+                Some(_) => args.get(1).copied(),
+                None => args.first().copied(),
+            })
+            .and_then(|message| message.str().ok())
+            .map(|message| message.to_string_lossy().into_owned())
+            .unwrap_or_else(|| value.to_string());
+
+        Some(Self {
+            class_chain,
+            status_code,
+            message,
+        })
+    }
+
+    fn is(&self, name: &str) -> bool {
+        self.class_chain.iter().any(|class| class == name)
+    }
+
+    fn into_error(self) -> Error {
+        let status_code = self.status_code;
+        if self.is("TokenError") {
+            Error::Token {
+                status_code,
+                message: self.message,
+            }
+        } else if self.is("SystemMaintenance") {
+            Error::SystemMaintenance {
+                status_code,
+                message: self.message,
+            }
+        } else if self.is("TimeoutError") {
+            Error::Timeout(self.message)
+        } else if self.is("AccountNotSignError") {
+            Error::AccountNotSigned(self.message)
+        } else if self.is("AccountNotProvideError") {
+            Error::AccountNotProvided(self.message)
+        } else if self.is("TargetContractNotExistError") {
+            Error::InvalidContract(self.message)
+        } else {
+            Error::Sdk {
+                exception: self.class_chain.into_iter().next().unwrap_or_default(),
+                status_code,
+                message: self.message,
+            }
+        }
+    }
 }
 
 impl<'a> From<PyDowncastError<'a>> for Error {
@@ -89,3 +269,104 @@ impl<'a> From<PyDowncastError<'a>> for Error {
         Error::PyDowncast(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Raise `expr` after defining exception classes under `shioaji.error`
+    fn raise(py: Python<'_>, expr: &str) -> PyErr {
+        let code = format!(
+            r#"
+class BaseError(Exception):
+    __module__ = "shioaji.error"
+
+class TokenError(BaseError):
+    def __init__(self, code, message):
+        super().__init__(code, message)
+        self.code = code
+        self.message = message
+
+class AccountNotProvideError(BaseError):
+    pass
+
+class BrokerRejected(AccountNotProvideError):
+    pass
+
+class OrderError(BaseError):
+    pass
+
+raise {}
+"#,
+            expr
+        );
+        py.run(&code, None, None).unwrap_err()
+    }
+
+    fn map(expr: &str) -> Error {
+        let err = Python::with_gil(|py| raise(py, expr));
+        Error::from_sdk(err, |e| Error::Trading(e.to_string()))
+    }
+
+    #[test]
+    fn test_sdk_exceptions_keep_code_and_message() {
+        match map("TokenError(401, 'Token is expired')") {
+            Error::Token {
+                status_code,
+                message,
+            } => {
+                assert_eq!(status_code, Some(401));
+                assert_eq!(message, "Token is expired");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        // Subclasses map like their parent
+        assert!(matches!(
+            map("BrokerRejected('no account')"),
+            Error::AccountNotProvided(ref message) if message == "no account"
+        ));
+        match map("OrderError(88, 'price out of range')") {
+            Error::Sdk {
+                exception,
+                status_code,
+                message,
+            } => {
+                assert_eq!(exception, "OrderError");
+                assert_eq!(status_code, Some(88));
+                assert_eq!(message, "price out of range");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(map("ValueError('plain')"), Error::Trading(_)));
+    }
+
+    #[test]
+    fn test_retryable_classification() {
+        let retryable = [
+            Error::Timeout("place_order".into()),
+            Error::Connection("reset".into()),
+            Error::Network("unreachable".into()),
+            Error::SystemMaintenance {
+                status_code: Some(503),
+                message: "maintenance".into(),
+            },
+        ];
+        for error in &retryable {
+            assert!(error.is_retryable(), "{}", error);
+        }
+        let final_errors = [
+            Error::Token {
+                status_code: Some(401),
+                message: "expired".into(),
+            },
+            Error::AccountNotSigned("0000001".into()),
+            Error::Timeout("place_order".into()).into_order_outcome(),
+            Error::InvalidOrder("qty".into()),
+            Error::InvalidInput("proxy".into()),
+        ];
+        for error in &final_errors {
+            assert!(!error.is_retryable(), "{}", error);
+        }
+        assert_eq!(final_errors[0].to_string(), "Token error [401]: expired");
+    }
+}
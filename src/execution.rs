@@ -0,0 +1,1086 @@
+//! Execution algorithms (拆單演算法): TWAP, VWAP and iceberg
+//!
+//! A parent order is split into child orders that are submitted through
+//! `Shioaji::place_order` / `Shioaji::place_futures_order`. Fills are tracked
+//! from the order/deal callback (`Shioaji::on_order`), so a slice that is only
+//! partially filled is topped up by the next one. A child the broker rejects,
+//! or whose remainder is cancelled (IOC/FOK, manual cancel), stops counting as
+//! working; a child the engine cancels itself keeps counting until the broker
+//! confirms the cancel, since it may still fill until then. An iceberg fails
+//! on a rejected child. Progress is reported as a
+//! stream of [`ExecutionEvent`]s on the returned [`ExecutionHandle`].
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use rshioaji::execution::{ExecutionEngine, ParentOrder, Schedule};
+//! # async fn run(client: Arc<rshioaji::Shioaji>, parent: ParentOrder) -> rshioaji::Result<()> {
+//! let engine = ExecutionEngine::new(client).await?;
+//! let start = chrono::Utc::now();
+//! let mut handle = engine.start(
+//!     parent,
+//!     Schedule::Twap { start, end: start + chrono::Duration::minutes(30), slices: 10 },
+//! )?;
+//! while let Some(event) = handle.next_event().await {
+//!     println!("{:?}", event);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, FixedOffset, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+
+use crate::client::Shioaji;
+use crate::error::{Error, Result};
+use crate::types::{Contract, Deal, Kbar, OrderEventType, OrderTemplate, Status, Trade};
+use crate::utils::new_id;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+/// 台北時間 (UTC+8)，用於把 K 棒對齊到交易時段
+const TAIPEI_OFFSET_SECS: i32 = 8 * 3600;
+/// Deals received before the matching `place_order` call returns are kept
+/// for this many order ids at most
+const MAX_UNMATCHED_ORDERS: usize = 1024;
+
+/// Intraday volume profile, stored as volume per Taipei minute-of-day
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VolumeProfile {
+    buckets: BTreeMap<u32, f64>,
+}
+
+impl VolumeProfile {
+    /// Build a profile from historical 1-minute K bars; several days of bars
+    /// are summed per minute-of-day
+    pub fn from_kbars(kbars: &[Kbar]) -> Self {
+        let mut buckets = BTreeMap::new();
+        for kbar in kbars {
+            if kbar.volume > 0 {
+                *buckets.entry(minute_of_day(kbar.ts)).or_insert(0.0) += kbar.volume as f64;
+            }
+        }
+        Self { buckets }
+    }
+
+    /// Fetch K bars with `get_kbars` (dates as `YYYY-MM-DD`) and build the profile
+    pub async fn fetch(
+        client: &Shioaji,
+        contract: Contract,
+        start: &str,
+        end: &str,
+    ) -> Result<Self> {
+        let kbars = client.get_kbars(contract, start, end).await?;
+        let profile = Self::from_kbars(&kbars);
+        if profile.is_empty() {
+            return Err(Error::DataFetch(format!(
+                "No volume in K bars between {} and {}",
+                start, end
+            )));
+        }
+        Ok(profile)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Share of the `[start, end)` window's expected volume traded by `at`,
+    /// in `0.0..=1.0`. Falls back to linear (TWAP) when the profile has no
+    /// volume inside the window.
+    pub fn cumulative_fraction(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        at: DateTime<Utc>,
+    ) -> f64 {
+        if at <= start {
+            return 0.0;
+        }
+        if at >= end {
+            return 1.0;
+        }
+
+        let window = minutes_between(start, end);
+        let elapsed = minutes_between(start, at);
+        let first = minute_of_day(start);
+        let volume_at = |offset: u32| {
+            self.buckets
+                .get(&((first + offset) % MINUTES_PER_DAY))
+                .copied()
+                .unwrap_or(0.0)
+        };
+
+        let total: f64 = (0..window).map(volume_at).sum();
+        if total <= 0.0 {
+            return linear_fraction(start, end, at);
+        }
+        let done: f64 = (0..elapsed.min(window)).map(volume_at).sum();
+        (done / total).clamp(0.0, 1.0)
+    }
+}
+
+/// How the parent order is split over time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Schedule {
+    /// Equal slices evenly spaced over `[start, end)`
+    Twap {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        slices: u32,
+    },
+    /// Slices sized by the historical volume profile over `[start, end)`
+    Vwap {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        slices: u32,
+        profile: VolumeProfile,
+    },
+    /// Show at most `visible_quantity` at a time; refill after each child fills
+    Iceberg { visible_quantity: i32 },
+}
+
+/// One scheduled slice: at `at`, the cumulative target becomes `cumulative`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slice {
+    pub at: DateTime<Utc>,
+    pub cumulative: i32,
+}
+
+impl Schedule {
+    fn validate(&self) -> Result<()> {
+        match self {
+            Schedule::Twap { start, end, slices }
+            | Schedule::Vwap {
+                start, end, slices, ..
+            } => {
+                if end <= start {
+                    return Err(Error::InvalidInput(
+                        "Schedule end must be after start".to_string(),
+                    ));
+                }
+                if *slices == 0 {
+                    return Err(Error::InvalidInput(
+                        "Schedule needs at least one slice".to_string(),
+                    ));
+                }
+            }
+            Schedule::Iceberg { visible_quantity } => {
+                if *visible_quantity <= 0 {
+                    return Err(Error::InvalidInput(
+                        "Iceberg visible quantity must be positive".to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Slice plan for a parent of `total` lots; empty for iceberg orders,
+    /// which are driven by fills rather than time
+    pub fn plan(&self, total: i32) -> Vec<Slice> {
+        let (start, end, slices) = match self {
+            Schedule::Twap { start, end, slices }
+            | Schedule::Vwap {
+                start, end, slices, ..
+            } => (*start, *end, (*slices).max(1)),
+            Schedule::Iceberg { .. } => return Vec::new(),
+        };
+
+        let step = (end - start) / slices as i32;
+        (0..slices)
+            .map(|i| {
+                let slice_end = if i + 1 == slices {
+                    end
+                } else {
+                    start + step * (i as i32 + 1)
+                };
+                let fraction = match self {
+                    Schedule::Vwap { profile, .. } => {
+                        profile.cumulative_fraction(start, end, slice_end)
+                    }
+                    _ => linear_fraction(start, end, slice_end),
+                };
+                Slice {
+                    at: start + step * i as i32,
+                    cumulative: (total as f64 * fraction).round() as i32,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parent order handed to the engine
+#[derive(Debug, Clone)]
+pub struct ParentOrder {
+    pub contract: Contract,
+    /// Child order template; its quantity is the parent's total quantity
+    pub order: OrderTemplate,
+    /// How long to wait for outstanding children after the last slice
+    pub completion_timeout: Duration,
+}
+
+impl ParentOrder {
+    pub fn new(contract: Contract, order: OrderTemplate) -> Self {
+        Self {
+            contract,
+            order,
+            completion_timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_completion_timeout(mut self, timeout: Duration) -> Self {
+        self.completion_timeout = timeout;
+        self
+    }
+}
+
+/// Snapshot of a running algorithm
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionProgress {
+    pub id: String,
+    pub total: i32,
+    pub filled: i32,
+    /// Quantity sent in children that are still working
+    pub working: i32,
+    /// Volume-weighted fill price (0.0 before the first fill)
+    pub avg_price: f64,
+}
+
+impl ExecutionProgress {
+    pub fn remaining(&self) -> i32 {
+        (self.total - self.filled).max(0)
+    }
+}
+
+/// Typed event stream of an execution algorithm
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExecutionEvent {
+    Started {
+        id: String,
+        total: i32,
+    },
+    ChildPlaced {
+        id: String,
+        order_id: String,
+        quantity: i32,
+    },
+    ChildFailed {
+        id: String,
+        quantity: i32,
+        error: String,
+    },
+    Fill {
+        id: String,
+        order_id: String,
+        price: f64,
+        quantity: i32,
+    },
+    Progress(ExecutionProgress),
+    /// Parent fully filled, or the schedule ended (check `remaining()`)
+    Completed(ExecutionProgress),
+    Cancelled(ExecutionProgress),
+    Failed {
+        progress: ExecutionProgress,
+        error: String,
+    },
+}
+
+impl ExecutionEvent {
+    /// Whether this is the last event of the algorithm
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            ExecutionEvent::Completed(_)
+                | ExecutionEvent::Cancelled(_)
+                | ExecutionEvent::Failed { .. }
+        )
+    }
+}
+
+/// Handle to a running algorithm
+pub struct ExecutionHandle {
+    pub id: String,
+    events: mpsc::UnboundedReceiver<ExecutionEvent>,
+    cancel: Arc<Notify>,
+}
+
+impl ExecutionHandle {
+    /// Next event; `None` once the algorithm has finished
+    pub async fn next_event(&mut self) -> Option<ExecutionEvent> {
+        self.events.recv().await
+    }
+
+    /// Stop scheduling new children and cancel working ones. Dropping the
+    /// handle does not cancel the algorithm.
+    pub fn cancel(&self) {
+        self.cancel.notify_one();
+    }
+}
+
+/// Order callback event concerning one child order
+#[derive(Debug, Clone)]
+enum ChildEvent {
+    Deal(Deal),
+    /// The broker rejected the order (`rejected`) or cancelled its remaining
+    /// quantity; nothing more will fill
+    Closed {
+        order_id: String,
+        rejected: bool,
+        message: String,
+    },
+}
+
+impl ChildEvent {
+    /// Parse a deal, a rejected new order or a successful cancel; other order
+    /// events (accepted orders, failed cancels, price updates) return `None`
+    fn from_event(event_type: &OrderEventType, msg: &serde_json::Value) -> Option<Self> {
+        if event_type.is_deal() {
+            return Deal::from_event(event_type, msg).map(ChildEvent::Deal);
+        }
+        let order_id = msg.pointer("/order/id")?.as_str()?.to_string();
+        let op_type = msg.pointer("/operation/op_type")?.as_str()?;
+        let succeeded = msg.pointer("/operation/op_code").and_then(|v| v.as_str()) == Some("00");
+        let message = msg
+            .pointer("/operation/op_msg")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        match (op_type, succeeded) {
+            ("New", false) => Some(ChildEvent::Closed {
+                order_id,
+                rejected: true,
+                message,
+            }),
+            ("Cancel", true) => Some(ChildEvent::Closed {
+                order_id,
+                rejected: false,
+                message,
+            }),
+            _ => None,
+        }
+    }
+
+    fn order_id(&self) -> &str {
+        match self {
+            ChildEvent::Deal(deal) => &deal.trade_id,
+            ChildEvent::Closed { order_id, .. } => order_id,
+        }
+    }
+}
+
+/// Routes deals and order events from the order callback to the algorithm
+/// that owns the order
+#[derive(Default)]
+struct FillRouter {
+    routes: HashMap<String, mpsc::UnboundedSender<ChildEvent>>,
+    unmatched: HashMap<String, Vec<ChildEvent>>,
+}
+
+impl FillRouter {
+    fn dispatch(&mut self, event: ChildEvent) {
+        let order_id = event.order_id();
+        if let Some(tx) = self.routes.get(order_id) {
+            let _ = tx.send(event);
+            return;
+        }
+        if self.unmatched.len() >= MAX_UNMATCHED_ORDERS && !self.unmatched.contains_key(order_id) {
+            self.unmatched.clear();
+        }
+        self.unmatched
+            .entry(order_id.to_string())
+            .or_default()
+            .push(event);
+    }
+
+    fn register(&mut self, order_id: &str, tx: &mpsc::UnboundedSender<ChildEvent>) {
+        for event in self.unmatched.remove(order_id).unwrap_or_default() {
+            let _ = tx.send(event);
+        }
+        self.routes.insert(order_id.to_string(), tx.clone());
+    }
+
+    fn unregister(&mut self, order_id: &str) {
+        self.routes.remove(order_id);
+    }
+}
+
+/// Runs execution algorithms against a logged-in client
+pub struct ExecutionEngine {
+    client: Arc<Shioaji>,
+    router: Arc<Mutex<FillRouter>>,
+}
+
+impl ExecutionEngine {
+    /// Create the engine and register its order callback on `client`
+    pub async fn new(client: Arc<Shioaji>) -> Result<Arc<Self>> {
+        let router = Arc::new(Mutex::new(FillRouter::default()));
+
+        let callback_router = router.clone();
+        client
+            .on_order(move |event_type, msg| {
+                if let Some(event) = ChildEvent::from_event(&event_type, &msg) {
+                    if let Ok(mut router) = callback_router.lock() {
+                        router.dispatch(event);
+                    }
+                }
+            })
+            .await?;
+
+        log::info!("✅ Execution engine attached to order events");
+        Ok(Arc::new(Self { client, router }))
+    }
+
+    /// Start an algorithm; must be called inside a tokio runtime
+    pub fn start(
+        self: &Arc<Self>,
+        parent: ParentOrder,
+        schedule: Schedule,
+    ) -> Result<ExecutionHandle> {
+        schedule.validate()?;
+        let total = parent.order.quantity();
+        if total <= 0 {
+            return Err(Error::InvalidOrder(
+                "Parent quantity must be positive".to_string(),
+            ));
+        }
+
+        let id = new_id("algo");
+        let (event_tx, events) = mpsc::unbounded_channel();
+        let cancel = Arc::new(Notify::new());
+
+        let run = AlgoRun {
+            engine: self.clone(),
+            id: id.clone(),
+            parent,
+            events: event_tx,
+            cancel: cancel.clone(),
+            children: HashMap::new(),
+            filled: 0,
+            notional: 0.0,
+        };
+        tokio::spawn(run.run(schedule));
+
+        Ok(ExecutionHandle { id, events, cancel })
+    }
+}
+
+struct Child {
+    trade: Trade,
+    quantity: i32,
+    filled: i32,
+    working: bool,
+    /// A cancel request was accepted; the child stays working until the
+    /// broker confirms it (or the child fills in full)
+    cancelling: bool,
+}
+
+enum Wake {
+    Child(ChildEvent),
+    Cancelled,
+    Timer,
+}
+
+struct AlgoRun {
+    engine: Arc<ExecutionEngine>,
+    id: String,
+    parent: ParentOrder,
+    events: mpsc::UnboundedSender<ExecutionEvent>,
+    cancel: Arc<Notify>,
+    children: HashMap<String, Child>,
+    filled: i32,
+    notional: f64,
+}
+
+impl AlgoRun {
+    async fn run(mut self, schedule: Schedule) {
+        let total = self.parent.order.quantity();
+        let (child_tx, mut child_events) = mpsc::unbounded_channel();
+        log::info!(
+            "🚀 Execution {} started: {} lots, {:?}",
+            self.id,
+            total,
+            schedule
+        );
+        self.emit(ExecutionEvent::Started {
+            id: self.id.clone(),
+            total,
+        });
+
+        let finished = match &schedule {
+            Schedule::Iceberg { visible_quantity } => {
+                self.run_iceberg(*visible_quantity, &child_tx, &mut child_events)
+                    .await
+            }
+            _ => {
+                self.run_slices(schedule.plan(total), &child_tx, &mut child_events)
+                    .await
+            }
+        };
+
+        if let Ok(mut router) = self.engine.router.lock() {
+            for order_id in self.children.keys() {
+                router.unregister(order_id);
+            }
+        }
+
+        let progress = self.progress();
+        let event = match finished {
+            Ok(true) => ExecutionEvent::Completed(progress),
+            Ok(false) => ExecutionEvent::Cancelled(progress),
+            Err(e) => ExecutionEvent::Failed {
+                progress,
+                error: e.to_string(),
+            },
+        };
+        log::info!("🏁 Execution {} finished: {:?}", self.id, event);
+        self.emit(event);
+    }
+
+    /// Time-sliced execution (TWAP / VWAP). Returns `Ok(false)` when cancelled.
+    async fn run_slices(
+        &mut self,
+        plan: Vec<Slice>,
+        child_tx: &mpsc::UnboundedSender<ChildEvent>,
+        child_events: &mut mpsc::UnboundedReceiver<ChildEvent>,
+    ) -> Result<bool> {
+        let total = self.parent.order.quantity();
+        for slice in plan {
+            loop {
+                let wait = (slice.at - Utc::now()).to_std().unwrap_or_default();
+                match self.wait(child_events, wait).await {
+                    Wake::Child(event) => {
+                        self.on_child_event(event);
+                    }
+                    Wake::Cancelled => {
+                        self.cancel_working().await;
+                        return Ok(false);
+                    }
+                    Wake::Timer => break,
+                }
+            }
+            if self.filled >= total {
+                break;
+            }
+
+            // Re-size leftovers from earlier slices into this one
+            self.cancel_working().await;
+            self.drain(child_events);
+            let quantity = slice.cumulative.min(total) - self.filled - self.working();
+            if quantity > 0 {
+                self.place_child(quantity, child_tx).await;
+            }
+            self.emit_progress();
+        }
+
+        self.await_completion(child_events).await
+    }
+
+    /// Iceberg execution. Returns `Ok(false)` when cancelled; fails when a
+    /// child cannot be placed or is rejected by the broker, rather than
+    /// re-sending the same order.
+    async fn run_iceberg(
+        &mut self,
+        visible_quantity: i32,
+        child_tx: &mpsc::UnboundedSender<ChildEvent>,
+        child_events: &mut mpsc::UnboundedReceiver<ChildEvent>,
+    ) -> Result<bool> {
+        let total = self.parent.order.quantity();
+        while self.filled < total {
+            if self.working() == 0 {
+                let quantity = visible_quantity.min(total - self.filled);
+                if !self.place_child(quantity, child_tx).await || self.working() == 0 {
+                    return Err(Error::Trading(format!(
+                        "Iceberg {} could not place its next child order",
+                        self.id
+                    )));
+                }
+            }
+            match self.wait(child_events, Duration::MAX).await {
+                Wake::Child(event) => {
+                    if let Some(message) = self.on_child_event(event) {
+                        return Err(Error::Trading(format!(
+                            "Iceberg {} child order rejected: {}",
+                            self.id, message
+                        )));
+                    }
+                }
+                Wake::Cancelled => {
+                    self.cancel_working().await;
+                    return Ok(false);
+                }
+                Wake::Timer => {}
+            }
+        }
+        Ok(true)
+    }
+
+    /// Wait for outstanding children after the last slice, then cancel the rest
+    async fn await_completion(
+        &mut self,
+        child_events: &mut mpsc::UnboundedReceiver<ChildEvent>,
+    ) -> Result<bool> {
+        let total = self.parent.order.quantity();
+        let deadline = tokio::time::Instant::now() + self.parent.completion_timeout;
+        while self.filled < total && self.working() > 0 {
+            let wait = deadline.saturating_duration_since(tokio::time::Instant::now());
+            match self.wait(child_events, wait).await {
+                Wake::Child(event) => {
+                    self.on_child_event(event);
+                }
+                Wake::Cancelled => {
+                    self.cancel_working().await;
+                    return Ok(false);
+                }
+                Wake::Timer => break,
+            }
+        }
+        self.cancel_working().await;
+        Ok(true)
+    }
+
+    async fn wait(
+        &mut self,
+        child_events: &mut mpsc::UnboundedReceiver<ChildEvent>,
+        timeout: Duration,
+    ) -> Wake {
+        // `Duration::MAX` would overflow the timer; a day is "forever" here
+        let timeout = timeout.min(Duration::from_secs(86_400));
+        tokio::select! {
+            Some(event) = child_events.recv() => Wake::Child(event),
+            _ = self.cancel.notified() => Wake::Cancelled,
+            _ = tokio::time::sleep(timeout) => Wake::Timer,
+        }
+    }
+
+    async fn place_child(
+        &mut self,
+        quantity: i32,
+        child_tx: &mpsc::UnboundedSender<ChildEvent>,
+    ) -> bool {
+        let client = self.engine.client.clone();
+        let contract = self.parent.contract.clone();
+        let result = match self.parent.order.with_quantity(quantity) {
+            OrderTemplate::Stock(order) => client.place_order(contract, order).await,
+            OrderTemplate::Futures(order) => client
+                .place_futures_order(contract, order)
+                .await
+                .map(|trade| trade.as_trade()),
+        };
+
+        match result {
+            Ok(trade) => {
+                let order_id = trade.order_id.clone();
+                if let Ok(mut router) = self.engine.router.lock() {
+                    router.register(&order_id, child_tx);
+                }
+                let working = !matches!(trade.status, Status::Failed | Status::Cancelled);
+                self.children.insert(
+                    order_id.clone(),
+                    Child {
+                        trade,
+                        quantity,
+                        filled: 0,
+                        working,
+                        cancelling: false,
+                    },
+                );
+                log::info!(
+                    "📤 Execution {} child {} placed: {} lots",
+                    self.id,
+                    order_id,
+                    quantity
+                );
+                self.emit(ExecutionEvent::ChildPlaced {
+                    id: self.id.clone(),
+                    order_id,
+                    quantity,
+                });
+                true
+            }
+            Err(e) => {
+                log::error!("❌ Execution {} child order failed: {}", self.id, e);
+                self.emit(ExecutionEvent::ChildFailed {
+                    id: self.id.clone(),
+                    quantity,
+                    error: e.to_string(),
+                });
+                false
+            }
+        }
+    }
+
+    /// Apply a child event; returns the broker message when it rejected a
+    /// child that was still working
+    fn on_child_event(&mut self, event: ChildEvent) -> Option<String> {
+        match event {
+            ChildEvent::Deal(deal) => {
+                self.on_deal(deal);
+                None
+            }
+            ChildEvent::Closed {
+                order_id,
+                rejected,
+                message,
+            } => {
+                let child = self.children.get_mut(&order_id)?;
+                if !child.working {
+                    return None;
+                }
+                child.working = false;
+                let unfilled = (child.quantity - child.filled).max(0);
+                if rejected {
+                    log::error!(
+                        "❌ Execution {} child {} rejected: {}",
+                        self.id,
+                        order_id,
+                        message
+                    );
+                    self.emit(ExecutionEvent::ChildFailed {
+                        id: self.id.clone(),
+                        quantity: unfilled,
+                        error: message.clone(),
+                    });
+                } else {
+                    log::info!(
+                        "🚫 Execution {} child {} cancelled with {} lots unfilled",
+                        self.id,
+                        order_id,
+                        unfilled
+                    );
+                }
+                self.emit_progress();
+                rejected.then_some(message)
+            }
+        }
+    }
+
+    fn on_deal(&mut self, deal: Deal) {
+        let Some(child) = self.children.get_mut(&deal.trade_id) else {
+            return;
+        };
+        child.filled += deal.quantity;
+        if child.filled >= child.quantity {
+            child.working = false;
+        }
+        self.filled += deal.quantity;
+        self.notional += deal.price * deal.quantity as f64;
+
+        self.emit(ExecutionEvent::Fill {
+            id: self.id.clone(),
+            order_id: deal.trade_id,
+            price: deal.price,
+            quantity: deal.quantity,
+        });
+        self.emit_progress();
+    }
+
+    /// Apply the child events already received without waiting for more
+    fn drain(&mut self, child_events: &mut mpsc::UnboundedReceiver<ChildEvent>) {
+        while let Ok(event) = child_events.try_recv() {
+            self.on_child_event(event);
+        }
+    }
+
+    /// Request a cancel for every child that still has unfilled quantity.
+    /// Children stay working until the broker confirms the cancel, and
+    /// children whose cancel request fails are kept as working so they are
+    /// not re-sent.
+    async fn cancel_working(&mut self) {
+        let client = self.engine.client.clone();
+        for (order_id, child) in self
+            .children
+            .iter_mut()
+            .filter(|(_, c)| c.working && !c.cancelling)
+        {
+            match client.cancel_order(child.trade.clone(), None).await {
+                Ok(_) => child.cancelling = true,
+                Err(e) => log::warn!(
+                    "⚠️ Execution {} failed to cancel child {}: {}",
+                    self.id,
+                    order_id,
+                    e
+                ),
+            }
+        }
+    }
+
+    fn working(&self) -> i32 {
+        self.children
+            .values()
+            .filter(|c| c.working)
+            .map(|c| (c.quantity - c.filled).max(0))
+            .sum()
+    }
+
+    fn progress(&self) -> ExecutionProgress {
+        ExecutionProgress {
+            id: self.id.clone(),
+            total: self.parent.order.quantity(),
+            filled: self.filled,
+            working: self.working(),
+            avg_price: if self.filled > 0 {
+                self.notional / self.filled as f64
+            } else {
+                0.0
+            },
+        }
+    }
+
+    fn emit_progress(&self) {
+        self.emit(ExecutionEvent::Progress(self.progress()));
+    }
+
+    fn emit(&self, event: ExecutionEvent) {
+        // The handle may have been dropped; the algorithm keeps running
+        let _ = self.events.send(event);
+    }
+}
+
+fn taipei(ts: DateTime<Utc>) -> DateTime<FixedOffset> {
+    ts.with_timezone(&FixedOffset::east_opt(TAIPEI_OFFSET_SECS).expect("valid offset"))
+}
+
+fn minute_of_day(ts: DateTime<Utc>) -> u32 {
+    let local = taipei(ts);
+    local.hour() * 60 + local.minute()
+}
+
+fn minutes_between(start: DateTime<Utc>, end: DateTime<Utc>) -> u32 {
+    ((end - start).num_seconds().max(0) as u32).div_ceil(60)
+}
+
+fn linear_fraction(start: DateTime<Utc>, end: DateTime<Utc>, at: DateTime<Utc>) -> f64 {
+    let span = (end - start).num_milliseconds() as f64;
+    if span <= 0.0 {
+        return 1.0;
+    }
+    ((at - start).num_milliseconds() as f64 / span).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn taipei_time(hour: u32, minute: u32) -> DateTime<Utc> {
+        FixedOffset::east_opt(TAIPEI_OFFSET_SECS)
+            .unwrap()
+            .with_ymd_and_hms(2024, 1, 2, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn kbar(ts: DateTime<Utc>, volume: i64) -> Kbar {
+        Kbar {
+            ts,
+            open: 100.0,
+            high: 100.0,
+            low: 100.0,
+            close: 100.0,
+            volume,
+            amount: 100.0 * volume as f64,
+        }
+    }
+
+    #[test]
+    fn test_twap_plan_is_even_and_sums_to_total() {
+        let start = taipei_time(9, 0);
+        let schedule = Schedule::Twap {
+            start,
+            end: taipei_time(10, 0),
+            slices: 4,
+        };
+
+        let plan = schedule.plan(10);
+        assert_eq!(plan.len(), 4);
+        assert_eq!(plan[0].at, start);
+        assert_eq!(plan[1].at, taipei_time(9, 15));
+        let cumulative: Vec<i32> = plan.iter().map(|s| s.cumulative).collect();
+        assert_eq!(cumulative, vec![3, 5, 8, 10]);
+    }
+
+    #[test]
+    fn test_vwap_plan_follows_volume_profile() {
+        // Heavy first half hour, light second
+        let mut kbars = Vec::new();
+        for minute in 0..30 {
+            kbars.push(kbar(taipei_time(9, minute), 300));
+        }
+        for minute in 30..60 {
+            kbars.push(kbar(taipei_time(9, minute), 100));
+        }
+        let profile = VolumeProfile::from_kbars(&kbars);
+        let schedule = Schedule::Vwap {
+            start: taipei_time(9, 0),
+            end: taipei_time(10, 0),
+            slices: 2,
+            profile,
+        };
+
+        let plan = schedule.plan(100);
+        assert_eq!(plan[0].cumulative, 75);
+        assert_eq!(plan[1].cumulative, 100);
+    }
+
+    #[test]
+    fn test_volume_profile_falls_back_to_linear() {
+        let profile = VolumeProfile::default();
+        let start = taipei_time(9, 0);
+        let end = taipei_time(10, 0);
+        let fraction = profile.cumulative_fraction(start, end, taipei_time(9, 30));
+        assert!((fraction - 0.5).abs() < 1e-9);
+        assert_eq!(profile.cumulative_fraction(start, end, end), 1.0);
+    }
+
+    #[test]
+    fn test_schedule_validation() {
+        let start = taipei_time(9, 0);
+        assert!(Schedule::Twap {
+            start,
+            end: start,
+            slices: 3
+        }
+        .validate()
+        .is_err());
+        assert!(Schedule::Iceberg {
+            visible_quantity: 0
+        }
+        .validate()
+        .is_err());
+        assert!(Schedule::Iceberg {
+            visible_quantity: 5
+        }
+        .validate()
+        .is_ok());
+        assert!(Schedule::Iceberg {
+            visible_quantity: 5
+        }
+        .plan(100)
+        .is_empty());
+    }
+
+    fn deal(trade_id: &str, quantity: i32) -> Deal {
+        Deal {
+            trade_id: trade_id.to_string(),
+            seqno: String::new(),
+            ordno: String::new(),
+            exchange_seq: String::new(),
+            broker_id: String::new(),
+            account_id: String::new(),
+            action: crate::types::Action::Buy,
+            code: "2330".to_string(),
+            price: 500.0,
+            quantity,
+            order_lot: crate::types::StockOrderLot::Common,
+            security_type: crate::types::SecurityType::Stock,
+            ts: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_fill_router_buffers_early_deals() {
+        let mut router = FillRouter::default();
+        router.dispatch(ChildEvent::Deal(deal("abc", 2)));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        router.register("abc", &tx);
+        match rx.try_recv().unwrap() {
+            ChildEvent::Deal(deal) => assert_eq!(deal.quantity, 2),
+            other => panic!("expected a deal, got {:?}", other),
+        }
+        assert!(router.unmatched.is_empty());
+    }
+
+    #[test]
+    fn test_child_event_closes_on_reject_and_cancel() {
+        let order_event = |op_type: &str, op_code: &str| {
+            let msg = serde_json::json!({
+                "operation": {"op_type": op_type, "op_code": op_code, "op_msg": "價格超過漲跌停"},
+                "order": {"id": "abc"},
+            });
+            ChildEvent::from_event(&OrderEventType::StockOrder, &msg)
+        };
+
+        match order_event("New", "88") {
+            Some(ChildEvent::Closed {
+                order_id,
+                rejected: true,
+                message,
+            }) => {
+                assert_eq!(order_id, "abc");
+                assert_eq!(message, "價格超過漲跌停");
+            }
+            other => panic!("expected a rejection, got {:?}", other),
+        }
+        // IOC remainder or manual cancel
+        assert!(matches!(
+            order_event("Cancel", "00"),
+            Some(ChildEvent::Closed {
+                rejected: false,
+                ..
+            })
+        ));
+        // Accepted orders and failed cancels leave the child working
+        assert!(order_event("New", "00").is_none());
+        assert!(order_event("Cancel", "88").is_none());
+        assert!(order_event("UpdatePrice", "00").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_child_counts_until_confirmed() {
+        use crate::paper::{PaperBroker, PaperConfig};
+        use crate::types::{Action, Exchange, Order, OrderType, Stock, StockPriceType};
+
+        let client = Shioaji::new(true, Default::default())
+            .unwrap()
+            .with_backend(Arc::new(PaperBroker::new(PaperConfig::default())));
+        client.init().await.unwrap();
+        client.login_simple("key", "secret", true).await.unwrap();
+        let engine = ExecutionEngine::new(Arc::new(client)).await.unwrap();
+
+        // A resting limit order: no ticks, so the paper broker never fills it
+        let order = Order::new(Action::Buy, 500.0, 4, OrderType::ROD, StockPriceType::LMT);
+        let (events, _events) = mpsc::unbounded_channel();
+        let mut run = AlgoRun {
+            engine,
+            id: "algo".to_string(),
+            parent: ParentOrder::new(
+                Stock::new("2330", Exchange::TSE).contract,
+                OrderTemplate::Stock(order),
+            ),
+            events,
+            cancel: Arc::new(Notify::new()),
+            children: HashMap::new(),
+            filled: 0,
+            notional: 0.0,
+        };
+        let (child_tx, mut child_events) = mpsc::unbounded_channel();
+        assert!(run.place_child(4, &child_tx).await);
+        let order_id = run.children.keys().next().unwrap().clone();
+        run.drain(&mut child_events);
+
+        // The cancel request returned Ok, but the child may still fill
+        run.cancel_working().await;
+        assert_eq!(run.working(), 4);
+
+        // A deal that lands after the accepted cancel, before its confirmation
+        let confirmation = child_events.try_recv().unwrap();
+        assert!(matches!(
+            confirmation,
+            ChildEvent::Closed {
+                rejected: false,
+                ..
+            }
+        ));
+        child_tx.send(ChildEvent::Deal(deal(&order_id, 3))).unwrap();
+        child_tx.send(confirmation).unwrap();
+
+        run.drain(&mut child_events);
+        assert_eq!(run.filled, 3);
+        assert_eq!(run.working(), 0);
+        assert_eq!(run.progress().remaining(), 1);
+    }
+}
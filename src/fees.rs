@@ -0,0 +1,486 @@
+//! Taiwan fee and tax calculator (手續費與交易稅)
+//!
+//! - 股票手續費: 成交金額 × 0.1425% × 折扣，無條件捨去，有最低手續費
+//! - 證券交易稅: 賣出時課徵，一般 0.3%、現股當沖 0.15%、ETF 0.1%，無條件捨去
+//! - 期貨交易稅: 契約金額 × 十萬分之二，買賣雙方皆課，四捨五入
+//! - 選擇權交易稅: 權利金 × 千分之一，買賣雙方皆課，四捨五入
+//! - 期貨/選擇權手續費: 每口固定金額，依券商而定
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Action, Deal, FuturesTrade, SecurityType, StockOrderLot, Trade};
+
+/// Stock transaction tax category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StockTaxCategory {
+    /// 一般現股 0.3%
+    Normal,
+    /// 現股當沖 0.15%
+    DayTrade,
+    /// ETF 0.1%
+    Etf,
+}
+
+impl StockTaxCategory {
+    /// ETF codes start with `00` (0050, 00878, 00631L ...)
+    pub fn for_code(code: &str, day_trade: bool) -> Self {
+        if code.starts_with("00") {
+            StockTaxCategory::Etf
+        } else if day_trade {
+            StockTaxCategory::DayTrade
+        } else {
+            StockTaxCategory::Normal
+        }
+    }
+}
+
+/// Commission and tax of one fill or order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Fees {
+    pub commission: f64,
+    pub tax: f64,
+}
+
+impl Fees {
+    pub fn total(&self) -> f64 {
+        self.commission + self.tax
+    }
+}
+
+impl std::ops::Add for Fees {
+    type Output = Fees;
+
+    fn add(self, other: Fees) -> Fees {
+        Fees {
+            commission: self.commission + other.commission,
+            tax: self.tax + other.tax,
+        }
+    }
+}
+
+/// Broker commission and tax rates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// 股票手續費率，法定上限 0.1425%
+    pub commission_rate: f64,
+    /// 手續費折扣，例如 0.28 代表 2.8 折
+    pub commission_discount: f64,
+    /// 最低手續費 (整股)
+    pub min_commission: f64,
+    /// 盤中零股/零股最低手續費
+    pub odd_lot_min_commission: f64,
+    pub stock_tax_rate: f64,
+    pub day_trade_tax_rate: f64,
+    pub etf_tax_rate: f64,
+    pub futures_tax_rate: f64,
+    pub options_tax_rate: f64,
+    /// 期貨每口手續費 (單邊)
+    pub futures_fee_per_contract: f64,
+    /// 選擇權每口手續費 (單邊)
+    pub options_fee_per_contract: f64,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self {
+            commission_rate: 0.001425,
+            commission_discount: 1.0,
+            min_commission: 20.0,
+            odd_lot_min_commission: 1.0,
+            stock_tax_rate: 0.003,
+            day_trade_tax_rate: 0.0015,
+            etf_tax_rate: 0.001,
+            futures_tax_rate: 0.00002,
+            options_tax_rate: 0.001,
+            futures_fee_per_contract: 0.0,
+            options_fee_per_contract: 0.0,
+        }
+    }
+}
+
+impl FeeSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_discount(mut self, discount: f64) -> Self {
+        self.commission_discount = discount;
+        self
+    }
+
+    pub fn with_min_commission(mut self, min_commission: f64) -> Self {
+        self.min_commission = min_commission;
+        self
+    }
+
+    pub fn with_futures_fee(mut self, per_contract: f64) -> Self {
+        self.futures_fee_per_contract = per_contract;
+        self
+    }
+
+    pub fn with_options_fee(mut self, per_contract: f64) -> Self {
+        self.options_fee_per_contract = per_contract;
+        self
+    }
+
+    /// 股票手續費 (無條件捨去，不低於最低手續費)
+    pub fn commission(&self, amount: f64, odd_lot: bool) -> f64 {
+        if amount <= 0.0 {
+            return 0.0;
+        }
+        let minimum = if odd_lot {
+            self.odd_lot_min_commission
+        } else {
+            self.min_commission
+        };
+        (amount * self.commission_rate * self.commission_discount)
+            .floor()
+            .max(minimum)
+    }
+
+    /// 證券交易稅 (無條件捨去)
+    pub fn stock_tax(&self, amount: f64, category: StockTaxCategory) -> f64 {
+        let rate = match category {
+            StockTaxCategory::Normal => self.stock_tax_rate,
+            StockTaxCategory::DayTrade => self.day_trade_tax_rate,
+            StockTaxCategory::Etf => self.etf_tax_rate,
+        };
+        (amount * rate).floor()
+    }
+
+    /// Fees of a stock fill of `shares` shares; tax is only charged on sells
+    ///
+    /// The minimum commission follows `lot`: 零股 (`Odd` / `IntradayOdd`)
+    /// use `odd_lot_min_commission` whatever the number of shares.
+    pub fn stock(
+        &self,
+        action: Action,
+        price: f64,
+        shares: f64,
+        lot: &StockOrderLot,
+        category: StockTaxCategory,
+    ) -> Fees {
+        let amount = price * shares;
+        Fees {
+            commission: self.commission(amount, lot.is_odd()),
+            tax: match action {
+                Action::Buy => 0.0,
+                Action::Sell => self.stock_tax(amount, category),
+            },
+        }
+    }
+
+    /// Fees of a futures fill; `multiplier` is the value of one point
+    pub fn futures(&self, price: f64, multiplier: f64, contracts: i32) -> Fees {
+        let contracts = contracts.abs() as f64;
+        Fees {
+            commission: self.futures_fee_per_contract * contracts,
+            tax: (price * multiplier * contracts * self.futures_tax_rate).round(),
+        }
+    }
+
+    /// Fees of an options fill; `premium` is the option price in points
+    pub fn options(&self, premium: f64, multiplier: f64, contracts: i32) -> Fees {
+        let contracts = contracts.abs() as f64;
+        Fees {
+            commission: self.options_fee_per_contract * contracts,
+            tax: (premium * multiplier * contracts * self.options_tax_rate).round(),
+        }
+    }
+
+    /// Fees of a deal
+    ///
+    /// `multiplier` is the contract size: shares per lot (`Contract.unit`)
+    /// for stocks and the point value for futures/options. Odd-lot stock deals
+    /// are already in shares, as told by `deal.order_lot`.
+    pub fn for_deal(&self, deal: &Deal, multiplier: f64, day_trade: bool) -> Fees {
+        match deal.security_type {
+            SecurityType::Stock | SecurityType::Index => {
+                let shares_per_unit = if deal.order_lot.is_odd() {
+                    1.0
+                } else {
+                    multiplier
+                };
+                self.stock(
+                    deal.action.clone(),
+                    deal.price,
+                    deal.quantity as f64 * shares_per_unit,
+                    &deal.order_lot,
+                    StockTaxCategory::for_code(&deal.code, day_trade),
+                )
+            }
+            SecurityType::Future => self.futures(deal.price, multiplier, deal.quantity),
+            SecurityType::Option => self.options(deal.price, multiplier, deal.quantity),
+        }
+    }
+
+    /// Estimated fees of a stock trade if fully filled at its order price
+    pub fn for_trade(&self, trade: &Trade, day_trade: bool) -> Fees {
+        let code = trade
+            .contracts
+            .first()
+            .map(|c| c.base.code.as_str())
+            .unwrap_or_default();
+        let lot = trade.order.order_lot.clone().unwrap_or_default();
+        let shares_per_unit = match lot {
+            StockOrderLot::Odd | StockOrderLot::IntradayOdd => 1.0,
+            _ => trade
+                .contracts
+                .first()
+                .map(|c| c.unit)
+                .filter(|unit| *unit > 0.0)
+                .unwrap_or(1000.0),
+        };
+        self.stock(
+            trade.order.action.clone(),
+            trade.order.price,
+            trade.order.quantity as f64 * shares_per_unit,
+            &lot,
+            StockTaxCategory::for_code(code, day_trade),
+        )
+    }
+
+    /// Estimated fees of a futures/options trade if fully filled at its order price
+    pub fn for_futures_trade(&self, trade: &FuturesTrade) -> Fees {
+        let contract = trade.contracts.first();
+        let multiplier = contract.map(|c| c.multiplier.max(1) as f64).unwrap_or(1.0);
+        match contract.map(|c| &c.base.security_type) {
+            Some(SecurityType::Option) => {
+                self.options(trade.order.price, multiplier, trade.order.quantity)
+            }
+            _ => self.futures(trade.order.price, multiplier, trade.order.quantity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        Account, AccountType, Contract, Exchange, Future, FuturesOCType, FuturesOrder,
+        FuturesPriceType, OptionContract, OptionRight, Order, OrderType, Status, Stock,
+        StockPriceType,
+    };
+
+    #[test]
+    fn test_stock_commission_and_tax() {
+        let schedule = FeeSchedule::new().with_discount(0.6);
+
+        // 2330 買 1 張 @ 600: 600000 × 0.1425% × 0.6 = 513
+        let buy = schedule.stock(
+            Action::Buy,
+            600.0,
+            1000.0,
+            &StockOrderLot::Common,
+            StockTaxCategory::Normal,
+        );
+        assert_eq!(
+            buy,
+            Fees {
+                commission: 513.0,
+                tax: 0.0
+            }
+        );
+
+        let sell = schedule.stock(
+            Action::Sell,
+            600.0,
+            1000.0,
+            &StockOrderLot::Common,
+            StockTaxCategory::Normal,
+        );
+        assert_eq!(sell.tax, 1800.0);
+        let sell = schedule.stock(
+            Action::Sell,
+            600.0,
+            1000.0,
+            &StockOrderLot::Common,
+            StockTaxCategory::DayTrade,
+        );
+        assert_eq!(sell.tax, 900.0);
+        let sell = schedule.stock(
+            Action::Sell,
+            150.0,
+            1000.0,
+            &StockOrderLot::Common,
+            StockTaxCategory::for_code("0050", true),
+        );
+        assert_eq!(sell.tax, 150.0);
+
+        // 小額成交適用最低手續費
+        let small = schedule.stock(
+            Action::Buy,
+            10.0,
+            1000.0,
+            &StockOrderLot::Common,
+            StockTaxCategory::Normal,
+        );
+        assert_eq!(small.commission, 20.0);
+        let odd = schedule.stock(
+            Action::Buy,
+            10.0,
+            5.0,
+            &StockOrderLot::Odd,
+            StockTaxCategory::Normal,
+        );
+        assert_eq!(odd.commission, 1.0);
+    }
+
+    #[test]
+    fn test_futures_and_options_fees() {
+        let schedule = FeeSchedule::new()
+            .with_futures_fee(50.0)
+            .with_options_fee(20.0);
+
+        // TXF 17000 × 200 × 2 × 0.00002 = 136
+        let fut = schedule.futures(17000.0, 200.0, 2);
+        assert_eq!(
+            fut,
+            Fees {
+                commission: 100.0,
+                tax: 136.0
+            }
+        );
+
+        // TXO 權利金 85 × 50 × 0.001 = 4.25 → 4
+        let opt = schedule.options(85.0, 50.0, 1);
+        assert_eq!(
+            opt,
+            Fees {
+                commission: 20.0,
+                tax: 4.0
+            }
+        );
+        assert_eq!(opt.total(), 24.0);
+    }
+
+    fn account() -> Account {
+        Account::new(
+            "9A95".to_string(),
+            "0000001".to_string(),
+            AccountType::Stock,
+            "user".to_string(),
+            true,
+        )
+    }
+
+    fn stock_trade(price: f64, quantity: i32, lot: StockOrderLot) -> Trade {
+        Trade {
+            order: Order::new(
+                Action::Buy,
+                price,
+                quantity,
+                OrderType::ROD,
+                StockPriceType::LMT,
+            )
+            .with_order_lot(lot),
+            status: Status::Submitted,
+            order_id: String::new(),
+            seqno: String::new(),
+            ordno: String::new(),
+            account: account(),
+            contracts: vec![Stock::new("2330", Exchange::TSE).contract],
+        }
+    }
+
+    fn futures_trade(contract: Contract, price: f64, quantity: i32) -> FuturesTrade {
+        FuturesTrade {
+            order: FuturesOrder::new(
+                Action::Sell,
+                price,
+                quantity,
+                OrderType::ROD,
+                FuturesPriceType::LMT,
+                FuturesOCType::Auto,
+            ),
+            status: Status::Submitted,
+            order_id: String::new(),
+            seqno: String::new(),
+            ordno: String::new(),
+            account: account(),
+            contracts: vec![contract],
+        }
+    }
+
+    fn stock_deal(price: f64, quantity: i32, lot: StockOrderLot) -> Deal {
+        Deal {
+            trade_id: String::new(),
+            seqno: String::new(),
+            ordno: String::new(),
+            exchange_seq: String::new(),
+            broker_id: String::new(),
+            account_id: String::new(),
+            action: Action::Sell,
+            code: "2330".to_string(),
+            price,
+            quantity,
+            order_lot: lot,
+            security_type: SecurityType::Stock,
+            ts: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_for_trade_uses_order_lot() {
+        let schedule = FeeSchedule::new().with_discount(0.6);
+
+        // 2 張 @ 600 → 1,200,000 × 0.1425% × 0.6 = 1026
+        let common = schedule.for_trade(&stock_trade(600.0, 2, StockOrderLot::Common), false);
+        assert_eq!(common.commission, 1026.0);
+
+        // 盤中零股 5 股 @ 100：數量為股，適用零股最低手續費
+        let odd = schedule.for_trade(&stock_trade(100.0, 5, StockOrderLot::IntradayOdd), false);
+        assert_eq!(odd.commission, 1.0);
+
+        // 整股小額仍適用整股最低手續費
+        let small = schedule.for_trade(&stock_trade(10.0, 1, StockOrderLot::Common), false);
+        assert_eq!(small.commission, 20.0);
+    }
+
+    #[test]
+    fn test_for_futures_trade() {
+        let schedule = FeeSchedule::new()
+            .with_futures_fee(50.0)
+            .with_options_fee(20.0);
+
+        let fut =
+            schedule.for_futures_trade(&futures_trade(Future::new("TXFA4").contract, 17000.0, 2));
+        assert_eq!(
+            fut,
+            Fees {
+                commission: 100.0,
+                tax: 136.0
+            }
+        );
+
+        let call = OptionContract::new("TXO17900A4", OptionRight::Call, 17900.0).contract;
+        let opt = schedule.for_futures_trade(&futures_trade(call, 85.0, 1));
+        assert_eq!(
+            opt,
+            Fees {
+                commission: 20.0,
+                tax: 4.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_for_deal_minimum_follows_lot() {
+        let schedule = FeeSchedule::new();
+
+        // 零股成交 5 股 @ 100，即使合約單位為 1000 股，也只收零股最低手續費
+        let odd = schedule.for_deal(&stock_deal(100.0, 5, StockOrderLot::Odd), 1000.0, false);
+        assert_eq!(odd.commission, 1.0);
+        assert_eq!(odd.tax, 1.0);
+
+        // 整股成交 1 張 @ 10：成交 10000，手續費 14 → 最低 20
+        let common = schedule.for_deal(&stock_deal(10.0, 1, StockOrderLot::Common), 1000.0, false);
+        assert_eq!(common.commission, 20.0);
+        assert_eq!(common.tax, 30.0);
+
+        let mut fut = stock_deal(17000.0, 1, StockOrderLot::Common);
+        fut.security_type = SecurityType::Future;
+        fut.code = "TXFA4".to_string();
+        assert_eq!(schedule.for_deal(&fut, 200.0, false).tax, 68.0);
+    }
+}
@@ -0,0 +1,434 @@
+//! Futures roll calendar and continuous contracts (期貨轉倉與連續月)
+//!
+//! TAIFEX monthly futures settle on the third Wednesday of the contract month;
+//! when that day is a holiday settlement moves to the next business day.
+//! Trading in the expiring month ends on the settlement day, so the front
+//! month (`R1`) switches to the next month the day after.
+//!
+//! ```no_run
+//! # use rshioaji::futures::{Adjustment, RollCalendar};
+//! # async fn run(client: &rshioaji::Shioaji) -> rshioaji::Result<()> {
+//! let calendar = RollCalendar::new();
+//! let contracts = client.get_contracts().await.unwrap_or_default();
+//! let kbars = calendar
+//!     .continuous_kbars(client, &contracts, "TXF", "2024-01-01", "2024-06-30", Adjustment::Ratio)
+//!     .await?;
+//! println!("{} bars", kbars.data.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::client::Shioaji;
+use crate::error::{Error, Result};
+use crate::types::{Contract, Contracts, Kbar, Kbars};
+
+/// TAIFEX 月份代碼 A (1 月) ~ L (12 月)
+const MONTH_LETTERS: &[u8; 12] = b"ABCDEFGHIJKL";
+
+/// Back-adjustment applied to earlier contracts when stitching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Adjustment {
+    /// Raw prices, gaps at every roll
+    None,
+    /// Shift earlier bars by the price difference at the roll
+    Difference,
+    /// Scale earlier bars by the price ratio at the roll
+    Ratio,
+}
+
+/// One contract's span in a continuous series
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollSegment {
+    pub contract: Contract,
+    /// First trading day held (inclusive)
+    pub start: NaiveDate,
+    /// Last trading day held (inclusive)
+    pub end: NaiveDate,
+}
+
+/// Settlement and roll rules with a holiday calendar
+#[derive(Debug, Clone, Default)]
+pub struct RollCalendar {
+    holidays: BTreeSet<NaiveDate>,
+    /// Roll this many business days before settlement
+    roll_offset: u32,
+}
+
+impl RollCalendar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_holidays<I: IntoIterator<Item = NaiveDate>>(mut self, holidays: I) -> Self {
+        self.holidays.extend(holidays);
+        self
+    }
+
+    /// Roll to the next month `days` business days before settlement
+    pub fn with_roll_offset(mut self, days: u32) -> Self {
+        self.roll_offset = days;
+        self
+    }
+
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.holidays.contains(&date)
+    }
+
+    /// `date` itself if it is a business day, otherwise the next one
+    pub fn next_business_day(&self, mut date: NaiveDate) -> NaiveDate {
+        while !self.is_business_day(date) {
+            date += Duration::days(1);
+        }
+        date
+    }
+
+    fn previous_business_days(&self, mut date: NaiveDate, days: u32) -> NaiveDate {
+        for _ in 0..days {
+            date -= Duration::days(1);
+            while !self.is_business_day(date) {
+                date -= Duration::days(1);
+            }
+        }
+        date
+    }
+
+    /// 最後結算日：第三個星期三，遇假日順延
+    pub fn settlement_date(&self, year: i32, month: u32) -> NaiveDate {
+        let third_wednesday = NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Wed, 3)
+            .expect("every month has a third Wednesday");
+        self.next_business_day(third_wednesday)
+    }
+
+    /// Last day `(year, month)` is held in a continuous series
+    fn roll_date(&self, year: i32, month: u32) -> NaiveDate {
+        self.previous_business_days(self.settlement_date(year, month), self.roll_offset)
+    }
+
+    /// Contract month `n` months after the front month on `date` (`n = 0` is R1)
+    pub fn contract_month(&self, date: NaiveDate, n: u32) -> (i32, u32) {
+        let (mut year, mut month) = (date.year(), date.month());
+        if date > self.roll_date(year, month) {
+            (year, month) = next_month(year, month);
+        }
+        for _ in 0..n {
+            (year, month) = next_month(year, month);
+        }
+        (year, month)
+    }
+
+    /// 近月 (R1) contract month
+    pub fn front_month(&self, date: NaiveDate) -> (i32, u32) {
+        self.contract_month(date, 0)
+    }
+
+    /// 次月 (R2) contract month
+    pub fn next_month(&self, date: NaiveDate) -> (i32, u32) {
+        self.contract_month(date, 1)
+    }
+
+    /// Resolve `TXFR1`/`TXFR2` (or a concrete code such as `TXFA4`) to the
+    /// concrete contract in `contracts.futures` on `date`
+    pub fn resolve(&self, contracts: &Contracts, code: &str, date: NaiveDate) -> Result<Contract> {
+        let code = code.trim().to_uppercase();
+        let (product, n) = match code.len().checked_sub(2).map(|i| code.split_at(i)) {
+            Some((product, "R1")) => (product.to_string(), 0),
+            Some((product, "R2")) => (product.to_string(), 1),
+            _ => {
+                return contracts.futures.get(&code).cloned().ok_or_else(|| {
+                    Error::InvalidContract(format!("Futures contract {} not found", code))
+                })
+            }
+        };
+        let (year, month) = self.contract_month(date, n);
+        find_month(contracts, &product, year, month)
+    }
+
+    /// Contracts and date spans making up a continuous series of `product`
+    pub fn roll_schedule(
+        &self,
+        contracts: &Contracts,
+        product: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<RollSegment>> {
+        if end < start {
+            return Err(Error::InvalidInput(format!(
+                "Roll schedule end {} is before start {}",
+                end, start
+            )));
+        }
+        let product = product.trim().to_uppercase();
+        let mut segments = Vec::new();
+        let mut from = start;
+        while from <= end {
+            let (year, month) = self.front_month(from);
+            let until = self.roll_date(year, month).min(end);
+            segments.push(RollSegment {
+                contract: find_month(contracts, &product, year, month)?,
+                start: from,
+                end: until,
+            });
+            from = until + Duration::days(1);
+        }
+        Ok(segments)
+    }
+
+    /// Fetch K bars for every segment and stitch them into one series
+    ///
+    /// Each later contract is also fetched for the previous segment's last day
+    /// so the roll gap is measured on the same bar.
+    pub async fn continuous_kbars(
+        &self,
+        client: &Shioaji,
+        contracts: &Contracts,
+        product: &str,
+        start: &str,
+        end: &str,
+        adjustment: Adjustment,
+    ) -> Result<Kbars> {
+        let segments =
+            self.roll_schedule(contracts, product, parse_date(start)?, parse_date(end)?)?;
+
+        let mut pieces = Vec::with_capacity(segments.len());
+        let mut previous_end: Option<NaiveDate> = None;
+        for segment in &segments {
+            let from = previous_end.unwrap_or(segment.start);
+            let bars = client
+                .get_kbars(
+                    segment.contract.clone(),
+                    &from.format("%Y-%m-%d").to_string(),
+                    &segment.end.format("%Y-%m-%d").to_string(),
+                )
+                .await?;
+            log::info!(
+                "📊 {} K bars for {} ({} ~ {})",
+                bars.len(),
+                segment.contract.base.code,
+                from,
+                segment.end
+            );
+            pieces.push(bars);
+            previous_end = Some(segment.end);
+        }
+
+        let mut contract = segments
+            .last()
+            .map(|s| s.contract.clone())
+            .ok_or_else(|| Error::DataFetch("Empty roll schedule".to_string()))?;
+        contract.base.code = format!("{}R1", product.trim().to_uppercase());
+        Ok(Kbars {
+            contract,
+            data: stitch(pieces, adjustment),
+        })
+    }
+}
+
+/// Stitch consecutive contracts' bars into one back-adjusted series
+///
+/// Each piece may start with bars overlapping the previous piece; the roll gap
+/// is measured between the previous piece's last bar and the newer piece's bar
+/// at (or just before) the same time, and overlapping bars are dropped.
+pub fn stitch(pieces: Vec<Vec<Kbar>>, adjustment: Adjustment) -> Vec<Kbar> {
+    // (bars kept from each piece, gap to the next piece)
+    let mut kept: Vec<Vec<Kbar>> = Vec::with_capacity(pieces.len());
+    let mut gaps: Vec<(f64, f64)> = Vec::new();
+
+    for mut bars in pieces {
+        bars.sort_by_key(|bar| bar.ts);
+        let previous_last = kept.iter().rev().find_map(|piece| piece.last()).cloned();
+        if let Some(last) = previous_last {
+            let reference = bars
+                .iter()
+                .rev()
+                .find(|bar| bar.ts <= last.ts)
+                .map(|bar| bar.close)
+                .or_else(|| bars.first().map(|bar| bar.open));
+            gaps.push(match reference {
+                Some(price) if last.close > 0.0 => (price - last.close, price / last.close),
+                _ => (0.0, 1.0),
+            });
+            bars.retain(|bar| bar.ts > last.ts);
+        }
+        kept.push(bars);
+    }
+
+    // Walk backwards accumulating the adjustment of every later roll
+    let (mut shift, mut scale) = (0.0, 1.0);
+    for index in (0..kept.len()).rev() {
+        if index < gaps.len() {
+            let (difference, ratio) = gaps[index];
+            shift += difference;
+            scale *= ratio;
+        }
+        if index + 1 == kept.len() {
+            continue;
+        }
+        for bar in &mut kept[index] {
+            match adjustment {
+                Adjustment::None => {}
+                Adjustment::Difference => {
+                    bar.open += shift;
+                    bar.high += shift;
+                    bar.low += shift;
+                    bar.close += shift;
+                }
+                Adjustment::Ratio => {
+                    bar.open *= scale;
+                    bar.high *= scale;
+                    bar.low *= scale;
+                    bar.close *= scale;
+                }
+            }
+        }
+    }
+
+    kept.into_iter().flatten().collect()
+}
+
+/// TAIFEX contract code, e.g. `("TXF", 2024, 1)` -> `TXFA4`
+pub fn month_code(product: &str, year: i32, month: u32) -> String {
+    let letter = MONTH_LETTERS[(month.clamp(1, 12) - 1) as usize] as char;
+    format!("{}{}{}", product, letter, year.rem_euclid(10))
+}
+
+fn next_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    }
+}
+
+fn find_month(contracts: &Contracts, product: &str, year: i32, month: u32) -> Result<Contract> {
+    let code = month_code(product, year, month);
+    if let Some(contract) = contracts.futures.get(&code) {
+        return Ok(contract.clone());
+    }
+    // 合約代碼格式不同時，以商品類別與交割月份比對
+    let delivery_month = format!("{:04}{:02}", year, month);
+    contracts
+        .futures
+        .values()
+        .find(|c| {
+            (c.category == product || c.base.code.starts_with(product))
+                && c.delivery_month == delivery_month
+                && !c.base.code.ends_with("R1")
+                && !c.base.code.ends_with("R2")
+        })
+        .cloned()
+        .ok_or_else(|| {
+            Error::InvalidContract(format!(
+                "No {} futures for {} ({})",
+                product, delivery_month, code
+            ))
+        })
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|e| Error::InvalidInput(format!("Invalid date {}: {}", value, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Future;
+    use chrono::{TimeZone, Utc};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn bar(day: u32, close: f64) -> Kbar {
+        Kbar {
+            ts: Utc.with_ymd_and_hms(2024, 1, day, 5, 0, 0).unwrap(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1,
+            amount: close,
+        }
+    }
+
+    #[test]
+    fn test_settlement_and_front_month() {
+        let calendar = RollCalendar::new().with_holidays([date(2024, 2, 21)]);
+        assert_eq!(calendar.settlement_date(2024, 1), date(2024, 1, 17));
+        assert_eq!(calendar.settlement_date(2024, 2), date(2024, 2, 22));
+
+        assert_eq!(calendar.front_month(date(2024, 1, 17)), (2024, 1));
+        assert_eq!(calendar.front_month(date(2024, 1, 18)), (2024, 2));
+        assert_eq!(calendar.next_month(date(2024, 12, 31)), (2025, 2));
+
+        let early = calendar.clone().with_roll_offset(1);
+        assert_eq!(early.front_month(date(2024, 1, 17)), (2024, 2));
+        assert_eq!(month_code("TXF", 2024, 1), "TXFA4");
+        assert_eq!(month_code("MXF", 2025, 12), "MXFL5");
+    }
+
+    #[test]
+    fn test_resolve_and_roll_schedule() {
+        let mut contracts = Contracts::new();
+        for code in ["TXFA4", "TXFB4", "TXFC4"] {
+            contracts.add_future(code.to_string(), Future::new(code).contract);
+        }
+        let calendar = RollCalendar::new();
+        let r1 = calendar
+            .resolve(&contracts, "TXFR1", date(2024, 1, 10))
+            .unwrap();
+        assert_eq!(r1.base.code, "TXFA4");
+        let r2 = calendar
+            .resolve(&contracts, "txfr2", date(2024, 1, 18))
+            .unwrap();
+        assert_eq!(r2.base.code, "TXFC4");
+        assert!(calendar
+            .resolve(&contracts, "TXFR1", date(2024, 3, 21))
+            .is_err());
+
+        let segments = calendar
+            .roll_schedule(&contracts, "TXF", date(2024, 1, 2), date(2024, 2, 29))
+            .unwrap();
+        let spans: Vec<_> = segments
+            .iter()
+            .map(|s| (s.contract.base.code.as_str(), s.start, s.end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("TXFA4", date(2024, 1, 2), date(2024, 1, 17)),
+                ("TXFB4", date(2024, 1, 18), date(2024, 2, 21)),
+                ("TXFC4", date(2024, 2, 22), date(2024, 2, 29)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stitch_back_adjustment() {
+        // 舊合約最後一根 100，新合約同時點 110
+        let pieces = || {
+            vec![
+                vec![bar(16, 90.0), bar(17, 100.0)],
+                vec![bar(17, 110.0), bar(18, 120.0)],
+            ]
+        };
+
+        let raw = stitch(pieces(), Adjustment::None);
+        let closes: Vec<f64> = raw.iter().map(|b| b.close).collect();
+        assert_eq!(closes, vec![90.0, 100.0, 120.0]);
+
+        let diff = stitch(pieces(), Adjustment::Difference);
+        let closes: Vec<f64> = diff.iter().map(|b| b.close).collect();
+        assert_eq!(closes, vec![100.0, 110.0, 120.0]);
+
+        let ratio = stitch(pieces(), Adjustment::Ratio);
+        let closes: Vec<f64> = ratio.iter().map(|b| b.close).collect();
+        assert!((closes[0] - 99.0).abs() < 1e-9);
+        assert!((closes[1] - 110.0).abs() < 1e-9);
+        assert_eq!(closes[2], 120.0);
+    }
+}
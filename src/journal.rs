@@ -0,0 +1,679 @@
+//! Append-only order and fill journal (委託/成交日誌)
+//!
+//! Every `place_order`, `place_futures_order`, `update_order`, `cancel_order`
+//! call and every order/deal callback can be appended to a JSONL journal. Each
+//! line is written in one `write` and fsynced before `append` returns, and the
+//! file rotates at Taipei midnight (`journal-YYYY-MM-DD.jsonl`).
+//!
+//! New orders are journaled twice: a [`JournalRecord::OrderIntent`] before
+//! the broker call and the placed trade (or failure) after it, linked by the
+//! intent's sequence number. An intent with no result means the process died
+//! while the order was in flight; [`ReplayState::unresolved_intents`] lists
+//! them so they can be checked against the broker.
+//!
+//! After a crash, [`replay`] reads the journal back and rebuilds the state of
+//! every trade:
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # async fn run(client: rshioaji::Shioaji) -> rshioaji::Result<()> {
+//! let journal = Arc::new(rshioaji::Journal::open("./journal")?);
+//! client.set_journal(journal);
+//!
+//! let state = rshioaji::journal::replay("./journal")?;
+//! for trade in state.open_trades() {
+//!     println!("{} {:?}", trade.order_id, trade.status);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::{Error, Result};
+use crate::types::{Contract, Deal, FuturesTrade, OrderEventType, Status, Trade};
+
+const FILE_PREFIX: &str = "journal-";
+const FILE_SUFFIX: &str = ".jsonl";
+/// 以台北時間 (UTC+8) 換日
+const TAIPEI_OFFSET_SECS: i32 = 8 * 3600;
+
+/// One journaled action or event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalRecord {
+    /// A new order about to be sent to the broker (送單前)
+    OrderIntent {
+        operation: String,
+        contract: Contract,
+        /// The `Order` or `FuturesOrder` as sent
+        order: serde_json::Value,
+    },
+    OrderPlaced {
+        trade: Trade,
+        /// Sequence number of the matching `OrderIntent`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        intent: Option<u64>,
+    },
+    FuturesOrderPlaced {
+        trade: FuturesTrade,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        intent: Option<u64>,
+    },
+    OrderUpdated {
+        order_id: String,
+        price: Option<f64>,
+        quantity: Option<i32>,
+        status: Status,
+    },
+    OrderCancelled {
+        order_id: String,
+        status: Status,
+    },
+    /// Raw order/deal callback (`set_order_callback`) message
+    OrderEvent {
+        event_type: OrderEventType,
+        message: serde_json::Value,
+    },
+    /// An order call that returned an error
+    CallFailed {
+        operation: String,
+        order_id: Option<String>,
+        error: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        intent: Option<u64>,
+    },
+}
+
+/// A journal line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Monotonic sequence number across files
+    pub seq: u64,
+    pub ts: DateTime<Utc>,
+    #[serde(flatten)]
+    pub record: JournalRecord,
+}
+
+struct JournalWriter {
+    date: Option<NaiveDate>,
+    file: Option<File>,
+    seq: u64,
+}
+
+/// Crash-safe, append-only JSONL journal rotated daily
+pub struct Journal {
+    dir: PathBuf,
+    writer: Mutex<JournalWriter>,
+}
+
+impl Journal {
+    /// Open (or create) a journal directory; sequence numbers continue from
+    /// the last entry already on disk
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+
+        let seq = match journal_files(&dir)?.last() {
+            Some(path) => read_entries(path)?.0.last().map(|e| e.seq).unwrap_or(0),
+            None => 0,
+        };
+
+        log::info!("📒 Journal opened at {} (seq {})", dir.display(), seq);
+        Ok(Self {
+            dir,
+            writer: Mutex::new(JournalWriter {
+                date: None,
+                file: None,
+                seq,
+            }),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the journal file for a trading date
+    pub fn file_for(dir: &Path, date: NaiveDate) -> PathBuf {
+        dir.join(format!(
+            "{}{}{}",
+            FILE_PREFIX,
+            date.format("%Y-%m-%d"),
+            FILE_SUFFIX
+        ))
+    }
+
+    /// Append a record and fsync it; returns the entry's sequence number
+    pub fn append(&self, record: JournalRecord) -> Result<u64> {
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| Error::System("Journal lock poisoned".to_string()))?;
+
+        let ts = Utc::now();
+        let date = taipei_date(ts);
+        if writer.date != Some(date) || writer.file.is_none() {
+            writer.file = Some(open_for_append(&Self::file_for(&self.dir, date))?);
+            writer.date = Some(date);
+        }
+
+        let entry = JournalEntry {
+            seq: writer.seq + 1,
+            ts,
+            record,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+
+        let file = writer.file.as_mut().expect("journal file opened above");
+        file.write_all(&line)?;
+        file.sync_data()?;
+        writer.seq = entry.seq;
+        Ok(entry.seq)
+    }
+}
+
+/// Trade state rebuilt from a journal
+#[derive(Debug, Clone, Default)]
+pub struct ReplayState {
+    /// Stock trades by order id
+    pub trades: BTreeMap<String, Trade>,
+    /// Futures/options trades by order id
+    pub futures_trades: BTreeMap<String, FuturesTrade>,
+    /// Filled quantity by order id
+    pub filled: HashMap<String, i32>,
+    pub deals: Vec<Deal>,
+    pub last_seq: u64,
+    /// `OrderIntent` entries by sequence number that never got a result; the
+    /// broker may or may not have the order
+    pub unresolved_intents: BTreeMap<u64, JournalEntry>,
+    /// Lines that could not be parsed (e.g. a write torn by a crash)
+    pub skipped_lines: usize,
+    /// Cancel / reject events journaled before their order's placed line
+    early_closed: HashMap<String, Status>,
+}
+
+impl ReplayState {
+    /// Apply one journal entry
+    pub fn apply(&mut self, entry: &JournalEntry) {
+        self.last_seq = self.last_seq.max(entry.seq);
+        match &entry.record {
+            JournalRecord::OrderIntent { .. } => {
+                self.unresolved_intents.insert(entry.seq, entry.clone());
+            }
+            JournalRecord::OrderPlaced { trade, intent } => {
+                self.resolve_intent(*intent);
+                let mut trade = trade.clone();
+                if let Some(status) = self.seen_status(&trade.order_id, trade.order.quantity) {
+                    trade.status = status;
+                }
+                self.trades.insert(trade.order_id.clone(), trade);
+            }
+            JournalRecord::FuturesOrderPlaced { trade, intent } => {
+                self.resolve_intent(*intent);
+                let mut trade = trade.clone();
+                if let Some(status) = self.seen_status(&trade.order_id, trade.order.quantity) {
+                    trade.status = status;
+                }
+                self.futures_trades.insert(trade.order_id.clone(), trade);
+            }
+            JournalRecord::OrderUpdated {
+                order_id,
+                price,
+                quantity,
+                status,
+            } => {
+                self.amend(order_id, *price, *quantity);
+                self.set_status(order_id, status.clone());
+            }
+            JournalRecord::OrderCancelled { order_id, status } => {
+                self.set_status(order_id, status.clone());
+            }
+            JournalRecord::OrderEvent {
+                event_type,
+                message,
+            } => self.apply_order_event(event_type, message),
+            JournalRecord::CallFailed { intent, .. } => self.resolve_intent(*intent),
+        }
+    }
+
+    fn resolve_intent(&mut self, intent: Option<u64>) {
+        if let Some(seq) = intent {
+            self.unresolved_intents.remove(&seq);
+        }
+    }
+
+    /// Status implied by deals and order events journaled before the order's
+    /// placed line: the result is written after the broker call returns,
+    /// while the callback thread may already have journaled its fills
+    fn seen_status(&mut self, order_id: &str, quantity: i32) -> Option<Status> {
+        let filled = self.filled.get(order_id).copied().unwrap_or(0);
+        let closed = self.early_closed.remove(order_id);
+        if filled > 0 && filled >= quantity {
+            return Some(Status::Filled);
+        }
+        closed.or((filled > 0).then_some(Status::PartFilled))
+    }
+
+    /// Trades (stock and futures, as `Trade`) that are still working
+    pub fn open_trades(&self) -> Vec<Trade> {
+        self.trades
+            .values()
+            .cloned()
+            .chain(self.futures_trades.values().map(|t| t.as_trade()))
+            .filter(|t| t.status.is_open())
+            .collect()
+    }
+
+    fn apply_order_event(&mut self, event_type: &OrderEventType, message: &serde_json::Value) {
+        if let Some(deal) = Deal::from_event(event_type, message) {
+            let filled = self.filled.entry(deal.trade_id.clone()).or_insert(0);
+            *filled += deal.quantity;
+            let filled = *filled;
+
+            let quantity = self
+                .trades
+                .get(&deal.trade_id)
+                .map(|t| t.order.quantity)
+                .or_else(|| {
+                    self.futures_trades
+                        .get(&deal.trade_id)
+                        .map(|t| t.order.quantity)
+                });
+            if let Some(quantity) = quantity {
+                let status = if filled >= quantity {
+                    Status::Filled
+                } else {
+                    Status::PartFilled
+                };
+                self.set_status(&deal.trade_id, status);
+            }
+            self.deals.push(deal);
+            return;
+        }
+
+        // 委託回報：{"operation": {"op_type", "op_code"}, "order": {"id", ...}}
+        let Some(order_id) = message
+            .pointer("/order/id")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+        else {
+            return;
+        };
+        let op_type = message
+            .pointer("/operation/op_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let succeeded = message
+            .pointer("/operation/op_code")
+            .and_then(|v| v.as_str())
+            .is_none_or(|code| code == "00");
+
+        match (op_type, succeeded) {
+            ("Cancel", true) => self.close(order_id, Status::Cancelled),
+            ("New", false) => self.close(order_id, Status::Failed),
+            ("UpdatePrice" | "UpdateQty", true) => {
+                let price = message.pointer("/order/price").and_then(|v| v.as_f64());
+                let quantity = message
+                    .pointer("/order/quantity")
+                    .and_then(|v| v.as_i64())
+                    .map(|q| q as i32);
+                self.amend(&order_id, price, quantity);
+            }
+            _ => {}
+        }
+    }
+
+    fn amend(&mut self, order_id: &str, price: Option<f64>, quantity: Option<i32>) {
+        if let Some(trade) = self.trades.get_mut(order_id) {
+            trade.order.price = price.unwrap_or(trade.order.price);
+            trade.order.quantity = quantity.unwrap_or(trade.order.quantity);
+        }
+        if let Some(trade) = self.futures_trades.get_mut(order_id) {
+            trade.order.price = price.unwrap_or(trade.order.price);
+            trade.order.quantity = quantity.unwrap_or(trade.order.quantity);
+        }
+    }
+
+    /// Close an order, or remember the status until its placed line arrives
+    fn close(&mut self, order_id: String, status: Status) {
+        if self.trades.contains_key(&order_id) || self.futures_trades.contains_key(&order_id) {
+            self.set_status(&order_id, status);
+        } else {
+            self.early_closed.insert(order_id, status);
+        }
+    }
+
+    fn set_status(&mut self, order_id: &str, status: Status) {
+        if let Some(trade) = self.trades.get_mut(order_id) {
+            trade.status = status.clone();
+        }
+        if let Some(trade) = self.futures_trades.get_mut(order_id) {
+            trade.status = status;
+        }
+    }
+}
+
+/// Rebuild trade state from every journal file in `dir`, oldest first
+pub fn replay<P: AsRef<Path>>(dir: P) -> Result<ReplayState> {
+    let mut state = ReplayState::default();
+    for path in journal_files(dir.as_ref())? {
+        replay_into(&path, &mut state)?;
+    }
+    log::info!(
+        "📒 Replayed journal: {} trades, {} deals, last seq {}",
+        state.trades.len() + state.futures_trades.len(),
+        state.deals.len(),
+        state.last_seq
+    );
+    Ok(state)
+}
+
+/// Rebuild trade state from a single journal file
+pub fn replay_file<P: AsRef<Path>>(path: P) -> Result<ReplayState> {
+    let mut state = ReplayState::default();
+    replay_into(path.as_ref(), &mut state)?;
+    Ok(state)
+}
+
+fn replay_into(path: &Path, state: &mut ReplayState) -> Result<()> {
+    let (entries, skipped) = read_entries(path)?;
+    for entry in &entries {
+        state.apply(entry);
+    }
+    state.skipped_lines += skipped;
+    Ok(())
+}
+
+/// Parse a journal file, skipping lines that are not valid entries
+fn read_entries(path: &Path) -> Result<(Vec<JournalEntry>, usize)> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<JournalEntry>(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => {
+                log::warn!(
+                    "⚠️ Skipping unreadable journal line in {}: {}",
+                    path.display(),
+                    e
+                );
+                skipped += 1;
+            }
+        }
+    }
+    Ok((entries, skipped))
+}
+
+/// Journal files in `dir`, sorted by date
+fn journal_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Open a journal file for appending. A torn last line from a crash is
+/// terminated first so the next entry starts on its own line.
+fn open_for_append(path: &Path) -> Result<File> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+
+    let len = file.metadata()?.len();
+    if len > 0 {
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::Start(len - 1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            file.write_all(b"\n")?;
+            file.sync_data()?;
+        }
+    }
+    Ok(file)
+}
+
+fn taipei_date(ts: DateTime<Utc>) -> NaiveDate {
+    ts.with_timezone(&FixedOffset::east_opt(TAIPEI_OFFSET_SECS).expect("valid offset"))
+        .date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        Account, AccountType, Action, Exchange, Order, OrderType, Stock, StockPriceType,
+    };
+    use serde_json::json;
+
+    fn trade(order_id: &str, quantity: i32) -> Trade {
+        Trade {
+            order: Order::new(
+                Action::Buy,
+                500.0,
+                quantity,
+                OrderType::ROD,
+                StockPriceType::LMT,
+            ),
+            status: Status::Submitted,
+            order_id: order_id.to_string(),
+            seqno: "000001".to_string(),
+            ordno: "W0001".to_string(),
+            account: Account::new(
+                "9A95".to_string(),
+                "1234567".to_string(),
+                AccountType::Stock,
+                "user".to_string(),
+                true,
+            ),
+            contracts: vec![Stock::new("2330", Exchange::TSE).contract],
+        }
+    }
+
+    fn deal(order_id: &str, quantity: i32) -> JournalRecord {
+        JournalRecord::OrderEvent {
+            event_type: OrderEventType::StockDeal,
+            message: json!({
+                "trade_id": order_id,
+                "code": "2330",
+                "action": "Buy",
+                "price": 500.0,
+                "quantity": quantity,
+                "ts": 1_700_000_000.0,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_replay_rebuilds_trade_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Journal::open(dir.path()).unwrap();
+
+        journal
+            .append(JournalRecord::OrderPlaced {
+                trade: trade("a1", 3),
+                intent: None,
+            })
+            .unwrap();
+        journal
+            .append(JournalRecord::OrderPlaced {
+                trade: trade("b2", 1),
+                intent: None,
+            })
+            .unwrap();
+        journal.append(deal("a1", 1)).unwrap();
+        journal.append(deal("a1", 2)).unwrap();
+        journal
+            .append(JournalRecord::OrderEvent {
+                event_type: OrderEventType::StockOrder,
+                message: json!({
+                    "operation": {"op_type": "UpdatePrice", "op_code": "00"},
+                    "order": {"id": "b2", "price": 505.0, "quantity": 1},
+                }),
+            })
+            .unwrap();
+        let last = journal
+            .append(JournalRecord::OrderCancelled {
+                order_id: "b2".to_string(),
+                status: Status::Cancelled,
+            })
+            .unwrap();
+
+        let state = replay(dir.path()).unwrap();
+        assert_eq!(state.last_seq, last);
+        assert_eq!(state.trades["a1"].status, Status::Filled);
+        assert_eq!(state.filled["a1"], 3);
+        assert_eq!(state.deals.len(), 2);
+        assert_eq!(state.trades["b2"].status, Status::Cancelled);
+        assert_eq!(state.trades["b2"].order.price, 505.0);
+        assert!(state.open_trades().is_empty());
+    }
+
+    #[test]
+    fn test_torn_line_is_skipped_and_sequence_continues() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let journal = Journal::open(dir.path()).unwrap();
+            journal
+                .append(JournalRecord::OrderPlaced {
+                    trade: trade("a1", 2),
+                    intent: None,
+                })
+                .unwrap();
+        }
+
+        // Simulate a crash in the middle of a write
+        let path = journal_files(dir.path()).unwrap().pop().unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"seq":2,"ts":"2024-"#).unwrap();
+
+        let journal = Journal::open(dir.path()).unwrap();
+        let seq = journal.append(deal("a1", 1)).unwrap();
+        assert_eq!(seq, 2);
+
+        let state = replay(dir.path()).unwrap();
+        assert_eq!(state.skipped_lines, 1);
+        assert_eq!(state.trades["a1"].status, Status::PartFilled);
+        assert_eq!(state.open_trades().len(), 1);
+    }
+
+    #[test]
+    fn test_deals_before_placed_line_set_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Journal::open(dir.path()).unwrap();
+
+        // 成交回報早於 place_order 回傳
+        journal.append(deal("a1", 2)).unwrap();
+        journal
+            .append(JournalRecord::OrderPlaced {
+                trade: trade("a1", 2),
+                intent: None,
+            })
+            .unwrap();
+        journal.append(deal("b2", 1)).unwrap();
+        journal
+            .append(JournalRecord::OrderEvent {
+                event_type: OrderEventType::StockOrder,
+                message: json!({
+                    "operation": {"op_type": "Cancel", "op_code": "00"},
+                    "order": {"id": "b2"},
+                }),
+            })
+            .unwrap();
+        journal
+            .append(JournalRecord::OrderPlaced {
+                trade: trade("b2", 3),
+                intent: None,
+            })
+            .unwrap();
+        journal.append(deal("c3", 1)).unwrap();
+        journal
+            .append(JournalRecord::OrderPlaced {
+                trade: trade("c3", 3),
+                intent: None,
+            })
+            .unwrap();
+
+        let state = replay(dir.path()).unwrap();
+        assert_eq!(state.trades["a1"].status, Status::Filled);
+        assert_eq!(state.trades["b2"].status, Status::Cancelled);
+        assert_eq!(state.trades["c3"].status, Status::PartFilled);
+        let open: Vec<String> = state
+            .open_trades()
+            .into_iter()
+            .map(|t| t.order_id)
+            .collect();
+        assert_eq!(open, vec!["c3".to_string()]);
+    }
+
+    #[test]
+    fn test_intent_without_result_is_unresolved() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Journal::open(dir.path()).unwrap();
+        let intent = |order_id: &str| JournalRecord::OrderIntent {
+            operation: "place_order".to_string(),
+            contract: Stock::new("2330", Exchange::TSE).contract,
+            order: serde_json::to_value(trade(order_id, 1).order).unwrap(),
+        };
+
+        let placed = journal.append(intent("a1")).unwrap();
+        journal
+            .append(JournalRecord::OrderPlaced {
+                trade: trade("a1", 1),
+                intent: Some(placed),
+            })
+            .unwrap();
+        let failed = journal.append(intent("b2")).unwrap();
+        journal
+            .append(JournalRecord::CallFailed {
+                operation: "place_order".to_string(),
+                order_id: None,
+                error: "rejected".to_string(),
+                intent: Some(failed),
+            })
+            .unwrap();
+        // 送單中途當機：只有意圖，沒有結果
+        let in_flight = journal.append(intent("c3")).unwrap();
+
+        let state = replay(dir.path()).unwrap();
+        assert_eq!(
+            state.unresolved_intents.keys().copied().collect::<Vec<_>>(),
+            vec![in_flight]
+        );
+        assert!(matches!(
+            state.unresolved_intents[&in_flight].record,
+            JournalRecord::OrderIntent { ref contract, .. } if contract.base.code == "2330"
+        ));
+        assert_eq!(state.trades.len(), 1);
+    }
+
+    #[test]
+    fn test_file_naming() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let path = Journal::file_for(Path::new("/tmp/j"), date);
+        assert_eq!(path, PathBuf::from("/tmp/j/journal-2024-03-05.jsonl"));
+    }
+}
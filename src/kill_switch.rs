@@ -0,0 +1,720 @@
+//! Kill switch (緊急停止): cancel every open order and flatten positions
+//!
+//! Both operations fan out one request per order, run them concurrently and
+//! keep within Shioaji's order rate limit. They never stop at the first
+//! error; every order gets an [`OrderOutcome`] in the returned report.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use rshioaji::{Shioaji, TradeFilter};
+//! # async fn run(client: Arc<Shioaji>) -> rshioaji::Result<()> {
+//! let cancelled = client.cancel_all(TradeFilter::all()).await?;
+//! for account in client.list_accounts().await? {
+//!     let flattened = client.flatten(account).await?;
+//!     println!("{} ok / {} failed", flattened.succeeded(), flattened.failed());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+use crate::client::Shioaji;
+use crate::error::{Error, Result};
+use crate::types::{
+    Account, AccountType, Action, Contract, FuturesOCType, FuturesOrder, FuturesPriceType, Order,
+    OrderType, Position, SecurityType, Status, StockOrderLot, StockPriceType, Trade, Unit,
+};
+
+/// 永豐 API 委託類 (下單/改單/刪單) 流量限制：每 10 秒 250 次
+const ORDER_RATE_LIMIT: usize = 250;
+const ORDER_RATE_WINDOW: Duration = Duration::from_secs(10);
+/// 台股整股一張 = 1000 股
+const SHARES_PER_LOT: i64 = 1000;
+/// Requests in flight at the same time
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// Which open trades `cancel_all` should cancel; empty fields match anything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradeFilter {
+    pub account_id: Option<String>,
+    pub code: Option<String>,
+    pub security_type: Option<SecurityType>,
+}
+
+impl TradeFilter {
+    /// Match every open trade
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn with_account(mut self, account_id: &str) -> Self {
+        self.account_id = Some(account_id.to_string());
+        self
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    pub fn with_security_type(mut self, security_type: SecurityType) -> Self {
+        self.security_type = Some(security_type);
+        self
+    }
+
+    /// Whether `trade` is open and matches every field that is set
+    pub fn matches(&self, trade: &Trade) -> bool {
+        if !trade.status.is_open() {
+            return false;
+        }
+        if let Some(account_id) = &self.account_id {
+            if &trade.account.account_id != account_id {
+                return false;
+            }
+        }
+        let contract = trade.contracts.first();
+        if let Some(code) = &self.code {
+            if contract.map(|c| &c.base.code) != Some(code) {
+                return false;
+            }
+        }
+        if let Some(security_type) = &self.security_type {
+            if contract.map(|c| &c.base.security_type) != Some(security_type) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// How `flatten_with` prices the closing orders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlattenPricing {
+    /// Market order, IOC
+    #[default]
+    Market,
+    /// Limit order at the position's last price, ROD
+    LastPrice,
+}
+
+/// Result of one cancel or closing order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderOutcome {
+    pub code: String,
+    /// Cancelled trade's order id, or the new closing order's id
+    pub order_id: Option<String>,
+    pub action: Action,
+    pub quantity: i32,
+    /// Order status reported by the API, or the error message
+    pub result: std::result::Result<Status, String>,
+}
+
+impl OrderOutcome {
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Per-order report of a kill switch operation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KillSwitchReport {
+    pub outcomes: Vec<OrderOutcome>,
+}
+
+impl KillSwitchReport {
+    pub fn succeeded(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.is_ok()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.outcomes.len() - self.succeeded()
+    }
+
+    /// True when every order went through (also when there was nothing to do)
+    pub fn is_success(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+/// Sliding-window rate limiter shared by the tasks of one operation
+struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            sent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Wait until another request fits in the window, then record it
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut sent = self.sent.lock().await;
+                let now = Instant::now();
+                while sent
+                    .front()
+                    .is_some_and(|t| now.duration_since(*t) >= self.window)
+                {
+                    sent.pop_front();
+                }
+                if sent.len() < self.limit {
+                    sent.push_back(now);
+                    return;
+                }
+                self.window - now.duration_since(sent[0])
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Run `jobs` concurrently within the order rate limit, keeping input order
+async fn run_limited<F, Fut>(jobs: Vec<F>) -> Vec<OrderOutcome>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = OrderOutcome> + Send + 'static,
+{
+    let limiter = Arc::new(RateLimiter::new(ORDER_RATE_LIMIT, ORDER_RATE_WINDOW));
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
+
+    let mut tasks = JoinSet::new();
+    for (index, job) in jobs.into_iter().enumerate() {
+        let limiter = limiter.clone();
+        let permits = permits.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            limiter.acquire().await;
+            (index, job().await)
+        });
+    }
+
+    let mut outcomes = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(e) => log::error!("❌ Kill switch task failed: {}", e),
+        }
+    }
+    outcomes.sort_by_key(|(index, _)| *index);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+impl Shioaji {
+    /// Cancel every open trade matching `filter`
+    ///
+    /// Trades come from `list_trades`; filled, cancelled and failed trades
+    /// are skipped. Takes `Arc<Self>` so cancels can run concurrently.
+    pub async fn cancel_all(self: &Arc<Self>, filter: TradeFilter) -> Result<KillSwitchReport> {
+        let trades: Vec<Trade> = self
+            .list_trades()
+            .await?
+            .into_iter()
+            .filter(|trade| filter.matches(trade))
+            .collect();
+        log::warn!("🛑 Kill switch: cancelling {} open trades", trades.len());
+
+        let jobs = trades
+            .into_iter()
+            .map(|trade| {
+                let client = self.clone();
+                move || async move {
+                    let code = trade
+                        .contracts
+                        .first()
+                        .map(|c| c.base.code.clone())
+                        .unwrap_or_default();
+                    let order_id = Some(trade.order_id.clone());
+                    let action = trade.order.action.clone();
+                    let quantity = trade.order.quantity;
+                    let result = client
+                        .cancel_order(trade, None)
+                        .await
+                        .map(|cancelled| cancelled.status)
+                        .map_err(|e| e.to_string());
+                    OrderOutcome {
+                        code,
+                        order_id,
+                        action,
+                        quantity,
+                        result,
+                    }
+                }
+            })
+            .collect();
+
+        let report = KillSwitchReport {
+            outcomes: run_limited(jobs).await,
+        };
+        log::warn!(
+            "🛑 Kill switch: {} cancelled, {} failed",
+            report.succeeded(),
+            report.failed()
+        );
+        Ok(report)
+    }
+
+    /// Close every position of `account` with market orders
+    pub async fn flatten(self: &Arc<Self>, account: Account) -> Result<KillSwitchReport> {
+        self.flatten_with(account, FlattenPricing::Market).await
+    }
+
+    /// Close every position of `account` with opposite orders
+    ///
+    /// Futures/options accounts use `FuturesOCType::Cover`. Stock positions
+    /// are queried in shares (`Unit::Share`) and closed with one board-lot
+    /// order and one intraday odd-lot order (盤中零股), each with the
+    /// position's own condition, so 融資 longs are sold and 融券 shorts
+    /// bought back as margin orders instead of opening cash positions.
+    /// Odd-lot orders are limit-only, so with [`FlattenPricing::Market`] they
+    /// are priced at the limit-down (sell) or limit-up (buy) price.
+    pub async fn flatten_with(
+        self: &Arc<Self>,
+        account: Account,
+        pricing: FlattenPricing,
+    ) -> Result<KillSwitchReport> {
+        let unit = match account.account_type {
+            AccountType::Future => None,
+            AccountType::Stock | AccountType::Simulation => Some(Unit::Share),
+        };
+        let positions = self
+            .list_positions(Some(account.clone()), unit, None)
+            .await?;
+        let option_codes = match self.get_contracts().await {
+            Some(contracts) => contracts.options.keys().cloned().collect(),
+            None => std::collections::HashSet::new(),
+        };
+
+        let positions: Vec<Position> = positions.into_iter().filter(|p| p.quantity > 0).collect();
+        log::warn!(
+            "🛑 Kill switch: flattening {} positions in account {}",
+            positions.len(),
+            account.account_id
+        );
+
+        let jobs = positions
+            .into_iter()
+            .flat_map(|position| closing_legs(&account, position))
+            .map(|leg| {
+                let client = self.clone();
+                let account = account.clone();
+                let is_option = option_codes.contains(&leg.position.code);
+                move || async move {
+                    let action = match leg.position.direction {
+                        Action::Buy => Action::Sell,
+                        Action::Sell => Action::Buy,
+                    };
+                    let result =
+                        place_closing_order(&client, &account, &leg, is_option, pricing).await;
+                    OrderOutcome {
+                        code: leg.position.code,
+                        order_id: result.as_ref().ok().map(|(id, _)| id.clone()),
+                        action,
+                        quantity: leg.quantity,
+                        result: result.map(|(_, status)| status).map_err(|e| e.to_string()),
+                    }
+                }
+            })
+            .collect();
+
+        let report = KillSwitchReport {
+            outcomes: run_limited(jobs).await,
+        };
+        log::warn!(
+            "🛑 Kill switch: {} closing orders placed, {} failed",
+            report.succeeded(),
+            report.failed()
+        );
+        Ok(report)
+    }
+}
+
+/// One closing order: a whole futures/options position, or the board-lot
+/// or odd-lot part of a stock position
+struct ClosingLeg {
+    position: Position,
+    lot: StockOrderLot,
+    /// Lots for `StockOrderLot::Common` and futures, shares for odd lots
+    quantity: i32,
+}
+
+/// Split a position into its closing orders; stock positions are in shares
+fn closing_legs(account: &Account, position: Position) -> Vec<ClosingLeg> {
+    if account.account_type == AccountType::Future {
+        let quantity = position.quantity as i32;
+        return vec![ClosingLeg {
+            position,
+            lot: StockOrderLot::Common,
+            quantity,
+        }];
+    }
+    let lots = (position.quantity / SHARES_PER_LOT) as i32;
+    let odd = (position.quantity % SHARES_PER_LOT) as i32;
+    [
+        (StockOrderLot::Common, lots),
+        (StockOrderLot::IntradayOdd, odd),
+    ]
+    .into_iter()
+    .filter(|(_, quantity)| *quantity > 0)
+    .map(|(lot, quantity)| ClosingLeg {
+        position: position.clone(),
+        lot,
+        quantity,
+    })
+    .collect()
+}
+
+/// Submit the order that closes `leg`; returns the new order id and status
+async fn place_closing_order(
+    client: &Shioaji,
+    account: &Account,
+    leg: &ClosingLeg,
+    is_option: bool,
+    pricing: FlattenPricing,
+) -> Result<(String, Status)> {
+    let position = &leg.position;
+    let action = match position.direction {
+        Action::Buy => Action::Sell,
+        Action::Sell => Action::Buy,
+    };
+    let quantity = leg.quantity;
+    let (price, order_type) = match pricing {
+        FlattenPricing::Market => (0.0, OrderType::IOC),
+        FlattenPricing::LastPrice => (position.last_price, OrderType::ROD),
+    };
+
+    match account.account_type {
+        AccountType::Future => {
+            let security_type = if is_option {
+                SecurityType::Option
+            } else {
+                SecurityType::Future
+            };
+            let contract = resolve_position_contract(client, security_type, position).await?;
+            let price_type = match pricing {
+                FlattenPricing::Market => FuturesPriceType::MKT,
+                FlattenPricing::LastPrice => FuturesPriceType::LMT,
+            };
+            let order = FuturesOrder::new(
+                action,
+                price,
+                quantity,
+                order_type,
+                price_type,
+                FuturesOCType::Cover,
+            )
+            .with_account(account.clone());
+            let trade = client.place_futures_order(contract, order).await?;
+            Ok((trade.order_id, trade.status))
+        }
+        AccountType::Stock | AccountType::Simulation => {
+            let contract = resolve_position_contract(client, SecurityType::Stock, position).await?;
+            let order = closing_stock_order(account, leg, &contract, pricing);
+            let trade = client.place_order(contract, order).await?;
+            Ok((trade.order_id, trade.status))
+        }
+    }
+}
+
+/// Board-lot or odd-lot order closing a stock position under its own condition
+fn closing_stock_order(
+    account: &Account,
+    leg: &ClosingLeg,
+    contract: &Contract,
+    pricing: FlattenPricing,
+) -> Order {
+    let position = &leg.position;
+    let action = match position.direction {
+        Action::Buy => Action::Sell,
+        Action::Sell => Action::Buy,
+    };
+    let (price, order_type, price_type) = match (pricing, &leg.lot) {
+        (FlattenPricing::Market, StockOrderLot::Common) => {
+            (0.0, OrderType::IOC, StockPriceType::MKT)
+        }
+        // 零股不接受市價單：以跌停 (賣) / 漲停 (買) 價委託
+        (FlattenPricing::Market, _) => {
+            let limit = match action {
+                Action::Sell => contract.limit_down,
+                Action::Buy => contract.limit_up,
+            };
+            let price = if limit > 0.0 {
+                limit
+            } else {
+                position.last_price
+            };
+            (price, OrderType::ROD, StockPriceType::LMT)
+        }
+        (FlattenPricing::LastPrice, _) => {
+            (position.last_price, OrderType::ROD, StockPriceType::LMT)
+        }
+    };
+    Order::new(action, price, leg.quantity, order_type, price_type)
+        .with_account(account.clone())
+        .with_order_lot(leg.lot.clone())
+        .with_order_cond(position.cond.clone())
+}
+
+/// The position's real contract (exchange, multiplier, strike); a lookup
+/// failure fails only this position's outcome
+async fn resolve_position_contract(
+    client: &Shioaji,
+    security_type: SecurityType,
+    position: &Position,
+) -> Result<Contract> {
+    client
+        .resolve_contract(security_type.clone(), &position.code)
+        .await
+        .map_err(|e| {
+            Error::InvalidContract(format!(
+                "Cannot resolve {:?} contract {} to close: {}",
+                security_type, position.code, e
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Exchange, Stock, StockOrderCond};
+
+    fn trade(code: &str, account_id: &str, status: Status) -> Trade {
+        let account = Account::new(
+            "9A95".to_string(),
+            account_id.to_string(),
+            AccountType::Stock,
+            "user".to_string(),
+            true,
+        );
+        Trade {
+            order: Order::new(Action::Buy, 100.0, 1, OrderType::ROD, StockPriceType::LMT),
+            status,
+            order_id: format!("{}-{}", code, account_id),
+            seqno: String::new(),
+            ordno: String::new(),
+            account,
+            contracts: vec![Stock::new(code, Exchange::TSE).contract],
+        }
+    }
+
+    #[test]
+    fn test_filter_skips_closed_trades() {
+        let filter = TradeFilter::all();
+        assert!(filter.matches(&trade("2330", "A1", Status::Submitted)));
+        assert!(filter.matches(&trade("2330", "A1", Status::PartFilled)));
+        assert!(!filter.matches(&trade("2330", "A1", Status::Filled)));
+        assert!(!filter.matches(&trade("2330", "A1", Status::Cancelled)));
+    }
+
+    #[test]
+    fn test_filter_fields() {
+        let filter = TradeFilter::all()
+            .with_account("A1")
+            .with_code("2330")
+            .with_security_type(SecurityType::Stock);
+        assert!(filter.matches(&trade("2330", "A1", Status::Submitted)));
+        assert!(!filter.matches(&trade("2317", "A1", Status::Submitted)));
+        assert!(!filter.matches(&trade("2330", "A2", Status::Submitted)));
+        assert!(!TradeFilter::all()
+            .with_security_type(SecurityType::Future)
+            .matches(&trade("2330", "A1", Status::Submitted)));
+    }
+
+    fn stock_position(direction: Action, cond: StockOrderCond) -> Position {
+        Position {
+            account: trade("2330", "A1", Status::Filled).account,
+            code: "2330".to_string(),
+            quantity: 3000,
+            price: 575.0,
+            last_price: 580.0,
+            pnl: 0.0,
+            yd_quantity: 3000,
+            direction,
+            cond,
+        }
+    }
+
+    fn board_lot(position: Position) -> ClosingLeg {
+        let account = position.account.clone();
+        closing_legs(&account, position).remove(0)
+    }
+
+    #[test]
+    fn test_closing_order_keeps_margin_condition() {
+        let position = stock_position(Action::Buy, StockOrderCond::MarginTrading);
+        let contract = Stock::new("2330", Exchange::TSE).contract;
+        let order = closing_stock_order(
+            &position.account,
+            &board_lot(position.clone()),
+            &contract,
+            FlattenPricing::Market,
+        );
+        assert_eq!(order.action, Action::Sell);
+        assert_eq!(order.quantity, 3);
+        assert_eq!(order.order_cond, Some(StockOrderCond::MarginTrading));
+        assert_eq!(order.order_lot, Some(StockOrderLot::Common));
+        assert_eq!(order.price_type, StockPriceType::MKT);
+    }
+
+    #[test]
+    fn test_closing_order_covers_short_sale() {
+        let position = stock_position(Action::Sell, StockOrderCond::ShortSelling);
+        let contract = Stock::new("2330", Exchange::TSE).contract;
+        let order = closing_stock_order(
+            &position.account,
+            &board_lot(position.clone()),
+            &contract,
+            FlattenPricing::LastPrice,
+        );
+        assert_eq!(order.action, Action::Buy);
+        assert_eq!(order.order_cond, Some(StockOrderCond::ShortSelling));
+        assert_eq!(order.price, 580.0);
+        assert_eq!(order.order_type, OrderType::ROD);
+
+        let cash = stock_position(Action::Buy, StockOrderCond::Cash);
+        let order = closing_stock_order(
+            &cash.account,
+            &board_lot(cash.clone()),
+            &contract,
+            FlattenPricing::Market,
+        );
+        assert_eq!(order.order_cond, Some(StockOrderCond::Cash));
+    }
+
+    #[test]
+    fn test_odd_shares_close_with_odd_lot_order() {
+        let mut position = stock_position(Action::Buy, StockOrderCond::Cash);
+        position.quantity = 2300;
+        let legs = closing_legs(&position.account.clone(), position);
+        let split: Vec<(StockOrderLot, i32)> =
+            legs.iter().map(|l| (l.lot.clone(), l.quantity)).collect();
+        assert_eq!(
+            split,
+            vec![
+                (StockOrderLot::Common, 2),
+                (StockOrderLot::IntradayOdd, 300)
+            ]
+        );
+
+        let mut contract = Stock::new("2330", Exchange::TSE).contract;
+        contract.limit_down = 522.0;
+        let order = closing_stock_order(
+            &legs[1].position.account,
+            &legs[1],
+            &contract,
+            FlattenPricing::Market,
+        );
+        assert_eq!(order.quantity, 300);
+        assert_eq!(order.order_lot, Some(StockOrderLot::IntradayOdd));
+        assert_eq!(order.price_type, StockPriceType::LMT);
+        assert_eq!(order.price, 522.0);
+
+        // Less than a board lot: only the odd-lot order
+        let mut odd_only = stock_position(Action::Buy, StockOrderCond::Cash);
+        odd_only.quantity = 450;
+        let legs = closing_legs(&odd_only.account.clone(), odd_only);
+        assert_eq!(legs.len(), 1);
+        assert_eq!(legs[0].lot, StockOrderLot::IntradayOdd);
+    }
+
+    #[tokio::test]
+    async fn test_flatten_resolves_futures_contracts() {
+        use crate::paper::{PaperBroker, PaperConfig};
+        use crate::types::{Contracts, Future, TickFOPv1};
+
+        let mut listed = Future::new("TXFA4").contract;
+        listed.name = "臺股期貨".to_string();
+        let mut contracts = Contracts::new();
+        contracts.add_future("TXFA4".to_string(), listed);
+        let broker = Arc::new(PaperBroker::new(PaperConfig::default()).with_contracts(contracts));
+        let client = Shioaji::new(true, Default::default())
+            .unwrap()
+            .with_backend(broker.clone());
+        client.init().await.unwrap();
+        client.login_simple("key", "secret", true).await.unwrap();
+
+        // MXFA4 is not in the loaded contracts and cannot be looked up
+        for code in ["TXFA4", "MXFA4"] {
+            let order = FuturesOrder::new(
+                Action::Buy,
+                0.0,
+                1,
+                OrderType::ROD,
+                FuturesPriceType::MKT,
+                FuturesOCType::Auto,
+            );
+            client
+                .place_futures_order(Future::new(code).contract, order)
+                .await
+                .unwrap();
+            broker.on_tick_fop(&TickFOPv1 {
+                code: code.to_string(),
+                close: 17000.0,
+                volume: 5,
+                ..Default::default()
+            });
+        }
+
+        let client = Arc::new(client);
+        let account = broker.config().futopt_account.clone();
+        let report = client.flatten(account).await.unwrap();
+        assert_eq!(report.outcomes.len(), 2);
+        let outcome = |code: &str| report.outcomes.iter().find(|o| o.code == code).unwrap();
+        assert!(outcome("TXFA4").result.is_ok());
+        assert!(outcome("MXFA4")
+            .result
+            .as_ref()
+            .is_err_and(|e| e.contains("MXFA4")));
+
+        let closing = client.list_trades().await.unwrap().pop().unwrap();
+        assert_eq!(closing.order.action, Action::Sell);
+        assert_eq!(closing.contracts[0].name, "臺股期貨");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_waits_for_window() {
+        let limiter = RateLimiter::new(2, Duration::from_millis(100));
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_run_limited_keeps_order() {
+        let jobs: Vec<_> = (0..20)
+            .map(|i| {
+                move || async move {
+                    tokio::time::sleep(Duration::from_millis((20 - i) as u64)).await;
+                    OrderOutcome {
+                        code: i.to_string(),
+                        order_id: None,
+                        action: Action::Sell,
+                        quantity: 1,
+                        result: Ok(Status::Cancelled),
+                    }
+                }
+            })
+            .collect();
+        let outcomes = run_limited(jobs).await;
+        let codes: Vec<String> = outcomes.into_iter().map(|o| o.code).collect();
+        let expected: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        assert_eq!(codes, expected);
+    }
+}
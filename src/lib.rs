@@ -46,27 +46,74 @@
 //! ```
 
 // pub mod bindings; // Removed - using pure system shioaji architecture
+pub mod async_callbacks;
+pub mod backend;
+pub mod calendar;
 pub mod callbacks;
 pub mod client;
+pub mod conditional_orders;
 pub mod config;
 pub mod error;
+pub mod execution;
+pub mod fees;
+pub mod futures;
+pub mod journal;
+pub mod kill_switch;
+pub mod margin;
+pub mod option_pricing;
+pub mod paper;
 pub mod platform;
+pub mod portfolio;
+pub mod proxy;
+pub mod python_worker;
+pub mod scheduler;
+pub mod session_manager;
 pub mod types;
 pub mod utils;
+pub mod vol_surface;
 
 // Re-export commonly used types and functions
+pub use async_callbacks::{
+    AsyncBidAskCallback, AsyncHandler, AsyncOrderCallback, AsyncQueue, AsyncQuoteCallback,
+    AsyncSystemCallback, AsyncTickCallback,
+};
+pub use backend::{Backend, BackendFuture};
+pub use calendar::{Session, SessionKind, SessionWindow, TradingCalendar};
 pub use callbacks::{
-    BidAskCallback, EventHandlers, OrderCallback, QuoteCallback, SystemCallback, TickCallback,
+    BidAskCallback, CallbackErrorPolicy, CallbackGuard, CallbackHandle, CallbackRegistry,
+    CallbackStats, ContractCallback, EventHandlers, Handler, OrderCallback, QuoteCallback,
+    SystemCallback, TickCallback,
 };
 pub use client::Shioaji;
+pub use conditional_orders::{
+    ConditionalOrder, ConditionalOrderBook, ConditionalStatus, TrailAmount, Trigger,
+};
 pub use config::Config;
 pub use error::{Error, Result};
+pub use execution::{
+    ExecutionEngine, ExecutionEvent, ExecutionHandle, ExecutionProgress, ParentOrder, Schedule,
+    VolumeProfile,
+};
+pub use fees::{FeeSchedule, Fees, StockTaxCategory};
+pub use futures::{Adjustment, RollCalendar, RollSegment};
+pub use journal::{Journal, JournalEntry, JournalRecord};
+pub use kill_switch::{FlattenPricing, KillSwitchReport, OrderOutcome, TradeFilter};
+pub use margin::{BuyingPower, MarginEstimate, MarginLeg, MarginRequirement, MarginTable};
+pub use option_pricing::{Greeks, OptionAnalytics, OptionInputs, PricingModel};
+pub use paper::{PaperBroker, PaperConfig, QueueModel};
 pub use platform::Platform;
+pub use portfolio::{AccountPnl, Portfolio, PositionPnl};
+pub use python_worker::PythonWorker;
+pub use scheduler::{JobTrigger, Scheduler, SessionEvent};
+pub use session_manager::{ManagedSession, SessionManager};
 pub use utils::{
     check_contract_cache, clear_outdated_contract_cache, create_shared_folder, get_contract_folder,
     init_logging, raise_resp_error, set_error_tracking, status_error_wrapper, timeout_exception,
     EnvironmentConfig,
 };
+pub use vol_surface::{SnapshotFormat, SurfaceSnapshot, SviParams, VolSurface, VolSurfaceConfig};
 
 // Re-export all types from the types module
-pub use types::{accounts::*, constants::*, contracts::*, market_data::*, orders::*, positions::*};
+pub use types::{
+    accounts::*, constants::*, contracts::*, market_data::*, option_chain::*, orders::*, positions::*,
+};
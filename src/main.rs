@@ -1,7 +1,6 @@
 use clap::Parser;
 use log::info;
 use rshioaji::{Action, Config, Exchange, OrderType, Shioaji, StockPriceType};
-use std::collections::HashMap;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -55,10 +54,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Use command line arguments if provided, otherwise use environment variables
-    let (api_key, secret_key, simulation) = match (&cli.api_key, &cli.secret_key) {
+    let (api_key, secret_key, simulation, proxies, vpn) = match (&cli.api_key, &cli.secret_key) {
         (Some(api), Some(secret)) => {
             info!("Using API credentials from command line arguments");
-            (api.clone(), secret.clone(), cli.simulation)
+            (
+                api.clone(),
+                secret.clone(),
+                cli.simulation,
+                rshioaji::proxy::proxies_from_env(),
+                rshioaji::proxy::vpn_from_env(),
+            )
         }
         _ => match config {
             Ok(cfg) => {
@@ -68,7 +73,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     eprintln!("Configuration validation failed: {}", e);
                     std::process::exit(1);
                 }
-                (cfg.api_key, cfg.secret_key, cfg.simulation)
+                (
+                    cfg.api_key,
+                    cfg.secret_key,
+                    cfg.simulation,
+                    cfg.proxies,
+                    cfg.vpn,
+                )
             }
             Err(e) => {
                 eprintln!("Failed to load configuration: {}", e);
@@ -81,9 +92,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
     };
 
-    // Create Shioaji client
-    let proxies = HashMap::new();
-    let client = Shioaji::new(simulation, proxies)?;
+    // Create Shioaji client (proxies and VPN come from SHIOAJI_PROXY_* / SHIOAJI_VPN)
+    let client = Shioaji::new_with_options(simulation, proxies, vpn)?;
 
     // Initialize the client
     client.init().await?;
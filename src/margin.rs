@@ -0,0 +1,390 @@
+//! Futures margin and buying-power calculator (期貨保證金試算)
+//!
+//! Margin tables are keyed by TAIFEX product code (`TXF`, `MXF`, ...); contract
+//! codes such as `TXFA4` or `TXFR1` are mapped to their product by dropping the
+//! month letter and year digit. Tables are loaded from a local JSON file:
+//!
+//! ```json
+//! {
+//!   "TXF": { "original": 184000, "maintenance": 141000,
+//!            "spread_original": 46000, "spread_maintenance": 35000 },
+//!   "MXF": { "original": 46000, "maintenance": 35250 }
+//! }
+//! ```
+//!
+//! or derived from a `margin()` response with [`MarginRequirement::from_margin`].
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::client::Shioaji;
+use crate::error::{Error, Result};
+use crate::types::{Account, Action, FuturesOCType, FuturesOrder, Margin};
+
+/// 當沖保證金為原始保證金的一半
+const DAY_TRADE_RATIO: f64 = 0.5;
+
+/// Per-contract margin of one product
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarginRequirement {
+    /// 原始保證金
+    pub original: f64,
+    /// 維持保證金
+    pub maintenance: f64,
+    /// 跨月價差原始保證金 (每組)
+    #[serde(default)]
+    pub spread_original: Option<f64>,
+    /// 跨月價差維持保證金 (每組)
+    #[serde(default)]
+    pub spread_maintenance: Option<f64>,
+}
+
+impl MarginRequirement {
+    pub fn new(original: f64, maintenance: f64) -> Self {
+        Self {
+            original,
+            maintenance,
+            spread_original: None,
+            spread_maintenance: None,
+        }
+    }
+
+    pub fn with_spread(mut self, original: f64, maintenance: f64) -> Self {
+        self.spread_original = Some(original);
+        self.spread_maintenance = Some(maintenance);
+        self
+    }
+
+    /// Per-contract requirement implied by a `margin()` response on an
+    /// account holding `contracts` open contracts of a single product
+    pub fn from_margin(margin: &Margin, contracts: i64) -> Option<Self> {
+        if contracts <= 0 || margin.initial_margin <= 0.0 {
+            return None;
+        }
+        let contracts = contracts as f64;
+        Some(Self::new(
+            margin.initial_margin / contracts,
+            margin.maintenance_margin / contracts,
+        ))
+    }
+}
+
+/// One leg of a prospective order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarginLeg {
+    pub code: String,
+    pub action: Action,
+    pub quantity: i32,
+    pub day_trade: bool,
+}
+
+impl MarginLeg {
+    pub fn new(code: &str, action: Action, quantity: i32) -> Self {
+        Self {
+            code: code.to_string(),
+            action,
+            quantity,
+            day_trade: false,
+        }
+    }
+
+    /// Leg of a futures order; `None` for cover orders, which release margin
+    pub fn from_order(code: &str, order: &FuturesOrder) -> Option<Self> {
+        match order.octype {
+            FuturesOCType::Cover => None,
+            _ => Some(Self {
+                code: code.to_string(),
+                action: order.action.clone(),
+                quantity: order.quantity,
+                day_trade: order.octype == FuturesOCType::DayTrade,
+            }),
+        }
+    }
+}
+
+/// Estimated margin of a set of legs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarginEstimate {
+    pub initial: f64,
+    pub maintenance: f64,
+}
+
+/// Buying power left after the estimated margin is taken
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BuyingPower {
+    /// 可動用保證金 (from `margin()`)
+    pub available: f64,
+    /// 本次試算所需原始保證金
+    pub required: f64,
+}
+
+impl BuyingPower {
+    pub fn remaining(&self) -> f64 {
+        self.available - self.required
+    }
+
+    pub fn is_sufficient(&self) -> bool {
+        self.remaining() >= 0.0
+    }
+}
+
+/// TAIFEX margin table by product
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MarginTable {
+    products: BTreeMap<String, MarginRequirement>,
+}
+
+impl MarginTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a table from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            Error::FileSystem(format!(
+                "Failed to read margin table {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        let table: Self = serde_json::from_str(&content)?;
+        log::info!("📊 Loaded margin table with {} products", table.len());
+        Ok(table)
+    }
+
+    pub fn insert(&mut self, product: &str, requirement: MarginRequirement) {
+        self.products.insert(product.to_uppercase(), requirement);
+    }
+
+    pub fn with_product(mut self, product: &str, requirement: MarginRequirement) -> Self {
+        self.insert(product, requirement);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.products.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.products.is_empty()
+    }
+
+    /// Requirement of a contract or product code
+    pub fn get(&self, code: &str) -> Option<&MarginRequirement> {
+        self.products.get(&product_code(code))
+    }
+
+    fn requirement(&self, code: &str) -> Result<&MarginRequirement> {
+        self.get(code).ok_or_else(|| {
+            Error::InvalidContract(format!("No margin requirement for {}", product_code(code)))
+        })
+    }
+
+    /// Margin of a set of legs, netting same-month legs and offsetting
+    /// opposite legs in different months of a product as calendar spreads
+    pub fn estimate(&self, legs: &[MarginLeg]) -> Result<MarginEstimate> {
+        let mut estimate = MarginEstimate::default();
+        // product -> code -> signed quantity
+        let mut books: BTreeMap<String, HashMap<&str, i64>> = BTreeMap::new();
+
+        for leg in legs.iter().filter(|leg| leg.quantity != 0) {
+            let requirement = self.requirement(&leg.code)?;
+            if leg.day_trade {
+                let quantity = leg.quantity.abs() as f64;
+                estimate.initial += requirement.original * DAY_TRADE_RATIO * quantity;
+                estimate.maintenance += requirement.maintenance * DAY_TRADE_RATIO * quantity;
+                continue;
+            }
+            let signed = match leg.action {
+                Action::Buy => leg.quantity.abs() as i64,
+                Action::Sell => -(leg.quantity.abs() as i64),
+            };
+            *books
+                .entry(product_code(&leg.code))
+                .or_default()
+                .entry(leg.code.as_str())
+                .or_insert(0) += signed;
+        }
+
+        for (product, book) in books {
+            let requirement = self.requirement(&product)?;
+            let longs: i64 = book.values().filter(|q| **q > 0).sum();
+            let shorts: i64 = -book.values().filter(|q| **q < 0).sum::<i64>();
+            let spreads = match (requirement.spread_original, requirement.spread_maintenance) {
+                (Some(_), Some(_)) => longs.min(shorts),
+                _ => 0,
+            };
+            let outright = (longs + shorts - 2 * spreads) as f64;
+            let spreads = spreads as f64;
+
+            estimate.initial += outright * requirement.original
+                + spreads * requirement.spread_original.unwrap_or_default();
+            estimate.maintenance += outright * requirement.maintenance
+                + spreads * requirement.spread_maintenance.unwrap_or_default();
+        }
+
+        Ok(estimate)
+    }
+
+    /// Initial margin of a prospective futures order on `code`
+    pub fn order_margin(&self, code: &str, order: &FuturesOrder) -> Result<MarginEstimate> {
+        match MarginLeg::from_order(code, order) {
+            Some(leg) => self.estimate(&[leg]),
+            None => Ok(MarginEstimate::default()),
+        }
+    }
+
+    /// Buying power left on `margin` after placing `legs`
+    pub fn buying_power(&self, margin: &Margin, legs: &[MarginLeg]) -> Result<BuyingPower> {
+        Ok(BuyingPower {
+            available: margin.available_margin,
+            required: self.estimate(legs)?.initial,
+        })
+    }
+
+    /// Query `margin()` and compute the buying power left after placing `legs`
+    pub async fn check_buying_power(
+        &self,
+        client: &Shioaji,
+        account: Option<Account>,
+        legs: &[MarginLeg],
+    ) -> Result<BuyingPower> {
+        let margin = client.margin(account, None).await?;
+        self.buying_power(&margin, legs)
+    }
+
+    /// Most outright contracts of `code` that `margin` can open
+    pub fn max_contracts(&self, margin: &Margin, code: &str) -> Result<i64> {
+        let requirement = self.requirement(code)?;
+        if requirement.original <= 0.0 {
+            return Err(Error::InvalidInput(format!(
+                "Original margin of {} must be positive",
+                product_code(code)
+            )));
+        }
+        Ok((margin.available_margin / requirement.original)
+            .floor()
+            .max(0.0) as i64)
+    }
+}
+
+/// TAIFEX product of a contract code: `TXFA4` / `TXFR1` -> `TXF`
+pub fn product_code(code: &str) -> String {
+    let code = code.trim().to_uppercase();
+    let bytes = code.as_bytes();
+    if bytes.len() >= 5 {
+        let month = bytes[bytes.len() - 2];
+        let year = bytes[bytes.len() - 1];
+        if month.is_ascii_uppercase() && year.is_ascii_digit() {
+            return code[..code.len() - 2].to_string();
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FuturesPriceType, OrderType};
+
+    fn table() -> MarginTable {
+        MarginTable::new()
+            .with_product(
+                "TXF",
+                MarginRequirement::new(184_000.0, 141_000.0).with_spread(46_000.0, 35_000.0),
+            )
+            .with_product("MXF", MarginRequirement::new(46_000.0, 35_250.0))
+    }
+
+    fn margin(available: f64) -> Margin {
+        Margin {
+            account_balance: available,
+            available_margin: available,
+            initial_margin: 0.0,
+            maintenance_margin: 0.0,
+            margin_call: 0.0,
+            unrealized_pnl: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_product_code() {
+        assert_eq!(product_code("TXFA4"), "TXF");
+        assert_eq!(product_code("mxfr1"), "MXF");
+        assert_eq!(product_code("TXF"), "TXF");
+    }
+
+    #[test]
+    fn test_order_margin_and_calendar_spread() {
+        let table = table();
+        let order = FuturesOrder::new(
+            Action::Buy,
+            17000.0,
+            2,
+            OrderType::ROD,
+            FuturesPriceType::LMT,
+            FuturesOCType::Auto,
+        );
+        assert_eq!(
+            table.order_margin("TXFA4", &order).unwrap().initial,
+            368_000.0
+        );
+
+        let mut cover = order.clone();
+        cover.octype = FuturesOCType::Cover;
+        assert_eq!(table.order_margin("TXFA4", &cover).unwrap().initial, 0.0);
+
+        // 1 組跨月價差 + 1 口單邊
+        let legs = [
+            MarginLeg::new("TXFA4", Action::Buy, 2),
+            MarginLeg::new("TXFB4", Action::Sell, 1),
+        ];
+        let estimate = table.estimate(&legs).unwrap();
+        assert_eq!(estimate.initial, 46_000.0 + 184_000.0);
+        assert_eq!(estimate.maintenance, 35_000.0 + 141_000.0);
+
+        // 無價差保證金的商品不折抵
+        let legs = [
+            MarginLeg::new("MXFA4", Action::Buy, 1),
+            MarginLeg::new("MXFB4", Action::Sell, 1),
+        ];
+        assert_eq!(table.estimate(&legs).unwrap().initial, 92_000.0);
+
+        assert!(table
+            .estimate(&[MarginLeg::new("ZZFA4", Action::Buy, 1)])
+            .is_err());
+    }
+
+    #[test]
+    fn test_buying_power_and_table_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("margin.json");
+        std::fs::write(&path, serde_json::to_string(&table()).unwrap()).unwrap();
+        let table = MarginTable::load(&path).unwrap();
+        assert_eq!(table.len(), 2);
+
+        let power = table
+            .buying_power(
+                &margin(200_000.0),
+                &[MarginLeg::new("TXFA4", Action::Sell, 1)],
+            )
+            .unwrap();
+        assert_eq!(power.remaining(), 16_000.0);
+        assert!(power.is_sufficient());
+        assert_eq!(table.max_contracts(&margin(200_000.0), "MXFA4").unwrap(), 4);
+
+        let derived = MarginRequirement::from_margin(
+            &Margin {
+                initial_margin: 368_000.0,
+                maintenance_margin: 282_000.0,
+                ..margin(0.0)
+            },
+            2,
+        )
+        .unwrap();
+        assert_eq!(derived, MarginRequirement::new(184_000.0, 141_000.0));
+    }
+}
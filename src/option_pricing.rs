@@ -0,0 +1,503 @@
+//! Option pricing, implied volatility and Greeks (選擇權評價)
+//!
+//! Black-Scholes (spot underlying with continuous dividend yield) and Black-76
+//! (futures underlying) share one generalized formula with cost of carry `b`:
+//! Black-Scholes uses `b = r - q`, Black-76 uses `b = 0`.
+//!
+//! Greeks are reported in trader units: vega and rho per 1 percentage point,
+//! theta per calendar day.
+//!
+//! ```no_run
+//! # use rshioaji::option_pricing::{analyze_tick, PricingModel};
+//! # fn run(contract: &rshioaji::Contract, tick: &rshioaji::TickFOPv1) -> rshioaji::Result<()> {
+//! let analytics = analyze_tick(contract, tick, PricingModel::black_scholes(), 0.015)?;
+//! println!("IV {:.2}% delta {:.3}", analytics.implied_volatility * 100.0, analytics.greeks.delta);
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::types::{Contract, OptionRight, Snapshot, TickFOPv1};
+
+const TAIPEI_OFFSET_SECS: i32 = 8 * 3600;
+const SECONDS_PER_YEAR: f64 = 365.0 * 86400.0;
+const MIN_VOLATILITY: f64 = 1e-6;
+const MAX_VOLATILITY: f64 = 10.0;
+
+/// Pricing model
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PricingModel {
+    /// Spot underlying (TAIEX index for TXO, stock for stock options)
+    BlackScholes { dividend_yield: f64 },
+    /// Futures/forward underlying
+    Black76,
+}
+
+impl PricingModel {
+    pub fn black_scholes() -> Self {
+        PricingModel::BlackScholes {
+            dividend_yield: 0.0,
+        }
+    }
+
+    /// Forward price of `underlying` at `time_to_expiry` years
+    pub fn forward(&self, underlying: f64, rate: f64, time_to_expiry: f64) -> f64 {
+        underlying * (self.cost_of_carry(rate) * time_to_expiry.max(0.0)).exp()
+    }
+
+    fn cost_of_carry(&self, rate: f64) -> f64 {
+        match self {
+            PricingModel::BlackScholes { dividend_yield } => rate - dividend_yield,
+            PricingModel::Black76 => 0.0,
+        }
+    }
+}
+
+/// Contract terms and market inputs, everything except volatility
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionInputs {
+    pub right: OptionRight,
+    /// Spot (Black-Scholes) or futures price (Black-76)
+    pub underlying: f64,
+    pub strike: f64,
+    /// Time to expiry in years
+    pub time_to_expiry: f64,
+    /// Continuously compounded risk-free rate
+    pub rate: f64,
+}
+
+impl OptionInputs {
+    /// Inputs for an option contract, with expiry from `delivery_date`
+    pub fn from_contract(
+        contract: &Contract,
+        underlying: f64,
+        now: DateTime<Utc>,
+        rate: f64,
+    ) -> Result<Self> {
+        if contract.option_right == OptionRight::No {
+            return Err(Error::InvalidContract(format!(
+                "{} is not an option",
+                contract.base.code
+            )));
+        }
+        Ok(Self {
+            right: contract.option_right.clone(),
+            underlying,
+            strike: contract.strike_price,
+            time_to_expiry: time_to_expiry(&contract.delivery_date, now)?,
+            rate,
+        })
+    }
+}
+
+/// Option sensitivities
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Greeks {
+    pub delta: f64,
+    pub gamma: f64,
+    /// Price change per 1 volatility point
+    pub vega: f64,
+    /// Price change per calendar day
+    pub theta: f64,
+    /// Price change per 1 percentage point of rate
+    pub rho: f64,
+}
+
+/// Price, implied volatility and Greeks of one option quote
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionAnalytics {
+    pub code: String,
+    pub price: f64,
+    pub underlying: f64,
+    pub time_to_expiry: f64,
+    pub implied_volatility: f64,
+    pub greeks: Greeks,
+}
+
+/// Years from `now` to Taiwan settlement (13:30 台北時間) on `delivery_date`
+///
+/// Accepts `2024/01/17`, `2024-01-17` and `20240117`.
+pub fn time_to_expiry(delivery_date: &str, now: DateTime<Utc>) -> Result<f64> {
+    let date = ["%Y/%m/%d", "%Y-%m-%d", "%Y%m%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(delivery_date.trim(), format).ok())
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid delivery date: {}", delivery_date)))?;
+    let settlement = FixedOffset::east_opt(TAIPEI_OFFSET_SECS)
+        .expect("valid offset")
+        .from_local_datetime(
+            &date.and_time(NaiveTime::from_hms_opt(13, 30, 0).expect("valid time")),
+        )
+        .single()
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid delivery date: {}", delivery_date)))?;
+    let seconds = (settlement.with_timezone(&Utc) - now).num_seconds();
+    Ok(seconds.max(0) as f64 / SECONDS_PER_YEAR)
+}
+
+struct Terms {
+    /// Discounted underlying, S·e^((b-r)T)
+    carry: f64,
+    /// Discounted strike, K·e^(-rT)
+    discount: f64,
+    d1: f64,
+    d2: f64,
+    sqrt_t: f64,
+}
+
+fn terms(model: PricingModel, inputs: &OptionInputs, volatility: f64) -> Option<Terms> {
+    let t = inputs.time_to_expiry;
+    if t <= 0.0 || volatility <= 0.0 || inputs.underlying <= 0.0 || inputs.strike <= 0.0 {
+        return None;
+    }
+    let b = model.cost_of_carry(inputs.rate);
+    let sqrt_t = t.sqrt();
+    let d1 = ((inputs.underlying / inputs.strike).ln() + (b + 0.5 * volatility * volatility) * t)
+        / (volatility * sqrt_t);
+    Some(Terms {
+        carry: inputs.underlying * ((b - inputs.rate) * t).exp(),
+        discount: inputs.strike * (-inputs.rate * t).exp(),
+        d1,
+        d2: d1 - volatility * sqrt_t,
+        sqrt_t,
+    })
+}
+
+fn intrinsic(model: PricingModel, inputs: &OptionInputs) -> f64 {
+    let t = inputs.time_to_expiry.max(0.0);
+    let b = model.cost_of_carry(inputs.rate);
+    let carry = inputs.underlying * ((b - inputs.rate) * t).exp();
+    let discount = inputs.strike * (-inputs.rate * t).exp();
+    match inputs.right {
+        OptionRight::Call => (carry - discount).max(0.0),
+        OptionRight::Put => (discount - carry).max(0.0),
+        OptionRight::No => 0.0,
+    }
+}
+
+/// Theoretical option price
+pub fn price(model: PricingModel, inputs: &OptionInputs, volatility: f64) -> f64 {
+    let Some(x) = terms(model, inputs, volatility) else {
+        return intrinsic(model, inputs);
+    };
+    match inputs.right {
+        OptionRight::Call => x.carry * norm_cdf(x.d1) - x.discount * norm_cdf(x.d2),
+        OptionRight::Put => x.discount * norm_cdf(-x.d2) - x.carry * norm_cdf(-x.d1),
+        OptionRight::No => 0.0,
+    }
+}
+
+/// Delta, gamma, vega, theta and rho
+pub fn greeks(model: PricingModel, inputs: &OptionInputs, volatility: f64) -> Greeks {
+    let Some(x) = terms(model, inputs, volatility) else {
+        let delta = match inputs.right {
+            OptionRight::Call if inputs.underlying > inputs.strike => 1.0,
+            OptionRight::Put if inputs.underlying < inputs.strike => -1.0,
+            _ => 0.0,
+        };
+        return Greeks {
+            delta,
+            ..Default::default()
+        };
+    };
+
+    let r = inputs.rate;
+    let b = model.cost_of_carry(r);
+    let t = inputs.time_to_expiry;
+    let pdf = norm_pdf(x.d1);
+    let carry_factor = ((b - r) * t).exp();
+    let decay = -x.carry * pdf * volatility / (2.0 * x.sqrt_t);
+
+    let (delta, theta, rho) = match inputs.right {
+        OptionRight::Call => (
+            carry_factor * norm_cdf(x.d1),
+            decay - (b - r) * x.carry * norm_cdf(x.d1) - r * x.discount * norm_cdf(x.d2),
+            x.discount * t * norm_cdf(x.d2),
+        ),
+        OptionRight::Put => (
+            carry_factor * (norm_cdf(x.d1) - 1.0),
+            decay + (b - r) * x.carry * norm_cdf(-x.d1) + r * x.discount * norm_cdf(-x.d2),
+            -x.discount * t * norm_cdf(-x.d2),
+        ),
+        OptionRight::No => return Greeks::default(),
+    };
+    // Black-76: the futures price does not move with the rate
+    let rho = match model {
+        PricingModel::Black76 => -t * price(model, inputs, volatility),
+        PricingModel::BlackScholes { .. } => rho,
+    };
+
+    Greeks {
+        delta,
+        gamma: carry_factor * pdf / (inputs.underlying * volatility * x.sqrt_t),
+        vega: x.carry * pdf * x.sqrt_t / 100.0,
+        theta: theta / 365.0,
+        rho: rho / 100.0,
+    }
+}
+
+/// Implied volatility of `market_price`
+///
+/// Newton steps safeguarded by bisection on [1e-6, 10], so it converges even
+/// for deep ITM/OTM options where vega vanishes. Prices outside the no-arbitrage
+/// bounds are rejected.
+pub fn implied_volatility(
+    model: PricingModel,
+    inputs: &OptionInputs,
+    market_price: f64,
+) -> Result<f64> {
+    if inputs.right == OptionRight::No {
+        return Err(Error::InvalidInput("Not an option".to_string()));
+    }
+    if inputs.time_to_expiry <= 0.0 {
+        return Err(Error::InvalidInput("Option has expired".to_string()));
+    }
+    let lower = intrinsic(model, inputs);
+    let upper = price(model, inputs, MAX_VOLATILITY);
+    if !market_price.is_finite() || market_price < lower - 1e-9 || market_price > upper {
+        return Err(Error::InvalidInput(format!(
+            "Price {} outside no-arbitrage bounds [{:.4}, {:.4}]",
+            market_price, lower, upper
+        )));
+    }
+
+    let (mut lo, mut hi) = (MIN_VOLATILITY, MAX_VOLATILITY);
+    // Brenner-Subrahmanyam initial guess, clamped into the bracket
+    let mut sigma = ((2.0 * std::f64::consts::PI / inputs.time_to_expiry).sqrt() * market_price
+        / inputs.underlying)
+        .clamp(0.05, 2.0);
+
+    for _ in 0..100 {
+        let diff = price(model, inputs, sigma) - market_price;
+        if diff.abs() < 1e-10 {
+            return Ok(sigma);
+        }
+        if diff > 0.0 {
+            hi = sigma;
+        } else {
+            lo = sigma;
+        }
+        if hi - lo < 1e-12 {
+            break;
+        }
+        let vega = greeks(model, inputs, sigma).vega * 100.0;
+        let newton = sigma - diff / vega;
+        sigma = if vega > 1e-12 && newton > lo && newton < hi {
+            newton
+        } else {
+            0.5 * (lo + hi)
+        };
+    }
+    Ok(sigma)
+}
+
+/// Analytics of a live option tick, priced off `tick.underlying_price`
+pub fn analyze_tick(
+    contract: &Contract,
+    tick: &TickFOPv1,
+    model: PricingModel,
+    rate: f64,
+) -> Result<OptionAnalytics> {
+    analyze(
+        contract,
+        tick.close,
+        tick.underlying_price,
+        tick.datetime,
+        model,
+        rate,
+    )
+}
+
+/// Analytics of a snapshot; uses the bid/ask mid when both sides are quoted
+pub fn analyze_snapshot(
+    snapshot: &Snapshot,
+    underlying: f64,
+    model: PricingModel,
+    rate: f64,
+) -> Result<OptionAnalytics> {
+    let market_price = if snapshot.bid_price > 0.0 && snapshot.ask_price > 0.0 {
+        0.5 * (snapshot.bid_price + snapshot.ask_price)
+    } else {
+        snapshot.close
+    };
+    analyze(
+        &snapshot.contract,
+        market_price,
+        underlying,
+        snapshot.ts,
+        model,
+        rate,
+    )
+}
+
+fn analyze(
+    contract: &Contract,
+    market_price: f64,
+    underlying: f64,
+    now: DateTime<Utc>,
+    model: PricingModel,
+    rate: f64,
+) -> Result<OptionAnalytics> {
+    let inputs = OptionInputs::from_contract(contract, underlying, now, rate)?;
+    let implied_volatility = implied_volatility(model, &inputs, market_price)?;
+    Ok(OptionAnalytics {
+        code: contract.base.code.clone(),
+        price: market_price,
+        underlying,
+        time_to_expiry: inputs.time_to_expiry,
+        implied_volatility,
+        greeks: greeks(model, &inputs, implied_volatility),
+    })
+}
+
+fn norm_pdf(x: f64) -> f64 {
+    (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+/// Standard normal CDF, double precision (Hart 1968 / West 2005)
+fn norm_cdf(x: f64) -> f64 {
+    let xabs = x.abs();
+    let tail = if xabs > 37.0 {
+        0.0
+    } else {
+        let e = (-xabs * xabs / 2.0).exp();
+        if xabs < 7.071_067_811_865_47 {
+            let mut n = 3.526_249_659_989_11e-2 * xabs + 0.700_383_064_443_688;
+            n = n * xabs + 6.373_962_203_531_65;
+            n = n * xabs + 33.912_866_078_383;
+            n = n * xabs + 112.079_291_497_871;
+            n = n * xabs + 221.213_596_169_931;
+            n = n * xabs + 220.206_867_912_376;
+            let mut d = 8.838_834_764_831_84e-2 * xabs + 1.755_667_163_182_64;
+            d = d * xabs + 16.064_177_579_207;
+            d = d * xabs + 86.780_732_202_946_1;
+            d = d * xabs + 296.564_248_779_674;
+            d = d * xabs + 637.333_633_378_831;
+            d = d * xabs + 793.826_512_519_948;
+            d = d * xabs + 440.413_735_824_752;
+            e * n / d
+        } else {
+            let mut b = xabs + 0.65;
+            b = xabs + 4.0 / b;
+            b = xabs + 3.0 / b;
+            b = xabs + 2.0 / b;
+            b = xabs + 1.0 / b;
+            e / b / 2.506_628_274_631
+        }
+    };
+    if x > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OptionContract;
+
+    fn inputs(right: OptionRight) -> OptionInputs {
+        OptionInputs {
+            right,
+            underlying: 100.0,
+            strike: 100.0,
+            time_to_expiry: 1.0,
+            rate: 0.05,
+        }
+    }
+
+    #[test]
+    fn test_black_scholes_reference_values() {
+        let model = PricingModel::black_scholes();
+        let call = price(model, &inputs(OptionRight::Call), 0.2);
+        let put = price(model, &inputs(OptionRight::Put), 0.2);
+        assert!((call - 10.450_583_572).abs() < 1e-6);
+        // put-call parity: C - P = S - K e^(-rT)
+        assert!((call - put - (100.0 - 100.0 * (-0.05f64).exp())).abs() < 1e-9);
+
+        let g = greeks(model, &inputs(OptionRight::Call), 0.2);
+        assert!((g.delta - 0.636_830_651).abs() < 1e-6);
+        assert!((g.gamma - 0.018_762_017).abs() < 1e-6);
+        assert!((g.vega - 0.375_240_347).abs() < 1e-6);
+        assert!((g.rho - 0.532_324_815).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_black76_greeks_match_finite_differences() {
+        let model = PricingModel::Black76;
+        let base = OptionInputs {
+            right: OptionRight::Put,
+            underlying: 17000.0,
+            strike: 17200.0,
+            time_to_expiry: 20.0 / 365.0,
+            rate: 0.015,
+        };
+        let g = greeks(model, &base, 0.18);
+        let h = 0.01;
+        let bumped = |underlying: f64| {
+            price(
+                model,
+                &OptionInputs {
+                    underlying,
+                    ..base.clone()
+                },
+                0.18,
+            )
+        };
+        let delta = (bumped(17000.0 + h) - bumped(17000.0 - h)) / (2.0 * h);
+        assert!((g.delta - delta).abs() < 1e-6);
+        let vega = (price(model, &base, 0.1801) - price(model, &base, 0.1799)) / 0.02;
+        assert!((g.vega - vega).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_implied_volatility_round_trip_and_bounds() {
+        let model = PricingModel::black_scholes();
+        for (right, strike, vol) in [
+            (OptionRight::Call, 100.0, 0.2),
+            (OptionRight::Put, 60.0, 0.65),
+            (OptionRight::Call, 180.0, 0.35),
+            (OptionRight::Put, 100.0, 0.01),
+        ] {
+            let input = OptionInputs {
+                strike,
+                ..inputs(right)
+            };
+            let p = price(model, &input, vol);
+            let iv = implied_volatility(model, &input, p).unwrap();
+            assert!((iv - vol).abs() < 1e-6, "{} vs {}", iv, vol);
+        }
+
+        assert!(implied_volatility(model, &inputs(OptionRight::Call), 1.0).is_err());
+        assert!(implied_volatility(model, &inputs(OptionRight::Call), 150.0).is_err());
+    }
+
+    #[test]
+    fn test_time_to_expiry_and_tick_analytics() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 16, 5, 30, 0).unwrap();
+        // 2024/01/17 13:30 台北 = 05:30 UTC，剩一天
+        let t = time_to_expiry("2024/01/17", now).unwrap();
+        assert!((t - 1.0 / 365.0).abs() < 1e-12);
+        assert_eq!(time_to_expiry("20240110", now).unwrap(), 0.0);
+        assert!(time_to_expiry("soon", now).is_err());
+
+        let mut contract = OptionContract::new("TXO17000A4", OptionRight::Call, 17000.0).contract;
+        contract.delivery_date = "2024/01/17".to_string();
+        let model = PricingModel::black_scholes();
+        let input = OptionInputs::from_contract(&contract, 17050.0, now, 0.0).unwrap();
+        let quote = price(model, &input, 0.15);
+
+        let tick = TickFOPv1 {
+            code: contract.base.code.clone(),
+            datetime: now,
+            close: quote,
+            underlying_price: 17050.0,
+            ..Default::default()
+        };
+        let analytics = analyze_tick(&contract, &tick, model, 0.0).unwrap();
+        assert!((analytics.implied_volatility - 0.15).abs() < 1e-6);
+        assert!(analytics.greeks.delta > 0.5 && analytics.greeks.theta < 0.0);
+    }
+}
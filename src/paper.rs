@@ -0,0 +1,1203 @@
+//! Paper trading (模擬撮合): a local matching engine fed by live or recorded ticks
+//!
+//! `PaperBroker` accepts the same `Order` / `FuturesOrder` types as
+//! `Shioaji::place_order` / `Shioaji::place_futures_order`, returns the same
+//! `Trade` objects and emits the same order/deal callback messages as
+//! `set_order_callback`. Orders are matched against `TickSTKv1` /
+//! `BidAskSTKv1` (or the FOP equivalents) with a configurable latency and
+//! queue-position model.
+//!
+//! Time is taken from the market data (`datetime`), so replaying recorded
+//! ticks gives deterministic fills.
+//!
+//! ```no_run
+//! # use rshioaji::paper::{PaperBroker, PaperConfig, QueueModel};
+//! # use rshioaji::{Action, Exchange, Order, OrderType, Stock, StockPriceType, TickSTKv1};
+//! # async fn run(ticks: Vec<TickSTKv1>) -> rshioaji::Result<()> {
+//! let broker = PaperBroker::new(PaperConfig {
+//!     latency: std::time::Duration::from_millis(50),
+//!     queue_model: QueueModel::QueuePosition,
+//!     ..Default::default()
+//! });
+//! broker.on_order(|event_type, msg| println!("{} {}", event_type, msg)).await?;
+//!
+//! let order = Order::new(Action::Buy, 580.0, 1, OrderType::ROD, StockPriceType::LMT);
+//! let trade = broker.place_order(Stock::new("2330", Exchange::TSE).contract, order).await?;
+//! for tick in &ticks {
+//!     broker.on_tick_stk(tick);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::client::{OrderEventCallback, Shioaji, TickFOPCallback, TickSTKCallback};
+use crate::error::{Error, Result};
+use crate::types::{
+    Account, AccountType, Action, BidAskFOPv1, BidAskSTKv1, Contract, Contracts, Exchange,
+    FuturesOrder, FuturesPriceType, FuturesTrade, Margin, Order, OrderEventType, OrderTemplate,
+    OrderType, Position, SecurityType, Status, StockOrderCond, StockPriceType, TickFOPv1,
+    TickSTKv1, Trade, Unit,
+};
+
+/// How a resting limit order's place in the exchange queue is modelled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueueModel {
+    /// Filled by any trade at or through the limit price
+    Optimistic,
+    /// Joins the back of the visible queue at its price; trades at the limit
+    /// price consume the queue ahead first, trades through it fill directly
+    #[default]
+    QueuePosition,
+    /// Filled only when the market trades through the limit price
+    TradeThrough,
+}
+
+/// Paper broker settings
+#[derive(Debug, Clone)]
+pub struct PaperConfig {
+    /// Delay between placing an order and it reaching the (simulated) exchange
+    pub latency: Duration,
+    pub queue_model: QueueModel,
+    /// Account used for stock orders without an explicit account
+    pub stock_account: Account,
+    /// Account used for futures/options orders without an explicit account
+    pub futopt_account: Account,
+    /// Cash in the futures/options account, reported by `margin()` (權益數起點)
+    pub margin_balance: f64,
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::from_millis(0),
+            queue_model: QueueModel::default(),
+            stock_account: Account::new(
+                "PAPER".to_string(),
+                "0000000".to_string(),
+                AccountType::Stock,
+                "paper".to_string(),
+                true,
+            ),
+            futopt_account: Account::new(
+                "PAPER".to_string(),
+                "0000000".to_string(),
+                AccountType::Future,
+                "paper".to_string(),
+                true,
+            ),
+            margin_balance: 0.0,
+        }
+    }
+}
+
+/// Latest visible order book of one contract
+#[derive(Debug, Clone, Default)]
+struct Book {
+    bids: Vec<(f64, i64)>,
+    asks: Vec<(f64, i64)>,
+}
+
+impl Book {
+    fn from_levels(
+        bid_price: &[f64],
+        bid_volume: &[i64],
+        ask_price: &[f64],
+        ask_volume: &[i64],
+    ) -> Self {
+        let levels = |prices: &[f64], volumes: &[i64]| {
+            prices
+                .iter()
+                .zip(volumes)
+                .filter(|(p, v)| **p > 0.0 && **v > 0)
+                .map(|(p, v)| (*p, *v))
+                .collect()
+        };
+        Self {
+            bids: levels(bid_price, bid_volume),
+            asks: levels(ask_price, ask_volume),
+        }
+    }
+
+    /// Visible volume resting at `price` on our own side
+    fn volume_at(&self, action: &Action, price: f64) -> i64 {
+        let side = match action {
+            Action::Buy => &self.bids,
+            Action::Sell => &self.asks,
+        };
+        side.iter()
+            .find(|(p, _)| same_price(*p, price))
+            .map(|(_, v)| *v)
+            .unwrap_or(0)
+    }
+
+    /// Opposite side levels, best first
+    fn opposite(&self, action: &Action) -> &[(f64, i64)] {
+        match action {
+            Action::Buy => &self.asks,
+            Action::Sell => &self.bids,
+        }
+    }
+}
+
+struct PaperOrder {
+    id: String,
+    seqno: String,
+    ordno: String,
+    contract: Contract,
+    order: OrderTemplate,
+    account: Account,
+    status: Status,
+    filled: i32,
+    /// Sum of fill price × quantity
+    fill_value: f64,
+    cancelled: i32,
+    /// When the order reaches the exchange; `None` until the first market
+    /// event after placement when no market time was known yet
+    active_at: Option<DateTime<Utc>>,
+    activated: bool,
+    queue_ahead: i64,
+}
+
+impl PaperOrder {
+    fn action(&self) -> &Action {
+        self.order.action()
+    }
+
+    fn remaining(&self) -> i32 {
+        (self.order.quantity() - self.filled - self.cancelled).max(0)
+    }
+
+    fn is_market(&self) -> bool {
+        match &self.order {
+            OrderTemplate::Stock(order) => order.price_type == StockPriceType::MKT,
+            OrderTemplate::Futures(order) => order.price_type != FuturesPriceType::LMT,
+        }
+    }
+
+    fn order_type(&self) -> &OrderType {
+        match &self.order {
+            OrderTemplate::Stock(order) => &order.order_type,
+            OrderTemplate::Futures(order) => &order.order_type,
+        }
+    }
+
+    /// Whether a trade or quote at `price` is at least as good as our limit
+    fn accepts(&self, price: f64) -> bool {
+        if self.is_market() {
+            return true;
+        }
+        let limit = self.order.price();
+        match self.action() {
+            Action::Buy => price < limit || same_price(price, limit),
+            Action::Sell => price > limit || same_price(price, limit),
+        }
+    }
+
+    /// Whether `price` is strictly better than our limit (traded through)
+    fn through(&self, price: f64) -> bool {
+        self.accepts(price) && (self.is_market() || !same_price(price, self.order.price()))
+    }
+
+    fn trade(&self) -> Trade {
+        let order = match &self.order {
+            OrderTemplate::Stock(order) => order.clone(),
+            OrderTemplate::Futures(order) => Order::new(
+                order.action.clone(),
+                order.price,
+                order.quantity,
+                order.order_type.clone(),
+                StockPriceType::LMT,
+            ),
+        };
+        Trade {
+            order,
+            status: self.status.clone(),
+            order_id: self.id.clone(),
+            seqno: self.seqno.clone(),
+            ordno: self.ordno.clone(),
+            account: self.account.clone(),
+            contracts: vec![self.contract.clone()],
+        }
+    }
+}
+
+#[derive(Default)]
+struct PaperState {
+    orders: Vec<PaperOrder>,
+    books: HashMap<String, Book>,
+    clock: Option<DateTime<Utc>>,
+    last_prices: HashMap<String, f64>,
+    seq: u64,
+    deal_seq: u64,
+}
+
+impl PaperState {
+    fn order_mut(&mut self, order_id: &str) -> Result<&mut PaperOrder> {
+        self.orders
+            .iter_mut()
+            .find(|o| o.id == order_id)
+            .ok_or_else(|| Error::InvalidOrder(format!("Unknown paper order {}", order_id)))
+    }
+}
+
+/// Pending order/deal callback messages, dispatched after the state lock is released
+type Events = Vec<(OrderEventType, serde_json::Value)>;
+
+/// Local paper-trading broker
+pub struct PaperBroker {
+    config: PaperConfig,
+    state: Mutex<PaperState>,
+    callbacks: Mutex<Vec<OrderEventCallback>>,
+    tick_stk_callbacks: Mutex<Vec<TickSTKCallback>>,
+    tick_fop_callbacks: Mutex<Vec<TickFOPCallback>>,
+    contracts: Option<Contracts>,
+}
+
+impl PaperBroker {
+    pub fn new(config: PaperConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(PaperState::default()),
+            callbacks: Mutex::new(Vec::new()),
+            tick_stk_callbacks: Mutex::new(Vec::new()),
+            tick_fop_callbacks: Mutex::new(Vec::new()),
+            contracts: None,
+        }
+    }
+
+    /// Contracts served through `Backend::contracts`
+    pub fn with_contracts(mut self, contracts: Contracts) -> Self {
+        self.contracts = Some(contracts);
+        self
+    }
+
+    pub fn contracts(&self) -> Option<&Contracts> {
+        self.contracts.as_ref()
+    }
+
+    pub fn config(&self) -> &PaperConfig {
+        &self.config
+    }
+
+    /// Register an order/deal callback (same messages as `Shioaji::on_order`)
+    pub async fn on_order<F>(&self, callback: F) -> Result<()>
+    where
+        F: Fn(OrderEventType, serde_json::Value) + Send + Sync + 'static,
+    {
+        let mut callbacks = self
+            .callbacks
+            .lock()
+            .map_err(|_| Error::System("Paper callback lock poisoned".to_string()))?;
+        callbacks.push(Arc::new(callback));
+        Ok(())
+    }
+
+    /// Place a stock order
+    pub async fn place_order(&self, contract: Contract, order: Order) -> Result<Trade> {
+        validate(
+            order.quantity,
+            order.price,
+            order.price_type == StockPriceType::MKT,
+        )?;
+        let account = order
+            .account
+            .clone()
+            .unwrap_or_else(|| self.config.stock_account.clone());
+        self.submit(contract, OrderTemplate::Stock(order), account)
+    }
+
+    /// Place a futures/options order
+    pub async fn place_futures_order(
+        &self,
+        contract: Contract,
+        order: FuturesOrder,
+    ) -> Result<FuturesTrade> {
+        validate(
+            order.quantity,
+            order.price,
+            order.price_type != FuturesPriceType::LMT,
+        )?;
+        let account = order
+            .account
+            .clone()
+            .unwrap_or_else(|| self.config.futopt_account.clone());
+        let trade = self.submit(contract, OrderTemplate::Futures(order.clone()), account)?;
+        Ok(FuturesTrade {
+            order,
+            status: trade.status,
+            order_id: trade.order_id,
+            seqno: trade.seqno,
+            ordno: trade.ordno,
+            account: trade.account,
+            contracts: trade.contracts,
+        })
+    }
+
+    /// Change price and/or reduce quantity (`qty` is the quantity to cancel,
+    /// as in Python `update_order`). A price change loses queue position.
+    pub async fn update_order(
+        &self,
+        trade: Trade,
+        price: Option<f64>,
+        qty: Option<i32>,
+        _timeout: Option<i32>,
+    ) -> Result<Trade> {
+        let mut events = Events::new();
+        let updated = {
+            let mut state = self.lock_state()?;
+            let order = state.order_mut(&trade.order_id)?;
+            if !order.status.is_open() {
+                return Err(Error::InvalidOrder(format!(
+                    "Paper order {} is {}",
+                    order.id, order.status
+                )));
+            }
+
+            if let Some(price) = price {
+                if price <= 0.0 {
+                    return Err(Error::InvalidOrder("Price must be positive".to_string()));
+                }
+                match &mut order.order {
+                    OrderTemplate::Stock(o) => o.price = price,
+                    OrderTemplate::Futures(o) => o.price = price,
+                }
+                order.queue_ahead = i64::MAX;
+                events.push(order_event(order, "UpdatePrice"));
+            }
+            if let Some(qty) = qty {
+                order.cancelled += qty.clamp(0, order.remaining());
+                if order.remaining() == 0 {
+                    order.status = Status::Cancelled;
+                }
+                events.push(order_event(order, "UpdateQty"));
+            }
+            order.trade()
+        };
+        self.dispatch(events);
+        Ok(updated)
+    }
+
+    /// Cancel the remaining quantity of an order
+    pub async fn cancel_order(&self, trade: Trade, _timeout: Option<i32>) -> Result<Trade> {
+        let mut events = Events::new();
+        let cancelled = {
+            let mut state = self.lock_state()?;
+            let order = state.order_mut(&trade.order_id)?;
+            if !order.status.is_open() {
+                return Err(Error::InvalidOrder(format!(
+                    "Paper order {} is {}",
+                    order.id, order.status
+                )));
+            }
+            cancel_remaining(order, &mut events);
+            order.trade()
+        };
+        self.dispatch(events);
+        Ok(cancelled)
+    }
+
+    /// All paper trades, oldest first (futures orders are shown stock-shaped,
+    /// like `FuturesTrade::as_trade`)
+    pub async fn list_trades(&self) -> Result<Vec<Trade>> {
+        let state = self.lock_state()?;
+        Ok(state.orders.iter().map(|o| o.trade()).collect())
+    }
+
+    /// Net positions from paper fills at average cost. Stock quantities are
+    /// in lots (`Unit::Common`, the default) or shares (`Unit::Share`); `pnl`
+    /// is in money (share count or futures multiplier applied), like the
+    /// broker's
+    pub async fn list_positions(
+        &self,
+        account: Option<&Account>,
+        unit: Option<Unit>,
+    ) -> Result<Vec<Position>> {
+        let state = self.lock_state()?;
+        let mut positions: Vec<Position> = Vec::new();
+        // 每張股數 (期貨為 1) 與每單位金額 (期貨為契約乘數)
+        let mut sizes: HashMap<String, (i64, f64)> = HashMap::new();
+        for order in state.orders.iter().filter(|o| o.filled > 0) {
+            if account.is_some_and(|a| a.account_id != order.account.account_id) {
+                continue;
+            }
+            let code = &order.contract.base.code;
+            // 現股、融資、融券各自成一筆部位
+            let cond = match &order.order {
+                OrderTemplate::Stock(stock) => stock.order_cond.clone().unwrap_or_default(),
+                OrderTemplate::Futures(_) => StockOrderCond::Cash,
+            };
+            // 股票部位以股計：零股成交即股數，整股乘上每張股數
+            let multiplier = crate::portfolio::contract_multiplier(&order.contract);
+            let (lot_size, point_value) = match order.contract.base.security_type {
+                SecurityType::Future | SecurityType::Option => (1, multiplier),
+                _ => (multiplier as i64, 1.0),
+            };
+            let odd_lot = matches!(&order.order, OrderTemplate::Stock(stock)
+                if stock.order_lot.as_ref().is_some_and(|lot| lot.is_odd()));
+            let per_fill = if odd_lot { 1 } else { lot_size };
+            sizes.insert(code.clone(), (lot_size, point_value));
+            let index = match positions.iter().position(|p| {
+                &p.code == code
+                    && p.account.account_id == order.account.account_id
+                    && p.cond == cond
+            }) {
+                Some(index) => index,
+                None => {
+                    positions.push(Position {
+                        account: order.account.clone(),
+                        code: code.clone(),
+                        quantity: 0,
+                        price: 0.0,
+                        last_price: 0.0,
+                        pnl: 0.0,
+                        yd_quantity: 0,
+                        direction: Action::Buy,
+                        cond,
+                    });
+                    positions.len() - 1
+                }
+            };
+            let position = &mut positions[index];
+            let held = match position.direction {
+                Action::Buy => position.quantity,
+                Action::Sell => -position.quantity,
+            };
+            let fill = match order.action() {
+                Action::Buy => order.filled as i64 * per_fill,
+                Action::Sell => -(order.filled as i64 * per_fill),
+            };
+            let fill_price = order.fill_value / order.filled as f64;
+            let net = held + fill;
+            if held == 0 || held.signum() == fill.signum() {
+                position.price = (position.price * held.abs() as f64
+                    + fill_price * fill.abs() as f64)
+                    / net.abs() as f64;
+            } else if net == 0 {
+                position.price = 0.0;
+            } else if net.signum() != held.signum() {
+                // 反手：剩餘部位以本次成交價計
+                position.price = fill_price;
+            }
+            position.quantity = net.abs();
+            if net != 0 {
+                position.direction = if net > 0 { Action::Buy } else { Action::Sell };
+            }
+        }
+        positions.retain(|p| p.quantity > 0);
+        for position in &mut positions {
+            position.last_price = state
+                .last_prices
+                .get(&position.code)
+                .copied()
+                .unwrap_or(position.price);
+            let sign = match position.direction {
+                Action::Buy => 1.0,
+                Action::Sell => -1.0,
+            };
+            let (lot_size, point_value) = sizes.get(&position.code).copied().unwrap_or((1, 1.0));
+            position.pnl = sign
+                * (position.last_price - position.price)
+                * position.quantity as f64
+                * point_value;
+            if unit != Some(Unit::Share) {
+                position.quantity /= lot_size.max(1);
+            }
+        }
+        positions.retain(|p| p.quantity > 0);
+        Ok(positions)
+    }
+
+    /// Futures/options account equity: `PaperConfig::margin_balance` plus the
+    /// unrealized pnl of open futures/options positions. Paper orders post no
+    /// margin, so the whole equity is available.
+    pub async fn margin(&self, account: Option<&Account>) -> Result<Margin> {
+        let unrealized_pnl: f64 = self
+            .list_positions(account, None)
+            .await?
+            .iter()
+            .filter(|p| p.account.account_type == AccountType::Future)
+            .map(|p| p.pnl)
+            .sum();
+        let account_balance = self.config.margin_balance;
+        Ok(Margin {
+            account_balance,
+            available_margin: account_balance + unrealized_pnl,
+            initial_margin: 0.0,
+            maintenance_margin: 0.0,
+            margin_call: 0.0,
+            unrealized_pnl,
+        })
+    }
+
+    /// Forward fed stock ticks to `callback` (after matching)
+    pub fn on_tick_stk_v1<F>(&self, callback: F) -> Result<()>
+    where
+        F: Fn(Exchange, TickSTKv1) + Send + Sync + 'static,
+    {
+        self.tick_stk_callbacks
+            .lock()
+            .map_err(|_| Error::System("Paper callback lock poisoned".to_string()))?
+            .push(Arc::new(callback));
+        Ok(())
+    }
+
+    /// Forward fed futures/options ticks to `callback` (after matching)
+    pub fn on_tick_fop_v1<F>(&self, callback: F) -> Result<()>
+    where
+        F: Fn(Exchange, TickFOPv1) + Send + Sync + 'static,
+    {
+        self.tick_fop_callbacks
+            .lock()
+            .map_err(|_| Error::System("Paper callback lock poisoned".to_string()))?
+            .push(Arc::new(callback));
+        Ok(())
+    }
+
+    /// Feed a stock tick
+    pub fn on_tick_stk(&self, tick: &TickSTKv1) {
+        if tick.simtrade {
+            return;
+        }
+        self.on_trade(&tick.code, tick.datetime, tick.close, tick.volume);
+        let callbacks = match self.tick_stk_callbacks.lock() {
+            Ok(callbacks) => callbacks.clone(),
+            Err(_) => return,
+        };
+        for callback in &callbacks {
+            callback(Exchange::TSE, tick.clone());
+        }
+    }
+
+    /// Feed a futures/options tick
+    pub fn on_tick_fop(&self, tick: &TickFOPv1) {
+        if tick.simtrade {
+            return;
+        }
+        self.on_trade(&tick.code, tick.datetime, tick.close, tick.volume);
+        let callbacks = match self.tick_fop_callbacks.lock() {
+            Ok(callbacks) => callbacks.clone(),
+            Err(_) => return,
+        };
+        for callback in &callbacks {
+            callback(Exchange::TAIFEX, tick.clone());
+        }
+    }
+
+    /// Feed a stock order book snapshot
+    pub fn on_bidask_stk(&self, quote: &BidAskSTKv1) {
+        if quote.simtrade {
+            return;
+        }
+        let book = Book::from_levels(
+            &quote.bid_price,
+            &quote.bid_volume,
+            &quote.ask_price,
+            &quote.ask_volume,
+        );
+        self.on_book(&quote.code, quote.datetime, book);
+    }
+
+    /// Feed a futures/options order book snapshot
+    pub fn on_bidask_fop(&self, quote: &BidAskFOPv1) {
+        if quote.simtrade {
+            return;
+        }
+        let book = Book::from_levels(
+            &quote.bid_price,
+            &quote.bid_volume,
+            &quote.ask_price,
+            &quote.ask_volume,
+        );
+        self.on_book(&quote.code, quote.datetime, book);
+    }
+
+    /// Feed live ticks from a logged-in client into this broker
+    pub async fn attach(self: &Arc<Self>, client: &Shioaji) -> Result<()> {
+        let broker = self.clone();
+        client
+            .on_tick_stk_v1(move |_exchange, tick| broker.on_tick_stk(&tick), false)
+            .await?;
+        let broker = self.clone();
+        client
+            .on_tick_fop_v1(move |_exchange, tick| broker.on_tick_fop(&tick), false)
+            .await?;
+        log::info!("✅ Paper broker attached to live ticks");
+        Ok(())
+    }
+
+    fn submit(&self, contract: Contract, order: OrderTemplate, account: Account) -> Result<Trade> {
+        let mut state = self.lock_state()?;
+        state.seq += 1;
+        let seq = state.seq;
+        let active_at = state.clock.map(|clock| clock + self.latency());
+
+        let paper_order = PaperOrder {
+            // 由流水號產生，同一模擬券商內不會重複
+            id: format!("{:08x}", seq),
+            seqno: format!("{:06}", seq),
+            ordno: format!("P{:05}", seq % 100_000),
+            contract,
+            order,
+            account,
+            status: Status::PendingSubmit,
+            filled: 0,
+            fill_value: 0.0,
+            cancelled: 0,
+            active_at,
+            activated: false,
+            queue_ahead: 0,
+        };
+        log::info!(
+            "📝 Paper order {} placed: {} {} x{} @ {}",
+            paper_order.id,
+            paper_order.action(),
+            paper_order.contract.base.code,
+            paper_order.order.quantity(),
+            paper_order.order.price()
+        );
+        let trade = paper_order.trade();
+        state.orders.push(paper_order);
+        Ok(trade)
+    }
+
+    fn on_trade(&self, code: &str, ts: DateTime<Utc>, price: f64, volume: i64) {
+        if price <= 0.0 {
+            return;
+        }
+        let mut events = Events::new();
+        if let Ok(mut guard) = self.state.lock() {
+            let state = &mut *guard;
+            advance_clock(state, ts);
+            state.last_prices.insert(code.to_string(), price);
+            self.activate(state, code, ts, &mut events);
+
+            let mut volume = volume.max(0);
+            for order in state
+                .orders
+                .iter_mut()
+                .filter(|o| o.activated && o.status.is_open() && o.contract.base.code == code)
+            {
+                if volume <= 0 {
+                    break;
+                }
+                let fill = match self.config.queue_model {
+                    _ if order.is_market() || order.through(price) => volume,
+                    QueueModel::Optimistic if order.accepts(price) => volume,
+                    QueueModel::QueuePosition if order.accepts(price) => {
+                        let consumed = volume.min(order.queue_ahead);
+                        order.queue_ahead -= consumed;
+                        volume - consumed
+                    }
+                    _ => 0,
+                };
+                let quantity = (fill.min(order.remaining() as i64)) as i32;
+                if quantity > 0 {
+                    let fill_price = if order.is_market() {
+                        price
+                    } else {
+                        order.order.price()
+                    };
+                    fill_order(
+                        &mut state.deal_seq,
+                        order,
+                        fill_price,
+                        quantity,
+                        ts,
+                        &mut events,
+                    );
+                    volume -= quantity as i64;
+                }
+            }
+        }
+        self.dispatch(events);
+    }
+
+    fn on_book(&self, code: &str, ts: DateTime<Utc>, book: Book) {
+        let mut events = Events::new();
+        if let Ok(mut guard) = self.state.lock() {
+            let state = &mut *guard;
+            advance_clock(state, ts);
+            state.books.insert(code.to_string(), book);
+            self.activate(state, code, ts, &mut events);
+
+            let book = state.books.get_mut(code).expect("book inserted above");
+            for order in state
+                .orders
+                .iter_mut()
+                .filter(|o| o.activated && o.status.is_open() && o.contract.base.code == code)
+            {
+                // The visible queue at our price can only shrink ahead of us
+                let visible = book.volume_at(order.action(), order.order.price());
+                order.queue_ahead = order.queue_ahead.min(visible);
+                cross_book(&mut state.deal_seq, order, book, ts, &mut events);
+            }
+        }
+        self.dispatch(events);
+    }
+
+    /// Activate orders of `code` whose latency has elapsed, matching
+    /// marketable ones against the current book
+    fn activate(&self, state: &mut PaperState, code: &str, ts: DateTime<Utc>, events: &mut Events) {
+        let latency = self.latency();
+        let mut empty = Book::default();
+        let book = state.books.get_mut(code).unwrap_or(&mut empty);
+        for order in state
+            .orders
+            .iter_mut()
+            .filter(|o| !o.activated && o.status.is_open() && o.contract.base.code == code)
+        {
+            let active_at = *order.active_at.get_or_insert(ts + latency);
+            if active_at > ts {
+                continue;
+            }
+            order.activated = true;
+            order.status = Status::Submitted;
+            order.queue_ahead = book.volume_at(order.action(), order.order.price());
+            events.push(order_event(order, "New"));
+
+            cross_book(&mut state.deal_seq, order, book, ts, events);
+
+            let immediate = matches!(order.order_type(), OrderType::IOC | OrderType::FOK);
+            if immediate && order.status.is_open() {
+                cancel_remaining(order, events);
+            }
+        }
+    }
+
+    /// Configured latency, capped so timestamp arithmetic cannot overflow
+    fn latency(&self) -> chrono::Duration {
+        let cap = chrono::Duration::days(365);
+        chrono::Duration::from_std(self.config.latency).map_or(cap, |latency| latency.min(cap))
+    }
+
+    fn lock_state(&self) -> Result<MutexGuard<'_, PaperState>> {
+        self.state
+            .lock()
+            .map_err(|_| Error::System("Paper state lock poisoned".to_string()))
+    }
+
+    fn dispatch(&self, events: Events) {
+        if events.is_empty() {
+            return;
+        }
+        let callbacks = match self.callbacks.lock() {
+            Ok(callbacks) => callbacks.clone(),
+            Err(_) => return,
+        };
+        for (event_type, msg) in events {
+            for callback in &callbacks {
+                callback(event_type.clone(), msg.clone());
+            }
+        }
+    }
+}
+
+fn validate(quantity: i32, price: f64, is_market: bool) -> Result<()> {
+    if quantity <= 0 {
+        return Err(Error::InvalidOrder("Quantity must be positive".to_string()));
+    }
+    if !is_market && price <= 0.0 {
+        return Err(Error::InvalidOrder(
+            "Limit price must be positive".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn advance_clock(state: &mut PaperState, ts: DateTime<Utc>) {
+    state.clock = Some(state.clock.map_or(ts, |clock| clock.max(ts)));
+}
+
+/// Match an order against the opposite side of the book, consuming liquidity
+fn cross_book(
+    deal_seq: &mut u64,
+    order: &mut PaperOrder,
+    book: &mut Book,
+    ts: DateTime<Utc>,
+    events: &mut Events,
+) {
+    if order.order_type() == &OrderType::FOK {
+        let available: i64 = book
+            .opposite(order.action())
+            .iter()
+            .filter(|(price, _)| order.accepts(*price))
+            .map(|(_, volume)| *volume)
+            .sum();
+        if available < order.remaining() as i64 {
+            return;
+        }
+    }
+
+    let levels = match order.action() {
+        Action::Buy => &mut book.asks,
+        Action::Sell => &mut book.bids,
+    };
+    for (price, volume) in levels.iter_mut() {
+        if order.remaining() == 0 || !order.accepts(*price) {
+            break;
+        }
+        let quantity = (*volume).min(order.remaining() as i64) as i32;
+        if quantity > 0 {
+            *volume -= quantity as i64;
+            fill_order(deal_seq, order, *price, quantity, ts, events);
+        }
+    }
+    levels.retain(|(_, volume)| *volume > 0);
+}
+
+fn fill_order(
+    deal_seq: &mut u64,
+    order: &mut PaperOrder,
+    price: f64,
+    quantity: i32,
+    ts: DateTime<Utc>,
+    events: &mut Events,
+) {
+    *deal_seq += 1;
+    order.filled += quantity;
+    order.fill_value += price * quantity as f64;
+    order.status = if order.remaining() == 0 {
+        Status::Filled
+    } else {
+        Status::PartFilled
+    };
+    log::info!(
+        "💰 Paper fill {} {} x{} @ {}",
+        order.id,
+        order.contract.base.code,
+        quantity,
+        price
+    );
+
+    let event_type = match order.order {
+        OrderTemplate::Stock(_) => OrderEventType::StockDeal,
+        OrderTemplate::Futures(_) => OrderEventType::FuturesDeal,
+    };
+    let ts_secs = ts.timestamp() as f64 + ts.timestamp_subsec_nanos() as f64 / 1e9;
+    let msg = json!({
+        "trade_id": order.id,
+        "seqno": order.seqno,
+        "ordno": order.ordno,
+        "exchange_seq": format!("{:06}", deal_seq),
+        "broker_id": order.account.broker_id,
+        "account_id": order.account.account_id,
+        "action": order.action().to_string(),
+        "code": order.contract.base.code,
+        "security_type": order.contract.base.security_type.to_string(),
+        "price": price,
+        "quantity": quantity,
+        "ts": ts_secs,
+    });
+    events.push((event_type, msg));
+}
+
+fn cancel_remaining(order: &mut PaperOrder, events: &mut Events) {
+    order.cancelled += order.remaining();
+    order.status = Status::Cancelled;
+    events.push(order_event(order, "Cancel"));
+}
+
+/// Order (委託) callback message in the shape of Python `set_order_callback`
+fn order_event(order: &PaperOrder, op_type: &str) -> (OrderEventType, serde_json::Value) {
+    let event_type = match order.contract.base.security_type {
+        SecurityType::Future | SecurityType::Option => OrderEventType::FuturesOrder,
+        _ => OrderEventType::StockOrder,
+    };
+    let msg = json!({
+        "operation": {"op_type": op_type, "op_code": "00", "op_msg": ""},
+        "order": {
+            "id": order.id,
+            "seqno": order.seqno,
+            "ordno": order.ordno,
+            "account": {
+                "account_type": order.account.account_type.to_string(),
+                "broker_id": order.account.broker_id,
+                "account_id": order.account.account_id,
+            },
+            "action": order.action().to_string(),
+            "price": order.order.price(),
+            "quantity": order.remaining(),
+            "order_type": order.order_type().to_string(),
+        },
+        "status": {
+            "id": order.id,
+            "order_quantity": order.order.quantity(),
+            "cancel_quantity": order.cancelled,
+        },
+        "contract": {
+            "security_type": order.contract.base.security_type.to_string(),
+            "exchange": order.contract.base.exchange.to_string(),
+            "code": order.contract.base.code,
+        },
+    });
+    (event_type, msg)
+}
+
+fn same_price(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Deal, Exchange, Future, FuturesOCType, Stock, StockOrderLot};
+    use chrono::TimeZone;
+
+    fn at(second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 2, 1, 30, second).unwrap()
+    }
+
+    fn tick(price: f64, volume: i64, second: u32) -> TickSTKv1 {
+        TickSTKv1 {
+            code: "2330".to_string(),
+            datetime: at(second),
+            close: price,
+            volume,
+            ..Default::default()
+        }
+    }
+
+    fn bidask(bid: (f64, i64), ask: (f64, i64), second: u32) -> BidAskSTKv1 {
+        BidAskSTKv1 {
+            code: "2330".to_string(),
+            datetime: at(second),
+            bid_price: vec![bid.0],
+            bid_volume: vec![bid.1],
+            ask_price: vec![ask.0],
+            ask_volume: vec![ask.1],
+            ..Default::default()
+        }
+    }
+
+    fn buy(price: f64, quantity: i32, order_type: OrderType) -> Order {
+        Order::new(
+            Action::Buy,
+            price,
+            quantity,
+            order_type,
+            StockPriceType::LMT,
+        )
+    }
+
+    fn stock() -> Contract {
+        Stock::new("2330", Exchange::TSE).contract
+    }
+
+    async fn recorded(
+        broker: &PaperBroker,
+    ) -> Arc<Mutex<Vec<(OrderEventType, serde_json::Value)>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        broker
+            .on_order(move |event_type, msg| {
+                sink.lock().unwrap().push((event_type, msg));
+            })
+            .await
+            .unwrap();
+        events
+    }
+
+    #[tokio::test]
+    async fn test_queue_position_fills_after_queue_ahead() {
+        let broker = PaperBroker::new(PaperConfig::default());
+        let events = recorded(&broker).await;
+        broker.on_bidask_stk(&bidask((580.0, 5), (581.0, 10), 0));
+
+        let trade = broker
+            .place_order(stock(), buy(580.0, 3, OrderType::ROD))
+            .await
+            .unwrap();
+        assert_eq!(trade.status, Status::PendingSubmit);
+
+        // Activation happens on the next market event; 5 lots are ahead of us
+        broker.on_tick_stk(&tick(580.0, 4, 1));
+        broker.on_tick_stk(&tick(580.0, 3, 2));
+
+        let trades = broker.list_trades().await.unwrap();
+        assert_eq!(trades[0].status, Status::PartFilled);
+
+        broker.on_tick_stk(&tick(579.0, 10, 3));
+        let trades = broker.list_trades().await.unwrap();
+        assert_eq!(trades[0].status, Status::Filled);
+
+        let deals: Vec<Deal> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(event_type, msg)| Deal::from_event(event_type, msg))
+            .collect();
+        let quantities: Vec<i32> = deals.iter().map(|d| d.quantity).collect();
+        assert_eq!(quantities, vec![2, 1]);
+        assert!(deals
+            .iter()
+            .all(|d| d.trade_id == trade.order_id && d.price == 580.0));
+    }
+
+    #[tokio::test]
+    async fn test_trade_through_model_ignores_prints_at_limit() {
+        let broker = PaperBroker::new(PaperConfig {
+            queue_model: QueueModel::TradeThrough,
+            ..Default::default()
+        });
+        broker
+            .place_order(stock(), buy(580.0, 1, OrderType::ROD))
+            .await
+            .unwrap();
+        broker.on_tick_stk(&tick(580.0, 100, 0));
+        assert_eq!(
+            broker.list_trades().await.unwrap()[0].status,
+            Status::Submitted
+        );
+        broker.on_tick_stk(&tick(579.5, 1, 1));
+        assert_eq!(
+            broker.list_trades().await.unwrap()[0].status,
+            Status::Filled
+        );
+    }
+
+    #[tokio::test]
+    async fn test_latency_delays_activation() {
+        let broker = PaperBroker::new(PaperConfig {
+            latency: Duration::from_secs(2),
+            ..Default::default()
+        });
+        broker.on_bidask_stk(&bidask((580.0, 5), (581.0, 10), 0));
+        broker
+            .place_order(stock(), buy(581.0, 2, OrderType::ROD))
+            .await
+            .unwrap();
+
+        broker.on_bidask_stk(&bidask((580.0, 5), (581.0, 10), 1));
+        assert_eq!(
+            broker.list_trades().await.unwrap()[0].status,
+            Status::PendingSubmit
+        );
+
+        broker.on_bidask_stk(&bidask((580.0, 5), (581.0, 10), 2));
+        assert_eq!(
+            broker.list_trades().await.unwrap()[0].status,
+            Status::Filled
+        );
+    }
+
+    #[tokio::test]
+    async fn test_order_ids_follow_sequence() {
+        let broker = PaperBroker::new(PaperConfig::default());
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let trade = broker
+                .place_order(stock(), buy(580.0, 1, OrderType::ROD))
+                .await
+                .unwrap();
+            ids.push(trade.order_id);
+        }
+        assert_eq!(ids, vec!["00000001", "00000002", "00000003"]);
+    }
+
+    #[tokio::test]
+    async fn test_ioc_cancels_unfilled_remainder() {
+        let broker = PaperBroker::new(PaperConfig::default());
+        let events = recorded(&broker).await;
+        broker.on_bidask_stk(&bidask((580.0, 5), (581.0, 2), 0));
+        broker
+            .place_order(stock(), buy(581.0, 5, OrderType::IOC))
+            .await
+            .unwrap();
+        broker.on_tick_stk(&tick(580.0, 1, 1));
+
+        let trade = &broker.list_trades().await.unwrap()[0];
+        assert_eq!(trade.status, Status::Cancelled);
+        let ops: Vec<String> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(_, msg)| msg.pointer("/operation/op_type").map(|v| v.to_string()))
+            .collect();
+        assert_eq!(ops, vec!["\"New\"", "\"Cancel\""]);
+    }
+
+    #[tokio::test]
+    async fn test_futures_market_order_and_cancel() {
+        let broker = PaperBroker::new(PaperConfig::default());
+        let mut contract = Future::new("TXFA4").contract;
+        contract.multiplier = 200;
+        let order = FuturesOrder::new(
+            Action::Sell,
+            0.0,
+            2,
+            OrderType::ROD,
+            FuturesPriceType::MKT,
+            FuturesOCType::Auto,
+        );
+        let trade = broker.place_futures_order(contract, order).await.unwrap();
+        assert_eq!(trade.account.account_type, AccountType::Future);
+
+        broker.on_tick_fop(&TickFOPv1 {
+            code: "TXFA4".to_string(),
+            datetime: at(0),
+            close: 17000.0,
+            volume: 1,
+            ..Default::default()
+        });
+        let cancelled = broker.cancel_order(trade.as_trade(), None).await.unwrap();
+        assert_eq!(cancelled.status, Status::Cancelled);
+        assert!(broker.cancel_order(trade.as_trade(), None).await.is_err());
+
+        // 空單 pnl 以契約乘數換算成金額
+        broker.on_tick_fop(&TickFOPv1 {
+            code: "TXFA4".to_string(),
+            datetime: at(1),
+            close: 16990.0,
+            volume: 1,
+            ..Default::default()
+        });
+        let positions = broker.list_positions(None, None).await.unwrap();
+        let position = &positions[0];
+        assert_eq!(position.direction, Action::Sell);
+        assert_eq!(position.last_price, 16990.0);
+        let points = position.price - position.last_price;
+        assert!((position.pnl - points * position.quantity as f64 * 200.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_positions_in_lots_or_shares_and_margin() {
+        let broker = PaperBroker::new(PaperConfig {
+            margin_balance: 500_000.0,
+            ..Default::default()
+        });
+        broker
+            .place_order(stock(), buy(580.0, 2, OrderType::ROD))
+            .await
+            .unwrap();
+        let odd = buy(580.0, 300, OrderType::ROD).with_order_lot(StockOrderLot::IntradayOdd);
+        broker.place_order(stock(), odd).await.unwrap();
+        broker.on_tick_stk(&tick(579.0, 1000, 0));
+        broker.on_tick_stk(&tick(581.0, 1, 1));
+
+        let lots = broker.list_positions(None, None).await.unwrap();
+        assert_eq!(lots[0].quantity, 2);
+        let shares = broker
+            .list_positions(None, Some(Unit::Share))
+            .await
+            .unwrap();
+        assert_eq!(shares[0].quantity, 2300);
+        // 損益以股數計：(581 - 成本) × 2300 股
+        let points = shares[0].last_price - shares[0].price;
+        assert_eq!(shares[0].last_price, 581.0);
+        assert!((shares[0].pnl - points * 2300.0).abs() < 1e-6);
+        assert_eq!(lots[0].pnl, shares[0].pnl);
+
+        // 股票部位不計入期貨權益數
+        let margin = broker.margin(None).await.unwrap();
+        assert_eq!(margin.account_balance, 500_000.0);
+        assert_eq!(margin.unrealized_pnl, 0.0);
+        assert_eq!(margin.available_margin, 500_000.0);
+    }
+}
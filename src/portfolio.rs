@@ -0,0 +1,644 @@
+//! Real-time position keeper (即時部位與損益)
+//!
+//! Starts from `list_positions`, applies fills from deal events and marks
+//! positions to market with live ticks. P&L is computed with the contract
+//! size: `Contract.unit` for stocks (shares per lot) and `Contract.multiplier`
+//! for futures/options (points value). Odd-lot (零股) fills are reported in
+//! shares, so a stock position that receives one is kept in shares from then
+//! on. With a [`FeeSchedule`] the commission and tax of every fill are
+//! accumulated in `fees` and deducted from `total()`.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use rshioaji::portfolio::Portfolio;
+//! # async fn run(client: Arc<rshioaji::Shioaji>) -> rshioaji::Result<()> {
+//! let portfolio = Arc::new(Portfolio::new());
+//! for account in client.list_accounts().await? {
+//!     portfolio.load(&client, &account).await?;
+//! }
+//! portfolio.on_pnl_change(|pnl| println!("{} {:.0}", pnl.code, pnl.total()));
+//! portfolio.attach(&client).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::client::Shioaji;
+use crate::error::Result;
+use crate::fees::{FeeSchedule, StockTaxCategory};
+use crate::types::{Account, AccountType, Action, Contract, Deal, SecurityType};
+
+/// 整股一張 = 1000 股
+const DEFAULT_STOCK_UNIT: f64 = 1000.0;
+
+type PnlCallback = Arc<dyn Fn(&PositionPnl) + Send + Sync>;
+
+/// P&L of one symbol in one account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionPnl {
+    pub account_id: String,
+    pub code: String,
+    pub security_type: SecurityType,
+    /// Signed quantity: positive long, negative short (lots / contracts;
+    /// shares once an odd-lot fill has been applied)
+    pub quantity: i64,
+    pub avg_price: f64,
+    pub last_price: f64,
+    /// Value of one price point per unit of `quantity`
+    pub multiplier: f64,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    /// 累計手續費與交易稅
+    pub fees: f64,
+}
+
+impl PositionPnl {
+    /// Net P&L after fees
+    pub fn total(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl - self.fees
+    }
+
+    fn new(account_id: &str, code: &str, security_type: SecurityType, multiplier: f64) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            code: code.to_string(),
+            security_type,
+            quantity: 0,
+            avg_price: 0.0,
+            last_price: 0.0,
+            multiplier,
+            realized_pnl: 0.0,
+            unrealized_pnl: 0.0,
+            fees: 0.0,
+        }
+    }
+
+    /// Convert a fill of `quantity` units of `unit` shares into this
+    /// position's units, switching the position to shares when the fill is
+    /// not a whole number of its lots
+    fn convert_fill(&mut self, quantity: i64, unit: f64) -> i64 {
+        if unit == self.multiplier {
+            return quantity;
+        }
+        let shares = quantity as f64 * unit;
+        if (shares / self.multiplier).fract() != 0.0 {
+            self.quantity = (self.quantity as f64 * self.multiplier).round() as i64;
+            self.multiplier = 1.0;
+        }
+        (shares / self.multiplier).round() as i64
+    }
+
+    /// Apply a fill; `quantity` is signed (buy positive)
+    fn fill(&mut self, quantity: i64, price: f64) {
+        let same_direction = self.quantity == 0 || (self.quantity > 0) == (quantity > 0);
+        if same_direction {
+            let total = self.quantity + quantity;
+            self.avg_price = (self.avg_price * self.quantity.abs() as f64
+                + price * quantity.abs() as f64)
+                / total.abs() as f64;
+            self.quantity = total;
+        } else {
+            let closed = quantity.abs().min(self.quantity.abs());
+            let direction = self.quantity.signum() as f64;
+            self.realized_pnl +=
+                (price - self.avg_price) * closed as f64 * direction * self.multiplier;
+            self.quantity += quantity;
+            if self.quantity == 0 {
+                self.avg_price = 0.0;
+            } else if (self.quantity > 0) == (quantity > 0) {
+                // Flipped through zero: the remainder opens at the fill price
+                self.avg_price = price;
+            }
+        }
+        if self.last_price <= 0.0 {
+            self.last_price = price;
+        }
+        self.revalue();
+    }
+
+    fn revalue(&mut self) {
+        self.unrealized_pnl = if self.quantity == 0 || self.last_price <= 0.0 {
+            0.0
+        } else {
+            (self.last_price - self.avg_price) * self.quantity as f64 * self.multiplier
+        };
+    }
+}
+
+/// P&L summed over an account
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountPnl {
+    pub account_id: String,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub fees: f64,
+}
+
+impl AccountPnl {
+    /// Net P&L after fees
+    pub fn total(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl - self.fees
+    }
+}
+
+#[derive(Default)]
+struct PortfolioState {
+    /// (account_id, code) -> position
+    positions: BTreeMap<(String, String), PositionPnl>,
+    /// code -> value of one point per lot/contract
+    multipliers: HashMap<String, f64>,
+    /// (account_id, code) -> stock lots bought from deals this session (現股當沖判斷)
+    bought_today: HashMap<(String, String), i64>,
+}
+
+/// Position keeper fed by deals and ticks
+#[derive(Default)]
+pub struct Portfolio {
+    state: Mutex<PortfolioState>,
+    callbacks: Mutex<Vec<PnlCallback>>,
+    fees: Option<FeeSchedule>,
+}
+
+impl Portfolio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deduct commission and tax of every fill using `schedule`
+    pub fn with_fees(mut self, schedule: FeeSchedule) -> Self {
+        self.fees = Some(schedule);
+        self
+    }
+
+    /// Register a contract so its size is used for P&L
+    pub fn add_contract(&self, contract: &Contract) {
+        self.lock()
+            .multipliers
+            .insert(contract.base.code.clone(), contract_multiplier(contract));
+    }
+
+    /// Seed the positions of `account` from `list_positions`
+    ///
+    /// Existing positions of that account are replaced; realized P&L starts at 0.
+    pub async fn load(&self, client: &Shioaji, account: &Account) -> Result<usize> {
+        if let Some(contracts) = client.get_contracts().await {
+            let mut state = self.lock();
+            for contract in contracts
+                .stocks
+                .values()
+                .chain(contracts.futures.values())
+                .chain(contracts.options.values())
+            {
+                state
+                    .multipliers
+                    .insert(contract.base.code.clone(), contract_multiplier(contract));
+            }
+        }
+
+        let positions = client
+            .list_positions(Some(account.clone()), None, None)
+            .await?;
+        let security_type = match account.account_type {
+            AccountType::Future => SecurityType::Future,
+            AccountType::Stock | AccountType::Simulation => SecurityType::Stock,
+        };
+
+        let mut changed = Vec::new();
+        {
+            let mut state = self.lock();
+            state
+                .positions
+                .retain(|(account_id, _), _| account_id != &account.account_id);
+            for position in positions.iter().filter(|p| p.quantity != 0) {
+                let multiplier = state.multiplier(&position.code, &security_type);
+                let mut pnl = PositionPnl::new(
+                    &account.account_id,
+                    &position.code,
+                    security_type.clone(),
+                    multiplier,
+                );
+                pnl.quantity = match position.direction {
+                    Action::Buy => position.quantity.abs(),
+                    Action::Sell => -position.quantity.abs(),
+                };
+                pnl.avg_price = position.price;
+                pnl.last_price = position.last_price;
+                pnl.revalue();
+                changed.push(pnl.clone());
+                state
+                    .positions
+                    .insert((account.account_id.clone(), position.code.clone()), pnl);
+            }
+        }
+
+        log::info!(
+            "📊 Portfolio loaded {} positions for account {}",
+            changed.len(),
+            account.account_id
+        );
+        let count = changed.len();
+        self.notify(changed);
+        Ok(count)
+    }
+
+    /// Start a new trading day: earlier buys no longer count as day trades
+    pub fn reset_session(&self) {
+        self.lock().bought_today.clear();
+    }
+
+    /// Apply a fill from a deal event
+    pub fn apply_deal(&self, deal: &Deal) {
+        let quantity = match deal.action {
+            Action::Buy => deal.quantity as i64,
+            Action::Sell => -(deal.quantity as i64),
+        };
+        let changed = {
+            let mut state = self.lock();
+            let multiplier = state.multiplier(&deal.code, &deal.security_type);
+            let unit = deal_unit(deal, multiplier);
+            let key = (deal.account_id.clone(), deal.code.clone());
+            let fees = self.deal_fees(&mut state, &key, deal, multiplier);
+            let position = state
+                .positions
+                .entry((deal.account_id.clone(), deal.code.clone()))
+                .or_insert_with(|| {
+                    PositionPnl::new(
+                        &deal.account_id,
+                        &deal.code,
+                        deal.security_type.clone(),
+                        multiplier,
+                    )
+                });
+            position.fees += fees;
+            let quantity = position.convert_fill(quantity, unit);
+            position.fill(quantity, deal.price);
+            position.clone()
+        };
+        self.notify(vec![changed]);
+    }
+
+    /// Mark every position in `code` to `price`
+    pub fn on_price(&self, code: &str, price: f64) {
+        if price <= 0.0 {
+            return;
+        }
+        let changed: Vec<PositionPnl> = {
+            let mut state = self.lock();
+            state
+                .positions
+                .values_mut()
+                .filter(|p| p.code == code && p.last_price != price)
+                .map(|p| {
+                    p.last_price = price;
+                    p.revalue();
+                    p.clone()
+                })
+                .collect()
+        };
+        self.notify(changed);
+    }
+
+    /// Called with the updated position whenever its P&L changes
+    pub fn on_pnl_change<F>(&self, callback: F)
+    where
+        F: Fn(&PositionPnl) + Send + Sync + 'static,
+    {
+        if let Ok(mut callbacks) = self.callbacks.lock() {
+            callbacks.push(Arc::new(callback));
+        }
+    }
+
+    pub fn position(&self, account_id: &str, code: &str) -> Option<PositionPnl> {
+        self.lock()
+            .positions
+            .get(&(account_id.to_string(), code.to_string()))
+            .cloned()
+    }
+
+    /// All positions, including flat ones that still carry realized P&L
+    pub fn positions(&self) -> Vec<PositionPnl> {
+        self.lock().positions.values().cloned().collect()
+    }
+
+    /// P&L per account
+    pub fn accounts(&self) -> Vec<AccountPnl> {
+        let mut totals: BTreeMap<String, AccountPnl> = BTreeMap::new();
+        for position in self.lock().positions.values() {
+            let total = totals
+                .entry(position.account_id.clone())
+                .or_insert_with(|| AccountPnl {
+                    account_id: position.account_id.clone(),
+                    ..Default::default()
+                });
+            total.realized_pnl += position.realized_pnl;
+            total.unrealized_pnl += position.unrealized_pnl;
+            total.fees += position.fees;
+        }
+        totals.into_values().collect()
+    }
+
+    /// Feed deals and ticks from a logged-in client
+    pub async fn attach(self: &Arc<Self>, client: &Shioaji) -> Result<()> {
+        let portfolio = self.clone();
+        client
+            .on_order(move |event_type, msg| {
+                if let Some(deal) = Deal::from_event(&event_type, &msg) {
+                    portfolio.apply_deal(&deal);
+                }
+            })
+            .await?;
+
+        let portfolio = self.clone();
+        client
+            .on_tick_stk_v1(
+                move |_exchange, tick| {
+                    if !tick.simtrade {
+                        portfolio.on_price(&tick.code, tick.close);
+                    }
+                },
+                false,
+            )
+            .await?;
+
+        let portfolio = self.clone();
+        client
+            .on_tick_fop_v1(
+                move |_exchange, tick| {
+                    if !tick.simtrade {
+                        portfolio.on_price(&tick.code, tick.close);
+                    }
+                },
+                false,
+            )
+            .await?;
+
+        log::info!("✅ Portfolio attached to deals and ticks");
+        Ok(())
+    }
+
+    /// Fees of `deal`; stock sells that close same-day buys are taxed at the
+    /// day-trade rate
+    ///
+    /// Sells beyond the long position (融券 or oversold) pay the normal rate:
+    /// whether they are covered the same day is not known when they fill.
+    fn deal_fees(
+        &self,
+        state: &mut PortfolioState,
+        key: &(String, String),
+        deal: &Deal,
+        multiplier: f64,
+    ) -> f64 {
+        let Some(schedule) = &self.fees else {
+            return 0.0;
+        };
+        if !matches!(
+            deal.security_type,
+            SecurityType::Stock | SecurityType::Index
+        ) {
+            return schedule.for_deal(deal, multiplier, false).total();
+        }
+
+        // Day-trade matching is done in shares so 整股 and 零股 fills mix
+        let quantity = (deal.quantity as f64 * deal_unit(deal, multiplier)).round() as i64;
+        let long = state
+            .positions
+            .get(key)
+            .map(|p| (p.quantity.max(0) as f64 * p.multiplier).round() as i64)
+            .unwrap_or(0);
+        let bought_today = state.bought_today.entry(key.clone()).or_insert(0);
+        match deal.action {
+            Action::Buy => {
+                *bought_today += quantity;
+                schedule.for_deal(deal, multiplier, false).total()
+            }
+            Action::Sell => {
+                let closing = quantity.min(long);
+                let closing_today = closing.min(*bought_today);
+                *bought_today -= closing_today;
+                let day_trade = closing_today;
+
+                let amount = |shares: i64| deal.price * shares as f64;
+                let commission = schedule.commission(amount(quantity), deal.order_lot.is_odd());
+                let tax = schedule.stock_tax(
+                    amount(day_trade),
+                    StockTaxCategory::for_code(&deal.code, true),
+                ) + schedule.stock_tax(
+                    amount(quantity - day_trade),
+                    StockTaxCategory::for_code(&deal.code, false),
+                );
+                commission + tax
+            }
+        }
+    }
+
+    fn notify(&self, changed: Vec<PositionPnl>) {
+        if changed.is_empty() {
+            return;
+        }
+        let callbacks = match self.callbacks.lock() {
+            Ok(callbacks) => callbacks.clone(),
+            Err(_) => return,
+        };
+        for pnl in &changed {
+            for callback in &callbacks {
+                callback(pnl);
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PortfolioState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl PortfolioState {
+    fn multiplier(&self, code: &str, security_type: &SecurityType) -> f64 {
+        if let Some(multiplier) = self.multipliers.get(code) {
+            return *multiplier;
+        }
+        match security_type {
+            SecurityType::Stock | SecurityType::Index => DEFAULT_STOCK_UNIT,
+            SecurityType::Future | SecurityType::Option => {
+                log::warn!(
+                    "⚠️ No contract size for {}; register it with Portfolio::add_contract",
+                    code
+                );
+                1.0
+            }
+        }
+    }
+}
+
+/// Shares (stocks) or point value (futures/options) per unit of `deal.quantity`
+fn deal_unit(deal: &Deal, multiplier: f64) -> f64 {
+    match deal.security_type {
+        SecurityType::Stock | SecurityType::Index if deal.order_lot.is_odd() => 1.0,
+        _ => multiplier,
+    }
+}
+
+/// Value of one price point per lot (stocks) or contract (futures/options)
+pub(crate) fn contract_multiplier(contract: &Contract) -> f64 {
+    match contract.base.security_type {
+        SecurityType::Stock | SecurityType::Index if contract.unit > 0.0 => contract.unit,
+        SecurityType::Stock | SecurityType::Index => DEFAULT_STOCK_UNIT,
+        SecurityType::Future | SecurityType::Option => contract.multiplier.max(1) as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Exchange, Future, Stock, StockOrderLot};
+    use chrono::Utc;
+
+    fn deal(account_id: &str, code: &str, action: Action, price: f64, quantity: i32) -> Deal {
+        Deal {
+            trade_id: "t".to_string(),
+            seqno: String::new(),
+            ordno: String::new(),
+            exchange_seq: String::new(),
+            broker_id: String::new(),
+            account_id: account_id.to_string(),
+            action,
+            code: code.to_string(),
+            price,
+            quantity,
+            order_lot: StockOrderLot::Common,
+            security_type: if code.starts_with("TX") {
+                SecurityType::Future
+            } else {
+                SecurityType::Stock
+            },
+            ts: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_stock_fills_and_mark_to_market() {
+        let portfolio = Portfolio::new();
+        portfolio.add_contract(&Stock::new("2330", Exchange::TSE).contract);
+
+        portfolio.apply_deal(&deal("A1", "2330", Action::Buy, 500.0, 2));
+        portfolio.apply_deal(&deal("A1", "2330", Action::Buy, 510.0, 2));
+        let position = portfolio.position("A1", "2330").unwrap();
+        assert_eq!(position.quantity, 4);
+        assert!((position.avg_price - 505.0).abs() < 1e-9);
+
+        portfolio.on_price("2330", 515.0);
+        let position = portfolio.position("A1", "2330").unwrap();
+        assert!((position.unrealized_pnl - 10.0 * 4.0 * 1000.0).abs() < 1e-6);
+
+        portfolio.apply_deal(&deal("A1", "2330", Action::Sell, 520.0, 3));
+        let position = portfolio.position("A1", "2330").unwrap();
+        assert_eq!(position.quantity, 1);
+        assert!((position.realized_pnl - 15.0 * 3.0 * 1000.0).abs() < 1e-6);
+        assert!((position.unrealized_pnl - 10.0 * 1000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_futures_short_flip_uses_multiplier() {
+        let portfolio = Portfolio::new();
+        portfolio.add_contract(&Future::new("TXFA4").contract);
+
+        portfolio.apply_deal(&deal("F1", "TXFA4", Action::Sell, 17000.0, 1));
+        portfolio.apply_deal(&deal("F1", "TXFA4", Action::Buy, 16900.0, 3));
+        let position = portfolio.position("F1", "TXFA4").unwrap();
+        assert_eq!(position.quantity, 2);
+        assert_eq!(position.avg_price, 16900.0);
+        assert!((position.realized_pnl - 100.0 * 200.0).abs() < 1e-6);
+
+        portfolio.on_price("TXFA4", 16950.0);
+        let accounts = portfolio.accounts();
+        assert_eq!(accounts.len(), 1);
+        assert!((accounts[0].unrealized_pnl - 50.0 * 2.0 * 200.0).abs() < 1e-6);
+        assert!((accounts[0].total() - 40_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fees_deducted_with_day_trade_tax() {
+        let portfolio = Portfolio::new().with_fees(FeeSchedule::new().with_discount(0.5));
+
+        portfolio.apply_deal(&deal("A1", "2330", Action::Buy, 500.0, 1));
+        portfolio.reset_session();
+        portfolio.apply_deal(&deal("A1", "2330", Action::Buy, 600.0, 1));
+        portfolio.apply_deal(&deal("A1", "2330", Action::Sell, 600.0, 2));
+
+        let position = portfolio.position("A1", "2330").unwrap();
+        // 昨買 500000: 手續費 356；今買 600000: 手續費 427；賣 1200000: 手續費 855，
+        // 當沖 1 張稅 900 + 庫存 1 張稅 1800
+        assert_eq!(position.fees, 356.0 + 427.0 + 855.0 + 900.0 + 1800.0);
+        assert!((position.realized_pnl - 100.0 * 1000.0).abs() < 1e-6);
+        assert!((position.total() - (100_000.0 - position.fees)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_short_open_taxed_at_normal_rate() {
+        let portfolio = Portfolio::new().with_fees(FeeSchedule::new().with_discount(0.5));
+
+        portfolio.apply_deal(&deal("A1", "2330", Action::Buy, 600.0, 1));
+        portfolio.apply_deal(&deal("A1", "2330", Action::Sell, 600.0, 2));
+
+        let position = portfolio.position("A1", "2330").unwrap();
+        assert_eq!(position.quantity, -1);
+        // 買 600000: 手續費 427；賣 1200000: 手續費 855，
+        // 當沖 1 張稅 900 + 融券 1 張稅 1800
+        assert_eq!(position.fees, 427.0 + 855.0 + 900.0 + 1800.0);
+    }
+
+    #[test]
+    fn test_odd_lot_fills_are_counted_in_shares() {
+        let portfolio = Portfolio::new().with_fees(FeeSchedule::new());
+        portfolio.add_contract(&Stock::new("2330", Exchange::TSE).contract);
+
+        portfolio.apply_deal(&deal("A1", "2330", Action::Buy, 500.0, 2));
+        let odd = Deal {
+            order_lot: StockOrderLot::IntradayOdd,
+            ..deal("A1", "2330", Action::Buy, 510.0, 500)
+        };
+        portfolio.apply_deal(&odd);
+
+        let position = portfolio.position("A1", "2330").unwrap();
+        assert_eq!(position.quantity, 2500);
+        assert_eq!(position.multiplier, 1.0);
+        assert!((position.avg_price - 502.0).abs() < 1e-9);
+
+        portfolio.on_price("2330", 520.0);
+        let position = portfolio.position("A1", "2330").unwrap();
+        assert!((position.unrealized_pnl - 18.0 * 2500.0).abs() < 1e-6);
+
+        // A board-lot sell is converted to shares as well
+        portfolio.apply_deal(&deal("A1", "2330", Action::Sell, 520.0, 1));
+        let position = portfolio.position("A1", "2330").unwrap();
+        assert_eq!(position.quantity, 1500);
+        assert!((position.realized_pnl - 18.0 * 1000.0).abs() < 1e-6);
+
+        // Odd-lot only position: 300 shares, not 300 lots
+        portfolio.apply_deal(&Deal {
+            order_lot: StockOrderLot::Odd,
+            ..deal("A1", "2317", Action::Buy, 100.0, 300)
+        });
+        let position = portfolio.position("A1", "2317").unwrap();
+        assert_eq!(position.quantity, 300);
+        portfolio.on_price("2317", 101.0);
+        let position = portfolio.position("A1", "2317").unwrap();
+        assert!((position.unrealized_pnl - 300.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pnl_callback_fires_on_change_only() {
+        let portfolio = Portfolio::new();
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        portfolio.on_pnl_change(move |_| *counter.lock().unwrap() += 1);
+
+        portfolio.apply_deal(&deal("A1", "2330", Action::Buy, 500.0, 1));
+        portfolio.on_price("2330", 501.0);
+        portfolio.on_price("2330", 501.0);
+        portfolio.on_price("2317", 100.0);
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+}
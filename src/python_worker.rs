@@ -0,0 +1,196 @@
+//! Dedicated Python executor thread (Python 執行緒)
+//!
+//! Calls into the shioaji SDK block for a full network round trip. Running
+//! them under `Python::with_gil` inside an `async fn` stalls the tokio worker
+//! that polls the future, and with it every quote callback scheduled there.
+//!
+//! [`PythonWorker`] owns one OS thread that executes Python jobs in order.
+//! Async callers send a closure over a channel and await a oneshot reply; the
+//! tokio runtime keeps running while the job is in flight. The GIL is taken
+//! per job, so SDK threads delivering callbacks are never starved between
+//! requests.
+//!
+//! Every request carries a deadline. When it passes the caller gets
+//! [`Error::Timeout`]; a job still waiting in the queue is then dropped
+//! without running, but a job already executing runs to completion because a
+//! Python call cannot be interrupted. A timed-out `place_order` may therefore
+//! still have reached the broker, so the client reports order calls as
+//! [`Error::OutcomeUnknown`] instead — reconcile with `list_trades` before
+//! retrying.
+//!
+//! ```no_run
+//! # async fn run() -> rshioaji::Result<()> {
+//! use std::time::Duration;
+//!
+//! let worker = rshioaji::PythonWorker::shared();
+//! let version: String = worker
+//!     .run("sys.version", Duration::from_secs(1), |py| {
+//!         Ok(py.import("sys")?.getattr("version")?.extract()?)
+//!     })
+//!     .await?;
+//! println!("{}", version);
+//! # Ok(())
+//! # }
+//! ```
+
+use pyo3::Python;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::error::{Error, Result};
+
+/// Deadline applied to SDK calls that do not pass their own timeout
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+const THREAD_NAME: &str = "rshioaji-python";
+
+type Job = Box<dyn FnOnce(Python<'_>) + Send>;
+
+static SHARED: OnceLock<Arc<PythonWorker>> = OnceLock::new();
+
+/// Single thread that runs every Python call
+pub struct PythonWorker {
+    sender: mpsc::Sender<Job>,
+}
+
+impl PythonWorker {
+    /// Start a new worker thread
+    ///
+    /// The thread exits once the worker is dropped and the queue drains.
+    pub fn spawn() -> Result<Self> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name(THREAD_NAME.to_string())
+            .spawn(move || {
+                log::info!("🐍 Python worker thread started");
+                for job in receiver {
+                    // 單一請求 panic 不應終止整個執行緒
+                    let outcome = panic::catch_unwind(AssertUnwindSafe(|| Python::with_gil(job)));
+                    if outcome.is_err() {
+                        log::error!("❌ Python job panicked; worker continues");
+                    }
+                }
+                log::info!("🐍 Python worker thread stopped");
+            })
+            .map_err(|e| Error::System(format!("Failed to start Python worker: {}", e)))?;
+        Ok(Self { sender })
+    }
+
+    /// The process-wide worker, started on first use
+    ///
+    /// The GIL serialises Python anyway, so one thread per process is enough
+    /// for any number of clients.
+    pub fn shared() -> Arc<Self> {
+        SHARED
+            .get_or_init(|| Arc::new(Self::spawn().expect("spawn shared Python worker")))
+            .clone()
+    }
+
+    /// Run `job` on the worker thread and await its result
+    ///
+    /// `name` labels the request in logs and timeout errors.
+    pub async fn run<T, F>(&self, name: &str, deadline: Duration, job: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(Python<'_>) -> Result<T> + Send + 'static,
+    {
+        let expires = Instant::now() + deadline;
+        let (reply, response) = oneshot::channel();
+        let label = name.to_string();
+        let task: Job = Box::new(move |py| {
+            if Instant::now() >= expires {
+                log::warn!("⏰ Dropping expired Python request {}", label);
+                return;
+            }
+            // 呼叫端已逾時放棄時，回覆失敗可忽略
+            let _ = reply.send(job(py));
+        });
+        self.sender
+            .send(task)
+            .map_err(|_| Error::System("Python worker thread is not running".to_string()))?;
+
+        match tokio::time::timeout(deadline, response).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(Error::System(format!(
+                "Python request {} was dropped before completing",
+                name
+            ))),
+            Err(_) => {
+                log::warn!("⏰ Python request {} exceeded {:?}", name, deadline);
+                Err(Error::Timeout(format!(
+                    "{} did not complete within {:?}",
+                    name, deadline
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runs_jobs_off_the_runtime_thread() {
+        let worker = PythonWorker::spawn().unwrap();
+        let thread = worker
+            .run("thread name", Duration::from_secs(5), |_py| {
+                Ok(thread::current().name().map(str::to_string))
+            })
+            .await
+            .unwrap();
+        assert_eq!(thread.as_deref(), Some(THREAD_NAME));
+
+        let sum: i64 = worker
+            .run("eval", Duration::from_secs(5), |py| {
+                Ok(py.eval("20 + 22", None, None)?.extract()?)
+            })
+            .await
+            .unwrap();
+        assert_eq!(sum, 42);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_maps_to_timeout_and_skips_queued_jobs() {
+        let worker = PythonWorker::spawn().unwrap();
+        let ran = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let slow = worker.run("slow", Duration::from_millis(50), |_py| {
+            thread::sleep(Duration::from_millis(200));
+            Ok(())
+        });
+        let flag = ran.clone();
+        let queued = worker.run("queued", Duration::from_millis(50), move |_py| {
+            flag.store(true, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        });
+        let (slow, queued) = tokio::join!(slow, queued);
+        assert!(matches!(slow, Err(Error::Timeout(_))));
+        assert!(matches!(queued, Err(Error::Timeout(_))));
+
+        // The worker survives: a later request still runs, after the
+        // expired one was skipped
+        worker
+            .run("after", Duration::from_secs(5), |_py| Ok(()))
+            .await
+            .unwrap();
+        assert!(!ran.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_panicking_job_reports_error() {
+        let worker = PythonWorker::spawn().unwrap();
+        let result: Result<()> = worker
+            .run("panics", Duration::from_secs(5), |_py| panic!("boom"))
+            .await;
+        assert!(matches!(result, Err(Error::System(_))));
+        worker
+            .run("after", Duration::from_secs(5), |_py| Ok(()))
+            .await
+            .unwrap();
+    }
+}
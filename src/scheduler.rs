@@ -0,0 +1,432 @@
+//! Session-aware job scheduler (交易時段排程)
+//!
+//! Runs async jobs relative to market session events in Asia/Taipei time,
+//! only on trading days of the attached [`TradingCalendar`]:
+//!
+//! ```no_run
+//! use chrono::{Duration, NaiveTime};
+//! use rshioaji::{Exchange, JobTrigger, Scheduler, SessionKind, Shioaji, TradingCalendar};
+//! use std::collections::HashMap;
+//! use std::sync::Arc;
+//!
+//! # async fn run() -> rshioaji::Result<()> {
+//! let client = Arc::new(Shioaji::new(true, HashMap::new())?);
+//! let handle = Scheduler::new(Arc::new(TradingCalendar::bundled()))
+//!     .with_job(
+//!         "positions",
+//!         JobTrigger::daily(NaiveTime::from_hms_opt(8, 0, 0).unwrap()),
+//!         |client| async move { client.list_positions(None, None, None).await.map(|_| ()) },
+//!     )
+//!     .with_job(
+//!         "flatten",
+//!         JobTrigger::before_close(Exchange::TSE, SessionKind::Regular, Duration::minutes(10)),
+//!         |_client| async move { Ok(()) },
+//!     )
+//!     .spawn(client);
+//! # handle.abort();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Failed or panicked jobs are reported through `Shioaji::emit_event` with
+//! [`SCHEDULER_RESP_CODE`] / [`JOB_FAILED_EVENT_CODE`], the job name as `info`
+//! and the error as `event`.
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::calendar::{local_date, taipei, SessionKind, TradingCalendar};
+use crate::client::Shioaji;
+use crate::error::Result;
+use crate::types::Exchange;
+
+/// `resp_code` of scheduler system events
+pub const SCHEDULER_RESP_CODE: i32 = 500;
+/// `event_code` reported when a job returns an error or panics
+pub const JOB_FAILED_EVENT_CODE: i32 = 9001;
+
+/// Jobs firing later than this (e.g. after the host slept) are skipped
+const MISFIRE_GRACE_SECS: i64 = 300;
+
+/// Point of a session a job is anchored to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionEvent {
+    /// 開始接受委託
+    OrderEntry,
+    /// 開盤
+    Open,
+    /// 收盤
+    Close,
+}
+
+/// When a job runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum JobTrigger {
+    /// `offset` from a session event; negative offsets run before it
+    Session {
+        exchange: Exchange,
+        session: SessionKind,
+        event: SessionEvent,
+        offset_secs: i64,
+    },
+    /// Fixed Taipei time on every trading day
+    Daily(NaiveTime),
+}
+
+impl JobTrigger {
+    pub fn session(
+        exchange: Exchange,
+        session: SessionKind,
+        event: SessionEvent,
+        offset: Duration,
+    ) -> Self {
+        JobTrigger::Session {
+            exchange,
+            session,
+            event,
+            offset_secs: offset.num_seconds(),
+        }
+    }
+
+    /// 開盤前 `before`
+    pub fn before_open(exchange: Exchange, session: SessionKind, before: Duration) -> Self {
+        Self::session(exchange, session, SessionEvent::Open, -before)
+    }
+
+    pub fn at_open(exchange: Exchange, session: SessionKind) -> Self {
+        Self::session(exchange, session, SessionEvent::Open, Duration::zero())
+    }
+
+    /// 收盤前 `before`
+    pub fn before_close(exchange: Exchange, session: SessionKind, before: Duration) -> Self {
+        Self::session(exchange, session, SessionEvent::Close, -before)
+    }
+
+    /// 收盤後 `after`
+    pub fn after_close(exchange: Exchange, session: SessionKind, after: Duration) -> Self {
+        Self::session(exchange, session, SessionEvent::Close, after)
+    }
+
+    pub fn daily(time: NaiveTime) -> Self {
+        JobTrigger::Daily(time)
+    }
+
+    /// First firing strictly after `after`
+    pub fn next_after(
+        &self,
+        calendar: &TradingCalendar,
+        after: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let today = local_date(after);
+        // 前一交易日的夜盤收盤可能落在今天
+        (-1..=31)
+            .map(|offset| today + Duration::days(offset))
+            .filter_map(|day| self.fire_time(calendar, day))
+            .find(|ts| *ts > after)
+    }
+
+    fn fire_time(
+        &self,
+        calendar: &TradingCalendar,
+        day: chrono::NaiveDate,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            JobTrigger::Session {
+                exchange,
+                session,
+                event,
+                offset_secs,
+            } => {
+                let window = calendar.window(exchange, *session, day)?;
+                let anchor = match event {
+                    SessionEvent::OrderEntry => window.order_entry,
+                    SessionEvent::Open => window.open,
+                    SessionEvent::Close => window.close,
+                };
+                Some(anchor + Duration::seconds(*offset_secs))
+            }
+            JobTrigger::Daily(time) => calendar.is_trading_day(day).then(|| taipei(day, *time)),
+        }
+    }
+}
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+type JobFn = Arc<dyn Fn(Arc<Shioaji>) -> JobFuture + Send + Sync>;
+
+struct Job {
+    name: String,
+    trigger: JobTrigger,
+    run: JobFn,
+}
+
+/// Runs jobs on session events alongside a `Shioaji` client
+pub struct Scheduler {
+    calendar: Arc<TradingCalendar>,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new(calendar: Arc<TradingCalendar>) -> Self {
+        Self {
+            calendar,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Add a job; it receives the client the scheduler was spawned with
+    pub fn with_job<F, Fut>(mut self, name: &str, trigger: JobTrigger, job: F) -> Self
+    where
+        F: Fn(Arc<Shioaji>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.jobs.push(Job {
+            name: name.to_string(),
+            trigger,
+            run: Arc::new(move |client| Box::pin(job(client))),
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Next firing time and the names of jobs due then
+    pub fn next_run(&self, after: DateTime<Utc>) -> Option<(DateTime<Utc>, Vec<String>)> {
+        let (at, due) = self.due_after(after)?;
+        Some((
+            at,
+            due.into_iter().map(|i| self.jobs[i].name.clone()).collect(),
+        ))
+    }
+
+    /// Upcoming `(time, job name)` firings after `after`, in order
+    pub fn upcoming(&self, after: DateTime<Utc>, count: usize) -> Vec<(DateTime<Utc>, String)> {
+        let mut runs = Vec::new();
+        let mut cursor = after;
+        while runs.len() < count {
+            let Some((at, names)) = self.next_run(cursor) else {
+                break;
+            };
+            runs.extend(names.into_iter().map(|name| (at, name)));
+            cursor = at;
+        }
+        runs.truncate(count);
+        runs
+    }
+
+    fn due_after(&self, after: DateTime<Utc>) -> Option<(DateTime<Utc>, Vec<usize>)> {
+        let times: Vec<Option<DateTime<Utc>>> = self
+            .jobs
+            .iter()
+            .map(|job| job.trigger.next_after(&self.calendar, after))
+            .collect();
+        let at = times.iter().flatten().min().copied()?;
+        let due = times
+            .iter()
+            .enumerate()
+            .filter(|(_, ts)| **ts == Some(at))
+            .map(|(i, _)| i)
+            .collect();
+        Some((at, due))
+    }
+
+    /// Run the scheduler until the handle is aborted
+    pub fn spawn(self, client: Arc<Shioaji>) -> tokio::task::JoinHandle<()> {
+        log::info!("⏰ Scheduler started with {} jobs", self.jobs.len());
+        tokio::spawn(async move {
+            let mut cursor = Utc::now();
+            loop {
+                let Some((at, due)) = self.due_after(cursor) else {
+                    log::warn!("⚠️ Scheduler has no upcoming jobs, stopping");
+                    return;
+                };
+                if let Ok(wait) = (at - Utc::now()).to_std() {
+                    tokio::time::sleep(wait).await;
+                }
+                cursor = at;
+
+                if (Utc::now() - at).num_seconds() > MISFIRE_GRACE_SECS {
+                    for i in due {
+                        log::warn!(
+                            "⚠️ Skipping missed job '{}' due at {}",
+                            self.jobs[i].name,
+                            at
+                        );
+                    }
+                    continue;
+                }
+                for i in due {
+                    let job = &self.jobs[i];
+                    tokio::spawn(Self::run_job(
+                        job.name.clone(),
+                        job.run.clone(),
+                        client.clone(),
+                    ));
+                }
+            }
+        })
+    }
+
+    async fn run_job(name: String, run: JobFn, client: Arc<Shioaji>) {
+        log::info!("⏰ Running scheduled job '{}'", name);
+        let error = match tokio::spawn(run(client.clone())).await {
+            Ok(Ok(())) => {
+                log::info!("✅ Scheduled job '{}' completed", name);
+                return;
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(e) => format!("job panicked: {}", e),
+        };
+        log::error!("❌ Scheduled job '{}' failed: {}", name, error);
+        client
+            .emit_event(SCHEDULER_RESP_CODE, JOB_FAILED_EVENT_CODE, name, error)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use chrono::NaiveDate;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        taipei(
+            NaiveDate::from_ymd_opt(y, m, d).unwrap(),
+            NaiveTime::from_hms_opt(h, min, 0).unwrap(),
+        )
+    }
+
+    fn calendar() -> TradingCalendar {
+        // 2024/03/11 (Mon) 設為假日
+        TradingCalendar::empty().with_holiday(
+            NaiveDate::from_ymd_opt(2024, 3, 11).unwrap(),
+            "test holiday",
+        )
+    }
+
+    #[test]
+    fn test_trigger_skips_holidays() {
+        let calendar = calendar();
+        let pre_open =
+            JobTrigger::before_open(Exchange::TSE, SessionKind::Regular, Duration::minutes(5));
+        assert_eq!(
+            pre_open.next_after(&calendar, at(2024, 3, 8, 8, 0)),
+            Some(at(2024, 3, 8, 8, 55))
+        );
+        assert_eq!(
+            pre_open.next_after(&calendar, at(2024, 3, 8, 8, 55)),
+            Some(at(2024, 3, 12, 8, 55))
+        );
+
+        let daily = JobTrigger::daily(NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+        assert_eq!(
+            daily.next_after(&calendar, at(2024, 3, 9, 12, 0)),
+            Some(at(2024, 3, 12, 8, 0))
+        );
+
+        // 週五夜盤收盤在週六 05:00
+        let night_close =
+            JobTrigger::after_close(Exchange::TAIFEX, SessionKind::Night, Duration::minutes(5));
+        assert_eq!(
+            night_close.next_after(&calendar, at(2024, 3, 9, 1, 0)),
+            Some(at(2024, 3, 9, 5, 5))
+        );
+    }
+
+    #[test]
+    fn test_upcoming_orders_jobs() {
+        let scheduler = Scheduler::new(Arc::new(calendar()))
+            .with_job(
+                "export",
+                JobTrigger::after_close(Exchange::TSE, SessionKind::Regular, Duration::minutes(65)),
+                |_| async { Ok(()) },
+            )
+            .with_job(
+                "flatten",
+                JobTrigger::before_close(
+                    Exchange::TSE,
+                    SessionKind::Regular,
+                    Duration::minutes(10),
+                ),
+                |_| async { Ok(()) },
+            )
+            .with_job(
+                "night",
+                JobTrigger::at_open(Exchange::TAIFEX, SessionKind::Night),
+                |_| async { Ok(()) },
+            );
+        assert_eq!(scheduler.len(), 3);
+
+        let runs = scheduler.upcoming(at(2024, 3, 8, 12, 0), 4);
+        let names: Vec<&str> = runs.iter().map(|(_, name)| name.as_str()).collect();
+        assert_eq!(names, vec!["flatten", "export", "night", "flatten"]);
+        assert_eq!(runs[0].0, at(2024, 3, 8, 13, 20));
+        assert_eq!(runs[1].0, at(2024, 3, 8, 14, 35));
+        assert_eq!(runs[3].0, at(2024, 3, 12, 13, 20));
+    }
+
+    type Events = Arc<Mutex<Vec<(i32, i32, String, String)>>>;
+
+    async fn event_client() -> (Arc<Shioaji>, Events) {
+        let client = Arc::new(Shioaji::new(true, HashMap::new()).unwrap());
+        let events: Events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        client
+            .on_event(move |resp_code, event_code, info, event| {
+                sink.lock()
+                    .unwrap()
+                    .push((resp_code, event_code, info, event));
+            })
+            .await
+            .unwrap();
+        (client, events)
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_emits_event() {
+        let (client, events) = event_client().await;
+
+        let ok: JobFn = Arc::new(|_| Box::pin(async { Ok(()) }));
+        Scheduler::run_job("quiet".to_string(), ok, client.clone()).await;
+        assert!(events.lock().unwrap().is_empty());
+
+        let failing: JobFn =
+            Arc::new(|_| Box::pin(async { Err(Error::Trading("boom".to_string())) }));
+        Scheduler::run_job("failing".to_string(), failing, client.clone()).await;
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let (resp_code, event_code, info, event) = &events[0];
+        assert_eq!(*resp_code, SCHEDULER_RESP_CODE);
+        assert_eq!(*event_code, JOB_FAILED_EVENT_CODE);
+        assert_eq!(info, "failing");
+        assert!(event.contains("boom"));
+    }
+
+    #[tokio::test]
+    async fn test_panicked_job_emits_event() {
+        let (client, events) = event_client().await;
+
+        let panicking: JobFn = Arc::new(|_| Box::pin(async { panic!("kaboom") }));
+        Scheduler::run_job("panicking".to_string(), panicking, client.clone()).await;
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let (resp_code, event_code, info, event) = &events[0];
+        assert_eq!(*resp_code, SCHEDULER_RESP_CODE);
+        assert_eq!(*event_code, JOB_FAILED_EVENT_CODE);
+        assert_eq!(info, "panicking");
+        assert!(event.starts_with("job panicked"));
+    }
+}
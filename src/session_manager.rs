@@ -0,0 +1,547 @@
+//! Several logged-in sessions in one process (多帳號連線管理)
+//!
+//! A [`Shioaji`] client holds exactly one SDK connection. Desks running
+//! several API keys — a prop account next to client accounts — use a
+//! [`SessionManager`] instead: it logs in one client per key under a tag,
+//! routes each order to the session that owns its [`Account`], and merges
+//! the sessions' order and market-data callbacks (ticks, bid/ask and stock
+//! quotes) into one stream that carries the session tag.
+//!
+//! Contract data is loaded once. The first session downloads it; later
+//! sessions log in without fetching, share the Rust contract tree and reuse
+//! the first session's SDK contracts.
+//!
+//! Market-data subscriptions belong to a session: subscribe through
+//! [`SessionManager::session`] on one session only, or every tick arrives
+//! once per subscribed session.
+//!
+//! ```no_run
+//! # async fn run() -> rshioaji::Result<()> {
+//! use rshioaji::{Action, Exchange, Order, OrderType, SessionManager, StockPriceType};
+//!
+//! let manager = SessionManager::new(false);
+//! manager.add_session("desk", "desk_key", "desk_secret").await?;
+//! let accounts = manager.add_session("client", "client_key", "client_secret").await?;
+//!
+//! manager.on_order(|tag, event_type, data| println!("[{}] {} {}", tag, event_type, data));
+//!
+//! let desk = manager.session("desk").unwrap();
+//! let stock = desk.create_stock("2330", Exchange::TSE);
+//! let order = Order::new(Action::Buy, 580.0, 1, OrderType::ROD, StockPriceType::LMT);
+//! manager.place_order(&accounts[0], stock.contract, order).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Mutex as StdMutex;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+
+use crate::callbacks::{CallbackErrorPolicy, CallbackHandle, CallbackRegistry, CallbackStats};
+use crate::client::Shioaji;
+use crate::error::{Error, Result};
+use crate::types::{
+    Account, BidAskFOPv1, BidAskSTKv1, Contract, Contracts, Exchange, FuturesOrder, FuturesTrade,
+    Order, OrderEventType, QuoteSTKv1, TickFOPv1, TickSTKv1, Trade,
+};
+
+/// Order/deal callback tagged with the session it came from
+pub type SessionOrderCallback = Arc<dyn Fn(&str, OrderEventType, serde_json::Value) + Send + Sync>;
+/// Stock tick callback tagged with the session it came from
+pub type SessionTickSTKCallback = Arc<dyn Fn(&str, Exchange, TickSTKv1) + Send + Sync>;
+/// Futures/options tick callback tagged with the session it came from
+pub type SessionTickFOPCallback = Arc<dyn Fn(&str, Exchange, TickFOPv1) + Send + Sync>;
+/// Stock bid/ask callback tagged with the session it came from
+pub type SessionBidAskSTKCallback = Arc<dyn Fn(&str, Exchange, BidAskSTKv1) + Send + Sync>;
+/// Futures/options bid/ask callback tagged with the session it came from
+pub type SessionBidAskFOPCallback = Arc<dyn Fn(&str, Exchange, BidAskFOPv1) + Send + Sync>;
+/// Stock quote callback tagged with the session it came from
+pub type SessionQuoteSTKCallback = Arc<dyn Fn(&str, Exchange, QuoteSTKv1) + Send + Sync>;
+/// System event callback tagged with the session it came from
+pub type SessionEventCallback = Arc<dyn Fn(&str, i32, i32, String, String) + Send + Sync>;
+
+/// One logged-in client and the accounts it owns
+pub struct ManagedSession {
+    tag: Arc<str>,
+    client: Arc<Shioaji>,
+    accounts: Vec<Account>,
+    forwarders: StdMutex<Vec<CallbackHandle>>,
+}
+
+impl ManagedSession {
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn client(&self) -> &Arc<Shioaji> {
+        &self.client
+    }
+
+    /// Accounts returned by this session's login
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    fn owns(&self, account: &Account) -> bool {
+        self.accounts.iter().any(|owned| {
+            owned.account_id == account.account_id && owned.broker_id == account.broker_id
+        })
+    }
+}
+
+/// Owns several logged-in sessions and merges their events
+pub struct SessionManager {
+    simulation: bool,
+    sessions: RwLock<Vec<Arc<ManagedSession>>>,
+    /// 所有 session 共用的合約資料
+    contracts: Arc<Mutex<Option<Contracts>>>,
+    policy: Arc<CallbackErrorPolicy>,
+    order: Arc<CallbackRegistry<SessionOrderCallback>>,
+    tick_stk: Arc<CallbackRegistry<SessionTickSTKCallback>>,
+    tick_fop: Arc<CallbackRegistry<SessionTickFOPCallback>>,
+    bidask_stk: Arc<CallbackRegistry<SessionBidAskSTKCallback>>,
+    bidask_fop: Arc<CallbackRegistry<SessionBidAskFOPCallback>>,
+    quote_stk: Arc<CallbackRegistry<SessionQuoteSTKCallback>>,
+    event: Arc<CallbackRegistry<SessionEventCallback>>,
+}
+
+impl SessionManager {
+    pub fn new(simulation: bool) -> Self {
+        fn registry<T: Send + Sync + 'static>(
+            stream: &'static str,
+            policy: &Arc<CallbackErrorPolicy>,
+        ) -> Arc<CallbackRegistry<T>> {
+            Arc::new(CallbackRegistry::new(stream).with_policy(policy.clone()))
+        }
+
+        let policy = Arc::new(CallbackErrorPolicy::new());
+        Self {
+            simulation,
+            sessions: RwLock::new(Vec::new()),
+            contracts: Arc::new(Mutex::new(None)),
+            order: registry("session_order", &policy),
+            tick_stk: registry("session_tick_stk", &policy),
+            tick_fop: registry("session_tick_fop", &policy),
+            bidask_stk: registry("session_bidask_stk", &policy),
+            bidask_fop: registry("session_bidask_fop", &policy),
+            quote_stk: registry("session_quote_stk", &policy),
+            event: registry("session_event", &policy),
+            policy,
+        }
+    }
+
+    /// Log in a new session under `tag` and return its accounts
+    ///
+    /// Only the first session downloads contracts; later ones reuse them.
+    /// Fails with `InvalidInput` when `tag` is taken or one of the login's
+    /// accounts already belongs to another session; the new login is then
+    /// logged out again, as it is when adopting contracts or registering the
+    /// event forwarders fails.
+    pub async fn add_session(
+        &self,
+        tag: &str,
+        api_key: &str,
+        secret_key: &str,
+    ) -> Result<Vec<Account>> {
+        if self.session(tag).is_some() {
+            return Err(Error::InvalidInput(format!(
+                "Session {} already exists",
+                tag
+            )));
+        }
+        let source = self.snapshot().first().cloned();
+
+        let client = Arc::new(
+            Shioaji::new(self.simulation, HashMap::new())?.with_contracts(self.contracts.clone()),
+        );
+        client.init().await?;
+        let accounts = client
+            .login(api_key, secret_key, source.is_none(), 0, None, true, 30000)
+            .await?;
+
+        let tag: Arc<str> = Arc::from(tag);
+        let session = Arc::new(ManagedSession {
+            tag: tag.clone(),
+            client,
+            accounts: accounts.clone(),
+            forwarders: StdMutex::new(Vec::new()),
+        });
+        // 登入已成功，之後的失敗都要登出，不能留下孤兒連線
+        if let Err(e) = self.setup(&session, source.as_deref()).await {
+            log::warn!("⚠️ Session {} setup failed: {}, logging it out", tag, e);
+            Self::discard(&session).await;
+            return Err(e);
+        }
+
+        // 登入期間可能有同名或同帳號的 session 先完成，在寫鎖內重新檢查
+        let conflict = {
+            let mut sessions = self.sessions.write().unwrap_or_else(|e| e.into_inner());
+            let conflict = Self::conflict(&sessions, &session);
+            if conflict.is_none() {
+                sessions.push(session.clone());
+                log::info!(
+                    "✅ Session {} logged in with {} accounts ({} sessions)",
+                    tag,
+                    accounts.len(),
+                    sessions.len()
+                );
+            }
+            conflict
+        };
+        if let Some(message) = conflict {
+            log::warn!("⚠️ {}, logging session {} out", message, tag);
+            Self::discard(&session).await;
+            return Err(Error::InvalidInput(message));
+        }
+        Ok(accounts)
+    }
+
+    /// Why `session` cannot join `sessions`, if it cannot
+    fn conflict(sessions: &[Arc<ManagedSession>], session: &ManagedSession) -> Option<String> {
+        if sessions.iter().any(|s| s.tag == session.tag) {
+            return Some(format!("Session {} already exists", session.tag));
+        }
+        session.accounts.iter().find_map(|account| {
+            sessions.iter().find(|s| s.owns(account)).map(|owner| {
+                format!(
+                    "Account {} is already routed to session {}",
+                    account.account_id, owner.tag
+                )
+            })
+        })
+    }
+
+    /// Drop a session's forwarders and log it out
+    async fn discard(session: &ManagedSession) {
+        let forwarders =
+            std::mem::take(&mut *session.forwarders.lock().unwrap_or_else(|e| e.into_inner()));
+        for handle in forwarders {
+            handle.remove();
+        }
+        if let Err(e) = session.client.logout().await {
+            log::warn!("⚠️ Failed to log out session {}: {}", session.tag, e);
+        }
+    }
+
+    /// Share `source`'s contracts with a freshly logged-in session and start
+    /// forwarding its events
+    async fn setup(&self, session: &ManagedSession, source: Option<&ManagedSession>) -> Result<()> {
+        if let Some(source) = source {
+            session.client.adopt_contracts(&source.client).await?;
+        }
+        self.forward_events(session).await
+    }
+
+    /// Register forwarders that tag the session's events with its tag
+    ///
+    /// Each handle is stored on the session as soon as it is registered, so
+    /// [`Self::discard`] also removes the ones added before a failure.
+    async fn forward_events(&self, session: &ManagedSession) -> Result<()> {
+        let (client, tag) = (&session.client, &session.tag);
+        let keep = |handle: CallbackHandle| {
+            session
+                .forwarders
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(handle);
+        };
+
+        let (registry, session_tag) = (self.order.clone(), tag.clone());
+        keep(
+            client
+                .on_order(move |event_type, data| {
+                    registry.dispatch(|cb| cb(&session_tag, event_type.clone(), data.clone()));
+                })
+                .await?,
+        );
+        let (registry, session_tag) = (self.tick_stk.clone(), tag.clone());
+        keep(
+            client
+                .on_tick_stk_v1(
+                    move |exchange, tick| {
+                        registry.dispatch(|cb| cb(&session_tag, exchange, tick.clone()));
+                    },
+                    false,
+                )
+                .await?,
+        );
+        let (registry, session_tag) = (self.tick_fop.clone(), tag.clone());
+        keep(
+            client
+                .on_tick_fop_v1(
+                    move |exchange, tick| {
+                        registry.dispatch(|cb| cb(&session_tag, exchange, tick.clone()));
+                    },
+                    false,
+                )
+                .await?,
+        );
+        let (registry, session_tag) = (self.bidask_stk.clone(), tag.clone());
+        keep(
+            client
+                .on_bidask_stk_v1(
+                    move |exchange, bidask| {
+                        registry.dispatch(|cb| cb(&session_tag, exchange, bidask.clone()));
+                    },
+                    false,
+                )
+                .await?,
+        );
+        let (registry, session_tag) = (self.bidask_fop.clone(), tag.clone());
+        keep(
+            client
+                .on_bidask_fop_v1(
+                    move |exchange, bidask| {
+                        registry.dispatch(|cb| cb(&session_tag, exchange, bidask.clone()));
+                    },
+                    false,
+                )
+                .await?,
+        );
+        let (registry, session_tag) = (self.quote_stk.clone(), tag.clone());
+        keep(
+            client
+                .on_quote_stk_v1(
+                    move |exchange, quote| {
+                        registry.dispatch(|cb| cb(&session_tag, exchange, quote.clone()));
+                    },
+                    false,
+                )
+                .await?,
+        );
+        let (registry, session_tag) = (self.event.clone(), tag.clone());
+        keep(
+            client
+                .on_event(move |resp_code, event_code, info, event| {
+                    registry.dispatch(|cb| {
+                        cb(
+                            &session_tag,
+                            resp_code,
+                            event_code,
+                            info.clone(),
+                            event.clone(),
+                        )
+                    });
+                })
+                .await?,
+        );
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Vec<Arc<ManagedSession>> {
+        self.sessions
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Client of the session registered under `tag`
+    pub fn session(&self, tag: &str) -> Option<Arc<Shioaji>> {
+        self.snapshot()
+            .into_iter()
+            .find(|session| &*session.tag == tag)
+            .map(|session| session.client.clone())
+    }
+
+    /// All sessions in the order they were added
+    pub fn sessions(&self) -> Vec<Arc<ManagedSession>> {
+        self.snapshot()
+    }
+
+    /// Session owning `account` (matched by broker and account id)
+    pub fn session_for(&self, account: &Account) -> Result<Arc<ManagedSession>> {
+        self.snapshot()
+            .into_iter()
+            .find(|session| session.owns(account))
+            .ok_or(Error::AccountNotFound)
+    }
+
+    /// Contract data shared by every session
+    pub fn contracts(&self) -> Arc<Mutex<Option<Contracts>>> {
+        self.contracts.clone()
+    }
+
+    /// Place a stock order on the session that owns `account`
+    pub async fn place_order(
+        &self,
+        account: &Account,
+        contract: Contract,
+        mut order: Order,
+    ) -> Result<Trade> {
+        let session = self.session_for(account)?;
+        log::info!(
+            "📊 Routing order for {} to session {}",
+            account.account_id,
+            session.tag
+        );
+        order.account = Some(account.clone());
+        session.client.place_order(contract, order).await
+    }
+
+    /// Place a futures/options order on the session that owns `account`
+    pub async fn place_futures_order(
+        &self,
+        account: &Account,
+        contract: Contract,
+        mut order: FuturesOrder,
+    ) -> Result<FuturesTrade> {
+        let session = self.session_for(account)?;
+        log::info!(
+            "📊 Routing futures order for {} to session {}",
+            account.account_id,
+            session.tag
+        );
+        order.account = Some(account.clone());
+        session.client.place_futures_order(contract, order).await
+    }
+
+    /// Log out and drop the session registered under `tag`
+    ///
+    /// Returns `false` when no such session exists. Contracts stay loaded for
+    /// the remaining sessions.
+    pub async fn remove_session(&self, tag: &str) -> Result<bool> {
+        let removed = {
+            let mut sessions = self.sessions.write().unwrap_or_else(|e| e.into_inner());
+            let index = sessions.iter().position(|session| &*session.tag == tag);
+            index.map(|index| sessions.remove(index))
+        };
+        let Some(session) = removed else {
+            return Ok(false);
+        };
+        let forwarders =
+            std::mem::take(&mut *session.forwarders.lock().unwrap_or_else(|e| e.into_inner()));
+        for handle in forwarders {
+            handle.remove();
+        }
+        session.client.logout().await?;
+        log::info!("👋 Session {} removed", tag);
+        Ok(true)
+    }
+
+    /// Log out every session
+    pub async fn logout_all(&self) -> Result<()> {
+        let tags: Vec<String> = self
+            .snapshot()
+            .iter()
+            .map(|session| session.tag.to_string())
+            .collect();
+        for tag in tags {
+            self.remove_session(&tag).await?;
+        }
+        Ok(())
+    }
+
+    /// Order/deal events from every session, with the session tag
+    pub fn on_order<F>(&self, callback: F) -> CallbackHandle
+    where
+        F: Fn(&str, OrderEventType, serde_json::Value) + Send + Sync + 'static,
+    {
+        self.order.register(Arc::new(callback))
+    }
+
+    /// Stock ticks from every session, with the session tag
+    pub fn on_tick_stk_v1<F>(&self, callback: F) -> CallbackHandle
+    where
+        F: Fn(&str, Exchange, TickSTKv1) + Send + Sync + 'static,
+    {
+        self.tick_stk.register(Arc::new(callback))
+    }
+
+    /// Futures/options ticks from every session, with the session tag
+    pub fn on_tick_fop_v1<F>(&self, callback: F) -> CallbackHandle
+    where
+        F: Fn(&str, Exchange, TickFOPv1) + Send + Sync + 'static,
+    {
+        self.tick_fop.register(Arc::new(callback))
+    }
+
+    /// Stock bid/ask from every session, with the session tag
+    pub fn on_bidask_stk_v1<F>(&self, callback: F) -> CallbackHandle
+    where
+        F: Fn(&str, Exchange, BidAskSTKv1) + Send + Sync + 'static,
+    {
+        self.bidask_stk.register(Arc::new(callback))
+    }
+
+    /// Futures/options bid/ask from every session, with the session tag
+    pub fn on_bidask_fop_v1<F>(&self, callback: F) -> CallbackHandle
+    where
+        F: Fn(&str, Exchange, BidAskFOPv1) + Send + Sync + 'static,
+    {
+        self.bidask_fop.register(Arc::new(callback))
+    }
+
+    /// Stock quotes from every session, with the session tag
+    pub fn on_quote_stk_v1<F>(&self, callback: F) -> CallbackHandle
+    where
+        F: Fn(&str, Exchange, QuoteSTKv1) + Send + Sync + 'static,
+    {
+        self.quote_stk.register(Arc::new(callback))
+    }
+
+    /// System events from every session, with the session tag
+    pub fn on_event<F>(&self, callback: F) -> CallbackHandle
+    where
+        F: Fn(&str, i32, i32, String, String) + Send + Sync + 'static,
+    {
+        self.event.register(Arc::new(callback))
+    }
+
+    /// Report panics in the merged callbacks (see `Shioaji::on_callback_error`)
+    pub fn on_callback_error<F>(&self, hook: F)
+    where
+        F: Fn(Error) + Send + Sync + 'static,
+    {
+        self.policy.set_hook(Arc::new(hook));
+    }
+
+    /// Counters for the merged callback streams
+    pub fn callback_stats(&self) -> Vec<CallbackStats> {
+        vec![
+            self.order.stats(),
+            self.tick_stk.stats(),
+            self.tick_fop.stats(),
+            self.bidask_stk.stats(),
+            self.bidask_fop.stats(),
+            self.quote_stk.stats(),
+            self.event.stats(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AccountType, Action, OrderType, StockPriceType};
+
+    #[tokio::test]
+    async fn test_unknown_account_is_not_routed() {
+        let manager = SessionManager::new(true);
+        let account = Account::new(
+            "9A95".to_string(),
+            "0000009".to_string(),
+            AccountType::Stock,
+            "nobody".to_string(),
+            true,
+        );
+        assert!(manager.session("desk").is_none());
+        assert!(matches!(
+            manager.session_for(&account),
+            Err(Error::AccountNotFound)
+        ));
+
+        let contract = manager_contract();
+        let order = Order::new(Action::Buy, 580.0, 1, OrderType::ROD, StockPriceType::LMT);
+        let result = manager.place_order(&account, contract, order).await;
+        assert!(matches!(result, Err(Error::AccountNotFound)));
+        assert!(!manager.remove_session("desk").await.unwrap());
+    }
+
+    fn manager_contract() -> Contract {
+        Shioaji::new(true, HashMap::new())
+            .unwrap()
+            .create_stock("2330", Exchange::TSE)
+            .contract
+    }
+}
@@ -77,6 +77,18 @@ impl std::fmt::Display for SecurityType {
     }
 }
 
+impl SecurityType {
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "IND" | "Index" => SecurityType::Index,
+            "STK" | "Stock" => SecurityType::Stock,
+            "FUT" | "Future" => SecurityType::Future,
+            "OPT" | "Option" => SecurityType::Option,
+            _ => SecurityType::Stock, // Default
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum OrderType {
     ROD, // 當日有效
@@ -126,8 +138,9 @@ impl std::fmt::Display for FuturesPriceType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum StockOrderLot {
+    #[default]
     Common,      // 整股
     BlockTrade,  // 鉅額
     Fixing,      // 定盤
@@ -135,13 +148,63 @@ pub enum StockOrderLot {
     IntradayOdd, // 盤中零股
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+impl std::fmt::Display for StockOrderLot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StockOrderLot::Common => write!(f, "Common"),
+            StockOrderLot::BlockTrade => write!(f, "BlockTrade"),
+            StockOrderLot::Fixing => write!(f, "Fixing"),
+            StockOrderLot::Odd => write!(f, "Odd"),
+            StockOrderLot::IntradayOdd => write!(f, "IntradayOdd"),
+        }
+    }
+}
+
+impl StockOrderLot {
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "BlockTrade" => StockOrderLot::BlockTrade,
+            "Fixing" => StockOrderLot::Fixing,
+            "Odd" => StockOrderLot::Odd,
+            "IntradayOdd" => StockOrderLot::IntradayOdd,
+            _ => StockOrderLot::Common, // Default
+        }
+    }
+
+    /// 零股 (盤後零股或盤中零股)，數量單位為股而非張
+    pub fn is_odd(&self) -> bool {
+        matches!(self, StockOrderLot::Odd | StockOrderLot::IntradayOdd)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum StockOrderCond {
+    #[default]
     Cash,          // 現股
     MarginTrading, // 融資
     ShortSelling,  // 融券
 }
 
+impl std::fmt::Display for StockOrderCond {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StockOrderCond::Cash => write!(f, "Cash"),
+            StockOrderCond::MarginTrading => write!(f, "MarginTrading"),
+            StockOrderCond::ShortSelling => write!(f, "ShortSelling"),
+        }
+    }
+}
+
+impl StockOrderCond {
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "MarginTrading" => StockOrderCond::MarginTrading,
+            "ShortSelling" => StockOrderCond::ShortSelling,
+            _ => StockOrderCond::Cash, // Default
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FuturesOCType {
     Auto,     // 自動
@@ -150,6 +213,17 @@ pub enum FuturesOCType {
     DayTrade, // 當沖
 }
 
+impl std::fmt::Display for FuturesOCType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FuturesOCType::Auto => write!(f, "Auto"),
+            FuturesOCType::New => write!(f, "New"),
+            FuturesOCType::Cover => write!(f, "Cover"),
+            FuturesOCType::DayTrade => write!(f, "DayTrade"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum OptionRight {
     No,   // 無
@@ -185,6 +259,14 @@ impl std::fmt::Display for Status {
 }
 
 impl Status {
+    /// Whether the order can still be cancelled (not filled, cancelled or failed)
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            Status::PendingSubmit | Status::PreSubmitted | Status::Submitted | Status::PartFilled
+        )
+    }
+
     pub fn from_string(s: &str) -> Self {
         match s {
             "Cancelled" => Status::Cancelled,
@@ -208,6 +290,36 @@ pub enum OrderEventType {
     FuturesDeal,  // 期貨成交
 }
 
+impl std::fmt::Display for OrderEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderEventType::StockDeal => write!(f, "StockDeal"),
+            OrderEventType::StockOrder => write!(f, "StockOrder"),
+            OrderEventType::FuturesOrder => write!(f, "FuturesOrder"),
+            OrderEventType::FuturesDeal => write!(f, "FuturesDeal"),
+        }
+    }
+}
+
+impl OrderEventType {
+    /// 對應 Python shioaji 的 OrderState (名稱或數值皆可)
+    pub fn from_string(s: &str) -> Option<Self> {
+        let name = s.rsplit('.').next().unwrap_or(s);
+        match name {
+            "StockDeal" | "SDEAL" | "TFTDeal" | "TFTDEAL" => Some(OrderEventType::StockDeal),
+            "StockOrder" | "SORDER" | "TFTOrder" | "TFTORDER" => Some(OrderEventType::StockOrder),
+            "FuturesOrder" | "FORDER" | "FOrder" => Some(OrderEventType::FuturesOrder),
+            "FuturesDeal" | "FDEAL" | "FDeal" => Some(OrderEventType::FuturesDeal),
+            _ => None,
+        }
+    }
+
+    /// Whether the event reports a fill (成交回報)
+    pub fn is_deal(&self) -> bool {
+        matches!(self, OrderEventType::StockDeal | OrderEventType::FuturesDeal)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum QuoteType {
     Tick,   // tick
@@ -2,6 +2,7 @@ pub mod accounts;
 pub mod constants;
 pub mod contracts;
 pub mod market_data;
+pub mod option_chain;
 pub mod orders;
 pub mod positions;
 
@@ -9,5 +10,6 @@ pub use accounts::*;
 pub use constants::*;
 pub use contracts::*;
 pub use market_data::*;
+pub use option_chain::*;
 pub use orders::*;
 pub use positions::*;
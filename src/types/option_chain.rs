@@ -0,0 +1,387 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use super::constants::OptionRight;
+use super::contracts::{Contract, Contracts};
+
+/// 同一履約價的買權/賣權
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionStrike {
+    pub strike: f64,
+    pub call: Option<Contract>,
+    pub put: Option<Contract>,
+}
+
+/// 單一到期日的選擇權序列
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionExpiry {
+    /// 到期日 (原始格式，例如 2024/01/17)
+    pub delivery_date: String,
+    /// 到期月份，週選為 202401W2 格式
+    pub delivery_month: String,
+    /// 商品類別 (TXO 月選，TX1/TX2/TX4/TX5 週選)
+    pub category: String,
+    /// 依履約價由小到大排序
+    pub strikes: Vec<OptionStrike>,
+}
+
+impl OptionExpiry {
+    /// 是否為週選擇權
+    ///
+    /// `delivery_month` 帶 `W` 為週選；沒有月份資訊時以是否為第三個星期三判斷。
+    pub fn is_weekly(&self) -> bool {
+        if self.delivery_month.contains('W') {
+            return true;
+        }
+        if !self.delivery_month.is_empty() {
+            return false;
+        }
+        match parse_date(&self.delivery_date) {
+            Some(date) => !is_third_wednesday(date),
+            None => false,
+        }
+    }
+
+    pub fn is_monthly(&self) -> bool {
+        !self.is_weekly()
+    }
+
+    pub fn date(&self) -> Option<NaiveDate> {
+        parse_date(&self.delivery_date)
+    }
+
+    pub fn strike(&self, strike: f64) -> Option<&OptionStrike> {
+        self.strikes
+            .iter()
+            .find(|s| (s.strike - strike).abs() < 1e-9)
+    }
+
+    /// 最接近標的價格的履約價 (價平)，等距時取較低履約價
+    pub fn atm(&self, underlying_price: f64) -> Option<&OptionStrike> {
+        self.strikes.iter().min_by(|a, b| {
+            let da = (a.strike - underlying_price).abs();
+            let db = (b.strike - underlying_price).abs();
+            da.total_cmp(&db).then(a.strike.total_cmp(&b.strike))
+        })
+    }
+
+    /// 價平上下各 `count` 檔履約價
+    pub fn strikes_around(&self, underlying_price: f64, count: usize) -> &[OptionStrike] {
+        let Some(atm) = self.atm(underlying_price) else {
+            return &[];
+        };
+        let index = self
+            .strikes
+            .iter()
+            .position(|s| s.strike == atm.strike)
+            .unwrap_or(0);
+        let start = index.saturating_sub(count);
+        let end = (index + count + 1).min(self.strikes.len());
+        &self.strikes[start..end]
+    }
+
+    pub fn calls(&self) -> impl Iterator<Item = &Contract> {
+        self.strikes.iter().filter_map(|s| s.call.as_ref())
+    }
+
+    pub fn puts(&self) -> impl Iterator<Item = &Contract> {
+        self.strikes.iter().filter_map(|s| s.put.as_ref())
+    }
+}
+
+/// 選擇權鏈：到期日 → 履約價 → 買權/賣權
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionChain {
+    pub underlying: String,
+    /// 依到期日由近到遠排序
+    pub expiries: Vec<OptionExpiry>,
+}
+
+impl OptionChain {
+    pub fn is_empty(&self) -> bool {
+        self.expiries.is_empty()
+    }
+
+    /// 依到期日 (2024/01/17、20240117) 或到期月份 (202401、202401W2) 查詢
+    pub fn expiry(&self, delivery: &str) -> Option<&OptionExpiry> {
+        self.expiries.iter().find(|e| matches_delivery(e, delivery))
+    }
+
+    /// `date` 當天或之後最近的到期序列
+    pub fn nearest_expiry(&self, date: NaiveDate) -> Option<&OptionExpiry> {
+        self.expiries
+            .iter()
+            .find(|e| e.date().map(|d| d >= date).unwrap_or(false))
+    }
+
+    pub fn weekly(&self) -> impl Iterator<Item = &OptionExpiry> {
+        self.expiries.iter().filter(|e| e.is_weekly())
+    }
+
+    pub fn monthly(&self) -> impl Iterator<Item = &OptionExpiry> {
+        self.expiries.iter().filter(|e| e.is_monthly())
+    }
+}
+
+impl Contracts {
+    /// 建立選擇權鏈
+    ///
+    /// `underlying` 比對合約的 `category` 或 `underlying_code`；`TXO` 另外包含
+    /// TX1/TX2/TX4/TX5 等週選類別。`delivery` 可為到期日或到期月份，`None` 取全部。
+    pub fn option_chain(&self, underlying: &str, delivery: Option<&str>) -> OptionChain {
+        let mut expiries: Vec<OptionExpiry> = Vec::new();
+
+        for contract in self
+            .options
+            .values()
+            .filter(|c| belongs_to(c, underlying) && c.option_right != OptionRight::No)
+        {
+            let index = match expiries
+                .iter()
+                .position(|e| e.delivery_date == contract.delivery_date)
+            {
+                Some(index) => index,
+                None => {
+                    expiries.push(OptionExpiry {
+                        delivery_date: contract.delivery_date.clone(),
+                        delivery_month: contract.delivery_month.clone(),
+                        category: contract.category.clone(),
+                        strikes: Vec::new(),
+                    });
+                    expiries.len() - 1
+                }
+            };
+            let expiry = &mut expiries[index];
+
+            let strike = match expiry
+                .strikes
+                .iter()
+                .position(|s| (s.strike - contract.strike_price).abs() < 1e-9)
+            {
+                Some(index) => &mut expiry.strikes[index],
+                None => {
+                    expiry.strikes.push(OptionStrike {
+                        strike: contract.strike_price,
+                        call: None,
+                        put: None,
+                    });
+                    expiry.strikes.last_mut().expect("just pushed")
+                }
+            };
+            match contract.option_right {
+                OptionRight::Call => strike.call = Some(contract.clone()),
+                OptionRight::Put => strike.put = Some(contract.clone()),
+                OptionRight::No => {}
+            }
+        }
+
+        if let Some(delivery) = delivery {
+            expiries.retain(|e| matches_delivery(e, delivery));
+        }
+        for expiry in &mut expiries {
+            expiry.strikes.sort_by(|a, b| a.strike.total_cmp(&b.strike));
+        }
+        expiries.sort_by(|a, b| {
+            a.date()
+                .cmp(&b.date())
+                .then_with(|| a.delivery_date.cmp(&b.delivery_date))
+        });
+
+        OptionChain {
+            underlying: underlying.to_string(),
+            expiries,
+        }
+    }
+}
+
+fn belongs_to(contract: &Contract, underlying: &str) -> bool {
+    if contract.category == underlying || contract.underlying_code == underlying {
+        return true;
+    }
+    // TXO 週選類別：TX1、TX2、TX4、TX5 ...
+    underlying == "TXO"
+        && contract.category.len() == 3
+        && contract.category.starts_with("TX")
+        && contract
+            .category
+            .chars()
+            .nth(2)
+            .map(|c| c.is_ascii_digit())
+            .unwrap_or(false)
+}
+
+fn matches_delivery(expiry: &OptionExpiry, delivery: &str) -> bool {
+    let wanted = normalize(delivery);
+    wanted == normalize(&expiry.delivery_date) || wanted == normalize(&expiry.delivery_month)
+}
+
+fn normalize(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .filter(|c| *c != '/' && *c != '-')
+        .collect::<String>()
+        .to_uppercase()
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&normalize(value), "%Y%m%d").ok()
+}
+
+fn is_third_wednesday(date: NaiveDate) -> bool {
+    date.weekday() == Weekday::Wed && (15..=21).contains(&date.day())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::contracts::OptionContract;
+
+    fn add(
+        contracts: &mut Contracts,
+        category: &str,
+        month: &str,
+        date: &str,
+        right: OptionRight,
+        strike: f64,
+    ) {
+        let code = format!("{}{}{:?}{}", category, strike, right, date);
+        let mut option = OptionContract::new(&code, right, strike).contract;
+        option.category = category.to_string();
+        option.delivery_month = month.to_string();
+        option.delivery_date = date.to_string();
+        contracts.add_option(code, option);
+    }
+
+    #[test]
+    fn test_strikes_and_expiries_are_sorted() {
+        let mut contracts = Contracts::new();
+        // 刻意以亂序加入
+        for strike in [17100.0, 16900.0, 17000.0] {
+            add(
+                &mut contracts,
+                "TX4",
+                "202401W4",
+                "2024/01/24",
+                OptionRight::Call,
+                strike,
+            );
+            add(
+                &mut contracts,
+                "TXO",
+                "202401",
+                "2024/01/17",
+                OptionRight::Call,
+                strike,
+            );
+        }
+
+        let chain = contracts.option_chain("TXO", None);
+        let dates: Vec<&str> = chain
+            .expiries
+            .iter()
+            .map(|e| e.delivery_date.as_str())
+            .collect();
+        assert_eq!(dates, vec!["2024/01/17", "2024/01/24"]);
+        for expiry in &chain.expiries {
+            let strikes: Vec<f64> = expiry.strikes.iter().map(|s| s.strike).collect();
+            assert_eq!(strikes, vec![16900.0, 17000.0, 17100.0]);
+        }
+    }
+
+    #[test]
+    fn test_missing_leg_stays_none() {
+        let mut contracts = Contracts::new();
+        add(
+            &mut contracts,
+            "TXO",
+            "202401",
+            "2024/01/17",
+            OptionRight::Call,
+            17000.0,
+        );
+        add(
+            &mut contracts,
+            "TXO",
+            "202401",
+            "2024/01/17",
+            OptionRight::Put,
+            17000.0,
+        );
+        add(
+            &mut contracts,
+            "TXO",
+            "202401",
+            "2024/01/17",
+            OptionRight::Call,
+            17100.0,
+        );
+        add(
+            &mut contracts,
+            "TXO",
+            "202401",
+            "2024/01/17",
+            OptionRight::Put,
+            16900.0,
+        );
+
+        let chain = contracts.option_chain("TXO", None);
+        let expiry = &chain.expiries[0];
+        assert_eq!(expiry.strikes.len(), 3);
+
+        let low = expiry.strike(16900.0).unwrap();
+        assert!(low.call.is_none());
+        assert_eq!(low.put.as_ref().unwrap().strike_price, 16900.0);
+
+        let high = expiry.strike(17100.0).unwrap();
+        assert!(high.put.is_none());
+        assert_eq!(high.call.as_ref().unwrap().option_right, OptionRight::Call);
+
+        let both = expiry.strike(17000.0).unwrap();
+        assert!(both.call.is_some() && both.put.is_some());
+        assert_eq!(expiry.calls().count(), 2);
+        assert_eq!(expiry.puts().count(), 2);
+    }
+
+    #[test]
+    fn test_delivery_filter() {
+        let mut contracts = Contracts::new();
+        add(
+            &mut contracts,
+            "TXO",
+            "202401",
+            "2024/01/17",
+            OptionRight::Call,
+            17000.0,
+        );
+        add(
+            &mut contracts,
+            "TX4",
+            "202401W4",
+            "2024/01/24",
+            OptionRight::Call,
+            17000.0,
+        );
+        add(
+            &mut contracts,
+            "TXO",
+            "202402",
+            "2024/02/21",
+            OptionRight::Call,
+            17000.0,
+        );
+
+        for delivery in ["202401", "2024/01/17", "20240117", "2024-01-17"] {
+            let chain = contracts.option_chain("TXO", Some(delivery));
+            assert_eq!(chain.expiries.len(), 1, "delivery {}", delivery);
+            assert_eq!(chain.expiries[0].delivery_date, "2024/01/17");
+        }
+
+        let weekly = contracts.option_chain("TXO", Some("202401w4"));
+        assert_eq!(weekly.expiries.len(), 1);
+        assert!(weekly.expiries[0].is_weekly());
+
+        assert!(contracts.option_chain("TXO", Some("202403")).is_empty());
+        assert_eq!(contracts.option_chain("TXO", None).expiries.len(), 3);
+    }
+}
@@ -1,6 +1,11 @@
 use crate::types::accounts::Account;
 use crate::types::constants::*;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// [`OrderStateTracker`] forgets all orders beyond this many open ones
+const MAX_TRACKED_ORDERS: usize = 4096;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
@@ -125,6 +130,49 @@ impl ComboOrder {
     }
 }
 
+/// Either a stock or a futures/options order, for components that submit
+/// both kinds (conditional orders, execution algorithms, ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum OrderTemplate {
+    /// 證券委託
+    Stock(Order),
+    /// 期貨/選擇權委託
+    Futures(FuturesOrder),
+}
+
+impl OrderTemplate {
+    pub fn action(&self) -> &Action {
+        match self {
+            OrderTemplate::Stock(order) => &order.action,
+            OrderTemplate::Futures(order) => &order.action,
+        }
+    }
+
+    pub fn price(&self) -> f64 {
+        match self {
+            OrderTemplate::Stock(order) => order.price,
+            OrderTemplate::Futures(order) => order.price,
+        }
+    }
+
+    pub fn quantity(&self) -> i32 {
+        match self {
+            OrderTemplate::Stock(order) => order.quantity,
+            OrderTemplate::Futures(order) => order.quantity,
+        }
+    }
+
+    /// Copy of this order with a different quantity
+    pub fn with_quantity(&self, quantity: i32) -> Self {
+        let mut template = self.clone();
+        match &mut template {
+            OrderTemplate::Stock(order) => order.quantity = quantity,
+            OrderTemplate::Futures(order) => order.quantity = quantity,
+        }
+        template
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub order: Order,
@@ -147,6 +195,28 @@ pub struct FuturesTrade {
     pub contracts: Vec<crate::types::contracts::Contract>,
 }
 
+impl FuturesTrade {
+    /// Stock-shaped view of this trade for APIs that only need the order
+    /// identifiers, such as `cancel_order` and `update_order`
+    pub fn as_trade(&self) -> Trade {
+        Trade {
+            order: Order::new(
+                self.order.action.clone(),
+                self.order.price,
+                self.order.quantity,
+                self.order.order_type.clone(),
+                StockPriceType::LMT,
+            ),
+            status: self.status.clone(),
+            order_id: self.order_id.clone(),
+            seqno: self.seqno.clone(),
+            ordno: self.ordno.clone(),
+            account: self.account.clone(),
+            contracts: self.contracts.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComboTrade {
     pub order: ComboOrder,
@@ -159,7 +229,7 @@ pub struct ComboTrade {
 }
 
 /// Order state for callbacks
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderState {
     /// Order has been submitted
     Submitted,
@@ -197,6 +267,26 @@ impl std::fmt::Display for OrderState {
     }
 }
 
+impl OrderState {
+    /// State reported by an order/deal callback message
+    ///
+    /// Deal events map to `PartFilled`: a single deal message does not say
+    /// whether the order is complete (use [`OrderStateTracker`] for that).
+    /// Order events map to `Failed` when `operation.op_code` is not `"00"`,
+    /// `Cancelled` for cancel operations and `Submitted` otherwise.
+    pub fn from_order_event(event_type: &OrderEventType, msg: &serde_json::Value) -> Self {
+        if event_type.is_deal() {
+            return OrderState::PartFilled;
+        }
+        let operation = &msg["operation"];
+        match (operation["op_code"].as_str(), operation["op_type"].as_str()) {
+            (Some(code), _) if code != "00" => OrderState::Failed,
+            (_, Some("Cancel")) => OrderState::Cancelled,
+            _ => OrderState::Submitted,
+        }
+    }
+}
+
 impl From<&str> for OrderState {
     fn from(s: &str) -> Self {
         match s {
@@ -213,3 +303,316 @@ impl From<&str> for OrderState {
         }
     }
 }
+
+/// 追蹤委託數量與累計成交，將成交回報判定為 `PartFilled` 或 `Filled`
+///
+/// Order events record the order quantity (less any `status.cancel_quantity`);
+/// a deal is `Filled` once the deals of its `trade_id` add up to it, and
+/// `PartFilled` before that or when the order event was never seen.
+#[derive(Debug, Default)]
+pub struct OrderStateTracker {
+    /// order id → (order quantity, cumulative deal quantity)
+    orders: HashMap<String, (i64, i64)>,
+}
+
+impl OrderStateTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// State of an order/deal callback message, given the earlier ones
+    pub fn state(&mut self, event_type: &OrderEventType, msg: &serde_json::Value) -> OrderState {
+        let state = OrderState::from_order_event(event_type, msg);
+        let order_id = if event_type.is_deal() {
+            msg.get("trade_id")
+        } else {
+            msg.pointer("/order/id")
+        };
+        let Some(order_id) = order_id.and_then(|v| v.as_str()) else {
+            return state;
+        };
+        if self.orders.len() >= MAX_TRACKED_ORDERS && !self.orders.contains_key(order_id) {
+            self.orders.clear();
+        }
+
+        if event_type.is_deal() {
+            let quantity = msg.get("quantity").and_then(|v| v.as_i64()).unwrap_or(0);
+            let entry = self.orders.entry(order_id.to_string()).or_default();
+            entry.1 += quantity;
+            if entry.0 > 0 && entry.1 >= entry.0 {
+                self.orders.remove(order_id);
+                return OrderState::Filled;
+            }
+            return OrderState::PartFilled;
+        }
+
+        match state {
+            OrderState::Failed | OrderState::Cancelled => {
+                self.orders.remove(order_id);
+            }
+            _ => {
+                if let Some(quantity) = msg.pointer("/order/quantity").and_then(|v| v.as_i64()) {
+                    let cancelled = msg
+                        .pointer("/status/cancel_quantity")
+                        .and_then(|v| v.as_i64())
+                        .unwrap_or(0);
+                    self.orders.entry(order_id.to_string()).or_default().0 = quantity - cancelled;
+                }
+            }
+        }
+        state
+    }
+}
+
+/// 成交回報 (對應 Python shioaji 的 StockDeal / FuturesDeal 事件)
+///
+/// 對應原始 Python 回報內容：
+/// ```python
+/// {
+///     'trade_id': '12ab3456', 'seqno': '123456', 'ordno': 'IM0001',
+///     'exchange_seq': '000001', 'broker_id': '9A95', 'account_id': '1234567',
+///     'action': 'Buy', 'code': '2330', 'order_cond': 'Cash', 'order_lot': 'Common',
+///     'price': 590.0, 'quantity': 1, 'ts': 1673577256
+/// }
+/// ```
+///
+/// 股票成交的 `quantity` 單位依 `order_lot` 而定：整股為張，零股為股。
+///
+/// `FuturesDeal` 的 `code` 只有商品代號 ('TXF'、'TXO')，月份與履約價另列於
+/// `delivery_month`、`strike_price`、`option_right`；[`Deal::code`] 會組回完整
+/// 合約代碼 ('TXFA4'、'TXO17900A4')，與下單合約一致。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deal {
+    /// 對應委託的 order id (trade.order.id)
+    pub trade_id: String,
+    pub seqno: String,
+    pub ordno: String,
+    pub exchange_seq: String,
+    pub broker_id: String,
+    pub account_id: String,
+    pub action: Action,
+    /// 完整合約代碼 (期權由商品代號、月份、履約價組成)
+    pub code: String,
+    pub price: f64,
+    /// 成交數量 (整股為張、零股為股、期權為口)
+    pub quantity: i32,
+    /// 股票委託的盤別；期權成交為 `Common`
+    #[serde(default)]
+    pub order_lot: StockOrderLot,
+    pub security_type: SecurityType,
+    pub ts: DateTime<Utc>,
+}
+
+impl Deal {
+    /// Parse a deal from an order callback message; returns `None` for
+    /// order (non-deal) events or malformed messages
+    pub fn from_event(event_type: &OrderEventType, msg: &serde_json::Value) -> Option<Self> {
+        if !event_type.is_deal() {
+            return None;
+        }
+
+        let text = |key: &str| -> String {
+            match msg.get(key) {
+                Some(serde_json::Value::String(s)) => s.clone(),
+                Some(serde_json::Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            }
+        };
+
+        let code = text("code");
+        let price = msg.get("price")?.as_f64()?;
+        let quantity = msg.get("quantity")?.as_i64()? as i32;
+        if code.is_empty() {
+            return None;
+        }
+
+        let option_right = match text("option_right").as_str() {
+            "OptionCall" | "Call" | "C" => OptionRight::Call,
+            "OptionPut" | "Put" | "P" => OptionRight::Put,
+            _ => OptionRight::No,
+        };
+        let security_type = match event_type {
+            OrderEventType::StockDeal => SecurityType::Stock,
+            _ => match text("security_type").as_str() {
+                "OPT" | "Option" => SecurityType::Option,
+                _ if option_right != OptionRight::No => SecurityType::Option,
+                _ => SecurityType::Future,
+            },
+        };
+        let code = match security_type {
+            SecurityType::Stock => code,
+            _ => futopt_code(
+                &code,
+                &text("delivery_month"),
+                msg.get("strike_price").and_then(|v| v.as_f64()),
+                &option_right,
+            ),
+        };
+
+        let ts = msg
+            .get("ts")
+            .and_then(|v| v.as_f64())
+            .and_then(|secs| {
+                DateTime::<Utc>::from_timestamp(secs.trunc() as i64, (secs.fract() * 1e9) as u32)
+            })
+            .unwrap_or_else(Utc::now);
+
+        Some(Self {
+            trade_id: text("trade_id"),
+            seqno: text("seqno"),
+            ordno: text("ordno"),
+            exchange_seq: text("exchange_seq"),
+            broker_id: text("broker_id"),
+            account_id: text("account_id"),
+            action: Action::from_string(&text("action")),
+            code,
+            price,
+            quantity,
+            order_lot: StockOrderLot::from_string(&text("order_lot")),
+            security_type,
+            ts,
+        })
+    }
+}
+
+/// 由商品代號與月份組出期貨/選擇權完整代碼
+///
+/// 月份代碼：期貨與買權 A-L 代表 1-12 月，賣權 M-X；之後接年份個位數。
+/// `code` 已是完整代碼或缺少 `delivery_month` (YYYYMM) 時原樣回傳。
+pub fn futopt_code(
+    code: &str,
+    delivery_month: &str,
+    strike_price: Option<f64>,
+    option_right: &OptionRight,
+) -> String {
+    let (Some(year), Some(month)) = (
+        delivery_month
+            .get(3..4)
+            .filter(|_| delivery_month.len() == 6),
+        delivery_month
+            .get(4..6)
+            .and_then(|m| m.parse::<u8>().ok())
+            .filter(|m| (1..=12).contains(m)),
+    ) else {
+        return code.to_string();
+    };
+    let letter_base = match option_right {
+        OptionRight::Put => b'M',
+        _ => b'A',
+    };
+    let month_code = format!("{}{}", (letter_base + month - 1) as char, year);
+    let suffix = match (option_right, strike_price) {
+        (OptionRight::Call | OptionRight::Put, Some(strike)) => {
+            let strike = if strike.fract() == 0.0 {
+                format!("{}", strike as i64)
+            } else {
+                strike.to_string()
+            };
+            format!("{}{}", strike, month_code)
+        }
+        _ => month_code,
+    };
+    if code.ends_with(&suffix) {
+        code.to_string()
+    } else {
+        format!("{}{}", code, suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deals_are_part_filled_until_order_quantity() {
+        let mut tracker = OrderStateTracker::new();
+        let order = serde_json::json!({
+            "operation": {"op_type": "New", "op_code": "00"},
+            "order": {"id": "o1", "quantity": 3},
+        });
+        let deal = |quantity: i64| serde_json::json!({"trade_id": "o1", "quantity": quantity});
+
+        assert_eq!(
+            tracker.state(&OrderEventType::StockOrder, &order),
+            OrderState::Submitted
+        );
+        assert_eq!(
+            tracker.state(&OrderEventType::StockDeal, &deal(1)),
+            OrderState::PartFilled
+        );
+        assert_eq!(
+            tracker.state(&OrderEventType::StockDeal, &deal(2)),
+            OrderState::Filled
+        );
+        assert!(tracker.orders.is_empty());
+
+        // Without the order event a deal cannot complete anything
+        assert_eq!(
+            OrderState::from_order_event(&OrderEventType::FuturesDeal, &deal(3)),
+            OrderState::PartFilled
+        );
+        let mut unseen = OrderStateTracker::new();
+        assert_eq!(
+            unseen.state(&OrderEventType::StockDeal, &deal(3)),
+            OrderState::PartFilled
+        );
+    }
+
+    #[test]
+    fn test_stock_deal_keeps_order_lot() {
+        let msg = serde_json::json!({
+            "trade_id": "t1", "action": "Buy", "code": "2330", "price": 590.0,
+            "quantity": 300, "order_cond": "Cash", "order_lot": "IntradayOdd",
+        });
+        let deal = Deal::from_event(&OrderEventType::StockDeal, &msg).unwrap();
+        assert_eq!(deal.order_lot, StockOrderLot::IntradayOdd);
+        assert_eq!(deal.quantity, 300);
+
+        let msg = serde_json::json!({
+            "trade_id": "t2", "action": "Sell", "code": "2330", "price": 590.0, "quantity": 1,
+        });
+        let deal = Deal::from_event(&OrderEventType::StockDeal, &msg).unwrap();
+        assert_eq!(deal.order_lot, StockOrderLot::Common);
+    }
+
+    #[test]
+    fn test_futures_deal_code_is_rebuilt_from_root() {
+        let deal =
+            |msg: serde_json::Value| Deal::from_event(&OrderEventType::FuturesDeal, &msg).unwrap();
+        let future = deal(serde_json::json!({
+            "trade_id": "t1", "action": "Buy", "code": "TXF", "price": 17900.0,
+            "quantity": 1, "security_type": "FUT", "delivery_month": "202401",
+            "strike_price": 0.0, "option_right": "Future",
+        }));
+        assert_eq!(future.code, "TXFA4");
+        assert_eq!(future.security_type, SecurityType::Future);
+
+        let call = deal(serde_json::json!({
+            "trade_id": "t2", "action": "Sell", "code": "TXO", "price": 150.0,
+            "quantity": 2, "security_type": "OPT", "delivery_month": "202402",
+            "strike_price": 17800.0, "option_right": "OptionCall",
+        }));
+        assert_eq!(call.code, "TXO17800B4");
+        assert_eq!(call.security_type, SecurityType::Option);
+
+        let put = deal(serde_json::json!({
+            "trade_id": "t3", "action": "Buy", "code": "TXO", "price": 140.0,
+            "quantity": 1, "delivery_month": "202412", "strike_price": 17900.0,
+            "option_right": "OptionPut",
+        }));
+        assert_eq!(put.code, "TXO17900X4");
+        assert_eq!(put.security_type, SecurityType::Option);
+
+        // Already complete codes and messages without a month are kept
+        let full = deal(serde_json::json!({
+            "trade_id": "t4", "action": "Buy", "code": "TXFA4", "price": 17900.0,
+            "quantity": 1, "delivery_month": "202401",
+        }));
+        assert_eq!(full.code, "TXFA4");
+        let bare = deal(serde_json::json!({
+            "trade_id": "t5", "action": "Buy", "code": "MXFB4", "price": 17900.0,
+            "quantity": 1,
+        }));
+        assert_eq!(bare.code, "MXFB4");
+    }
+}
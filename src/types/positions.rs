@@ -12,6 +12,11 @@ pub struct Position {
     pub last_price: f64,
     pub pnl: f64,
     pub yd_quantity: i64,
+    /// 多空方向 (Buy 為多單, Sell 為空單)
+    pub direction: Action,
+    /// 股票部位的交易條件 (現股/融資/融券)；平倉須使用相同條件
+    #[serde(default)]
+    pub cond: StockOrderCond,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -296,11 +296,17 @@ pub fn raise_resp_error(status_code: u16, resp: serde_json::Value) -> crate::err
     match status_code {
         401 => {
             // TokenError - 對應 Python 的 TokenError(status_code, detail)
-            crate::error::Error::Authentication(format!("Token Error {}: {}", status_code, detail))
+            crate::error::Error::Token {
+                status_code: Some(status_code.into()),
+                message: detail,
+            }
         }
         503 => {
             // SystemMaintenance - 對應 Python 的 SystemMaintenance(status_code, detail)
-            crate::error::Error::Api(format!("System Maintenance {}: {}", status_code, detail))
+            crate::error::Error::SystemMaintenance {
+                status_code: Some(status_code.into()),
+                message: detail,
+            }
         }
         _ => {
             // 其他錯誤 - 對應 Python 的 Exception(resp)
@@ -721,6 +727,16 @@ pub fn create_default_test_contracts() -> Contracts {
     contracts
 }
 
+/// 產生本地物件 ID (條件單、演算法母單等)，格式為 `{prefix}_{毫秒時間戳}_{亂數}`
+pub(crate) fn new_id(prefix: &str) -> String {
+    format!(
+        "{}_{}_{}",
+        prefix,
+        Utc::now().timestamp_millis(),
+        fastrand::u32(1000..9999)
+    )
+}
+
 /// 初始化 utils 模組
 pub fn init() -> Result<(), Box<dyn std::error::Error>> {
     // 載入環境配置
@@ -804,6 +820,13 @@ mod tests {
         // 錯誤回應
         let result = status_error_wrapper(401, "{\"response\": {\"detail\": \"Unauthorized\"}}");
         assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(crate::error::Error::Token { status_code: Some(401), ref message }) if message == "Unauthorized"
+        ));
+
+        let result = status_error_wrapper(503, "{\"response\": {\"detail\": \"Maintenance\"}}");
+        assert!(result.unwrap_err().is_retryable());
     }
 
     #[test]
@@ -0,0 +1,642 @@
+//! Live implied volatility surface (隱含波動率曲面)
+//!
+//! Keeps mid prices of an [`OptionChain`] from `BidAskFOPv1`/`TickFOPv1`,
+//! inverts them to implied volatilities per strike × expiry and fits each smile
+//! with raw SVI:
+//!
+//! `w(k) = a + b (ρ (k - m) + sqrt((k - m)² + σ²))`
+//!
+//! where `w = iv² · T` is total variance and `k = ln(K / F)` log-moneyness.
+//! Out-of-the-money options are preferred at each strike.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use std::time::Duration;
+//! # use rshioaji::vol_surface::{SnapshotFormat, VolSurface, VolSurfaceConfig};
+//! # async fn run(client: Arc<rshioaji::Shioaji>) -> rshioaji::Result<()> {
+//! let contracts = client.get_contracts().await.unwrap_or_default();
+//! let chain = contracts.option_chain("TXO", None);
+//! let surface = Arc::new(VolSurface::new(chain, VolSurfaceConfig::default()));
+//! surface.attach(&client).await?;
+//! surface.spawn_exporter("./surface", Duration::from_secs(60), SnapshotFormat::Csv);
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::client::Shioaji;
+use crate::error::Result;
+use crate::option_pricing::{implied_volatility, time_to_expiry, OptionInputs, PricingModel};
+use crate::types::{BidAskFOPv1, OptionChain, OptionRight, TickFOPv1};
+
+const TAIPEI_OFFSET_SECS: i32 = 8 * 3600;
+
+/// Surface settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VolSurfaceConfig {
+    pub model: PricingModel,
+    pub rate: f64,
+    /// Minimum number of smile points before fitting SVI (at least 3 are
+    /// always required)
+    pub min_fit_points: usize,
+}
+
+impl Default for VolSurfaceConfig {
+    fn default() -> Self {
+        Self {
+            model: PricingModel::black_scholes(),
+            rate: 0.015,
+            min_fit_points: 5,
+        }
+    }
+}
+
+/// Raw SVI parameters of one smile
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SviParams {
+    pub a: f64,
+    pub b: f64,
+    pub rho: f64,
+    pub m: f64,
+    pub sigma: f64,
+}
+
+impl SviParams {
+    /// Total variance at log-moneyness `k`
+    pub fn total_variance(&self, k: f64) -> f64 {
+        let x = k - self.m;
+        self.a + self.b * (self.rho * x + (x * x + self.sigma * self.sigma).sqrt())
+    }
+
+    pub fn implied_volatility(&self, k: f64, time_to_expiry: f64) -> f64 {
+        if time_to_expiry <= 0.0 {
+            return 0.0;
+        }
+        (self.total_variance(k).max(0.0) / time_to_expiry).sqrt()
+    }
+
+    /// Least-squares fit to `(k, total variance)` points; `None` with fewer
+    /// than `min_points` (and never fewer than 3) points
+    ///
+    /// For fixed `(m, σ)` the model is linear in `(a, bρ, b)`, so `(m, σ)` are
+    /// searched on a grid that is refined around the best cell.
+    pub fn fit(points: &[(f64, f64)], min_points: usize) -> Option<Self> {
+        if points.len() < min_points.max(3) {
+            return None;
+        }
+        let k_min = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let k_max = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let width = (k_max - k_min).max(1e-4);
+
+        let mut best: Option<(Self, f64)> = None;
+        let consider = |best: &mut Option<(Self, f64)>, m: f64, sigma: f64| {
+            if let Some((params, error)) = fit_linear(points, m, sigma) {
+                if best.map(|(_, e)| error < e).unwrap_or(true) {
+                    *best = Some((params, error));
+                }
+            }
+        };
+        for i in 0..=24 {
+            let m = k_min - 0.5 * width + 2.0 * width * i as f64 / 24.0;
+            for j in 0..=19 {
+                consider(&mut best, m, 0.005 * 400f64.powf(j as f64 / 19.0));
+            }
+        }
+
+        let (mut m_step, mut sigma_factor) = (2.0 * width / 24.0, 400f64.powf(1.0 / 19.0));
+        for _ in 0..4 {
+            let (center, _) = best?;
+            for i in -5..=5 {
+                let m = center.m + m_step * i as f64 / 5.0;
+                for j in -5..=5 {
+                    let sigma = center.sigma * sigma_factor.powf(j as f64 / 5.0);
+                    consider(&mut best, m, sigma);
+                }
+            }
+            m_step /= 5.0;
+            sigma_factor = sigma_factor.powf(0.2);
+        }
+        best.map(|(params, _)| params)
+    }
+}
+
+/// Linear least squares of `w = a + p·x + b·z` for fixed `(m, σ)`
+fn fit_linear(points: &[(f64, f64)], m: f64, sigma: f64) -> Option<(SviParams, f64)> {
+    let mut ata = [[0.0f64; 3]; 3];
+    let mut atw = [0.0f64; 3];
+    for &(k, w) in points {
+        let x = k - m;
+        let row = [1.0, x, (x * x + sigma * sigma).sqrt()];
+        for r in 0..3 {
+            for c in 0..3 {
+                ata[r][c] += row[r] * row[c];
+            }
+            atw[r] += row[r] * w;
+        }
+    }
+    let [a, p, b] = solve3(ata, atw)?;
+    if b <= 0.0 {
+        return None;
+    }
+    let rho = (p / b).clamp(-0.999, 0.999);
+    let params = SviParams {
+        a,
+        b,
+        rho,
+        m,
+        sigma,
+    };
+    // Total variance must stay non-negative at its minimum
+    if a + b * sigma * (1.0 - rho * rho).sqrt() < 0.0 {
+        return None;
+    }
+    let error = points
+        .iter()
+        .map(|&(k, w)| (params.total_variance(k) - w).powi(2))
+        .sum();
+    Some((params, error))
+}
+
+fn solve3(mut a: [[f64; 3]; 3], mut b: [f64; 3]) -> Option<[f64; 3]> {
+    for col in 0..3 {
+        let pivot = (col..3).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-14 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = a[col];
+        for row in col + 1..3 {
+            let factor = a[row][col] / pivot_row[col];
+            for (value, pivot) in a[row].iter_mut().zip(pivot_row).skip(col) {
+                *value -= factor * pivot;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = [0.0; 3];
+    for row in (0..3).rev() {
+        let tail: f64 = (row + 1..3).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - tail) / a[row][row];
+    }
+    Some(x)
+}
+
+/// One implied volatility observation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmilePoint {
+    pub code: String,
+    pub strike: f64,
+    pub right: OptionRight,
+    pub price: f64,
+    pub log_moneyness: f64,
+    pub implied_volatility: f64,
+    /// SVI implied volatility at this strike, when the smile was fitted
+    pub fitted_volatility: Option<f64>,
+}
+
+/// Smile of one expiry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Smile {
+    pub delivery_date: String,
+    pub time_to_expiry: f64,
+    pub forward: f64,
+    pub points: Vec<SmilePoint>,
+    pub svi: Option<SviParams>,
+}
+
+/// Export format of [`SurfaceSnapshot`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SnapshotFormat {
+    Json,
+    Csv,
+}
+
+/// Point-in-time surface
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurfaceSnapshot {
+    pub ts: DateTime<Utc>,
+    pub underlying: f64,
+    pub smiles: Vec<Smile>,
+}
+
+impl SurfaceSnapshot {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// One row per smile point
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "ts,underlying,delivery_date,time_to_expiry,code,strike,right,price,log_moneyness,implied_volatility,fitted_volatility\n",
+        );
+        for smile in &self.smiles {
+            for point in &smile.points {
+                csv.push_str(&format!(
+                    "{},{},{},{:.8},{},{},{:?},{},{:.6},{:.6},{}\n",
+                    self.ts.to_rfc3339(),
+                    self.underlying,
+                    smile.delivery_date,
+                    smile.time_to_expiry,
+                    point.code,
+                    point.strike,
+                    point.right,
+                    point.price,
+                    point.log_moneyness,
+                    point.implied_volatility,
+                    point
+                        .fitted_volatility
+                        .map(|v| format!("{:.6}", v))
+                        .unwrap_or_default(),
+                ));
+            }
+        }
+        csv
+    }
+
+    /// Write to `dir/vol-surface-YYYYMMDD-HHMMSS.{json,csv}` (台北時間)
+    pub fn write<P: AsRef<Path>>(&self, dir: P, format: SnapshotFormat) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        let stamp = self
+            .ts
+            .with_timezone(&FixedOffset::east_opt(TAIPEI_OFFSET_SECS).expect("valid offset"))
+            .format("%Y%m%d-%H%M%S");
+        let (extension, content) = match format {
+            SnapshotFormat::Json => ("json", self.to_json()?),
+            SnapshotFormat::Csv => ("csv", self.to_csv()),
+        };
+        let path = dir
+            .as_ref()
+            .join(format!("vol-surface-{}.{}", stamp, extension));
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+#[derive(Default, Clone)]
+struct Quote {
+    bid: f64,
+    ask: f64,
+    last: f64,
+}
+
+impl Quote {
+    fn mid(&self) -> Option<f64> {
+        if self.bid > 0.0 && self.ask > 0.0 && self.ask >= self.bid {
+            Some(0.5 * (self.bid + self.ask))
+        } else if self.last > 0.0 {
+            Some(self.last)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+struct SurfaceState {
+    underlying: f64,
+    clock: Option<DateTime<Utc>>,
+    quotes: HashMap<String, Quote>,
+}
+
+/// Live IV surface over an option chain
+pub struct VolSurface {
+    chain: OptionChain,
+    /// Option codes in `chain`; ticks for other contracts are ignored
+    codes: HashSet<String>,
+    config: VolSurfaceConfig,
+    state: Mutex<SurfaceState>,
+}
+
+impl VolSurface {
+    pub fn new(chain: OptionChain, config: VolSurfaceConfig) -> Self {
+        let codes = chain
+            .expiries
+            .iter()
+            .flat_map(|e| e.calls().chain(e.puts()))
+            .map(|c| c.base.code.clone())
+            .collect();
+        Self {
+            chain,
+            codes,
+            config,
+            state: Mutex::new(SurfaceState::default()),
+        }
+    }
+
+    pub fn chain(&self) -> &OptionChain {
+        &self.chain
+    }
+
+    /// Override the underlying price (e.g. from a futures feed for Black-76)
+    pub fn set_underlying(&self, price: f64) {
+        if price > 0.0 {
+            self.lock().underlying = price;
+        }
+    }
+
+    /// Apply an option tick; codes outside the chain (stock options, other
+    /// underlyings on the same connection) are ignored
+    pub fn on_tick_fop(&self, tick: &TickFOPv1) {
+        if !self.codes.contains(&tick.code) {
+            return;
+        }
+        let mut state = self.lock();
+        state.clock = Some(tick.datetime);
+        if tick.underlying_price > 0.0 {
+            state.underlying = tick.underlying_price;
+        }
+        if tick.close > 0.0 {
+            state.quotes.entry(tick.code.clone()).or_default().last = tick.close;
+        }
+    }
+
+    /// Apply an option bid/ask; codes outside the chain are ignored
+    pub fn on_bidask_fop(&self, bidask: &BidAskFOPv1) {
+        if !self.codes.contains(&bidask.code) {
+            return;
+        }
+        let mut state = self.lock();
+        state.clock = Some(bidask.datetime);
+        if bidask.underlying_price > 0.0 {
+            state.underlying = bidask.underlying_price;
+        }
+        let quote = state.quotes.entry(bidask.code.clone()).or_default();
+        quote.bid = bidask.bid_price.first().copied().unwrap_or(0.0);
+        quote.ask = bidask.ask_price.first().copied().unwrap_or(0.0);
+    }
+
+    /// Current surface; times to expiry are measured from the last quote time
+    ///
+    /// Quotes are copied out first so feed callbacks are not blocked while
+    /// IVs are inverted and smiles fitted.
+    pub fn snapshot(&self) -> SurfaceSnapshot {
+        let (clock, underlying, quotes) = {
+            let state = self.lock();
+            (state.clock, state.underlying, state.quotes.clone())
+        };
+        let ts = clock.unwrap_or_else(Utc::now);
+        let model = self.config.model;
+        let rate = self.config.rate;
+
+        let mut smiles = Vec::new();
+        for expiry in &self.chain.expiries {
+            let Ok(t) = time_to_expiry(&expiry.delivery_date, ts) else {
+                continue;
+            };
+            if t <= 0.0 || underlying <= 0.0 {
+                continue;
+            }
+            let forward = model.forward(underlying, rate, t);
+
+            let mut points = Vec::new();
+            for strike in &expiry.strikes {
+                let (preferred, other) = if strike.strike >= forward {
+                    (&strike.call, &strike.put)
+                } else {
+                    (&strike.put, &strike.call)
+                };
+                let point = [preferred, other]
+                    .into_iter()
+                    .flatten()
+                    .find_map(|contract| {
+                        let price = quotes.get(&contract.base.code)?.mid()?;
+                        let inputs = OptionInputs {
+                            right: contract.option_right.clone(),
+                            underlying,
+                            strike: strike.strike,
+                            time_to_expiry: t,
+                            rate,
+                        };
+                        let iv = implied_volatility(model, &inputs, price).ok()?;
+                        Some(SmilePoint {
+                            code: contract.base.code.clone(),
+                            strike: strike.strike,
+                            right: contract.option_right.clone(),
+                            price,
+                            log_moneyness: (strike.strike / forward).ln(),
+                            implied_volatility: iv,
+                            fitted_volatility: None,
+                        })
+                    });
+                points.extend(point);
+            }
+
+            let observations: Vec<(f64, f64)> = points
+                .iter()
+                .map(|p| (p.log_moneyness, p.implied_volatility.powi(2) * t))
+                .collect();
+            let svi = SviParams::fit(&observations, self.config.min_fit_points);
+            if let Some(svi) = svi {
+                for point in &mut points {
+                    point.fitted_volatility = Some(svi.implied_volatility(point.log_moneyness, t));
+                }
+            }
+
+            if !points.is_empty() {
+                smiles.push(Smile {
+                    delivery_date: expiry.delivery_date.clone(),
+                    time_to_expiry: t,
+                    forward,
+                    points,
+                    svi,
+                });
+            }
+        }
+
+        SurfaceSnapshot {
+            ts,
+            underlying,
+            smiles,
+        }
+    }
+
+    /// Feed FOP ticks and bid/ask quotes from a logged-in client
+    pub async fn attach(self: &Arc<Self>, client: &Shioaji) -> Result<()> {
+        let surface = self.clone();
+        client
+            .on_tick_fop_v1(move |_exchange, tick| surface.on_tick_fop(&tick), false)
+            .await?;
+        let surface = self.clone();
+        client
+            .on_bidask_fop_v1(
+                move |_exchange, bidask| surface.on_bidask_fop(&bidask),
+                false,
+            )
+            .await?;
+        log::info!(
+            "✅ Vol surface attached for {} ({} expiries)",
+            self.chain.underlying,
+            self.chain.expiries.len()
+        );
+        Ok(())
+    }
+
+    /// Write a snapshot to `dir` every `interval`; abort the handle to stop
+    pub fn spawn_exporter<P: AsRef<Path>>(
+        self: &Arc<Self>,
+        dir: P,
+        interval: Duration,
+        format: SnapshotFormat,
+    ) -> tokio::task::JoinHandle<()> {
+        let surface = self.clone();
+        let dir = dir.as_ref().to_path_buf();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                let snapshot = surface.snapshot();
+                if snapshot.smiles.is_empty() {
+                    continue;
+                }
+                match snapshot.write(&dir, format) {
+                    Ok(path) => {
+                        log::debug!("📊 Vol surface snapshot written to {}", path.display())
+                    }
+                    Err(e) => log::warn!("⚠️ Failed to write vol surface snapshot: {}", e),
+                }
+            }
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SurfaceState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::option_pricing::price;
+    use crate::types::{Contracts, OptionContract};
+    use chrono::TimeZone;
+
+    const SVI: SviParams = SviParams {
+        a: 0.002,
+        b: 0.04,
+        rho: -0.4,
+        m: 0.01,
+        sigma: 0.05,
+    };
+
+    #[test]
+    fn test_svi_fit_recovers_smile() {
+        let points: Vec<(f64, f64)> = (-10..=10)
+            .map(|i| {
+                let k = i as f64 * 0.02;
+                (k, SVI.total_variance(k))
+            })
+            .collect();
+        let fitted = SviParams::fit(&points, 5).unwrap();
+        for (k, w) in &points {
+            assert!((fitted.total_variance(*k) - w).abs() < 1e-6);
+        }
+        assert!(SviParams::fit(&[(0.0, 0.01); 3], 5).is_none());
+        // 最少點數依設定，但至少三點
+        assert!(SviParams::fit(&points[..4], 4).is_some());
+        assert!(SviParams::fit(&points, 30).is_none());
+        assert!(SviParams::fit(&points[..2], 0).is_none());
+    }
+
+    #[test]
+    fn test_surface_from_quotes_and_export() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 2, 2, 0, 0).unwrap();
+        let underlying = 17500.0;
+        let t = time_to_expiry("2024/01/17", now).unwrap();
+        let config = VolSurfaceConfig {
+            rate: 0.0,
+            ..Default::default()
+        };
+
+        let mut contracts = Contracts::new();
+        for i in 0..15 {
+            let strike = 16800.0 + 100.0 * i as f64;
+            for right in [OptionRight::Call, OptionRight::Put] {
+                let code = format!("TXO{}{:?}", strike, right);
+                let mut option = OptionContract::new(&code, right, strike).contract;
+                option.category = "TXO".to_string();
+                option.delivery_month = "202401".to_string();
+                option.delivery_date = "2024/01/17".to_string();
+                contracts.add_option(code, option);
+            }
+        }
+        let surface = VolSurface::new(contracts.option_chain("TXO", None), config.clone());
+
+        for expiry in &surface.chain().expiries {
+            for strike in &expiry.strikes {
+                let k = (strike.strike / underlying).ln();
+                let vol = SVI.implied_volatility(k, t);
+                for contract in [&strike.call, &strike.put].into_iter().flatten() {
+                    let inputs = OptionInputs {
+                        right: contract.option_right.clone(),
+                        underlying,
+                        strike: strike.strike,
+                        time_to_expiry: t,
+                        rate: 0.0,
+                    };
+                    let mid = price(config.model, &inputs, vol);
+                    surface.on_bidask_fop(&BidAskFOPv1 {
+                        code: contract.base.code.clone(),
+                        datetime: now,
+                        bid_price: vec![mid - 0.5],
+                        ask_price: vec![mid + 0.5],
+                        underlying_price: underlying,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        // 同一連線上的股票選擇權不可覆蓋 TXO 標的價
+        surface.on_tick_fop(&TickFOPv1 {
+            code: "CDO580A4".to_string(),
+            datetime: now,
+            close: 12.0,
+            underlying_price: 580.0,
+            ..Default::default()
+        });
+        surface.on_bidask_fop(&BidAskFOPv1 {
+            code: "CDO580A4".to_string(),
+            datetime: now,
+            bid_price: vec![11.5],
+            ask_price: vec![12.5],
+            underlying_price: 580.0,
+            ..Default::default()
+        });
+
+        let snapshot = surface.snapshot();
+        assert_eq!(snapshot.underlying, underlying);
+        assert_eq!(snapshot.smiles.len(), 1);
+        let smile = &snapshot.smiles[0];
+        assert_eq!(smile.points.len(), 15);
+        assert!(smile.svi.is_some());
+        for point in &smile.points {
+            let expected = SVI.implied_volatility(point.log_moneyness, t);
+            assert!((point.implied_volatility - expected).abs() < 1e-3);
+            assert!((point.fitted_volatility.unwrap() - expected).abs() < 1e-3);
+            // 價外優先
+            let otm = if point.strike >= underlying {
+                OptionRight::Call
+            } else {
+                OptionRight::Put
+            };
+            assert_eq!(point.right, otm);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let csv = snapshot.write(dir.path(), SnapshotFormat::Csv).unwrap();
+        assert!(csv.ends_with("vol-surface-20240102-100000.csv"));
+        assert_eq!(std::fs::read_to_string(csv).unwrap().lines().count(), 16);
+        let json = snapshot.write(dir.path(), SnapshotFormat::Json).unwrap();
+        let parsed: SurfaceSnapshot =
+            serde_json::from_str(&std::fs::read_to_string(json).unwrap()).unwrap();
+        assert_eq!(parsed.smiles[0].points.len(), 15);
+    }
+}
@@ -0,0 +1,877 @@
+"""Deterministic stand-in for the ``shioaji`` package.
+
+Used by ``tests/fake_shioaji.rs`` to drive the PyO3 conversion layer in
+``src/client.rs`` without the real package, credentials or network. Put
+``tests/fixtures`` first on ``PYTHONPATH`` (the test does this itself) and
+``import shioaji`` resolves here.
+
+Only the surface rshioaji touches is implemented:
+
+* ``Shioaji(simulation, ...)`` with ``login`` / ``logout`` / ``list_accounts``
+* the ``Contracts`` tree (``Stocks``, ``Futures``, ``Options``, ``Indexs``)
+* ``place_order`` / ``update_order`` / ``cancel_order`` / ``list_trades``
+  with order and deal callbacks; every order fills in full at its price
+  (market orders at the contract reference price)
+* ``list_positions`` / ``margin`` / ``kbars``
+* ``quote.subscribe`` which acknowledges through the event callback and
+  then pushes one tick, bid/ask or quote snapshot to the registered callback
+* ``set_session_down_callback``; ``session_down()`` fires it on every
+  logged-in instance
+* ``shioaji.error`` exceptions: ``TokenError`` for an invalid secret,
+  ``SystemMaintenance`` for ``MAINTENANCE_API_KEY`` and
+  ``AccountNotSignError`` when ordering on ``UNSIGNED_API_KEY`` accounts
+* ``place_order`` sleeps for ``SLOW_ORDER_QUANTITY`` orders, to exercise
+  client deadlines, and records every order in ``PLACED_ORDERS``
+* orders priced at ``REJECT_ORDER_PRICE`` stay ``PendingSubmit`` and the
+  exchange rejects them through the order callback (``op_code`` "88")
+* ``MARGIN_API_KEY`` logins hold a 融資 long and a 融券 short instead of
+  the default cash position
+
+Everything is fixed data, so assertions can use exact values.
+"""
+
+import copy
+import datetime as _dt
+import threading
+import time
+from decimal import Decimal
+from enum import Enum
+
+from shioaji.error import AccountNotSignError, SystemMaintenance, TokenError
+
+__version__ = "1.2.5"
+
+TAIPEI = _dt.timezone(_dt.timedelta(hours=8))
+QUOTE_TIME = _dt.datetime(2024, 1, 2, 9, 0, 1, tzinfo=TAIPEI)
+
+PERSON_ID = "A123456789"
+INVALID_SECRET = "invalid"
+MAINTENANCE_API_KEY = "maintenance"
+# Logins with this key hold margin-trading and short-selling positions
+MARGIN_API_KEY = "margin_key"
+# Logins with this key can neither share nor fetch contracts
+NO_CONTRACTS_API_KEY = "no_contracts_key"
+# Orders of this quantity take SLOW_ORDER_SECONDS to place
+SLOW_ORDER_QUANTITY = 99
+SLOW_ORDER_SECONDS = 0.5
+# Orders at this price are rejected by the exchange after submission
+REJECT_ORDER_PRICE = 1.0
+
+
+class Exchange(str, Enum):
+    TSE = "TSE"
+    OTC = "OTC"
+    OES = "OES"
+    TAIFEX = "TAIFEX"
+
+
+class SecurityType(str, Enum):
+    Index = "IND"
+    Stock = "STK"
+    Future = "FUT"
+    Option = "OPT"
+
+
+class Action(str, Enum):
+    Buy = "Buy"
+    Sell = "Sell"
+
+
+class OptionRight(str, Enum):
+    No = ""
+    Call = "C"
+    Put = "P"
+
+
+# ``option_right`` as spelled in FuturesDeal messages
+DEAL_OPTION_RIGHT = {
+    OptionRight.No: "Future",
+    OptionRight.Call: "OptionCall",
+    OptionRight.Put: "OptionPut",
+}
+
+
+class AccountType(str, Enum):
+    Stock = "S"
+    Future = "F"
+    H = "H"
+
+
+class Status(str, Enum):
+    Cancelled = "Cancelled"
+    Filled = "Filled"
+    PartFilled = "PartFilled"
+    PendingSubmit = "PendingSubmit"
+    PreSubmitted = "PreSubmitted"
+    Submitted = "Submitted"
+    Failed = "Failed"
+
+
+class OrderState(str, Enum):
+    StockOrder = "SORDER"
+    StockDeal = "SDEAL"
+    FuturesOrder = "FORDER"
+    FuturesDeal = "FDEAL"
+
+
+class FetchStatus(str, Enum):
+    Unfetch = "Unfetch"
+    Fetching = "Fetching"
+    Fetched = "Fetched"
+
+
+# --------------------------------------------------------------------------
+# Contracts
+# --------------------------------------------------------------------------
+
+
+class Contract:
+    def __init__(self, security_type, exchange, code, name, reference,
+                 category="", delivery_month="", delivery_date="",
+                 strike_price=0.0, option_right=OptionRight.No,
+                 underlying_code="", multiplier=0, unit=1000):
+        self.security_type = security_type
+        self.exchange = exchange
+        self.code = code
+        self.symbol = exchange.value + code
+        self.name = name
+        self.category = category
+        self.reference = Decimal(str(reference))
+        self.limit_up = self.reference * Decimal("1.1")
+        self.limit_down = self.reference * Decimal("0.9")
+        self.delivery_month = delivery_month
+        self.delivery_date = delivery_date
+        self.strike_price = strike_price
+        self.option_right = option_right
+        self.underlying_code = underlying_code
+        self.multiplier = multiplier
+        self.unit = unit
+        self.update_date = "2024/01/02"
+
+    def __repr__(self):
+        return "Contract(code={!r}, exchange={!r})".format(self.code, self.exchange.value)
+
+
+class ContractGroup:
+    """One group such as ``Contracts.Stocks.TSE`` or ``Contracts.Futures.TXF``."""
+
+    def __init__(self, name, contracts):
+        self._name = name
+        self._contracts = {c.code: c for c in contracts}
+
+    def __getitem__(self, code):
+        return self._contracts[code]
+
+    def get(self, code, default=None):
+        return self._contracts.get(code, default)
+
+    def __iter__(self):
+        return iter(self._contracts.values())
+
+    def __len__(self):
+        return len(self._contracts)
+
+
+class ContractCategory:
+    """``Contracts.Stocks`` etc.: groups as attributes, codes via ``[]``."""
+
+    def __init__(self, groups):
+        self._groups = {group._name: group for group in groups}
+        for group in groups:
+            setattr(self, group._name, group)
+
+    def keys(self):
+        return iter(self._groups.keys())
+
+    def __getitem__(self, code):
+        for group in self._groups.values():
+            contract = group.get(code)
+            if contract is not None:
+                return contract
+        raise KeyError(code)
+
+    def get(self, code, default=None):
+        try:
+            return self[code]
+        except KeyError:
+            return default
+
+    def __iter__(self):
+        return iter(self._groups.values())
+
+
+def _stock(exchange, code, name, reference):
+    return Contract(SecurityType.Stock, exchange, code, name, reference,
+                    category="24" if code == "2330" else "")
+
+
+def _future(category, code, name, month, delivery, reference, multiplier):
+    return Contract(SecurityType.Future, Exchange.TAIFEX, code, name, reference,
+                    category=category, delivery_month=month,
+                    delivery_date=delivery, underlying_code="",
+                    multiplier=multiplier, unit=1)
+
+
+def _option(code, strike, right, reference):
+    return Contract(SecurityType.Option, Exchange.TAIFEX, code,
+                    "臺指選擇權", reference, category="TXO",
+                    delivery_month="202401", delivery_date="2024/01/17",
+                    strike_price=strike, option_right=right,
+                    underlying_code="TXF", multiplier=50, unit=1)
+
+
+class Contracts:
+    def __init__(self):
+        self.status = FetchStatus.Unfetch
+        self.Stocks = ContractCategory([])
+        self.Futures = ContractCategory([])
+        self.Options = ContractCategory([])
+        self.Indexs = ContractCategory([])
+
+    def fetch(self):
+        self.Stocks = ContractCategory([
+            ContractGroup("OES", []),
+            ContractGroup("OTC", [_stock(Exchange.OTC, "6488", "環球晶", 500.0)]),
+            ContractGroup("TSE", [
+                _stock(Exchange.TSE, "0050", "元大台灣50", 130.0),
+                _stock(Exchange.TSE, "2317", "鴻海", 104.0),
+                _stock(Exchange.TSE, "2330", "台積電", 580.0),
+            ]),
+        ])
+        self.Futures = ContractCategory([
+            ContractGroup("MXF", [
+                _future("MXF", "MXFA4", "小型臺指01", "202401", "2024/01/17", 17900.0, 50),
+            ]),
+            ContractGroup("TXF", [
+                _future("TXF", "TXFA4", "臺股期貨01", "202401", "2024/01/17", 17900.0, 200),
+                _future("TXF", "TXFB4", "臺股期貨02", "202402", "2024/02/21", 17920.0, 200),
+            ]),
+        ])
+        self.Options = ContractCategory([
+            ContractGroup("TXO", [
+                _option("TXO17900A4", 17900.0, OptionRight.Call, 150.0),
+                _option("TXO17900M4", 17900.0, OptionRight.Put, 140.0),
+            ]),
+        ])
+        self.Indexs = ContractCategory([
+            ContractGroup("TSE", [
+                Contract(SecurityType.Index, Exchange.TSE, "001", "加權指數", 17800.0),
+            ]),
+        ])
+        self.status = FetchStatus.Fetched
+
+
+# --------------------------------------------------------------------------
+# Accounts, orders and trades
+# --------------------------------------------------------------------------
+
+
+class Account:
+    def __init__(self, account_type, account_id, username="模擬帳戶", broker_id="9A95"):
+        self.account_type = account_type
+        self.person_id = PERSON_ID
+        self.broker_id = broker_id
+        self.account_id = account_id
+        self.signed = True
+        self.username = username
+
+    def __repr__(self):
+        return "Account({!r}, {!r})".format(self.account_type.value, self.account_id)
+
+
+STOCK_ACCOUNT = Account(AccountType.Stock, "0000001")
+FUTOPT_ACCOUNT = Account(AccountType.Future, "1000001")
+# A second API key with its own accounts, for multi-session tests
+CLIENT_API_KEY = "client_key"
+CLIENT_STOCK_ACCOUNT = Account(AccountType.Stock, "0000002", username="客戶帳戶")
+CLIENT_FUTOPT_ACCOUNT = Account(AccountType.Future, "1000002", username="客戶帳戶")
+# Accounts that have not signed the trading agreement
+UNSIGNED_API_KEY = "unsigned_key"
+UNSIGNED_STOCK_ACCOUNT = Account(AccountType.Stock, "0000003")
+UNSIGNED_STOCK_ACCOUNT.signed = False
+
+
+class Order:
+    def __init__(self, action, price, quantity, price_type="LMT", order_type="ROD",
+                 order_lot="Common", order_cond="Cash", octype="Auto",
+                 account=None, **kwargs):
+        self.action = Action(action)
+        self.price = Decimal(str(price))
+        self.quantity = int(quantity)
+        self.price_type = price_type
+        self.order_type = order_type
+        self.order_lot = order_lot
+        self.order_cond = order_cond
+        self.octype = octype
+        self.account = account
+        self.id = ""
+        self.seqno = ""
+        self.ordno = ""
+        for key, value in kwargs.items():
+            setattr(self, key, value)
+
+
+class OrderStatus:
+    def __init__(self, status):
+        self.status = status
+        self.status_code = "00"
+        self.order_datetime = QUOTE_TIME
+        self.deal_quantity = 0
+        self.cancel_quantity = 0
+        self.deals = []
+
+
+class Trade:
+    def __init__(self, contract, order, status):
+        self.contract = contract
+        self.order = order
+        self.status = status
+
+    def __repr__(self):
+        return "Trade({!r}, {!r})".format(self.order.id, self.status.status.value)
+
+
+class Margin:
+    def __init__(self):
+        self.today_balance = 1000000.0
+        self.available_margin = 820000.0
+        self.initial_margin = 184000.0
+        self.maintenance_margin = 141000.0
+        self.margin_call = 0.0
+        self.future_open_position = 4000.0
+
+
+class Position:
+    """A stock position; ``quantity`` is in lots, ``odd_shares`` the odd-lot remainder."""
+
+    def __init__(self, id, code, direction, quantity, price, last_price):
+        self.id = id
+        self.code = code
+        self.direction = direction
+        self.quantity = quantity
+        self.odd_shares = 0
+        self.price = price
+        self.last_price = last_price
+        self.pnl = 0.0
+        self.yd_quantity = quantity
+        self.cond = "Cash"
+        self.margin_purchase_amount = 0
+        self.collateral = 0
+        self.short_sale_margin = 0
+        self.interest = 0
+        self.update_pnl()
+
+    def update_pnl(self):
+        sign = 1 if self.direction == Action.Buy else -1
+        self.pnl = float(sign * (self.last_price - self.price) * self.quantity * 1000)
+
+    def in_shares(self):
+        shares = copy.copy(self)
+        shares.quantity = self.quantity * 1000 + self.odd_shares
+        shares.yd_quantity = self.yd_quantity * 1000 + self.odd_shares
+        return shares
+
+
+class KBar:
+    def __init__(self, ts, open, high, low, close, volume, amount):
+        self.ts = ts
+        self.open = open
+        self.high = high
+        self.low = low
+        self.close = close
+        self.volume = volume
+        self.amount = amount
+
+
+# --------------------------------------------------------------------------
+# Quote
+# --------------------------------------------------------------------------
+
+
+class TickSTKv1:
+    def __init__(self, contract):
+        price = contract.reference
+        self.code = contract.code
+        self.datetime = QUOTE_TIME.replace(tzinfo=None)
+        self.open = price
+        self.avg_price = price
+        self.close = price + Decimal("1")
+        self.high = price + Decimal("2")
+        self.low = price - Decimal("1")
+        self.amount = self.close * 2
+        self.total_amount = self.close * 2
+        self.volume = 2
+        self.total_volume = 2
+        self.tick_type = 1
+        self.chg_type = 2
+        self.price_chg = Decimal("1")
+        self.pct_chg = Decimal("1") / price * 100
+        self.bid_side_total_vol = 1
+        self.ask_side_total_vol = 1
+        self.bid_side_total_cnt = 1
+        self.ask_side_total_cnt = 1
+        self.closing_oddlot_shares = 0
+        self.fixed_trade_vol = 0
+        self.suspend = False
+        self.simtrade = False
+        self.intraday_odd = False
+
+
+class TickFOPv1:
+    def __init__(self, contract):
+        price = contract.reference
+        self.code = contract.code
+        self.datetime = QUOTE_TIME.replace(tzinfo=None)
+        self.open = price
+        self.underlying_price = Decimal("17850")
+        self.bid_side_total_vol = 3
+        self.ask_side_total_vol = 2
+        self.avg_price = price
+        self.close = price + Decimal("1")
+        self.high = price + Decimal("2")
+        self.low = price - Decimal("1")
+        self.amount = self.close * 3
+        self.total_amount = self.close * 3
+        self.volume = 3
+        self.total_volume = 3
+        self.tick_type = 1
+        self.chg_type = 2
+        self.price_chg = Decimal("1")
+        self.pct_chg = Decimal("1") / price * 100
+        self.simtrade = False
+
+
+class BidAskSTKv1:
+    def __init__(self, contract):
+        price = contract.reference
+        self.code = contract.code
+        self.datetime = QUOTE_TIME.replace(tzinfo=None)
+        self.bid_price = [price - Decimal(i) for i in range(5)]
+        self.bid_volume = [10, 20, 30, 40, 50]
+        self.diff_bid_vol = [0, 0, 0, 0, 0]
+        self.ask_price = [price + Decimal(i + 1) for i in range(5)]
+        self.ask_volume = [5, 15, 25, 35, 45]
+        self.diff_ask_vol = [0, 0, 0, 0, 0]
+        self.suspend = False
+        self.simtrade = False
+        self.intraday_odd = False
+
+
+class QuoteSTKv1(TickSTKv1):
+    def __init__(self, contract):
+        super().__init__(contract)
+        bidask = BidAskSTKv1(contract)
+        for name in ("bid_price", "bid_volume", "diff_bid_vol",
+                     "ask_price", "ask_volume", "diff_ask_vol"):
+            setattr(self, name, getattr(bidask, name))
+        self.closing_oddlot_close = Decimal("0")
+        self.closing_oddlot_amount = 0
+        self.closing_oddlot_bid_price = Decimal("0")
+        self.closing_oddlot_ask_price = Decimal("0")
+        self.fixed_trade_amount = Decimal("0")
+        self.avail_borrowing = 1000
+
+
+class BidAskFOPv1:
+    def __init__(self, contract):
+        price = contract.reference
+        self.code = contract.code
+        self.datetime = QUOTE_TIME.replace(tzinfo=None)
+        self.bid_total_vol = 150
+        self.ask_total_vol = 125
+        self.bid_price = [price - Decimal(i) for i in range(5)]
+        self.bid_volume = [10, 20, 30, 40, 50]
+        self.diff_bid_vol = [0, 0, 0, 0, 0]
+        self.ask_price = [price + Decimal(i + 1) for i in range(5)]
+        self.ask_volume = [5, 15, 25, 35, 45]
+        self.diff_ask_vol = [0, 0, 0, 0, 0]
+        self.first_derived_bid_price = price - Decimal("1")
+        self.first_derived_ask_price = price + Decimal("1")
+        self.first_derived_bid_vol = 1
+        self.first_derived_ask_vol = 1
+        self.underlying_price = Decimal("17850")
+        self.simtrade = False
+
+
+class Quote:
+    def __init__(self):
+        self._callbacks = {}
+        self.subscribed = []
+
+    def set_on_tick_stk_v1_callback(self, func, bind=False):
+        self._callbacks["tick_stk"] = func
+
+    def set_on_tick_fop_v1_callback(self, func, bind=False):
+        self._callbacks["tick_fop"] = func
+
+    def set_on_bidask_stk_v1_callback(self, func, bind=False):
+        self._callbacks["bidask_stk"] = func
+
+    def set_on_bidask_fop_v1_callback(self, func, bind=False):
+        self._callbacks["bidask_fop"] = func
+
+    def set_on_quote_stk_v1_callback(self, func, bind=False):
+        self._callbacks["quote_stk"] = func
+
+    def set_quote_callback(self, func):
+        self._callbacks["quote"] = func
+
+    def set_event_callback(self, func):
+        self._callbacks["event"] = func
+
+    def _emit(self, name, *args):
+        callback = self._callbacks.get(name)
+        if callback is not None:
+            callback(*args)
+
+    def subscribe(self, contract, quote_type="tick", intraday_odd=False, version="v1"):
+        quote_type = getattr(quote_type, "value", quote_type)
+        self.subscribed.append((contract.code, quote_type))
+        is_fop = contract.security_type in (SecurityType.Future, SecurityType.Option)
+        topic = "{}/{}/{}/{}".format(
+            "TIC" if quote_type == "tick" else "QUO",
+            "v1",
+            "FOP" if is_fop else "STK",
+            contract.code,
+        )
+        self._emit("event", 0, 16, topic, "Subscribe or Unsubscribe ok")
+
+        if quote_type == "tick":
+            if is_fop:
+                self._emit("tick_fop", Exchange.TAIFEX, TickFOPv1(contract))
+            else:
+                self._emit("tick_stk", contract.exchange, TickSTKv1(contract))
+        elif quote_type == "bidask":
+            if is_fop:
+                self._emit("bidask_fop", Exchange.TAIFEX, BidAskFOPv1(contract))
+            else:
+                self._emit("bidask_stk", contract.exchange, BidAskSTKv1(contract))
+        elif quote_type == "quote" and not is_fop:
+            self._emit("quote_stk", contract.exchange, QuoteSTKv1(contract))
+
+    def unsubscribe(self, contract, quote_type="tick", intraday_odd=False, version="v1"):
+        quote_type = getattr(quote_type, "value", quote_type)
+        self.subscribed.remove((contract.code, quote_type))
+        self._emit("event", 0, 16, contract.code, "Subscribe or Unsubscribe ok")
+
+
+# --------------------------------------------------------------------------
+# API
+# --------------------------------------------------------------------------
+
+
+class Solace:
+    def __init__(self, api):
+        self._api = api
+        self.default_stock_account = None
+        self.default_futopt_account = None
+
+    @property
+    def logged_in(self):
+        return self._api._logged_in
+
+    @property
+    def Contracts(self):
+        return self._api.Contracts
+
+    def error_tracking(self, person_id):
+        return False
+
+    def token_login(self, api_key, secret_key, subscribe_trade=True, receive_window=30000):
+        accounts = self._api.login(api_key, secret_key, fetch_contract=False)
+        return accounts, True, PERSON_ID
+
+    def fetch_all_contract(self, contracts_timeout=0, contracts_cb=None):
+        self._api.Contracts.fetch()
+        if contracts_cb is not None:
+            for security_type in SecurityType:
+                contracts_cb(security_type)
+
+    def logout(self):
+        return self._api.logout()
+
+
+# Constructor arguments of every Shioaji created, for tests to inspect
+CREATED = []
+# Python thread ident of every login call, newest last
+LOGIN_THREADS = []
+# API key of every logout call, newest last
+LOGOUT_KEYS = []
+# Session-down callbacks of logged-in instances, see session_down()
+SESSION_DOWN_CALLBACKS = []
+
+
+def session_down():
+    """Drop the connection of every logged-in instance"""
+    for callback in list(SESSION_DOWN_CALLBACKS):
+        callback()
+# (code, exchange, action, order_cond, order_lot) of every placed order
+PLACED_ORDERS = []
+
+
+class Shioaji:
+    def __init__(self, simulation=False, proxies=None, currency="NTD", vpn=False):
+        if not isinstance(proxies, (dict, type(None))):
+            raise TypeError("proxies must be a dict")
+        self.simulation = simulation
+        self.proxies = proxies
+        self.vpn = vpn
+        CREATED.append({"simulation": simulation, "proxies": proxies, "vpn": vpn})
+        self.person_id = ""
+        self.stock_account = None
+        self.futopt_account = None
+        self.Contracts = Contracts()
+        self.quote = Quote()
+        self._solace = Solace(self)
+        self._logged_in = False
+        self._api_key = None
+        self._order_callback = None
+        self._accounts = [STOCK_ACCOUNT, FUTOPT_ACCOUNT]
+        self._trades = []
+        self._positions = [
+            Position(0, "2330", Action.Buy, 1, 575.0, 580.0),
+        ]
+
+    def __setattr__(self, name, value):
+        if name == "Contracts" and getattr(self, "_api_key", None) == NO_CONTRACTS_API_KEY:
+            raise AttributeError("Contracts cannot be replaced")
+        super().__setattr__(name, value)
+
+    # ---- session -------------------------------------------------------
+
+    def login(self, api_key, secret_key, fetch_contract=True, contracts_timeout=0,
+              contracts_cb=None, subscribe_trade=True, receive_window=30000):
+        LOGIN_THREADS.append(threading.get_ident())
+        if secret_key == INVALID_SECRET:
+            raise TokenError(401, "Sign data is timeout or invalid secret key")
+        if api_key == MAINTENANCE_API_KEY:
+            raise SystemMaintenance(503, "System maintenance, please retry later")
+        self._logged_in = True
+        self._api_key = api_key
+        self.person_id = PERSON_ID
+        if api_key == CLIENT_API_KEY:
+            self._accounts = [CLIENT_STOCK_ACCOUNT, CLIENT_FUTOPT_ACCOUNT]
+        elif api_key == UNSIGNED_API_KEY:
+            self._accounts = [UNSIGNED_STOCK_ACCOUNT, FUTOPT_ACCOUNT]
+        elif api_key == MARGIN_API_KEY:
+            margin_long = Position(0, "2330", Action.Buy, 2, 575.0, 580.0)
+            margin_long.cond = "MarginTrading"
+            short_sale = Position(1, "6488", Action.Sell, 1, 500.0, 495.0)
+            short_sale.cond = "ShortSelling"
+            # 1 lot plus 300 odd shares, only fully visible with unit="Share"
+            mixed = Position(2, "2317", Action.Buy, 1, 100.0, 104.0)
+            mixed.odd_shares = 300
+            self._positions = [margin_long, short_sale, mixed]
+        self.stock_account, self.futopt_account = self._accounts
+        self._solace.default_stock_account = self.stock_account
+        self._solace.default_futopt_account = self.futopt_account
+        if fetch_contract:
+            self._solace.fetch_all_contract(contracts_timeout, contracts_cb)
+        return list(self._accounts)
+
+    def logout(self):
+        LOGOUT_KEYS.append(self._api_key)
+        self._logged_in = False
+        return True
+
+    def list_accounts(self):
+        return list(self._accounts)
+
+    def fetch_contracts(self, contract_download=False, contracts_timeout=0, contracts_cb=None):
+        if self._api_key == NO_CONTRACTS_API_KEY:
+            raise RuntimeError("Contract download failed")
+        self._solace.fetch_all_contract(contracts_timeout, contracts_cb)
+
+    def _require_login(self):
+        if not self._logged_in:
+            raise RuntimeError("Please login first")
+
+    # ---- orders ----------------------------------------------------------
+
+    def set_order_callback(self, func):
+        self._order_callback = func
+
+    def set_session_down_callback(self, func):
+        SESSION_DOWN_CALLBACKS.append(func)
+
+    def _notify(self, state, msg):
+        if self._order_callback is not None:
+            self._order_callback(state, msg)
+
+    def _find_trade(self, trade):
+        order_id = trade["order_id"] if isinstance(trade, dict) else trade.order.id
+        for known in self._trades:
+            if known.order.id == order_id:
+                return known
+        raise ValueError("Trade {} not found".format(order_id))
+
+    def place_order(self, contract, order, timeout=5000, cb=None):
+        self._require_login()
+        is_fop = contract.security_type in (SecurityType.Future, SecurityType.Option)
+        seq = len(self._trades) + 1
+        order.id = "fake{:04d}".format(seq)
+        order.seqno = "{:06d}".format(seq)
+        order.ordno = "F{:05d}".format(seq)
+        if order.account is None:
+            order.account = self.futopt_account if is_fop else self.stock_account
+        if order.quantity == SLOW_ORDER_QUANTITY:
+            time.sleep(SLOW_ORDER_SECONDS)
+        if not order.account.signed:
+            raise AccountNotSignError(
+                "Account {} has not signed the agreement".format(order.account.account_id))
+        PLACED_ORDERS.append((contract.code, contract.exchange.value, order.action.value,
+                              order.order_cond, order.order_lot))
+        price = order.price if order.price > 0 else contract.reference
+
+        order_state = OrderState.FuturesOrder if is_fop else OrderState.StockOrder
+        if order.price == Decimal(str(REJECT_ORDER_PRICE)):
+            trade = Trade(contract, order, OrderStatus(Status.PendingSubmit))
+            self._trades.append(trade)
+            self._notify(order_state, {
+                "operation": {"op_type": "New", "op_code": "88", "op_msg": "價格超過漲跌停"},
+                "order": {"id": order.id, "seqno": order.seqno, "ordno": order.ordno},
+                "status": {"id": order.id, "exchange_ts": QUOTE_TIME.timestamp()},
+                "contract": {"code": contract.code},
+            })
+            return trade
+
+        status = OrderStatus(Status.Filled)
+        status.deal_quantity = order.quantity
+        trade = Trade(contract, order, status)
+        self._trades.append(trade)
+
+        deal_state = OrderState.FuturesDeal if is_fop else OrderState.StockDeal
+        self._notify(order_state, {
+            "operation": {"op_type": "New", "op_code": "00", "op_msg": ""},
+            "order": {
+                "id": order.id,
+                "seqno": order.seqno,
+                "ordno": order.ordno,
+                "action": order.action.value,
+                "price": float(order.price),
+                "quantity": order.quantity,
+                "order_type": order.order_type,
+                "price_type": order.price_type,
+            },
+            "status": {"id": order.id, "exchange_ts": QUOTE_TIME.timestamp()},
+            "contract": {
+                "security_type": contract.security_type.value,
+                "exchange": contract.exchange.value,
+                "code": contract.code,
+            },
+        })
+        deal = {
+            "trade_id": order.id,
+            "seqno": order.seqno,
+            "ordno": order.ordno,
+            "exchange_seq": "{:06d}".format(seq),
+            "broker_id": order.account.broker_id,
+            "account_id": order.account.account_id,
+            "action": order.action.value,
+            "code": contract.code,
+            "order_cond": order.order_cond,
+            "order_lot": order.order_lot,
+            "price": float(price),
+            "quantity": order.quantity,
+            "ts": QUOTE_TIME.timestamp(),
+        }
+        if is_fop:
+            # Like the real FuturesDeal: product root plus month/strike fields
+            del deal["order_cond"], deal["order_lot"]
+            deal.update({
+                "code": contract.category,
+                "security_type": contract.security_type.value,
+                "delivery_month": contract.delivery_month,
+                "strike_price": float(contract.strike_price),
+                "option_right": DEAL_OPTION_RIGHT[contract.option_right],
+            })
+        self._notify(deal_state, deal)
+        # Odd-lot fills leave the seeded lot positions alone
+        if not is_fop and order.order_lot == "Common":
+            self._apply_fill(contract.code, order.action, order.quantity, float(price))
+        return trade
+
+    def _apply_fill(self, code, action, quantity, price):
+        for position in self._positions:
+            if position.code == code:
+                signed = position.quantity if position.direction == Action.Buy else -position.quantity
+                delta = quantity if action == Action.Buy else -quantity
+                net = signed + delta
+                if signed * delta > 0:
+                    position.price = (position.price * abs(signed) + price * quantity) / abs(net)
+                elif net * signed < 0:
+                    position.price = price
+                position.quantity = abs(net)
+                position.direction = Action.Buy if net >= 0 else Action.Sell
+                position.yd_quantity = min(position.yd_quantity, position.quantity)
+                position.update_pnl()
+                self._positions = [p for p in self._positions if p.quantity > 0]
+                return
+        position = Position(len(self._positions), code, action, quantity, price, price)
+        position.yd_quantity = 0
+        self._positions.append(position)
+
+    def update_order(self, trade, price=None, qty=None, timeout=5000, cb=None):
+        self._require_login()
+        known = self._find_trade(trade)
+        if price is not None:
+            known.order.price = Decimal(str(price))
+        if qty is not None:
+            known.order.quantity = int(qty)
+        return known
+
+    def cancel_order(self, trade, timeout=5000, cb=None):
+        self._require_login()
+        known = self._find_trade(trade)
+        if known.status.status != Status.Filled:
+            known.status.status = Status.Cancelled
+        return known
+
+    def list_trades(self):
+        return list(self._trades)
+
+    def update_status(self, account=None, trade=None, timeout=5000, cb=None):
+        return None
+
+    # ---- queries ---------------------------------------------------------
+
+    def list_positions(self, account=None, unit="Common", timeout=5000, cb=None):
+        self._require_login()
+        account_id = None
+        if isinstance(account, dict):
+            account_id = account.get("account_id")
+        elif account is not None:
+            account_id = account.account_id
+        if account_id == self.futopt_account.account_id:
+            return []
+        if unit == "Share":
+            return [p.in_shares() for p in self._positions]
+        return [p for p in self._positions if p.quantity > 0]
+
+    def margin(self, account=None, timeout=5000, cb=None):
+        self._require_login()
+        return Margin()
+
+    def kbars(self, contract, start=None, end=None, timeout=30000):
+        """Three 1-minute bars (09:01–09:03) per weekday in ``[start, end]``."""
+        self._require_login()
+        day = _dt.date.fromisoformat(start)
+        last = _dt.date.fromisoformat(end)
+        reference = float(contract.reference)
+        bars = []
+        while day <= last:
+            if day.weekday() < 5:
+                for minute in range(1, 4):
+                    ts = _dt.datetime(day.year, day.month, day.day, 9, minute, tzinfo=TAIPEI)
+                    close = reference + minute
+                    bars.append(KBar(
+                        ts=ts.isoformat(),
+                        open=close - 1.0,
+                        high=close + 0.5,
+                        low=close - 1.5,
+                        close=close,
+                        volume=100 * minute,
+                        amount=close * 100 * minute,
+                    ))
+            day += _dt.timedelta(days=1)
+        return bars
@@ -0,0 +1,46 @@
+"""Exception classes mirroring ``shioaji.error``.
+
+Constructor signatures follow the real package so rshioaji can read
+``code`` and ``message`` off raised instances.
+"""
+
+
+class BaseError(Exception):
+    pass
+
+
+class TokenError(BaseError):
+    def __init__(self, code, message):
+        super().__init__(code, message)
+        self.code = code
+        self.message = message
+
+
+class SystemMaintenance(BaseError):
+    def __init__(self, code, message):
+        super().__init__(code, message)
+        self.code = code
+        self.message = message
+
+
+class TimeoutError(BaseError):
+    def __init__(self, topic, partial=False):
+        super().__init__("Topic: {}, partial: {}".format(topic, partial))
+        self.topic = topic
+        self.partial = partial
+
+
+class AccountError(BaseError):
+    pass
+
+
+class AccountNotSignError(AccountError):
+    pass
+
+
+class AccountNotProvideError(AccountError):
+    pass
+
+
+class TargetContractNotExistError(BaseError):
+    pass
@@ -182,3 +182,42 @@ fn test_enums() {
     assert_eq!(OrderType::IOC.to_string(), "IOC");
     assert_eq!(OrderType::FOK.to_string(), "FOK");
 }
+
+#[test]
+fn test_option_chain() {
+    use rshioaji::*;
+
+    let mut contracts = Contracts::new();
+    let mut add = |category: &str, month: &str, date: &str, right: OptionRight, strike: f64| {
+        let code = format!("{}{}{:?}{}", category, strike, right, date);
+        let mut option = OptionContract::new(&code, right, strike).contract;
+        option.category = category.to_string();
+        option.delivery_month = month.to_string();
+        option.delivery_date = date.to_string();
+        contracts.add_option(code, option);
+    };
+    for strike in [16900.0, 17000.0, 17100.0] {
+        add("TXO", "202401", "2024/01/17", OptionRight::Call, strike);
+        add("TXO", "202401", "2024/01/17", OptionRight::Put, strike);
+        add("TX4", "202401W4", "2024/01/24", OptionRight::Call, strike);
+    }
+    add("TEO", "202401", "2024/01/17", OptionRight::Call, 1000.0);
+
+    let chain = contracts.option_chain("TXO", None);
+    assert_eq!(chain.expiries.len(), 2);
+    assert_eq!(chain.expiries[0].delivery_date, "2024/01/17");
+    assert_eq!(chain.monthly().count(), 1);
+    assert_eq!(chain.weekly().next().unwrap().category, "TX4");
+
+    let monthly = chain.expiry("20240117").unwrap();
+    assert_eq!(monthly.strikes.len(), 3);
+    assert_eq!(monthly.calls().count(), 3);
+    let atm = monthly.atm(17040.0).unwrap();
+    assert_eq!(atm.strike, 17000.0);
+    assert!(atm.call.is_some() && atm.put.is_some());
+    assert_eq!(monthly.strikes_around(17040.0, 1).len(), 3);
+
+    let weekly = contracts.option_chain("TXO", Some("202401W4"));
+    assert_eq!(weekly.expiries.len(), 1);
+    assert!(weekly.expiries[0].puts().next().is_none());
+}